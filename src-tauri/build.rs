@@ -1,3 +1,5 @@
 fn main() {
+    // Only the desktop app needs Tauri's generated context
+    #[cfg(feature = "app")]
     tauri_build::build()
 }
@@ -0,0 +1,334 @@
+use crate::encryption::{self, KEY_LEN};
+use crate::errors::AppError;
+use crate::models::{BundleKeyPair, BundleUnlock, KdfParams};
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::Utc;
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+// A vault bundle is a JSON file in the style of age: the contents are
+// encrypted with a random file key, and that key is wrapped once per
+// recipient ("stanza"), either with an Argon2id passphrase key or with an
+// X25519 key agreement against the recipient's public key. Public and secret
+// keys use age's encoding, so `age-keygen` keys work too.
+pub const BUNDLE_FORMAT: &str = "yotion-vault-bundle";
+pub const BUNDLE_VERSION: i64 = 1;
+
+const PUBLIC_KEY_PREFIX: &str = "age";
+const SECRET_KEY_PREFIX: &str = "age-secret-key-";
+const X25519_INFO: &[u8] = b"yotion-vault-bundle/v1 x25519";
+// Bundles come from elsewhere, so their Argon2id settings are capped before
+// any key is derived
+const MAX_KDF_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_KDF_ITERATIONS: u32 = 64;
+const MAX_KDF_PARALLELISM: u32 = 16;
+
+#[derive(Serialize, Deserialize)]
+struct SealedBundle {
+    format: String,
+    version: i64,
+    created_at: String,
+    stanzas: Vec<Stanza>,
+    // Nonce and ciphertext of the contents, base64 encoded
+    payload: String,
+}
+
+// `wrapped_key` is the file key sealed like the payload
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Stanza {
+    Passphrase { salt: String, kdf: KdfParams, wrapped_key: String },
+    X25519 { ephemeral_key: String, wrapped_key: String },
+}
+
+// Who a bundle is sealed for, already checked
+pub enum Recipients<'a> {
+    Passphrase(&'a str),
+    PublicKeys(Vec<PublicKey>),
+}
+
+pub fn generate_key_pair() -> Result<BundleKeyPair, AppError> {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public_key = encode_key(PUBLIC_KEY_PREFIX, PublicKey::from(&secret).as_bytes())?;
+    let secret_key = Zeroizing::new(encode_key(SECRET_KEY_PREFIX, secret.as_bytes())?);
+    Ok(BundleKeyPair {
+        public_key,
+        secret_key: secret_key.to_uppercase(),
+    })
+}
+
+pub fn parse_public_key(value: &str) -> Option<PublicKey> {
+    decode_key(value.trim(), PUBLIC_KEY_PREFIX).map(PublicKey::from)
+}
+
+pub fn seal(contents: &[u8], recipients: &Recipients) -> Result<String, AppError> {
+    let mut file_key = Zeroizing::new([0u8; KEY_LEN]);
+    OsRng.fill_bytes(file_key.as_mut());
+
+    let stanzas = match recipients {
+        Recipients::Passphrase(passphrase) => {
+            let kdf = KdfParams::default();
+            let salt = encryption::generate_salt();
+            let wrapping_key = encryption::derive_key(passphrase, &salt, kdf)?;
+            vec![Stanza::Passphrase {
+                salt: encryption::encode(&salt),
+                kdf,
+                wrapped_key: seal_with(&wrapping_key, file_key.as_ref())?,
+            }]
+        }
+        Recipients::PublicKeys(keys) => keys
+            .iter()
+            .map(|recipient| {
+                let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+                let ephemeral_key = PublicKey::from(&ephemeral);
+                let shared = ephemeral.diffie_hellman(recipient);
+                if !shared.was_contributory() {
+                    return Err(AppError::Encryption("Unusable public key".to_string()));
+                }
+                let wrapping_key = x25519_wrapping_key(shared.as_bytes(), &ephemeral_key, recipient)?;
+                Ok(Stanza::X25519 {
+                    ephemeral_key: encryption::encode(ephemeral_key.as_bytes()),
+                    wrapped_key: seal_with(&wrapping_key, file_key.as_ref())?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let bundle = SealedBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        version: BUNDLE_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        stanzas,
+        payload: seal_with(&file_key, contents)?,
+    };
+    serde_json::to_string_pretty(&bundle).map_err(|e| AppError::Io(e.to_string()))
+}
+
+// Decrypts a bundle with a passphrase or an `AGE-SECRET-KEY-1…` secret key,
+// trying every stanza of that kind
+pub fn open(data: &str, unlock: &BundleUnlock) -> Result<Zeroizing<Vec<u8>>, AppError> {
+    let bundle: SealedBundle = serde_json::from_str(data)
+        .map_err(|e| AppError::validation(format!("Not a valid vault bundle: {}", e)))?;
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(AppError::validation(format!("Not a vault bundle (format '{}')", bundle.format)));
+    }
+    if bundle.version > BUNDLE_VERSION {
+        return Err(AppError::validation(format!(
+            "The bundle was written by a newer version of YOTION (bundle version {})",
+            bundle.version
+        )));
+    }
+
+    let file_key = match unlock {
+        BundleUnlock::Passphrase { passphrase } => {
+            let mut has_stanza = false;
+            let mut file_key = None;
+            for stanza in &bundle.stanzas {
+                let Stanza::Passphrase { salt, kdf, wrapped_key } = stanza else {
+                    continue;
+                };
+                has_stanza = true;
+                check_kdf(*kdf)?;
+                let wrapping_key = encryption::derive_key(passphrase, &encryption::decode(salt)?, *kdf)?;
+                if let Some(key) = open_with(&wrapping_key, wrapped_key) {
+                    file_key = Some(key);
+                    break;
+                }
+            }
+            if !has_stanza {
+                return Err(AppError::invalid_field("passphrase", "This bundle is sealed to a public key; open it with a secret key"));
+            }
+            file_key.ok_or_else(|| AppError::invalid_field("passphrase", "Incorrect passphrase"))?
+        }
+        BundleUnlock::SecretKey { secret_key } => {
+            let secret = decode_key(secret_key.trim(), SECRET_KEY_PREFIX)
+                .map(StaticSecret::from)
+                .ok_or_else(|| AppError::invalid_field("secret_key", "Not an X25519 secret key (AGE-SECRET-KEY-1…)"))?;
+            let public = PublicKey::from(&secret);
+            let mut has_stanza = false;
+            let mut file_key = None;
+            for stanza in &bundle.stanzas {
+                let Stanza::X25519 { ephemeral_key, wrapped_key } = stanza else {
+                    continue;
+                };
+                has_stanza = true;
+                let Ok(ephemeral_key) = <[u8; 32]>::try_from(encryption::decode(ephemeral_key)?) else {
+                    continue;
+                };
+                let ephemeral_key = PublicKey::from(ephemeral_key);
+                let shared = secret.diffie_hellman(&ephemeral_key);
+                if !shared.was_contributory() {
+                    continue;
+                }
+                let wrapping_key = x25519_wrapping_key(shared.as_bytes(), &ephemeral_key, &public)?;
+                if let Some(key) = open_with(&wrapping_key, wrapped_key) {
+                    file_key = Some(key);
+                    break;
+                }
+            }
+            if !has_stanza {
+                return Err(AppError::invalid_field("secret_key", "This bundle is sealed with a passphrase"));
+            }
+            file_key.ok_or_else(|| AppError::invalid_field("secret_key", "This key can't open the bundle"))?
+        }
+    };
+
+    let file_key = <[u8; KEY_LEN]>::try_from(file_key.as_slice())
+        .map(Zeroizing::new)
+        .map_err(|_| damaged())?;
+    open_with(&file_key, &bundle.payload).ok_or_else(damaged)
+}
+
+// Nonce followed by ciphertext, base64 encoded
+fn seal_with(key: &[u8; KEY_LEN], data: &[u8]) -> Result<String, AppError> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(key.into())
+        .encrypt(&nonce, data)
+        .map_err(|_| AppError::Encryption("Encryption failed".to_string()))?;
+    Ok(encryption::encode(&[nonce.as_slice(), &ciphertext].concat()))
+}
+
+// None if the key is wrong or the data was changed
+fn open_with(key: &[u8; KEY_LEN], sealed: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let sealed = encryption::decode(sealed).ok()?;
+    let nonce_len = XNonce::default().len();
+    if sealed.len() < nonce_len {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(nonce_len);
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .ok()
+        .map(Zeroizing::new)
+}
+
+// Bound to both public keys, so a stanza can't be replayed for another
+// recipient
+fn x25519_wrapping_key(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> Result<Zeroizing<[u8; KEY_LEN]>, AppError> {
+    let salt = [ephemeral.as_bytes().as_slice(), recipient.as_bytes()].concat();
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Hkdf::<Sha256>::new(Some(&salt), shared)
+        .expand(X25519_INFO, key.as_mut())
+        .map_err(|e| AppError::Encryption(e.to_string()))?;
+    Ok(key)
+}
+
+fn check_kdf(kdf: KdfParams) -> Result<(), AppError> {
+    if kdf.memory_kib > MAX_KDF_MEMORY_KIB || kdf.iterations > MAX_KDF_ITERATIONS || kdf.parallelism > MAX_KDF_PARALLELISM {
+        return Err(AppError::validation("The bundle asks for unreasonably expensive key derivation"));
+    }
+    Ok(())
+}
+
+fn encode_key(prefix: &str, key: &[u8; 32]) -> Result<String, AppError> {
+    bech32::encode(prefix, key.to_base32(), Variant::Bech32).map_err(|e| AppError::Encryption(e.to_string()))
+}
+
+// Accepts either case, as secret keys are written in upper case
+fn decode_key(value: &str, prefix: &str) -> Option<[u8; 32]> {
+    let (hrp, data, variant) = bech32::decode(value).ok()?;
+    if hrp != prefix || variant != Variant::Bech32 {
+        return None;
+    }
+    let bytes = Zeroizing::new(Vec::<u8>::from_base32(&data).ok()?);
+    <[u8; 32]>::try_from(bytes.as_slice()).ok()
+}
+
+fn damaged() -> AppError {
+    AppError::Encryption("The bundle is damaged or was tampered with".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passphrase(passphrase: &str) -> BundleUnlock {
+        BundleUnlock::Passphrase { passphrase: passphrase.to_string() }
+    }
+
+    fn secret_key(secret_key: &str) -> BundleUnlock {
+        BundleUnlock::SecretKey { secret_key: secret_key.to_string() }
+    }
+
+    #[test]
+    fn passphrase_round_trip() {
+        let sealed = seal(b"vault contents", &Recipients::Passphrase("open sesame")).unwrap();
+        assert!(!sealed.contains("vault contents"));
+        assert_eq!(open(&sealed, &passphrase("open sesame")).unwrap().as_slice(), b"vault contents");
+
+        let wrong = open(&sealed, &passphrase("open sesame!")).unwrap_err();
+        assert_eq!(wrong.field(), Some("passphrase"));
+        let keys = generate_key_pair().unwrap();
+        assert_eq!(open(&sealed, &secret_key(&keys.secret_key)).unwrap_err().field(), Some("secret_key"));
+    }
+
+    #[test]
+    fn public_key_round_trip() {
+        let alice = generate_key_pair().unwrap();
+        let bob = generate_key_pair().unwrap();
+        let carol = generate_key_pair().unwrap();
+        assert!(alice.public_key.starts_with("age1"));
+        assert!(alice.secret_key.starts_with("AGE-SECRET-KEY-1"));
+
+        let recipients = [&alice, &bob].map(|keys| parse_public_key(&keys.public_key).unwrap());
+        let sealed = seal(b"vault contents", &Recipients::PublicKeys(recipients.to_vec())).unwrap();
+        for keys in [&alice, &bob] {
+            assert_eq!(open(&sealed, &secret_key(&keys.secret_key)).unwrap().as_slice(), b"vault contents");
+        }
+        // Secret keys are accepted in either case
+        let lower = alice.secret_key.to_lowercase();
+        assert_eq!(open(&sealed, &secret_key(&lower)).unwrap().as_slice(), b"vault contents");
+
+        assert!(open(&sealed, &secret_key(&carol.secret_key)).is_err());
+        assert_eq!(open(&sealed, &passphrase("anything")).unwrap_err().field(), Some("passphrase"));
+        assert_eq!(open(&sealed, &secret_key(&alice.public_key)).unwrap_err().field(), Some("secret_key"));
+    }
+
+    #[test]
+    fn parses_only_public_keys() {
+        let keys = generate_key_pair().unwrap();
+        assert!(parse_public_key(&format!("  {}\n", keys.public_key)).is_some());
+        assert!(parse_public_key(&keys.secret_key).is_none());
+        assert!(parse_public_key("age1notakey").is_none());
+    }
+
+    #[test]
+    fn refuses_tampered_and_foreign_bundles() {
+        let keys = generate_key_pair().unwrap();
+        let recipient = parse_public_key(&keys.public_key).unwrap();
+        let sealed = seal(b"vault contents", &Recipients::PublicKeys(vec![recipient])).unwrap();
+        let unlock = secret_key(&keys.secret_key);
+
+        let mut bundle: SealedBundle = serde_json::from_str(&sealed).unwrap();
+        let mut payload = encryption::decode(&bundle.payload).unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 1;
+        bundle.payload = encryption::encode(&payload);
+        let tampered = serde_json::to_string(&bundle).unwrap();
+        assert!(open(&tampered, &unlock).unwrap_err().to_string().contains("tampered"));
+
+        let mut bundle: SealedBundle = serde_json::from_str(&sealed).unwrap();
+        bundle.version = BUNDLE_VERSION + 1;
+        let newer = serde_json::to_string(&bundle).unwrap();
+        assert!(open(&newer, &unlock).unwrap_err().to_string().contains("newer"));
+
+        let mut bundle: SealedBundle = serde_json::from_str(&sealed).unwrap();
+        bundle.format = "something-else".to_string();
+        assert!(open(&serde_json::to_string(&bundle).unwrap(), &unlock).is_err());
+        assert!(open("{}", &unlock).is_err());
+    }
+
+    #[test]
+    fn caps_key_derivation_cost() {
+        assert!(check_kdf(KdfParams::default()).is_ok());
+        let greedy = KdfParams { memory_kib: MAX_KDF_MEMORY_KIB + 1, ..KdfParams::default() };
+        assert!(check_kdf(greedy).is_err());
+        assert!(check_kdf(KdfParams { iterations: MAX_KDF_ITERATIONS + 1, ..KdfParams::default() }).is_err());
+    }
+}
@@ -1,80 +1,142 @@
 use crate::database::Database;
+use crate::errors::AppError;
 use crate::models::*;
+use crate::bundle;
+use crate::passwords;
+use crate::validation::{validate_password_length, FieldResult};
 use tauri::State;
 
 #[tauri::command]
 pub fn create_language(
     db: State<'_, Database>,
     req: CreateLanguageRequest,
-) -> Result<Language, String> {
-    db.create_language(req).map_err(|e| e.to_string())
+) -> Result<Language, AppError> {
+    db.create_language(req)
 }
 
 #[tauri::command]
-pub fn get_languages(db: State<'_, Database>) -> Result<Vec<Language>, String> {
-    db.get_languages().map_err(|e| e.to_string())
+pub fn get_languages(db: State<'_, Database>) -> Result<Vec<Language>, AppError> {
+    db.get_languages()
 }
 
 #[tauri::command]
 pub fn create_vocabulary(
     db: State<'_, Database>,
     req: CreateVocabularyRequest,
-) -> Result<Vocabulary, String> {
-    db.create_vocabulary(req).map_err(|e| e.to_string())
+) -> Result<Vocabulary, AppError> {
+    db.create_vocabulary(req)
 }
 
 #[tauri::command]
 pub fn get_vocabulary_by_language(
     db: State<'_, Database>,
     language_id: i64,
-) -> Result<Vec<Vocabulary>, String> {
-    db.get_vocabulary_by_language(language_id).map_err(|e| e.to_string())
+) -> Result<Vec<Vocabulary>, AppError> {
+    db.get_vocabulary_by_language(language_id)
 }
 
 #[tauri::command]
 pub fn get_due_flashcards(
     db: State<'_, Database>,
     limit: Option<i64>,
-) -> Result<Vec<(Flashcard, Vocabulary)>, String> {
+) -> Result<Vec<(Flashcard, Vocabulary)>, AppError> {
     let limit = limit.unwrap_or(20);
-    db.get_due_flashcards(limit).map_err(|e| e.to_string())
+    db.get_due_flashcards(limit)
 }
 
 #[tauri::command]
 pub fn review_flashcard(
     db: State<'_, Database>,
     req: FlashcardReviewRequest,
-) -> Result<(), String> {
-    db.review_flashcard(req).map_err(|e| e.to_string())
+) -> Result<(), AppError> {
+    db.review_flashcard(req)
+}
+
+#[tauri::command]
+pub fn get_flashcard_history(
+    db: State<'_, Database>,
+    flashcard_id: i64,
+) -> Result<Vec<ReviewLogEntry>, AppError> {
+    db.get_flashcard_history(flashcard_id)
+}
+
+#[tauri::command]
+pub fn undo_last_review(
+    db: State<'_, Database>,
+    flashcard_id: Option<i64>,
+) -> Result<Flashcard, AppError> {
+    db.undo_last_review(flashcard_id)
+}
+
+#[tauri::command]
+pub fn get_scheduler_settings(
+    db: State<'_, Database>,
+    language_id: i64,
+) -> Result<SchedulerSettings, AppError> {
+    db.get_scheduler_settings(language_id)
+}
+
+#[tauri::command]
+pub fn update_scheduler_settings(
+    db: State<'_, Database>,
+    language_id: i64,
+    req: UpdateSchedulerSettingsRequest,
+) -> Result<SchedulerSettings, AppError> {
+    db.update_scheduler_settings(language_id, req)
+}
+
+#[tauri::command]
+pub fn fit_fsrs_parameters(
+    db: State<'_, Database>,
+    language_id: i64,
+) -> Result<FsrsFitReport, AppError> {
+    db.fit_fsrs_parameters(language_id)
 }
 
 #[tauri::command]
 pub fn create_tech_space(
     db: State<'_, Database>,
     req: CreateTechSpaceRequest,
-) -> Result<TechSpace, String> {
-    db.create_tech_space(req).map_err(|e| e.to_string())
+) -> Result<TechSpace, AppError> {
+    db.create_tech_space(req)
 }
 
 #[tauri::command]
-pub fn get_tech_spaces(db: State<'_, Database>) -> Result<Vec<TechSpace>, String> {
-    db.get_tech_spaces().map_err(|e| e.to_string())
+pub fn get_tech_spaces(db: State<'_, Database>) -> Result<Vec<TechSpace>, AppError> {
+    db.get_tech_spaces()
+}
+
+#[tauri::command]
+pub fn delete_tech_space(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_tech_space(id)
 }
 
 #[tauri::command]
 pub fn create_code_snippet(
     db: State<'_, Database>,
     req: CreateCodeSnippetRequest,
-) -> Result<CodeSnippet, String> {
-    db.create_code_snippet(req).map_err(|e| e.to_string())
+) -> Result<CodeSnippet, AppError> {
+    db.create_code_snippet(req)
+}
+
+#[tauri::command]
+pub fn update_code_snippet(
+    db: State<'_, Database>,
+    id: i64,
+    req: CreateCodeSnippetRequest,
+) -> Result<CodeSnippet, AppError> {
+    db.update_code_snippet(id, req)
 }
 
 #[tauri::command]
 pub fn get_code_snippets_by_tech_space(
     db: State<'_, Database>,
     tech_space_id: i64,
-) -> Result<Vec<CodeSnippet>, String> {
-    db.get_code_snippets_by_tech_space(tech_space_id).map_err(|e| e.to_string())
+) -> Result<Vec<CodeSnippet>, AppError> {
+    db.get_code_snippets_by_tech_space(tech_space_id)
 }
 
 #[tauri::command]
@@ -82,38 +144,72 @@ pub fn search_code_snippets(
     db: State<'_, Database>,
     query: String,
     limit: Option<i64>,
-) -> Result<Vec<CodeSnippet>, String> {
+) -> Result<Vec<CodeSnippet>, AppError> {
     let limit = limit.unwrap_or(50);
-    db.search_code_snippets(&query, limit).map_err(|e| e.to_string())
+    db.search_code_snippets(&query, limit)
+}
+
+#[tauri::command]
+pub fn delete_code_snippet(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_code_snippet(id)
 }
 
 #[tauri::command]
 pub fn create_project(
     db: State<'_, Database>,
     req: CreateProjectRequest,
-) -> Result<Project, String> {
-    db.create_project(req).map_err(|e| e.to_string())
+) -> Result<Project, AppError> {
+    db.create_project(req)
+}
+
+#[tauri::command]
+pub fn update_project(
+    db: State<'_, Database>,
+    id: i64,
+    req: CreateProjectRequest,
+) -> Result<Project, AppError> {
+    db.update_project(id, req)
+}
+
+#[tauri::command]
+pub fn get_projects(db: State<'_, Database>) -> Result<Vec<Project>, AppError> {
+    db.get_projects()
 }
 
 #[tauri::command]
-pub fn get_projects(db: State<'_, Database>) -> Result<Vec<Project>, String> {
-    db.get_projects().map_err(|e| e.to_string())
+pub fn delete_project(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_project(id)
 }
 
 #[tauri::command]
 pub fn create_task(
     db: State<'_, Database>,
     req: CreateTaskRequest,
-) -> Result<Task, String> {
-    db.create_task(req).map_err(|e| e.to_string())
+) -> Result<Task, AppError> {
+    db.create_task(req)
+}
+
+#[tauri::command]
+pub fn update_task(
+    db: State<'_, Database>,
+    id: i64,
+    req: CreateTaskRequest,
+) -> Result<Task, AppError> {
+    db.update_task(id, req)
 }
 
 #[tauri::command]
 pub fn get_tasks_by_project(
     db: State<'_, Database>,
     project_id: i64,
-) -> Result<Vec<Task>, String> {
-    db.get_tasks_by_project(project_id).map_err(|e| e.to_string())
+) -> Result<Vec<Task>, AppError> {
+    db.get_tasks_by_project(project_id)
 }
 
 #[tauri::command]
@@ -121,40 +217,73 @@ pub fn update_task_status(
     db: State<'_, Database>,
     task_id: i64,
     status: String,
-) -> Result<(), String> {
-    db.update_task_status(task_id, &status).map_err(|e| e.to_string())
+) -> Result<(), AppError> {
+    db.update_task_status(task_id, &status)
+}
+
+#[tauri::command]
+pub fn delete_task(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_task(id)
 }
 
 #[tauri::command]
 pub fn create_event(
     db: State<'_, Database>,
     req: CreateEventRequest,
-) -> Result<Event, String> {
-    db.create_event(req).map_err(|e| e.to_string())
+) -> Result<Event, AppError> {
+    db.create_event(req)
 }
 
 #[tauri::command]
 pub fn get_events_by_date(
     db: State<'_, Database>,
     date: String,
-) -> Result<Vec<Event>, String> {
-    db.get_events_by_date(&date).map_err(|e| e.to_string())
+) -> Result<Vec<Event>, AppError> {
+    db.get_events_by_date(&date)
+}
+
+#[tauri::command]
+pub fn delete_event(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_event(id)
 }
 
 #[tauri::command]
 pub fn create_note(
     db: State<'_, Database>,
     req: CreateNoteRequest,
-) -> Result<Note, String> {
-    db.create_note(req).map_err(|e| e.to_string())
+) -> Result<Note, AppError> {
+    db.create_note(req)
+}
+
+#[tauri::command]
+pub fn update_note(
+    db: State<'_, Database>,
+    id: i64,
+    req: CreateNoteRequest,
+) -> Result<Note, AppError> {
+    db.update_note(id, req)
 }
 
 #[tauri::command]
 pub fn get_notes_by_date(
     db: State<'_, Database>,
     date: String,
-) -> Result<Vec<Note>, String> {
-    db.get_notes_by_date(&date).map_err(|e| e.to_string())
+) -> Result<Vec<Note>, AppError> {
+    db.get_notes_by_date(&date)
+}
+
+#[tauri::command]
+pub fn delete_note(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_note(id)
 }
 
 #[tauri::command]
@@ -166,42 +295,272 @@ pub fn greet(name: &str) -> String {
 pub fn create_personal_account(
     db: State<'_, Database>,
     req: CreatePersonalAccountRequest,
-) -> Result<PersonalAccount, String> {
-    db.create_personal_account(req).map_err(|e| e.to_string())
+) -> Result<PersonalAccount, AppError> {
+    db.create_personal_account(req)
 }
 
 #[tauri::command]
-pub fn get_personal_accounts(db: State<'_, Database>) -> Result<Vec<PersonalAccount>, String> {
-    db.get_personal_accounts().map_err(|e| e.to_string())
+pub fn get_personal_accounts(db: State<'_, Database>) -> Result<Vec<PersonalAccountSummary>, AppError> {
+    db.get_personal_accounts()
 }
 
 #[tauri::command]
 pub fn get_personal_accounts_by_category(
     db: State<'_, Database>,
     category: String,
-) -> Result<Vec<PersonalAccount>, String> {
-    db.get_personal_accounts_by_category(&category).map_err(|e| e.to_string())
+) -> Result<Vec<PersonalAccountSummary>, AppError> {
+    db.get_personal_accounts_by_category(&category)
+}
+
+#[tauri::command]
+pub fn update_personal_account(
+    db: State<'_, Database>,
+    id: i64,
+    req: UpdatePersonalAccountRequest,
+) -> Result<PersonalAccount, AppError> {
+    db.update_personal_account(id, req)
+}
+
+#[tauri::command]
+pub fn get_password_history(
+    db: State<'_, Database>,
+    account_id: i64,
+) -> Result<Vec<PasswordHistoryEntry>, AppError> {
+    db.get_password_history(account_id)
+}
+
+#[tauri::command]
+pub fn restore_password(
+    db: State<'_, Database>,
+    account_id: i64,
+    history_id: i64,
+) -> Result<PersonalAccountSummary, AppError> {
+    db.restore_password(account_id, history_id)
+}
+
+#[tauri::command]
+pub fn reveal_secret(
+    db: State<'_, Database>,
+    id: i64,
+    field: SecretField,
+) -> Result<String, AppError> {
+    db.reveal_secret(id, field)
+}
+
+#[tauri::command]
+pub fn list_secret_access(
+    db: State<'_, Database>,
+    limit: Option<u32>,
+) -> Result<Vec<SecretAccess>, AppError> {
+    db.list_secret_access(limit)
+}
+
+#[tauri::command]
+pub fn add_info_attachment(
+    db: State<'_, Database>,
+    info_id: i64,
+    path: String,
+) -> Result<InfoAttachment, AppError> {
+    db.add_info_attachment(info_id, &path)
+}
+
+#[tauri::command]
+pub fn list_info_attachments(
+    db: State<'_, Database>,
+    info_id: i64,
+) -> Result<Vec<InfoAttachment>, AppError> {
+    db.list_info_attachments(info_id)
+}
+
+#[tauri::command]
+pub fn export_info_attachment(
+    db: State<'_, Database>,
+    id: i64,
+    path: String,
+) -> Result<String, AppError> {
+    db.export_info_attachment(id, &path)
+}
+
+#[tauri::command]
+pub fn delete_info_attachment(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_info_attachment(id)
+}
+
+#[tauri::command]
+pub fn delete_personal_account(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_personal_account(id)
+}
+
+#[tauri::command]
+pub fn import_accounts(
+    db: State<'_, Database>,
+    req: AccountImportRequest,
+) -> Result<AccountImportReport, AppError> {
+    db.import_accounts(req)
+}
+
+#[tauri::command]
+pub fn export_vault_bundle(
+    db: State<'_, Database>,
+    req: VaultBundleExportRequest,
+) -> Result<VaultBundleExport, AppError> {
+    db.export_vault_bundle(req)
+}
+
+#[tauri::command]
+pub fn import_vault_bundle(
+    db: State<'_, Database>,
+    req: VaultBundleImportRequest,
+) -> Result<VaultBundleImportReport, AppError> {
+    db.import_vault_bundle(req)
+}
+
+#[tauri::command]
+pub fn generate_bundle_key_pair() -> Result<BundleKeyPair, AppError> {
+    bundle::generate_key_pair()
+}
+
+#[tauri::command]
+pub fn audit_vault(
+    db: State<'_, Database>,
+    req: VaultAuditRequest,
+) -> Result<VaultAuditReport, AppError> {
+    db.audit_vault(req)
+}
+
+#[tauri::command]
+pub fn get_totp_code(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<TotpCode, AppError> {
+    db.get_totp_code(id)
+}
+
+#[tauri::command]
+pub fn generate_password(policy: PasswordPolicy) -> Result<GeneratedPassword, AppError> {
+    passwords::generate_password(&policy)
+}
+
+#[tauri::command]
+pub fn estimate_password_strength(password: String) -> Result<PasswordStrength, AppError> {
+    validate_password_length(&password).field("password")?;
+    Ok(passwords::estimate_strength(&password))
 }
 
 #[tauri::command]
 pub fn create_personal_info(
     db: State<'_, Database>,
     req: CreatePersonalInfoRequest,
-) -> Result<PersonalInfo, String> {
-    db.create_personal_info(req).map_err(|e| e.to_string())
+) -> Result<PersonalInfo, AppError> {
+    db.create_personal_info(req)
 }
 
 #[tauri::command]
-pub fn get_personal_info(db: State<'_, Database>) -> Result<Vec<PersonalInfo>, String> {
-    db.get_personal_info().map_err(|e| e.to_string())
+pub fn get_personal_info(db: State<'_, Database>) -> Result<Vec<PersonalInfoSummary>, AppError> {
+    db.get_personal_info()
 }
 
 #[tauri::command]
 pub fn get_personal_info_by_category(
     db: State<'_, Database>,
     category: String,
-) -> Result<Vec<PersonalInfo>, String> {
-    db.get_personal_info_by_category(&category).map_err(|e| e.to_string())
+) -> Result<Vec<PersonalInfoSummary>, AppError> {
+    db.get_personal_info_by_category(&category)
+}
+
+#[tauri::command]
+pub fn update_personal_info(
+    db: State<'_, Database>,
+    id: i64,
+    req: CreatePersonalInfoRequest,
+) -> Result<PersonalInfo, AppError> {
+    db.update_personal_info(id, req)
+}
+
+#[tauri::command]
+pub fn delete_personal_info(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_personal_info(id)
+}
+
+#[tauri::command]
+pub fn get_vault_status(db: State<'_, Database>) -> Result<VaultStatus, AppError> {
+    db.get_vault_status()
+}
+
+#[tauri::command]
+pub fn setup_vault(
+    db: State<'_, Database>,
+    master_password: String,
+) -> Result<(), AppError> {
+    db.setup_vault(&master_password)
+}
+
+#[tauri::command]
+pub fn unlock_vault(
+    db: State<'_, Database>,
+    master_password: String,
+) -> Result<(), AppError> {
+    db.unlock_vault(&master_password)
+}
+
+#[tauri::command]
+pub fn change_master_password(
+    db: State<'_, Database>,
+    current_password: String,
+    new_password: String,
+) -> Result<(), AppError> {
+    db.change_master_password(&current_password, &new_password)
+}
+
+#[tauri::command]
+pub fn rotate_vault_key(
+    db: State<'_, Database>,
+    master_password: String,
+) -> Result<(), AppError> {
+    db.rotate_vault_key(&master_password)
+}
+
+#[tauri::command]
+pub fn encrypt_database(
+    db: State<'_, Database>,
+    master_password: String,
+) -> Result<(), AppError> {
+    db.encrypt_database(&master_password)
+}
+
+#[tauri::command]
+pub fn decrypt_database(
+    db: State<'_, Database>,
+    master_password: String,
+) -> Result<(), AppError> {
+    db.decrypt_database(&master_password)
+}
+
+#[tauri::command]
+pub fn lock_vault(db: State<'_, Database>) -> Result<(), AppError> {
+    db.lock_vault()
+}
+
+#[tauri::command]
+pub fn get_vault_settings(db: State<'_, Database>) -> Result<VaultSettings, AppError> {
+    db.get_vault_settings()
+}
+
+#[tauri::command]
+pub fn update_vault_settings(
+    db: State<'_, Database>,
+    settings: VaultSettings,
+) -> Result<VaultSettings, AppError> {
+    db.update_vault_settings(settings)
 }
 
 #[tauri::command]
@@ -209,15 +568,183 @@ pub fn search_vocabulary(
     db: State<'_, Database>,
     query: String,
     limit: Option<i64>,
-) -> Result<Vec<Vocabulary>, String> {
+) -> Result<Vec<Vocabulary>, AppError> {
     let limit = limit.unwrap_or(50);
-    db.search_vocabulary(&query, limit).map_err(|e| e.to_string())
+    db.search_vocabulary(&query, limit)
+}
+
+#[tauri::command]
+pub fn delete_vocabulary(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<(), AppError> {
+    db.delete_vocabulary(id)
 }
 
 #[tauri::command]
 pub fn delete_language(
     db: State<'_, Database>,
     id: i64,
-) -> Result<(), String> {
-    db.delete_language(id).map_err(|e| e.to_string())
+) -> Result<(), AppError> {
+    db.delete_language(id)
+}
+
+#[tauri::command]
+pub fn create_workspace(
+    db: State<'_, Database>,
+    req: CreateWorkspaceRequest,
+) -> Result<Workspace, AppError> {
+    db.create_workspace(req)
+}
+
+#[tauri::command]
+pub fn list_workspaces(db: State<'_, Database>) -> Result<Vec<Workspace>, AppError> {
+    db.list_workspaces()
+}
+
+#[tauri::command]
+pub fn get_current_workspace(db: State<'_, Database>) -> Result<Option<Workspace>, AppError> {
+    db.get_current_workspace()
+}
+
+#[tauri::command]
+pub fn get_startup_error(db: State<'_, Database>) -> Result<Option<String>, AppError> {
+    db.get_startup_error()
+}
+
+#[tauri::command]
+pub fn open_workspace(
+    db: State<'_, Database>,
+    id: i64,
+) -> Result<Workspace, AppError> {
+    db.open_workspace(id)
+}
+
+#[tauri::command]
+pub fn close_workspace(db: State<'_, Database>) -> Result<(), AppError> {
+    db.close_workspace()
+}
+
+#[tauri::command]
+pub fn rename_workspace(
+    db: State<'_, Database>,
+    id: i64,
+    name: String,
+) -> Result<Workspace, AppError> {
+    db.rename_workspace(id, name)
+}
+
+#[tauri::command]
+pub fn get_backup_settings(db: State<'_, Database>) -> Result<BackupSettings, AppError> {
+    db.get_backup_settings()
+}
+
+#[tauri::command]
+pub fn update_backup_settings(
+    db: State<'_, Database>,
+    settings: BackupSettings,
+) -> Result<BackupSettings, AppError> {
+    db.update_backup_settings(settings)
+}
+
+#[tauri::command]
+pub fn create_backup(db: State<'_, Database>) -> Result<BackupInfo, AppError> {
+    db.create_backup()
+}
+
+#[tauri::command]
+pub fn list_backups(db: State<'_, Database>) -> Result<Vec<BackupInfo>, AppError> {
+    db.list_backups()
+}
+
+#[tauri::command]
+pub fn restore_backup(
+    db: State<'_, Database>,
+    file_name: String,
+) -> Result<(), AppError> {
+    db.restore_backup(&file_name)
+}
+
+#[tauri::command]
+pub fn export_workspace(
+    db: State<'_, Database>,
+    path: String,
+) -> Result<(), AppError> {
+    db.export_workspace(&path)
+}
+
+#[tauri::command]
+pub fn import_workspace(
+    db: State<'_, Database>,
+    path: String,
+    mode: ImportMode,
+) -> Result<ImportSummary, AppError> {
+    db.import_workspace(&path, mode)
+}
+
+#[tauri::command]
+pub fn list_trash(db: State<'_, Database>) -> Result<Vec<TrashItem>, AppError> {
+    db.list_trash()
+}
+
+#[tauri::command]
+pub fn restore_trash_item(
+    db: State<'_, Database>,
+    kind: TrashKind,
+    id: i64,
+) -> Result<(), AppError> {
+    db.restore_trash_item(kind, id)
+}
+
+#[tauri::command]
+pub fn purge_trash_item(
+    db: State<'_, Database>,
+    kind: TrashKind,
+    id: i64,
+) -> Result<(), AppError> {
+    db.purge_trash_item(kind, id)
+}
+
+#[tauri::command]
+pub fn empty_trash(db: State<'_, Database>) -> Result<usize, AppError> {
+    db.empty_trash()
+}
+
+#[tauri::command]
+pub fn get_trash_settings(db: State<'_, Database>) -> Result<TrashSettings, AppError> {
+    db.get_trash_settings()
+}
+
+#[tauri::command]
+pub fn update_trash_settings(
+    db: State<'_, Database>,
+    settings: TrashSettings,
+) -> Result<TrashSettings, AppError> {
+    db.update_trash_settings(settings)
+}
+
+#[tauri::command]
+pub fn list_revisions(
+    db: State<'_, Database>,
+    kind: RevisionKind,
+    entity_id: i64,
+) -> Result<Vec<Revision>, AppError> {
+    db.list_revisions(kind, entity_id)
+}
+
+#[tauri::command]
+pub fn diff_revisions(
+    db: State<'_, Database>,
+    from_id: i64,
+    to_id: i64,
+) -> Result<Vec<DiffLine>, AppError> {
+    db.diff_revisions(from_id, to_id)
+}
+
+#[tauri::command]
+pub fn restore_revision(
+    db: State<'_, Database>,
+    revision_id: i64,
+) -> Result<(), AppError> {
+    db.restore_revision(revision_id)
 }
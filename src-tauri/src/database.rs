@@ -1,3 +1,25 @@
 mod connection;
+mod migrations;
+mod workspace;
+mod backup;
+mod archive;
+mod trash;
+mod revisions;
+mod language;
+mod vocabulary;
+mod tech_notes;
+mod projects;
+mod planner;
+mod personal_vault;
+mod custom_fields;
+mod account_import;
+mod vault_audit;
+mod secret_access;
+mod info_attachments;
+mod vault;
+mod file_encryption;
+mod vault_bundle;
+mod scheduler_settings;
+mod review_log;
 
 pub use connection::Database;
@@ -0,0 +1,199 @@
+use crate::database::Database;
+use crate::encryption::{self, EncryptionService};
+use crate::errors::AppError;
+use crate::importers::{self, ImportRow};
+use crate::models::*;
+use crate::validation::*;
+use super::personal_vault::{decrypt_value, insert_personal_account};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+impl Database {
+    // Imports logins exported from another password manager. Rows that can't
+    // be imported, or whose website and username match an existing account or
+    // an earlier row, are skipped and listed in the report.
+    pub fn import_accounts(&self, req: AccountImportRequest) -> Result<AccountImportReport, AppError> {
+        validate_not_empty(&req.category, "Category").field("category")?;
+        let data = std::fs::read_to_string(req.path.trim())?;
+        let rows = importers::parse(req.format, &data, req.category.trim())?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+
+        let known = known_accounts(&tx, &key)?;
+
+        let mut report = AccountImportReport {
+            dry_run: req.dry_run,
+            total_rows: rows.len(),
+            imported: Vec::new(),
+            skipped: Vec::new(),
+        };
+        let mut imported_rows: HashMap<(String, String), usize> = HashMap::new();
+
+        for ImportRow { row, title, account } in rows {
+            let skip = |reason: String, duplicate_of: Option<i64>| SkippedImportRow {
+                row,
+                title: title.clone(),
+                reason,
+                duplicate_of,
+            };
+            let account = match account {
+                Ok(account) => account,
+                Err(reason) => {
+                    report.skipped.push(skip(reason, None));
+                    continue;
+                }
+            };
+            if let Err(e) = account.validate() {
+                report.skipped.push(skip(skip_reason(&e), None));
+                continue;
+            }
+
+            let match_key = match_key(&account.title, account.website.as_deref(), &account.email);
+            if let Some(existing) = known.get(&match_key) {
+                report.skipped.push(skip("An account with this website and username already exists".to_string(), Some(*existing)));
+                continue;
+            }
+            if let Some(earlier) = imported_rows.get(&match_key) {
+                report.skipped.push(skip(format!("Same website and username as row {}", earlier), None));
+                continue;
+            }
+
+            report.imported.push(ImportedAccount {
+                row,
+                title: account.title.trim().to_string(),
+                username: account.email.trim().to_string(),
+                website: account.website.clone(),
+                has_totp: account.totp_secret.as_deref().is_some_and(|s| !s.trim().is_empty()),
+            });
+            imported_rows.insert(match_key, row);
+            if !req.dry_run {
+                insert_personal_account(&tx, &key, account)?;
+            }
+        }
+
+        if !req.dry_run {
+            tx.commit()?;
+        }
+        Ok(report)
+    }
+}
+
+// (site, username) of every account already in the vault
+pub(super) fn known_accounts(conn: &Connection, key: &EncryptionService) -> Result<HashMap<(String, String), i64>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, title, website, email FROM personal_accounts WHERE deleted_at IS NULL")?;
+    let existing = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, String>(3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut known = HashMap::new();
+    for (id, title, website, encrypted_email) in existing {
+        let email = decrypt_value(
+            key,
+            &encrypted_email,
+            &encryption::aad("personal_accounts", "email", id),
+            &format!("email of personal account {}", id),
+        )?;
+        known.insert(match_key(&title, website.as_deref(), &email), id);
+    }
+    Ok(known)
+}
+
+// Why an entry that failed validation was skipped
+pub(super) fn skip_reason(e: &AppError) -> String {
+    match e.field() {
+        Some(field) => format!("Invalid {}: {}", field.replace('_', " "), e.message()),
+        None => e.message(),
+    }
+}
+
+// Accounts without a website are told apart by title instead
+pub(super) fn match_key(title: &str, website: Option<&str>, username: &str) -> (String, String) {
+    let site = website
+        .map(importers::site)
+        .filter(|site| !site.is_empty())
+        .unwrap_or_else(|| format!("title:{}", title.trim().to_lowercase()));
+    (site, username.trim().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPORT: &str = "name,url,username,password\n\
+                          Mail,https://mail.example.com,me@example.com,pw1\n\
+                          Mail again,https://www.mail.example.com/inbox,ME@example.com,pw2\n\
+                          Bank,https://bank.example.com,me@example.com,pw3\n\
+                          Empty,https://shop.example.com,me@example.com,\n";
+
+    fn request(path: &std::path::Path, dry_run: bool) -> AccountImportRequest {
+        AccountImportRequest {
+            path: path.to_string_lossy().to_string(),
+            format: AccountImportFormat::ChromiumCsv,
+            category: "imported".to_string(),
+            dry_run,
+        }
+    }
+
+    #[test]
+    fn dry_run_reports_without_importing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(&path, EXPORT).unwrap();
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+
+        let report = db.import_accounts(request(&path, true)).unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.total_rows, 4);
+        let imported: Vec<_> = report.imported.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(imported, ["Mail", "Bank"]);
+        let reasons: Vec<_> = report.skipped.iter().map(|s| (s.row, s.reason.as_str())).collect();
+        assert_eq!(reasons, [(3, "Same website and username as row 2"), (5, "No password")]);
+        assert!(db.get_personal_accounts().unwrap().is_empty());
+
+        let report = db.import_accounts(request(&path, false)).unwrap();
+        assert_eq!(report.imported.len(), 2);
+        let accounts = db.get_personal_accounts().unwrap();
+        assert_eq!(accounts.len(), 2);
+        assert!(accounts.iter().all(|a| a.category == "imported"));
+    }
+
+    #[test]
+    fn skips_accounts_already_in_the_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(&path, EXPORT).unwrap();
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        db.import_accounts(request(&path, false)).unwrap();
+
+        let report = db.import_accounts(request(&path, false)).unwrap();
+        assert!(report.imported.is_empty());
+        let existing: Vec<_> = report.skipped.iter().filter_map(|s| s.duplicate_of).collect();
+        assert_eq!(existing.len(), 3);
+        assert_eq!(db.get_personal_accounts().unwrap().len(), 2);
+
+        assert_eq!(match_key("Mail", Some("https://www.Mail.example.com/x"), " Me "), ("mail.example.com".to_string(), "me".to_string()));
+        assert_eq!(match_key(" Notes ", Some(""), "me"), ("title:notes".to_string(), "me".to_string()));
+    }
+
+    #[test]
+    fn needs_a_category_and_the_unlocked_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(&path, EXPORT).unwrap();
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+
+        let mut req = request(&path, true);
+        req.category = " ".to_string();
+        assert_eq!(db.import_accounts(req).unwrap_err().field(), Some("category"));
+
+        db.lock_vault().unwrap();
+        assert!(matches!(db.import_accounts(request(&path, true)), Err(AppError::VaultLocked)));
+    }
+}
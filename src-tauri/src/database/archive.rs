@@ -0,0 +1,1062 @@
+use crate::database::Database;
+use crate::encryption::EncryptionService;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use super::connection::lock;
+use super::custom_fields::{import_custom_fields, load_all_custom_fields, FieldOwner, SecretValues};
+use super::migrations;
+use super::revisions::prune_orphan_revisions;
+use super::scheduler_settings::load_scheduler_settings;
+use super::vault::{free_id, read_header, rebind_value, set_legacy_values_upgraded, upgrade_legacy_values, write_header};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const ARCHIVE_FORMAT: &str = "yotion-workspace";
+pub const ARCHIVE_VERSION: i64 = 1;
+
+// Every table covered by the archive, children before parents so a replace
+// import can clear them without tripping foreign keys
+const ARCHIVE_TABLES: &[&str] = &[
+    "review_log",
+    "flashcards",
+    "vocabulary",
+    "scheduler_settings",
+    "languages",
+    "code_snippets",
+    "tech_spaces",
+    "tasks",
+    "projects",
+    "events",
+    "notes",
+    "password_history",
+    "personal_accounts",
+    "personal_info",
+    "vault",
+];
+
+impl Database {
+    pub fn export_workspace(&self, path: &str) -> Result<(), AppError> {
+        let path = Path::new(path.trim());
+        if !path.is_absolute() {
+            return Err(AppError::validation("Export path must be absolute".to_string()));
+        }
+
+        let conn = self.reader()?;
+        // One read transaction so the archive is a consistent point-in-time view
+        let tx = conn.unchecked_transaction()?;
+
+        let archive = WorkspaceArchive {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            schema_version: migrations::current_version(&tx)?,
+            exported_at: Utc::now().to_rfc3339(),
+            languages: read_languages(&tx)?,
+            vocabulary: read_vocabulary(&tx)?,
+            flashcards: read_flashcards(&tx)?,
+            scheduler_settings: read_scheduler_settings(&tx)?,
+            review_log: read_review_log(&tx)?,
+            tech_spaces: read_tech_spaces(&tx)?,
+            code_snippets: read_code_snippets(&tx)?,
+            projects: read_projects(&tx)?,
+            tasks: read_tasks(&tx)?,
+            events: read_events(&tx)?,
+            notes: read_notes(&tx)?,
+            personal_accounts: read_personal_accounts(&tx)?,
+            password_history: read_password_history(&tx)?,
+            personal_info: read_personal_info(&tx)?,
+            vault: read_header(&tx)?,
+        };
+        tx.finish()?;
+
+        let json = serde_json::to_string_pretty(&archive)
+            .map_err(|e| AppError::Io(e.to_string()))?;
+        let tmp = path.with_extension("partial");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+
+        Ok(())
+    }
+
+    pub fn import_workspace(&self, path: &str, mode: ImportMode) -> Result<ImportSummary, AppError> {
+        let data = std::fs::read_to_string(path.trim())?;
+        let archive: WorkspaceArchive = serde_json::from_str(&data)
+            .map_err(|e| AppError::validation(format!("Not a valid workspace archive: {}", e)))?;
+        check_archive(&archive)?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key().ok();
+        let tx = conn.transaction()?;
+
+        let current_vault = read_header(&tx)?;
+        if mode == ImportMode::Replace {
+            refuse_unarchived_rows(&tx)?;
+            for table in ARCHIVE_TABLES {
+                tx.execute(&format!("DELETE FROM {}", table), [])?;
+            }
+        }
+
+        // Archived vault values only decrypt with the archive's master password
+        let has_vault_rows = !archive.personal_accounts.is_empty() || !archive.personal_info.is_empty();
+        let vault = match (mode, &archive.vault, &current_vault) {
+            (ImportMode::Replace, theirs, _) => theirs.clone(),
+            (ImportMode::Merge, None, ours) => ours.clone(),
+            (ImportMode::Merge, Some(theirs), None) => Some(theirs.clone()),
+            (ImportMode::Merge, Some(theirs), Some(ours)) => {
+                if has_vault_rows && theirs.salt != ours.salt {
+                    return Err(AppError::validation(
+                        "The archive's vault uses a different master password; import it with Replace instead",
+                    ));
+                }
+                Some(ours.clone())
+            }
+        };
+        if let Some(header) = &vault {
+            write_header(&tx, header)?;
+        }
+        let vault_changed = vault.map(|h| h.salt) != current_vault.map(|h| h.salt);
+
+        let summary = import_rows(&tx, key.as_deref(), &archive)?;
+        prune_orphan_revisions(&tx)?;
+        // Without the key, the next unlock upgrades any old values imported
+        match (&key, vault_changed) {
+            (Some(key), false) => upgrade_legacy_values(&tx, key)?,
+            _ => set_legacy_values_upgraded(&tx, false)?,
+        }
+        tx.commit()?;
+        drop(conn);
+
+        if vault_changed {
+            *lock(&self.vault)? = None;
+        }
+
+        Ok(summary)
+    }
+}
+
+// Clearing the archived tables cascades into rows the archive cannot carry,
+// so a replace is refused while there are any
+fn refuse_unarchived_rows(conn: &Connection) -> Result<(), AppError> {
+    let attachments: i64 = conn.query_row("SELECT COUNT(*) FROM info_attachments", [], |row| row.get(0))?;
+    if attachments > 0 {
+        return Err(AppError::validation(format!(
+            "The workspace has {} attachment(s), which workspace archives do not include and a Replace import would delete; \
+             export and remove them first, or import with Merge",
+            attachments
+        )));
+    }
+    Ok(())
+}
+
+// Rejects archives from other tools or newer builds, and dangling references
+fn check_archive(archive: &WorkspaceArchive) -> Result<(), AppError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(AppError::validation(format!("Unknown archive format '{}'", archive.format)));
+    }
+    if archive.version > ARCHIVE_VERSION {
+        return Err(AppError::validation(format!(
+            "Archive version {} is newer than this version of YOTION supports ({})",
+            archive.version, ARCHIVE_VERSION
+        )));
+    }
+
+    let missing = |what: &str, id: i64| {
+        AppError::validation(format!("Archive references missing {} {}", what, id))
+    };
+    let has = |ids: &[i64], id: i64| ids.contains(&id);
+
+    let languages: Vec<i64> = archive.languages.iter().map(|l| l.id).collect();
+    let vocabulary: Vec<i64> = archive.vocabulary.iter().map(|v| v.id).collect();
+    let tech_spaces: Vec<i64> = archive.tech_spaces.iter().map(|t| t.id).collect();
+    let projects: Vec<i64> = archive.projects.iter().map(|p| p.id).collect();
+    let flashcards: Vec<i64> = archive.flashcards.iter().map(|f| f.id).collect();
+    let accounts: Vec<i64> = archive.personal_accounts.iter().map(|a| a.id).collect();
+
+    if let Some(v) = archive.vocabulary.iter().find(|v| !has(&languages, v.language_id)) {
+        return Err(missing("language", v.language_id));
+    }
+    if let Some(f) = archive.flashcards.iter().find(|f| !has(&vocabulary, f.vocabulary_id)) {
+        return Err(missing("vocabulary", f.vocabulary_id));
+    }
+    if let Some(s) = archive.scheduler_settings.iter().find(|s| !has(&languages, s.language_id)) {
+        return Err(missing("language", s.language_id));
+    }
+    if let Some(r) = archive.review_log.iter().find(|r| !has(&flashcards, r.flashcard_id)) {
+        return Err(missing("flashcard", r.flashcard_id));
+    }
+    if let Some(s) = archive.code_snippets.iter().find(|s| !has(&tech_spaces, s.tech_space_id)) {
+        return Err(missing("tech space", s.tech_space_id));
+    }
+    if let Some(t) = archive.tasks.iter().find(|t| !has(&projects, t.project_id)) {
+        return Err(missing("project", t.project_id));
+    }
+    if let Some(p) = archive.password_history.iter().find(|p| !has(&accounts, p.account_id)) {
+        return Err(missing("account", p.account_id));
+    }
+
+    check_rows(archive)
+}
+
+// Every row must pass the checks its create request does, so an edited or
+// damaged archive can't bring in values the app would never have stored.
+// Vault values are still encrypted here, so only their plaintext is checked.
+fn check_rows(archive: &WorkspaceArchive) -> Result<(), AppError> {
+    for l in &archive.languages {
+        CreateLanguageRequest { name: l.name.clone(), code: l.code.clone(), flag_emoji: l.flag_emoji.clone() }
+            .validate()
+            .map_err(in_row("language", l.id))?;
+    }
+    for v in &archive.vocabulary {
+        CreateVocabularyRequest {
+            language_id: v.language_id,
+            word: v.word.clone(),
+            translation: v.translation.clone(),
+            pronunciation: v.pronunciation.clone(),
+            example_sentence: v.example_sentence.clone(),
+            difficulty_level: v.difficulty_level,
+        }
+        .validate()
+        .map_err(in_row("vocabulary", v.id))?;
+    }
+    for r in &archive.review_log {
+        FlashcardReviewRequest { flashcard_id: r.flashcard_id, quality: r.quality, duration_ms: r.duration_ms }
+            .validate()
+            .map_err(in_row("review of flashcard", r.flashcard_id))?;
+    }
+    for s in &archive.scheduler_settings {
+        UpdateSchedulerSettingsRequest { scheduler: s.scheduler, desired_retention: s.desired_retention, reset_weights: false }
+            .validate()
+            .map_err(in_row("scheduler settings of language", s.language_id))?;
+    }
+    for t in &archive.tech_spaces {
+        CreateTechSpaceRequest { name: t.name.clone(), description: t.description.clone(), icon: t.icon.clone() }
+            .validate()
+            .map_err(in_row("tech space", t.id))?;
+    }
+    for s in &archive.code_snippets {
+        CreateCodeSnippetRequest {
+            tech_space_id: s.tech_space_id,
+            title: s.title.clone(),
+            description: s.description.clone(),
+            code: s.code.clone(),
+            language: s.language.clone(),
+            tags: s.tags.clone(),
+        }
+        .validate()
+        .map_err(in_row("code snippet", s.id))?;
+    }
+    for p in &archive.projects {
+        CreateProjectRequest {
+            name: p.name.clone(),
+            description: p.description.clone(),
+            status: p.status.clone(),
+            priority: p.priority.clone(),
+            start_date: p.start_date.clone(),
+            end_date: p.end_date.clone(),
+        }
+        .validate()
+        .map_err(in_row("project", p.id))?;
+    }
+    for t in &archive.tasks {
+        CreateTaskRequest {
+            project_id: t.project_id,
+            title: t.title.clone(),
+            description: t.description.clone(),
+            status: t.status.clone(),
+            priority: t.priority.clone(),
+            due_date: t.due_date.clone(),
+        }
+        .validate()
+        .map_err(in_row("task", t.id))?;
+    }
+    for e in &archive.events {
+        CreateEventRequest {
+            title: e.title.clone(),
+            description: e.description.clone(),
+            event_date: e.event_date.clone(),
+            start_time: e.start_time.clone(),
+            end_time: e.end_time.clone(),
+            event_type: e.event_type.clone(),
+            priority: e.priority.clone(),
+        }
+        .validate()
+        .map_err(in_row("event", e.id))?;
+    }
+    for n in &archive.notes {
+        CreateNoteRequest {
+            title: n.title.clone(),
+            content: n.content.clone(),
+            note_date: n.note_date.clone(),
+            tags: n.tags.clone(),
+        }
+        .validate()
+        .map_err(in_row("note", n.id))?;
+    }
+    for a in &archive.personal_accounts {
+        check_vault_row(&a.title, &a.category, &a.custom_fields).map_err(in_row("account", a.id))?;
+    }
+    for i in &archive.personal_info {
+        check_vault_row(&i.title, &i.category, &i.custom_fields).map_err(in_row("personal info", i.id))?;
+    }
+    Ok(())
+}
+
+fn check_vault_row(title: &str, category: &str, custom_fields: &[CustomField]) -> Result<(), AppError> {
+    validate_not_empty(title, "Title").field("title")?;
+    validate_string_length(title.trim(), "Title", 1, 200).field("title")?;
+    validate_not_empty(category, "Category").field("category")?;
+    if custom_fields.len() > MAX_CUSTOM_FIELDS {
+        return Err(AppError::invalid_field(
+            "custom_fields",
+            format!("An entry can have at most {} custom fields", MAX_CUSTOM_FIELDS),
+        ));
+    }
+    for field in custom_fields {
+        validate_not_empty(&field.label, "Field label").field("custom_fields")?;
+    }
+    Ok(())
+}
+
+// Keeps the failed check's field, with the message saying which row it was
+fn in_row(what: &'static str, id: i64) -> impl Fn(AppError) -> AppError {
+    move |e| match e {
+        AppError::Validation { field, message } => AppError::Validation {
+            field,
+            message: format!("Archived {} {}: {}", what, id, message),
+        },
+        other => other,
+    }
+}
+
+// Inserts every archived row under a fresh id, rewriting foreign keys through
+// the old-to-new id maps. Languages and tech spaces that already exist (same
+// unique name or code) are reused so their children merge into them.
+fn import_rows(conn: &Connection, key: Option<&EncryptionService>, archive: &WorkspaceArchive) -> Result<ImportSummary, AppError> {
+    let mut summary = ImportSummary::default();
+
+    let mut language_ids = HashMap::new();
+    for l in &archive.languages {
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM languages WHERE name = ?1 OR code = ?2",
+                (&l.name, &l.code),
+                |row| row.get(0),
+            )
+            .optional()?;
+        let id = match existing {
+            Some(id) => {
+                // A trashed match comes back out so its new children are visible
+                conn.execute("UPDATE languages SET deleted_at = NULL WHERE id = ?1", [id])?;
+                summary.merged_languages += 1;
+                id
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO languages (name, code, flag_emoji, created_at) VALUES (?1, ?2, ?3, ?4)",
+                    (&l.name, &l.code, &l.flag_emoji, &l.created_at),
+                )?;
+                summary.languages += 1;
+                conn.last_insert_rowid()
+            }
+        };
+        language_ids.insert(l.id, id);
+    }
+
+    let mut vocabulary_ids = HashMap::new();
+    for v in &archive.vocabulary {
+        conn.execute(
+            "INSERT INTO vocabulary (language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (language_ids[&v.language_id], &v.word, &v.translation, &v.pronunciation, &v.example_sentence, v.difficulty_level, &v.created_at),
+        )?;
+        vocabulary_ids.insert(v.id, conn.last_insert_rowid());
+        summary.vocabulary += 1;
+    }
+
+    let mut flashcard_ids = HashMap::new();
+    for f in &archive.flashcards {
+        conn.execute(
+            "INSERT INTO flashcards (vocabulary_id, ease_factor, interval_days, repetitions, next_review, last_reviewed, stability, difficulty, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (
+                vocabulary_ids[&f.vocabulary_id],
+                f.ease_factor,
+                f.interval_days,
+                f.repetitions,
+                &f.next_review,
+                &f.last_reviewed,
+                f.stability,
+                f.difficulty,
+                &f.created_at,
+            ),
+        )?;
+        flashcard_ids.insert(f.id, conn.last_insert_rowid());
+        summary.flashcards += 1;
+    }
+
+    for r in &archive.review_log {
+        conn.execute(
+            "INSERT INTO review_log (flashcard_id, reviewed_at, quality, elapsed_days, duration_ms,
+                                     prev_ease_factor, prev_interval_days, prev_repetitions, prev_stability, prev_difficulty,
+                                     prev_next_review, prev_last_reviewed, new_ease_factor, new_interval_days)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            (
+                flashcard_ids[&r.flashcard_id],
+                &r.reviewed_at,
+                r.quality,
+                r.elapsed_days,
+                r.duration_ms,
+                r.prev_ease_factor,
+                r.prev_interval_days,
+                r.prev_repetitions,
+                r.prev_stability,
+                r.prev_difficulty,
+                &r.prev_next_review,
+                &r.prev_last_reviewed,
+                r.new_ease_factor,
+                r.new_interval_days,
+            ),
+        )?;
+    }
+
+    // A reused language keeps its own scheduler settings
+    for s in &archive.scheduler_settings {
+        let weights = s
+            .fsrs_weights
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        conn.execute(
+            "INSERT INTO scheduler_settings (language_id, scheduler, desired_retention, fsrs_weights, fitted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5) ON CONFLICT(language_id) DO NOTHING",
+            (language_ids[&s.language_id], s.scheduler.as_str(), s.desired_retention, &weights, &s.fitted_at),
+        )?;
+    }
+
+    let mut tech_space_ids = HashMap::new();
+    for t in &archive.tech_spaces {
+        let existing: Option<i64> = conn
+            .query_row("SELECT id FROM tech_spaces WHERE name = ?1", [&t.name], |row| row.get(0))
+            .optional()?;
+        let id = match existing {
+            Some(id) => {
+                conn.execute("UPDATE tech_spaces SET deleted_at = NULL WHERE id = ?1", [id])?;
+                summary.merged_tech_spaces += 1;
+                id
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO tech_spaces (name, description, icon, created_at) VALUES (?1, ?2, ?3, ?4)",
+                    (&t.name, &t.description, &t.icon, &t.created_at),
+                )?;
+                summary.tech_spaces += 1;
+                conn.last_insert_rowid()
+            }
+        };
+        tech_space_ids.insert(t.id, id);
+    }
+
+    for s in &archive.code_snippets {
+        conn.execute(
+            "INSERT INTO code_snippets (tech_space_id, title, description, code, language, tags, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (tech_space_ids[&s.tech_space_id], &s.title, &s.description, &s.code, &s.language, &s.tags, &s.created_at, &s.updated_at),
+        )?;
+        summary.code_snippets += 1;
+    }
+
+    let mut project_ids = HashMap::new();
+    for p in &archive.projects {
+        conn.execute(
+            "INSERT INTO projects (name, description, status, priority, start_date, end_date, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (&p.name, &p.description, &p.status, &p.priority, &p.start_date, &p.end_date, &p.created_at, &p.updated_at),
+        )?;
+        project_ids.insert(p.id, conn.last_insert_rowid());
+        summary.projects += 1;
+    }
+
+    for t in &archive.tasks {
+        conn.execute(
+            "INSERT INTO tasks (project_id, title, description, status, priority, due_date, completed_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (project_ids[&t.project_id], &t.title, &t.description, &t.status, &t.priority, &t.due_date, &t.completed_at, &t.created_at, &t.updated_at),
+        )?;
+        summary.tasks += 1;
+    }
+
+    for e in &archive.events {
+        conn.execute(
+            "INSERT INTO events (title, description, event_date, start_time, end_time, event_type, priority, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (&e.title, &e.description, &e.event_date, &e.start_time, &e.end_time, &e.event_type, &e.priority, &e.created_at, &e.updated_at),
+        )?;
+        summary.events += 1;
+    }
+
+    for n in &archive.notes {
+        conn.execute(
+            "INSERT INTO notes (title, content, note_date, tags, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (&n.title, &n.content, &n.note_date, &n.tags, &n.created_at, &n.updated_at),
+        )?;
+        summary.notes += 1;
+    }
+
+    // Vault values are bound to the row they were encrypted in, so vault rows
+    // keep their archived ids where those are free; the rest are re-encrypted
+    // for their new row
+    let mut account_ids = HashMap::new();
+    for a in &archive.personal_accounts {
+        conn.execute(
+            "INSERT INTO personal_accounts (id, title, email, password, website, notes, category, password_strength, totp_secret, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            (
+                free_id(conn, "personal_accounts", a.id)?,
+                &a.title, &a.email, &a.password, &a.website, &a.notes, &a.category, a.password_strength, &a.totp_secret, &a.created_at, &a.updated_at,
+            ),
+        )?;
+        let id = conn.last_insert_rowid();
+        for column in ["email", "password", "totp_secret"] {
+            rebind_value(conn, key, "personal_accounts", column, a.id, id)?;
+        }
+        import_custom_fields(conn, key, FieldOwner::Account, id, &a.custom_fields)?;
+        account_ids.insert(a.id, id);
+        summary.personal_accounts += 1;
+    }
+
+    for p in &archive.password_history {
+        conn.execute(
+            "INSERT INTO password_history (id, account_id, password, replaced_at) VALUES (?1, ?2, ?3, ?4)",
+            (free_id(conn, "password_history", p.id)?, account_ids[&p.account_id], &p.password, &p.replaced_at),
+        )?;
+        rebind_value(conn, key, "password_history", "password", p.id, conn.last_insert_rowid())?;
+    }
+
+    for i in &archive.personal_info {
+        conn.execute(
+            "INSERT INTO personal_info (id, title, content, category, is_sensitive, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (free_id(conn, "personal_info", i.id)?, &i.title, &i.content, &i.category, i.is_sensitive, &i.created_at, &i.updated_at),
+        )?;
+        let id = conn.last_insert_rowid();
+        if i.is_sensitive {
+            rebind_value(conn, key, "personal_info", "content", i.id, id)?;
+        }
+        import_custom_fields(conn, key, FieldOwner::Info, id, &i.custom_fields)?;
+        summary.personal_info += 1;
+    }
+
+    Ok(summary)
+}
+
+fn read_languages(conn: &Connection) -> Result<Vec<Language>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, name, code, flag_emoji, created_at FROM languages WHERE deleted_at IS NULL ORDER BY id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Language {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            code: row.get(2)?,
+            flag_emoji: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_vocabulary(conn: &Connection) -> Result<Vec<Vocabulary>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at
+         FROM vocabulary WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Vocabulary {
+            id: row.get(0)?,
+            language_id: row.get(1)?,
+            word: row.get(2)?,
+            translation: row.get(3)?,
+            pronunciation: row.get(4)?,
+            example_sentence: row.get(5)?,
+            difficulty_level: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_flashcards(conn: &Connection) -> Result<Vec<Flashcard>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, vocabulary_id, ease_factor, interval_days, repetitions, next_review, last_reviewed, stability, difficulty, created_at
+         FROM flashcards WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Flashcard {
+            id: row.get(0)?,
+            vocabulary_id: row.get(1)?,
+            ease_factor: row.get(2)?,
+            interval_days: row.get(3)?,
+            repetitions: row.get(4)?,
+            next_review: row.get(5)?,
+            last_reviewed: row.get(6)?,
+            stability: row.get(7)?,
+            difficulty: row.get(8)?,
+            created_at: row.get(9)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+// Reviews of the exported flashcards, oldest first
+fn read_review_log(conn: &Connection) -> Result<Vec<ArchivedReview>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT r.flashcard_id, r.reviewed_at, r.quality, r.elapsed_days, r.duration_ms,
+                r.prev_ease_factor, r.prev_interval_days, r.prev_repetitions, r.prev_stability, r.prev_difficulty,
+                r.prev_next_review, r.prev_last_reviewed, r.new_ease_factor, r.new_interval_days
+         FROM review_log r JOIN flashcards f ON f.id = r.flashcard_id
+         WHERE f.deleted_at IS NULL ORDER BY r.id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ArchivedReview {
+            flashcard_id: row.get(0)?,
+            reviewed_at: row.get(1)?,
+            quality: row.get(2)?,
+            elapsed_days: row.get(3)?,
+            duration_ms: row.get(4)?,
+            prev_ease_factor: row.get(5)?,
+            prev_interval_days: row.get(6)?,
+            prev_repetitions: row.get(7)?,
+            prev_stability: row.get(8)?,
+            prev_difficulty: row.get(9)?,
+            prev_next_review: row.get(10)?,
+            prev_last_reviewed: row.get(11)?,
+            new_ease_factor: row.get(12)?,
+            new_interval_days: row.get(13)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_scheduler_settings(conn: &Connection) -> Result<Vec<SchedulerSettings>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT s.language_id FROM scheduler_settings s JOIN languages l ON l.id = s.language_id
+         WHERE l.deleted_at IS NULL ORDER BY s.language_id"
+    )?;
+    let language_ids = stmt.query_map([], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>, _>>()?;
+    language_ids.into_iter().map(|id| load_scheduler_settings(conn, id)).collect()
+}
+
+fn read_tech_spaces(conn: &Connection) -> Result<Vec<TechSpace>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, name, description, icon, created_at FROM tech_spaces WHERE deleted_at IS NULL ORDER BY id")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TechSpace {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            icon: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_code_snippets(conn: &Connection) -> Result<Vec<CodeSnippet>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, tech_space_id, title, description, code, language, tags, created_at, updated_at
+         FROM code_snippets WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(CodeSnippet {
+            id: row.get(0)?,
+            tech_space_id: row.get(1)?,
+            title: row.get(2)?,
+            description: row.get(3)?,
+            code: row.get(4)?,
+            language: row.get(5)?,
+            tags: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_projects(conn: &Connection) -> Result<Vec<Project>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, description, status, priority, start_date, end_date, created_at, updated_at
+         FROM projects WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Project {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            status: row.get(3)?,
+            priority: row.get(4)?,
+            start_date: row.get(5)?,
+            end_date: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_tasks(conn: &Connection) -> Result<Vec<Task>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, project_id, title, description, status, priority, due_date, completed_at, created_at, updated_at
+         FROM tasks WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Task {
+            id: row.get(0)?,
+            project_id: row.get(1)?,
+            title: row.get(2)?,
+            description: row.get(3)?,
+            status: row.get(4)?,
+            priority: row.get(5)?,
+            due_date: row.get(6)?,
+            completed_at: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_events(conn: &Connection) -> Result<Vec<Event>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, description, event_date, start_time, end_time, event_type, priority, created_at, updated_at
+         FROM events WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Event {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            description: row.get(2)?,
+            event_date: row.get(3)?,
+            start_time: row.get(4)?,
+            end_time: row.get(5)?,
+            event_type: row.get(6)?,
+            priority: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+fn read_notes(conn: &Connection) -> Result<Vec<Note>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, note_date, tags, created_at, updated_at FROM notes WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Note {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get(2)?,
+            note_date: row.get(3)?,
+            tags: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+// Stored values are exported as-is: email, password and hidden custom
+// fields stay encrypted
+fn read_personal_accounts(conn: &Connection) -> Result<Vec<PersonalAccount>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, email, password, website, notes, category, password_strength, totp_secret, created_at, updated_at
+         FROM personal_accounts WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(PersonalAccount {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            email: row.get(2)?,
+            password: row.get(3)?,
+            website: row.get(4)?,
+            notes: row.get(5)?,
+            category: row.get(6)?,
+            password_strength: row.get(7)?,
+            totp_secret: row.get(8)?,
+            custom_fields: Vec::new(),
+            created_at: row.get(9)?,
+            updated_at: row.get(10)?,
+        })
+    })?;
+    let mut fields = load_all_custom_fields(conn, FieldOwner::Account, SecretValues::Stored)?;
+    rows.map(|row| {
+        let account = row?;
+        Ok(PersonalAccount { custom_fields: fields.remove(&account.id).unwrap_or_default(), ..account })
+    })
+    .collect()
+}
+
+// Previous passwords of the exported accounts, still encrypted
+fn read_password_history(conn: &Connection) -> Result<Vec<ArchivedPassword>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT h.id, h.account_id, h.password, h.replaced_at
+         FROM password_history h JOIN personal_accounts a ON a.id = h.account_id
+         WHERE a.deleted_at IS NULL ORDER BY h.id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ArchivedPassword {
+            id: row.get(0)?,
+            account_id: row.get(1)?,
+            password: row.get(2)?,
+            replaced_at: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+// Sensitive content stays encrypted
+fn read_personal_info(conn: &Connection) -> Result<Vec<PersonalInfo>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, category, is_sensitive, created_at, updated_at
+         FROM personal_info WHERE deleted_at IS NULL ORDER BY id"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(PersonalInfo {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get(2)?,
+            category: row.get(3)?,
+            is_sensitive: row.get(4)?,
+            custom_fields: Vec::new(),
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    })?;
+    let mut fields = load_all_custom_fields(conn, FieldOwner::Info, SecretValues::Stored)?;
+    rows.map(|row| {
+        let info = row?;
+        Ok(PersonalInfo { custom_fields: fields.remove(&info.id).unwrap_or_default(), ..info })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn language(name: &str, code: &str) -> CreateLanguageRequest {
+        CreateLanguageRequest { name: name.to_string(), code: code.to_string(), flag_emoji: "🏳".to_string() }
+    }
+
+    fn project(name: &str) -> CreateProjectRequest {
+        CreateProjectRequest {
+            name: name.to_string(),
+            description: None,
+            status: "active".to_string(),
+            priority: "high".to_string(),
+            start_date: None,
+            end_date: None,
+        }
+    }
+
+    // A workspace with a word under Spanish and a task under a project
+    fn source() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        let spanish = db.create_language(language("Spanish", "es")).unwrap();
+        db.create_vocabulary(CreateVocabularyRequest {
+            language_id: spanish.id,
+            word: "casa".to_string(),
+            translation: "house".to_string(),
+            pronunciation: None,
+            example_sentence: None,
+            difficulty_level: 1,
+        })
+        .unwrap();
+        let project = db.create_project(project("Website")).unwrap();
+        db.create_task(CreateTaskRequest {
+            project_id: project.id,
+            title: "Write copy".to_string(),
+            description: None,
+            status: "todo".to_string(),
+            priority: "low".to_string(),
+            due_date: None,
+        })
+        .unwrap();
+        db
+    }
+
+    fn export(db: &Database, dir: &Path) -> String {
+        let path = dir.join("archive.json");
+        db.export_workspace(&path.to_string_lossy()).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn rewrite(path: &str, edit: impl FnOnce(&mut Value)) {
+        let mut archive: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        edit(&mut archive);
+        std::fs::write(path, archive.to_string()).unwrap();
+    }
+
+    #[test]
+    fn merge_remaps_ids_and_reuses_languages() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(&source(), dir.path());
+
+        let target = Database::open_in_memory().unwrap();
+        target.create_language(language("German", "de")).unwrap();
+        let spanish = target.create_language(language("Spanish", "es")).unwrap();
+        target.create_project(project("Garden")).unwrap();
+
+        let summary = target.import_workspace(&path, ImportMode::Merge).unwrap();
+        assert_eq!((summary.languages, summary.merged_languages), (0, 1));
+        assert_eq!((summary.vocabulary, summary.flashcards), (1, 1));
+        assert_eq!((summary.projects, summary.tasks), (1, 1));
+
+        let words = target.get_vocabulary_by_language(spanish.id).unwrap();
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].word, "casa");
+
+        // The task follows its project to the project's new id
+        let website = target.get_projects().unwrap().into_iter().find(|p| p.name == "Website").unwrap();
+        let tasks = target.get_tasks_by_project(website.id).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].project_id, website.id);
+        assert_eq!(target.get_projects().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn replace_leaves_only_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(&source(), dir.path());
+
+        let target = Database::open_in_memory().unwrap();
+        target.create_language(language("German", "de")).unwrap();
+        target.create_project(project("Garden")).unwrap();
+
+        let summary = target.import_workspace(&path, ImportMode::Replace).unwrap();
+        assert_eq!((summary.languages, summary.projects, summary.tasks), (1, 1, 1));
+
+        let languages: Vec<_> = target.get_languages().unwrap().into_iter().map(|l| l.name).collect();
+        assert_eq!(languages, ["Spanish"]);
+        let projects: Vec<_> = target.get_projects().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(projects, ["Website"]);
+    }
+
+    #[test]
+    fn refuses_dangling_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(&source(), dir.path());
+        rewrite(&path, |archive| archive["tasks"][0]["project_id"] = json!(999));
+
+        let target = Database::open_in_memory().unwrap();
+        let err = target.import_workspace(&path, ImportMode::Merge).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert!(err.message().contains("missing project 999"), "{}", err.message());
+        assert!(target.get_languages().unwrap().is_empty());
+        assert!(target.get_projects().unwrap().is_empty());
+    }
+
+    #[test]
+    fn refuses_rows_that_fail_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(&source(), dir.path());
+
+        let cases = [
+            ("projects", "status", json!("done")),
+            ("tasks", "due_date", json!("next week")),
+            ("languages", "code", json!("ESP")),
+            ("vocabulary", "word", json!("  ")),
+        ];
+        let target = Database::open_in_memory().unwrap();
+        for (table, field, value) in cases {
+            let edited = dir.path().join(format!("{}.json", table));
+            std::fs::copy(&path, &edited).unwrap();
+            let edited = edited.to_string_lossy().to_string();
+            rewrite(&edited, |archive| archive[table][0][field] = value);
+
+            let err = target.import_workspace(&edited, ImportMode::Merge).unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+            assert_eq!(err.field(), Some(field));
+            assert!(err.message().starts_with("Archived "), "{}", err.message());
+        }
+        assert!(target.get_languages().unwrap().is_empty());
+        assert!(target.get_projects().unwrap().is_empty());
+    }
+
+    #[test]
+    fn merged_vault_entries_are_encrypted_for_their_new_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        let account = db
+            .create_personal_account(CreatePersonalAccountRequest {
+                title: "Bank".to_string(),
+                email: "me@example.com".to_string(),
+                password: "first".to_string(),
+                website: None,
+                notes: None,
+                category: "banking".to_string(),
+                totp_secret: None,
+                custom_fields: vec![CustomFieldInput {
+                    id: None,
+                    label: "PIN".to_string(),
+                    field_type: CustomFieldType::Hidden,
+                    value: "1234".to_string(),
+                }],
+            })
+            .unwrap();
+        // A new password, so there is history to carry over too
+        db.update_personal_account(account.id, UpdatePersonalAccountRequest {
+            title: account.title.clone(),
+            email: account.email.clone(),
+            password: Some("second".to_string()),
+            website: None,
+            notes: None,
+            category: account.category.clone(),
+            totp_secret: None,
+            custom_fields: vec![CustomFieldInput {
+                id: Some(account.custom_fields[0].id),
+                label: "PIN".to_string(),
+                field_type: CustomFieldType::Hidden,
+                value: "1234".to_string(),
+            }],
+        })
+        .unwrap();
+        db.create_personal_info(CreatePersonalInfoRequest {
+            title: "Passport".to_string(),
+            content: "X1234567".to_string(),
+            category: "documents".to_string(),
+            is_sensitive: true,
+            custom_fields: Vec::new(),
+        })
+        .unwrap();
+        let path = export(&db, dir.path());
+
+        // Merging into the workspace it came from, every id is taken
+        db.lock_vault().unwrap();
+        let err = db.import_workspace(&path, ImportMode::Merge).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        db.unlock_vault("correct horse battery staple").unwrap();
+        db.import_workspace(&path, ImportMode::Merge).unwrap();
+
+        let copy = db.get_personal_accounts().unwrap().into_iter().find(|a| a.id != account.id).unwrap();
+        assert_eq!(copy.email, "me@example.com");
+        assert_eq!(db.reveal_secret(copy.id, SecretField::Password).unwrap(), "second");
+        let history = db.get_password_history(copy.id).unwrap();
+        assert_eq!(db.reveal_secret(history[0].id, SecretField::PreviousPassword).unwrap(), "first");
+        assert_eq!(db.reveal_secret(copy.custom_fields[0].id, SecretField::CustomField).unwrap(), "1234");
+        let infos = db.get_personal_info().unwrap();
+        assert_eq!(infos.len(), 2);
+        for info in infos {
+            assert_eq!(db.reveal_secret(info.id, SecretField::Content).unwrap(), "X1234567");
+        }
+
+        // A fresh workspace keeps the archived ids
+        let target = Database::open_in_memory().unwrap();
+        target.import_workspace(&path, ImportMode::Replace).unwrap();
+        target.unlock_vault("correct horse battery staple").unwrap();
+        assert_eq!(target.reveal_secret(account.id, SecretField::Password).unwrap(), "second");
+    }
+}
@@ -0,0 +1,472 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use super::connection::{lock, with_suffix, IN_MEMORY};
+use super::file_encryption::DatabaseKey;
+use super::migrations;
+use chrono::{DateTime, Datelike, NaiveDateTime, SubsecRound, Utc};
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Snapshots are named `yotion-<workspace id>-<timestamp>.db`; older ones
+// were `yotion-<timestamp>.db`, to the second and without the id
+const SNAPSHOT_PREFIX: &str = "yotion-";
+const SNAPSHOT_EXTENSION: &str = ".db";
+const SNAPSHOT_TIMESTAMP: &str = "%Y%m%d-%H%M%S-%3f";
+const LEGACY_SNAPSHOT_TIMESTAMP: &str = "%Y%m%d-%H%M%S";
+
+// A file name recognised as a snapshot
+struct SnapshotName {
+    // None for the older names, which don't say
+    workspace_id: Option<i64>,
+    taken: NaiveDateTime,
+}
+
+impl Database {
+    pub fn get_backup_settings(&self) -> Result<BackupSettings, AppError> {
+        Ok(self.require_workspace()?.backup)
+    }
+
+    pub fn update_backup_settings(&self, settings: BackupSettings) -> Result<BackupSettings, AppError> {
+        settings.validate()?;
+
+        let settings = BackupSettings {
+            folder: sanitize_optional_string(settings.folder),
+            ..settings
+        };
+        let id = self.require_workspace()?.id;
+
+        let workspace = {
+            let mut registry = lock(&self.registry)?;
+            let workspace = registry.get_mut(id)?;
+            workspace.backup = settings.clone();
+            let workspace = workspace.clone();
+            registry.save()?;
+            workspace
+        };
+        *lock(&self.current_workspace)? = Some(workspace);
+
+        Ok(settings)
+    }
+
+    // Writes a consistent snapshot of the open workspace using SQLite's online
+    // backup API, then prunes old snapshots according to the retention policy
+    pub fn create_backup(&self) -> Result<BackupInfo, AppError> {
+        let workspace = self.require_workspace()?;
+        let folder = self.backup_folder(&workspace)?;
+        std::fs::create_dir_all(&folder)?;
+
+        // Names are to the millisecond; one already taken moves this one on
+        let mut now = Utc::now().trunc_subsecs(3);
+        let (file_name, path) = loop {
+            let file_name = snapshot_name(workspace.id, now);
+            let path = folder.join(&file_name);
+            if !path.exists() {
+                break (file_name, path);
+            }
+            now += chrono::Duration::milliseconds(1);
+        };
+
+        // Build the snapshot under a temporary name so a half-written file is
+        // never mistaken for a usable backup
+        let partial = folder.join(format!("{}.partial", file_name));
+        let result = (|| -> Result<(), AppError> {
+            let mut dst = Connection::open(&partial)?;
+            // Snapshots of an encrypted database are encrypted the same way
+            if let Some(key) = lock(&self.database_key)?.as_ref() {
+                key.apply(&dst)?;
+            }
+            {
+                let src = self.reader()?;
+                let backup = Backup::new(&src, &mut dst)?;
+                backup.run_to_completion(256, Duration::from_millis(10), None)?;
+            }
+            // Snapshots are standalone files; don't leave them needing a -wal
+            dst.pragma_update_and_check(None, "journal_mode", "DELETE", |row| row.get::<_, String>(0))?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+        std::fs::rename(&partial, &path)?;
+
+        self.prune_backups(&workspace, &folder)?;
+
+        Ok(BackupInfo {
+            file_name,
+            path: path.to_string_lossy().to_string(),
+            created_at: now.to_rfc3339(),
+            size_bytes: std::fs::metadata(&path)?.len(),
+        })
+    }
+
+    // Newest first
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, AppError> {
+        let workspace = self.require_workspace()?;
+        let folder = self.backup_folder(&workspace)?;
+        list_snapshots(&folder, |name| name.workspace_id.is_none_or(|id| id == workspace.id))
+    }
+
+    // Called periodically; takes a snapshot when the newest one is older than
+    // the configured interval. Returns the snapshot if one was taken.
+    pub fn run_scheduled_backup(&self) -> Result<Option<BackupInfo>, AppError> {
+        let workspace = match lock(&self.current_workspace)?.clone() {
+            Some(workspace) => workspace,
+            None => return Ok(None),
+        };
+        // An encrypted workspace can't be read until the vault is unlocked
+        if workspace.backup.interval_hours == 0 || self.database_locked()? {
+            return Ok(None);
+        }
+
+        let folder = self.backup_folder(&workspace)?;
+        let due = match list_snapshots(&folder, |name| name.workspace_id == Some(workspace.id))?.first() {
+            Some(latest) => {
+                let taken = DateTime::parse_from_rfc3339(&latest.created_at)
+                    .map_err(|e| AppError::Io(e.to_string()))?;
+                Utc::now().signed_duration_since(taken) >= chrono::Duration::hours(workspace.backup.interval_hours)
+            }
+            None => true,
+        };
+
+        if due {
+            self.create_backup().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    // Replaces the live database with a snapshot after checking that it is
+    // intact and not from a newer schema. The current state is snapshotted
+    // first so a restore can itself be undone.
+    pub fn restore_backup(&self, file_name: &str) -> Result<(), AppError> {
+        let workspace = self.require_workspace()?;
+        if workspace.path == IN_MEMORY {
+            return Err(AppError::validation("An in-memory database cannot be restored from a snapshot".to_string()));
+        }
+        let folder = self.backup_folder(&workspace)?;
+
+        let own = |name: SnapshotName| name.workspace_id.is_none_or(|id| id == workspace.id);
+        if !parse_snapshot_name(file_name).is_some_and(own) {
+            return Err(AppError::validation(format!("'{}' is not a YOTION snapshot of this workspace", file_name)));
+        }
+        let snapshot = folder.join(file_name);
+        if !snapshot.is_file() {
+            return Err(AppError::NotFound(format!("Snapshot {} not found", file_name)));
+        }
+        let key = self.key_for_file(&snapshot)?;
+        verify_snapshot(&snapshot, key.as_ref())?;
+
+        // Under the writer lock, so nothing is written between the safety
+        // snapshot and the swap. The copy is staged first, as the safety
+        // snapshot's pruning may remove the very file being restored. The
+        // swap reopens the file, which also migrates an older snapshot
+        // forward, and puts the current file back if that fails.
+        let live = PathBuf::from(&workspace.path);
+        let staging = with_suffix(&live, ".restore");
+        let restored = self.swap_database_file(&live, key, |_| {
+            std::fs::copy(&snapshot, &staging)?;
+            if let Err(e) = self.create_backup() {
+                let _ = std::fs::remove_file(&staging);
+                return Err(e);
+            }
+            Ok(staging.clone())
+        });
+
+        // The restored database has its own master password
+        *lock(&self.vault)? = None;
+        restored
+    }
+
+    pub(super) fn require_workspace(&self) -> Result<Workspace, AppError> {
+        lock(&self.current_workspace)?
+            .clone()
+            .ok_or_else(|| AppError::NotFound("No workspace is open".to_string()))
+    }
+
+    fn backup_folder(&self, workspace: &Workspace) -> Result<PathBuf, AppError> {
+        match &workspace.backup.folder {
+            Some(folder) => Ok(PathBuf::from(folder)),
+            None => Ok(self.default_backup_folder(workspace)?),
+        }
+    }
+
+    fn default_backup_folder(&self, workspace: &Workspace) -> Result<PathBuf, AppError> {
+        let registry = lock(&self.registry)?;
+        Ok(registry.data_dir().join("backups").join(workspace.id.to_string()))
+    }
+
+    // Keeps the newest snapshot of each of the last `keep_daily` days and of
+    // each of the last `keep_weekly` ISO weeks; the newest snapshot always
+    // stays. Only this workspace's snapshots are touched: a custom folder may
+    // be shared, so older unlabelled ones are only pruned from the default
+    // folder, which is the workspace's own.
+    fn prune_backups(&self, workspace: &Workspace, folder: &Path) -> Result<(), AppError> {
+        let settings = &workspace.backup;
+        let own_folder = folder == self.default_backup_folder(workspace)?;
+        let snapshots = list_snapshots(folder, |name| match name.workspace_id {
+            Some(id) => id == workspace.id,
+            None => own_folder,
+        })?;
+
+        let mut keep = HashSet::new();
+        let mut days = Vec::new();
+        let mut weeks = Vec::new();
+
+        for (index, snapshot) in snapshots.iter().enumerate() {
+            let taken = match parse_snapshot_name(&snapshot.file_name) {
+                Some(name) => name.taken,
+                None => continue,
+            };
+            let day = taken.date();
+            let week = (taken.iso_week().year(), taken.iso_week().week());
+
+            if index == 0 {
+                keep.insert(snapshot.file_name.clone());
+            }
+            if !days.contains(&day) && (days.len() as i64) < settings.keep_daily {
+                days.push(day);
+                keep.insert(snapshot.file_name.clone());
+            }
+            if !weeks.contains(&week) && (weeks.len() as i64) < settings.keep_weekly {
+                weeks.push(week);
+                keep.insert(snapshot.file_name.clone());
+            }
+        }
+
+        for snapshot in snapshots.iter().filter(|s| !keep.contains(&s.file_name)) {
+            std::fs::remove_file(&snapshot.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn snapshot_name(workspace_id: i64, taken: DateTime<Utc>) -> String {
+    format!("{}{}-{}{}", SNAPSHOT_PREFIX, workspace_id, taken.format(SNAPSHOT_TIMESTAMP), SNAPSHOT_EXTENSION)
+}
+
+fn parse_snapshot_name(file_name: &str) -> Option<SnapshotName> {
+    let stem = file_name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_EXTENSION)?;
+    if let Ok(taken) = NaiveDateTime::parse_from_str(stem, LEGACY_SNAPSHOT_TIMESTAMP) {
+        return Some(SnapshotName { workspace_id: None, taken });
+    }
+    let (workspace_id, stamp) = stem.split_once('-')?;
+    Some(SnapshotName {
+        workspace_id: Some(workspace_id.parse().ok()?),
+        taken: NaiveDateTime::parse_from_str(stamp, SNAPSHOT_TIMESTAMP).ok()?,
+    })
+}
+
+// Snapshots in `folder` whose names pass `include`, newest first
+fn list_snapshots(folder: &Path, include: impl Fn(&SnapshotName) -> bool) -> Result<Vec<BackupInfo>, AppError> {
+    if !folder.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(folder)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if let Some(name) = parse_snapshot_name(&file_name).filter(|name| include(name)) {
+            snapshots.push((
+                name.taken,
+                BackupInfo {
+                    path: entry.path().to_string_lossy().to_string(),
+                    created_at: name.taken.and_utc().to_rfc3339(),
+                    size_bytes: entry.metadata()?.len(),
+                    file_name,
+                },
+            ));
+        }
+    }
+
+    snapshots.sort_by_key(|(taken, _)| std::cmp::Reverse(*taken));
+    Ok(snapshots.into_iter().map(|(_, snapshot)| snapshot).collect())
+}
+
+fn verify_snapshot(path: &Path, key: Option<&DatabaseKey>) -> Result<(), AppError> {
+    let invalid = |e: rusqlite::Error| AppError::validation(format!("Snapshot is not a readable database: {}", e));
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(invalid)?;
+    if let Some(key) = key {
+        key.apply(&conn)?;
+    }
+    let integrity: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(invalid)?;
+    if integrity != "ok" {
+        return Err(AppError::validation(format!("Snapshot failed the integrity check: {}", integrity)));
+    }
+
+    let version = migrations::current_version(&conn)?;
+    if version == 0 {
+        return Err(AppError::validation("Snapshot is not a YOTION database".to_string()));
+    }
+    if version > migrations::latest_version() {
+        return Err(AppError::validation(format!(
+            "Snapshot uses schema version {}, which is newer than this version of YOTION supports ({})",
+            version,
+            migrations::latest_version()
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(name: &str, code: &str) -> CreateLanguageRequest {
+        CreateLanguageRequest { name: name.to_string(), code: code.to_string(), flag_emoji: "🏳".to_string() }
+    }
+
+    fn touch(folder: &Path, file_name: &str) {
+        std::fs::write(folder.join(file_name), b"").unwrap();
+    }
+
+    fn remaining(folder: &Path) -> Vec<String> {
+        let mut names: Vec<_> = std::fs::read_dir(folder)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+        db.create_language(language("Spanish", "es")).unwrap();
+
+        let snapshot = db.create_backup().unwrap();
+        assert!(Path::new(&snapshot.path).starts_with(dir.path().join("backups")));
+        db.create_language(language("German", "de")).unwrap();
+
+        let listed: Vec<_> = db.list_backups().unwrap().into_iter().map(|b| b.file_name).collect();
+        assert_eq!(listed, [snapshot.file_name.as_str()]);
+
+        db.restore_backup(&snapshot.file_name).unwrap();
+        let names: Vec<_> = db.get_languages().unwrap().into_iter().map(|l| l.name).collect();
+        assert_eq!(names, ["Spanish"]);
+
+        // The state before the restore was kept as a snapshot of its own,
+        // which as the newest of the day replaced the restored one
+        let listed = db.list_backups().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_ne!(listed[0].file_name, snapshot.file_name);
+        db.restore_backup(&listed[0].file_name).unwrap();
+        assert_eq!(db.get_languages().unwrap().len(), 2);
+        assert!(!with_suffix(&dir.path().join("main.db"), ".previous").exists());
+    }
+
+    #[test]
+    fn refuses_snapshots_from_a_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+        let snapshot = db.create_backup().unwrap();
+        Connection::open(&snapshot.path)
+            .unwrap()
+            .pragma_update(None, "user_version", migrations::latest_version() + 1)
+            .unwrap();
+
+        let err = db.restore_backup(&snapshot.file_name).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert!(err.message().contains("newer"), "{}", err.message());
+
+        let err = db.restore_backup("../main.db").unwrap_err();
+        assert!(err.message().contains("is not a YOTION snapshot"), "{}", err.message());
+        assert_eq!(db.list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn prune_keeps_the_newest_per_day_and_week() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("snapshots");
+        std::fs::create_dir(&folder).unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+        db.update_backup_settings(BackupSettings {
+            folder: Some(folder.to_string_lossy().to_string()),
+            interval_hours: 24,
+            keep_daily: 2,
+            keep_weekly: 2,
+        })
+        .unwrap();
+        let workspace = db.require_workspace().unwrap();
+        let id = workspace.id;
+
+        let ours = [
+            "20240315-100000-000",
+            "20240315-090000-000",
+            "20240314-120000-000",
+            // Same ISO week as the 15th
+            "20240312-120000-000",
+            "20240305-120000-000",
+            "20240220-120000-000",
+        ];
+        for stamp in ours {
+            touch(&folder, &format!("yotion-{}-{}.db", id, stamp));
+        }
+        // Another workspace's snapshot and an unlabelled one in a shared folder
+        touch(&folder, &format!("yotion-{}-20200101-000000-000.db", id + 1));
+        touch(&folder, "yotion-20200101-000000.db");
+
+        db.prune_backups(&workspace, &folder).unwrap();
+        let mut expected = vec![
+            format!("yotion-{}-20240305-120000-000.db", id),
+            format!("yotion-{}-20240314-120000-000.db", id),
+            format!("yotion-{}-20240315-100000-000.db", id),
+            format!("yotion-{}-20200101-000000-000.db", id + 1),
+            "yotion-20200101-000000.db".to_string(),
+        ];
+        expected.sort();
+        assert_eq!(remaining(&folder), expected);
+    }
+
+    #[test]
+    fn prune_always_keeps_the_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+        db.update_backup_settings(BackupSettings { folder: None, interval_hours: 0, keep_daily: 0, keep_weekly: 0 })
+            .unwrap();
+
+        db.create_backup().unwrap();
+        let newest = db.create_backup().unwrap();
+        let listed: Vec<_> = db.list_backups().unwrap().into_iter().map(|b| b.file_name).collect();
+        assert_eq!(listed, [newest.file_name]);
+        // Scheduling is off
+        assert!(db.run_scheduled_backup().unwrap().is_none());
+    }
+
+    #[test]
+    fn scheduled_backup_waits_for_the_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+        assert!(db.run_scheduled_backup().unwrap().is_some());
+        assert!(db.run_scheduled_backup().unwrap().is_none());
+        assert_eq!(db.list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn parses_current_and_legacy_names() {
+        let name = parse_snapshot_name("yotion-3-20240315-100000-250.db").unwrap();
+        assert_eq!(name.workspace_id, Some(3));
+        assert_eq!(name.taken.and_utc().timestamp_subsec_millis(), 250);
+
+        let legacy = parse_snapshot_name("yotion-20240315-100000.db").unwrap();
+        assert_eq!(legacy.workspace_id, None);
+
+        for other in ["yotion-3-20240315.db", "notes.db", "yotion-x-20240315-100000-000.db", "yotion-20240315-100000.db.partial"] {
+            assert!(parse_snapshot_name(other).is_none(), "{}", other);
+        }
+    }
+}
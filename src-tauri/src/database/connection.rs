@@ -1,836 +1,424 @@
-use rusqlite::Connection;
-use std::path::PathBuf;
-use std::sync::Mutex;
-use crate::errors::AppError;
-use crate::models::*;
-use crate::encryption::{encrypt_sensitive_data, decrypt_sensitive_data};
+use rusqlite::{Connection, OpenFlags};
+use std::ffi::OsString;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use crate::errors::{log_error, AppError};
+use crate::models::Workspace;
+use super::file_encryption::DatabaseKey;
+use super::migrations;
+use super::vault::VaultSession;
+use super::workspace::WorkspaceRegistry;
+
+// Read-only connections kept open alongside the single writer
+const READER_COUNT: usize = 4;
+// How long a connection waits on a locked database before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// Workspace path of a database that only exists in memory
+pub(super) const IN_MEMORY: &str = ":memory:";
 
 pub struct Database {
-    conn: Mutex<Connection>,
+    writer: Mutex<Option<Connection>>,
+    readers: Vec<Mutex<Option<Connection>>>,
+    next_reader: AtomicUsize,
+    pub(super) current_workspace: Mutex<Option<Workspace>>,
+    pub(super) registry: Mutex<WorkspaceRegistry>,
+    // Present while the vault is unlocked
+    pub(super) vault: Mutex<Option<VaultSession>>,
+    // Key of the open database when it is encrypted
+    pub(super) database_key: Mutex<Option<DatabaseKey>>,
+    // Why the active workspace could not be opened at startup, if it couldn't
+    pub(super) startup_error: Mutex<Option<String>>,
 }
 
-impl Database {
-    pub fn new() -> Result<Self, AppError> {
-        let db_path = Self::get_db_path()?;
-        let conn = Connection::open(db_path)?;
-        
-        // Initialize database schema
-        Self::init_schema(&conn)?;
-        
-        Ok(Database {
-            conn: Mutex::new(conn),
-        })
-    }
-    
-    fn get_db_path() -> Result<PathBuf, AppError> {
-        let mut path = dirs::data_dir().ok_or_else(|| AppError::Io("Could not find data directory".to_string()))?;
-        path.push("yotion");
-        std::fs::create_dir_all(&path)?;
-        path.push("yotion.db");
-        Ok(path)
-    }
-    
-    fn init_schema(conn: &Connection) -> Result<(), AppError> {
-        let schema = include_str!("../schema.sql");
-        conn.execute_batch(schema)?;
-        Ok(())
+// The writer plus its read-only companions for one database file
+pub(super) struct ConnectionSet {
+    writer: Connection,
+    readers: Vec<Connection>,
+    key: Option<DatabaseKey>,
+}
+
+// Borrow of an open pooled connection; only handed out while one is open
+pub(super) struct ConnectionGuard<'a>(MutexGuard<'a, Option<Connection>>);
+
+impl Deref for ConnectionGuard<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.0.as_ref().expect("ConnectionGuard holds an open connection")
     }
 }
 
-// Language methods
-impl Database {
-    pub fn create_language(&self, req: CreateLanguageRequest) -> Result<Language, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO languages (name, code, flag_emoji, created_at) VALUES (?1, ?2, ?3, ?4)",
-            (req.name.clone(), req.code.clone(), req.flag_emoji.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(Language {
-            id,
-            name: req.name,
-            code: req.code,
-            flag_emoji: req.flag_emoji,
-            created_at: now,
-        })
+impl DerefMut for ConnectionGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.0.as_mut().expect("ConnectionGuard holds an open connection")
     }
-    
-    pub fn get_languages(&self) -> Result<Vec<Language>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, code, flag_emoji, created_at FROM languages ORDER BY name")?;
-        let rows = stmt.query_map([], |row| {
-            Ok(Language {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                code: row.get(2)?,
-                flag_emoji: row.get(3)?,
-                created_at: row.get(4)?,
-            })
-        })?;
-        
-        let mut languages = Vec::new();
-        for row in rows {
-            languages.push(row?);
-        }
-        
-        Ok(languages)
+}
+
+// `path` with `suffix` appended to the file name, e.g. the -wal beside a database
+pub(super) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+// Creates an empty database file (and its folder) unless one is already
+// there; `open_connections` never creates one
+pub(super) fn create_database_file(path: &Path) -> Result<(), AppError> {
+    if path == Path::new(IN_MEMORY) || path.exists() {
+        return Ok(());
     }
-    
-    pub fn delete_language(&self, id: i64) -> Result<(), AppError> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM languages WHERE id = ?1", [id])?;
-        Ok(())
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    std::fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+    Ok(())
+}
+
+// Locks a mutex, turning poisoning into an error for this call only. The
+// poison flag is cleared because SQLite rolls back whatever the panicking
+// call left unfinished, so the connection itself is still sound.
+pub(super) fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>, AppError> {
+    mutex.lock().map_err(|_| {
+        mutex.clear_poison();
+        AppError::Database("A previous operation failed unexpectedly; please try again".to_string())
+    })
 }
 
-// Vocabulary methods
 impl Database {
-    pub fn create_vocabulary(&self, req: CreateVocabularyRequest) -> Result<Vocabulary, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO vocabulary (language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            (req.language_id, req.word.clone(), req.translation.clone(), req.pronunciation.clone(), req.example_sentence.clone(), req.difficulty_level, now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        // Create flashcard for spaced repetition
-        self.create_flashcard(id)?;
-        
-        Ok(Vocabulary {
-            id,
-            language_id: req.language_id,
-            word: req.word,
-            translation: req.translation,
-            pronunciation: req.pronunciation,
-            example_sentence: req.example_sentence,
-            difficulty_level: req.difficulty_level,
-            created_at: now,
-        })
-    }
-    
-    pub fn get_vocabulary_by_language(&self, language_id: i64) -> Result<Vec<Vocabulary>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at 
-             FROM vocabulary WHERE language_id = ?1 ORDER BY word"
-        )?;
-        
-        let rows = stmt.query_map([language_id], |row| {
-            Ok(Vocabulary {
-                id: row.get(0)?,
-                language_id: row.get(1)?,
-                word: row.get(2)?,
-                translation: row.get(3)?,
-                pronunciation: row.get(4)?,
-                example_sentence: row.get(5)?,
-                difficulty_level: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })?;
-        
-        let mut vocabulary = Vec::new();
-        for row in rows {
-            vocabulary.push(row?);
+    // Opens the user's workspace registry and its active workspace, as the app does
+    pub fn new() -> Result<Self, AppError> {
+        let data_dir = Self::get_data_dir()?;
+        let mut registry = WorkspaceRegistry::load(data_dir.join("workspaces.json"))?;
+
+        // First launch: adopt the original single database as the default workspace
+        if registry.workspaces.is_empty() {
+            let default_path = data_dir.join("yotion.db");
+            create_database_file(&default_path)?;
+            registry.add("Default", &default_path.to_string_lossy())?;
         }
-        
-        Ok(vocabulary)
-    }
-    
-    pub fn search_vocabulary(&self, query: &str, limit: i64) -> Result<Vec<Vocabulary>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let search_term = format!("%{}%", query);
-        let mut stmt = conn.prepare(
-            "SELECT id, language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at
-             FROM vocabulary 
-             WHERE word LIKE ?1 OR translation LIKE ?1 OR example_sentence LIKE ?1
-             ORDER BY word
-             LIMIT ?2"
-        )?;
-        
-        let rows = stmt.query_map((search_term, limit), |row| {
-            Ok(Vocabulary {
-                id: row.get(0)?,
-                language_id: row.get(1)?,
-                word: row.get(2)?,
-                translation: row.get(3)?,
-                pronunciation: row.get(4)?,
-                example_sentence: row.get(5)?,
-                difficulty_level: row.get(6)?,
-                created_at: row.get(7)?,
-            })
-        })?;
-        
-        let mut vocabulary = Vec::new();
-        for row in rows {
-            vocabulary.push(row?);
+
+        let startup_id = registry.active_id.unwrap_or(registry.workspaces[0].id);
+
+        // A workspace that won't open (moved, deleted, damaged) leaves the app
+        // running with none open so the user can pick another
+        let db = Self::with_registry(registry);
+        if let Err(e) = db.open_workspace(startup_id) {
+            log_error(&format!("Could not open workspace {}", startup_id), &e);
+            *lock(&db.startup_error)? = Some(e.to_string());
         }
-        
-        Ok(vocabulary)
+
+        Ok(db)
     }
-    
-    pub fn get_due_flashcards(&self, limit: i64) -> Result<Vec<(Flashcard, Vocabulary)>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        let mut stmt = conn.prepare(
-            "SELECT f.id, f.vocabulary_id, f.ease_factor, f.interval_days, f.repetitions, f.next_review, f.last_reviewed, f.created_at,
-                    v.id, v.language_id, v.word, v.translation, v.pronunciation, v.example_sentence, v.difficulty_level, v.created_at
-             FROM flashcards f
-             JOIN vocabulary v ON f.vocabulary_id = v.id
-             WHERE f.next_review <= ?1
-             ORDER BY f.next_review
-             LIMIT ?2"
-        )?;
-        
-        let rows = stmt.query_map((now, limit), |row| {
-            let flashcard = Flashcard {
-                id: row.get(0)?,
-                vocabulary_id: row.get(1)?,
-                ease_factor: row.get(2)?,
-                interval_days: row.get(3)?,
-                repetitions: row.get(4)?,
-                next_review: row.get(5)?,
-                last_reviewed: row.get(6)?,
-                created_at: row.get(7)?,
-            };
-            
-            let vocabulary = Vocabulary {
-                id: row.get(8)?,
-                language_id: row.get(9)?,
-                word: row.get(10)?,
-                translation: row.get(11)?,
-                pronunciation: row.get(12)?,
-                example_sentence: row.get(13)?,
-                difficulty_level: row.get(14)?,
-                created_at: row.get(15)?,
-            };
-            
-            Ok((flashcard, vocabulary))
-        })?;
-        
-        let mut flashcards = Vec::new();
-        for row in rows {
-            flashcards.push(row?);
+
+    // Stand-in when the workspace registry itself can't be loaded: nothing is
+    // open and the registry isn't saved, so the user's file is left alone
+    pub fn without_workspace(error: &AppError) -> Self {
+        Database {
+            startup_error: Mutex::new(Some(error.to_string())),
+            ..Self::with_registry(WorkspaceRegistry::detached(std::env::temp_dir().join("yotion")))
         }
-        
-        Ok(flashcards)
     }
-    
-    pub fn review_flashcard(&self, req: FlashcardReviewRequest) -> Result<(), AppError> {
-        let conn = self.conn.lock().unwrap();
-        
-        // Get current flashcard data
-        let mut stmt = conn.prepare(
-            "SELECT ease_factor, interval_days, repetitions FROM flashcards WHERE id = ?1"
-        )?;
-        
-        let (ease_factor, interval_days, repetitions): (f64, i64, i64) = stmt.query_row([req.flashcard_id], |row| {
-            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
-        })?;
-        
-        // Calculate new values based on quality (1-5)
-        let quality = req.quality as f64;
-        let new_ease_factor = if quality >= 3.0 {
-            ease_factor + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02))
-        } else {
-            ease_factor.max(1.3)
-        };
-        
-        let new_interval = if quality < 3.0 {
-            1
-        } else if repetitions == 0 {
-            1
-        } else if repetitions == 1 {
-            6
-        } else {
-            (interval_days as f64 * new_ease_factor) as i64
-        };
-        
-        let new_repetitions = if quality < 3.0 { 0 } else { repetitions + 1 };
-        let next_review = chrono::Utc::now() + chrono::Duration::days(new_interval);
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        // Update flashcard
-        conn.execute(
-            "UPDATE flashcards SET ease_factor = ?1, interval_days = ?2, repetitions = ?3, next_review = ?4, last_reviewed = ?5 WHERE id = ?6",
-            (new_ease_factor, new_interval, new_repetitions, next_review.to_rfc3339(), now, req.flashcard_id),
-        )?;
-        
-        Ok(())
+
+    // Opens (creating and migrating if needed) one database file without
+    // touching the user's workspace registry. Default backups go next to it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = std::env::current_dir()?.join(path);
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Workspace".to_string());
+        let data_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        Self::open_detached(&name, &path, data_dir)
     }
-    
-    fn create_flashcard(&self, vocabulary_id: i64) -> Result<(), AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now();
-        let next_review = now + chrono::Duration::days(1);
-        
-        conn.execute(
-            "INSERT INTO flashcards (vocabulary_id, ease_factor, interval_days, repetitions, next_review, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            (vocabulary_id, 2.5, 1, 0, next_review.to_rfc3339(), now.to_rfc3339()),
-        )?;
-        
-        Ok(())
+
+    // A fresh, private database that is discarded when dropped
+    pub fn open_in_memory() -> Result<Self, AppError> {
+        Self::open_detached("In-memory", Path::new(IN_MEMORY), std::env::temp_dir().join("yotion"))
     }
-}
 
-// Tech Space methods
-impl Database {
-    pub fn create_tech_space(&self, req: CreateTechSpaceRequest) -> Result<TechSpace, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO tech_spaces (name, description, icon, created_at) VALUES (?1, ?2, ?3, ?4)",
-            (req.name.clone(), req.description.clone(), req.icon.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(TechSpace {
-            id,
-            name: req.name,
-            description: req.description,
-            icon: req.icon,
-            created_at: now,
-        })
+    fn open_detached(name: &str, path: &Path, data_dir: PathBuf) -> Result<Self, AppError> {
+        create_database_file(path)?;
+        let mut registry = WorkspaceRegistry::detached(data_dir);
+        let id = registry.add(name, &path.to_string_lossy())?.id;
+
+        let db = Self::with_registry(registry);
+        db.open_workspace(id)?;
+
+        Ok(db)
     }
-    
-    pub fn get_tech_spaces(&self) -> Result<Vec<TechSpace>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, description, icon, created_at FROM tech_spaces ORDER BY name")?;
-        let rows = stmt.query_map([], |row| {
-            Ok(TechSpace {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                icon: row.get(3)?,
-                created_at: row.get(4)?,
-            })
-        })?;
-        
-        let mut spaces = Vec::new();
-        for row in rows {
-            spaces.push(row?);
+
+    fn with_registry(registry: WorkspaceRegistry) -> Self {
+        Database {
+            writer: Mutex::new(None),
+            readers: (0..READER_COUNT).map(|_| Mutex::new(None)).collect(),
+            next_reader: AtomicUsize::new(0),
+            current_workspace: Mutex::new(None),
+            registry: Mutex::new(registry),
+            vault: Mutex::new(None),
+            database_key: Mutex::new(None),
+            startup_error: Mutex::new(None),
         }
-        
-        Ok(spaces)
     }
-    
-    pub fn create_code_snippet(&self, req: CreateCodeSnippetRequest) -> Result<CodeSnippet, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO code_snippets (tech_space_id, title, description, code, language, tags, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            (req.tech_space_id, req.title.clone(), req.description.clone(), req.code.clone(), req.language.clone(), req.tags.clone(), now.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(CodeSnippet {
-            id,
-            tech_space_id: req.tech_space_id,
-            title: req.title,
-            description: req.description,
-            code: req.code,
-            language: req.language,
-            tags: req.tags,
-            created_at: now.clone(),
-            updated_at: now,
-        })
+
+    fn get_data_dir() -> Result<PathBuf, AppError> {
+        let mut path = dirs::data_dir().ok_or_else(|| AppError::Io("Could not find data directory".to_string()))?;
+        path.push("yotion");
+        std::fs::create_dir_all(&path)?;
+        Ok(path)
     }
-    
-    pub fn get_code_snippets_by_tech_space(&self, tech_space_id: i64) -> Result<Vec<CodeSnippet>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, tech_space_id, title, description, code, language, tags, created_at, updated_at 
-             FROM code_snippets WHERE tech_space_id = ?1 ORDER BY created_at DESC"
-        )?;
-        
-        let rows = stmt.query_map([tech_space_id], |row| {
-            Ok(CodeSnippet {
-                id: row.get(0)?,
-                tech_space_id: row.get(1)?,
-                title: row.get(2)?,
-                description: row.get(3)?,
-                code: row.get(4)?,
-                language: row.get(5)?,
-                tags: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })?;
-        
-        let mut snippets = Vec::new();
-        for row in rows {
-            snippets.push(row?);
-        }
-        
-        Ok(snippets)
+
+    // Why the active workspace failed to open at startup, if it did
+    pub fn get_startup_error(&self) -> Result<Option<String>, AppError> {
+        Ok(lock(&self.startup_error)?.clone())
     }
-    
-    pub fn search_code_snippets(&self, query: &str, limit: i64) -> Result<Vec<CodeSnippet>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let search_term = format!("%{}%", query);
-        let mut stmt = conn.prepare(
-            "SELECT id, tech_space_id, title, description, code, language, tags, created_at, updated_at
-             FROM code_snippets 
-             WHERE title LIKE ?1 OR description LIKE ?1 OR code LIKE ?1 OR tags LIKE ?1
-             ORDER BY title
-             LIMIT ?2"
-        )?;
-        
-        let rows = stmt.query_map((search_term, limit), |row| {
-            Ok(CodeSnippet {
-                id: row.get(0)?,
-                tech_space_id: row.get(1)?,
-                title: row.get(2)?,
-                description: row.get(3)?,
-                code: row.get(4)?,
-                language: row.get(5)?,
-                tags: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })?;
-        
-        let mut snippets = Vec::new();
-        for row in rows {
-            snippets.push(row?);
+
+    // Opens and migrates an existing database file without touching the
+    // current connections; `key` is required for an encrypted file. A missing
+    // file is an error rather than a new empty database.
+    pub(super) fn open_connections(path: &Path, key: Option<DatabaseKey>) -> Result<ConnectionSet, AppError> {
+        // Read-only connections can't see a private in-memory database, so
+        // everything goes through the writer
+        if path == Path::new(IN_MEMORY) {
+            let mut writer = Connection::open_in_memory()?;
+            writer.execute("PRAGMA foreign_keys = ON", [])?;
+            migrations::run(&mut writer)?;
+            return Ok(ConnectionSet { writer, readers: Vec::new(), key: None });
         }
-        
-        Ok(snippets)
-    }
-}
 
-// Project methods
-impl Database {
-    pub fn create_project(&self, req: CreateProjectRequest) -> Result<Project, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO projects (name, description, status, priority, start_date, end_date, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            (req.name.clone(), req.description.clone(), req.status.clone(), req.priority.clone(), req.start_date.clone(), req.end_date.clone(), now.clone(), now.clone()),
+        if !path.is_file() {
+            return Err(AppError::NotFound(format!("Database file {} not found", path.display())));
+        }
+
+        let mut writer = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(Project {
-            id,
-            name: req.name,
-            description: req.description,
-            status: req.status,
-            priority: req.priority,
-            start_date: req.start_date,
-            end_date: req.end_date,
-            created_at: now.clone(),
-            updated_at: now,
-        })
-    }
-    
-    pub fn get_projects(&self) -> Result<Vec<Project>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, name, description, status, priority, start_date, end_date, created_at, updated_at FROM projects ORDER BY created_at DESC")?;
-        let rows = stmt.query_map([], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                status: row.get(3)?,
-                priority: row.get(4)?,
-                start_date: row.get(5)?,
-                end_date: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })?;
-        
-        let mut projects = Vec::new();
-        for row in rows {
-            projects.push(row?);
+        if let Some(key) = &key {
+            key.apply(&writer)?;
+        }
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+        writer.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        writer.pragma_update(None, "synchronous", "NORMAL")?;
+        writer.execute("PRAGMA foreign_keys = ON", [])?;
+
+        // Bring the schema up to date (refuses databases from a newer build)
+        migrations::run(&mut writer)?;
+
+        let mut readers = Vec::with_capacity(READER_COUNT);
+        for _ in 0..READER_COUNT {
+            let reader = Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            if let Some(key) = &key {
+                key.apply(&reader)?;
+            }
+            reader.busy_timeout(BUSY_TIMEOUT)?;
+            readers.push(reader);
         }
-        
-        Ok(projects)
+
+        Ok(ConnectionSet { writer, readers, key })
     }
-    
-    pub fn create_task(&self, req: CreateTaskRequest) -> Result<Task, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO tasks (project_id, title, description, status, priority, due_date, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            (req.project_id, req.title.clone(), req.description.clone(), req.status.clone(), req.priority.clone(), req.due_date.clone(), now.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(Task {
-            id,
-            project_id: req.project_id,
-            title: req.title,
-            description: req.description,
-            status: req.status,
-            priority: req.priority,
-            due_date: req.due_date,
-            completed_at: None,
-            created_at: now.clone(),
-            updated_at: now,
-        })
+
+    // Connection for anything that modifies the database
+    pub(super) fn writer(&self) -> Result<ConnectionGuard<'_>, AppError> {
+        let guard = lock(&self.writer)?;
+        if guard.is_none() {
+            drop(guard);
+            if self.database_locked()? {
+                return Err(AppError::VaultLocked);
+            }
+            return Err(AppError::NotFound("No workspace is open".to_string()));
+        }
+        Ok(ConnectionGuard(guard))
     }
-    
-    pub fn get_tasks_by_project(&self, project_id: i64) -> Result<Vec<Task>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, project_id, title, description, status, priority, due_date, completed_at, created_at, updated_at 
-             FROM tasks WHERE project_id = ?1 ORDER BY created_at DESC"
-        )?;
-        
-        let rows = stmt.query_map([project_id], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                project_id: row.get(1)?,
-                title: row.get(2)?,
-                description: row.get(3)?,
-                status: row.get(4)?,
-                priority: row.get(5)?,
-                due_date: row.get(6)?,
-                completed_at: row.get(7)?,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        })?;
-        
-        let mut tasks = Vec::new();
-        for row in rows {
-            tasks.push(row?);
+
+    // Read-only connection; picks an idle one so slow queries don't block others
+    pub(super) fn reader(&self) -> Result<ConnectionGuard<'_>, AppError> {
+        let start = self.next_reader.fetch_add(1, Ordering::Relaxed);
+        let count = self.readers.len();
+
+        let mut guard = None;
+        for offset in 0..count {
+            if let Ok(idle) = self.readers[(start + offset) % count].try_lock() {
+                guard = Some(idle);
+                break;
+            }
         }
-        
-        Ok(tasks)
+        let guard = match guard {
+            Some(guard) => guard,
+            None => lock(&self.readers[start % count])?,
+        };
+
+        // Databases opened without readers (e.g. in memory) read through the writer
+        if guard.is_none() {
+            drop(guard);
+            return self.writer();
+        }
+        Ok(ConnectionGuard(guard))
     }
-    
-    pub fn update_task_status(&self, task_id: i64, status: &str) -> Result<(), AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        let completed_at = if status == "completed" { Some(now.clone()) } else { None };
-        
-        conn.execute(
-            "UPDATE tasks SET status = ?1, completed_at = ?2, updated_at = ?3 WHERE id = ?4",
-            (status, completed_at, now, task_id),
-        )?;
-        
+
+    // Swaps every pooled connection; the previous ones are closed when dropped.
+    // The vault locks too, since the new database has its own master password.
+    pub(super) fn replace_connections(&self, connections: Option<ConnectionSet>) -> Result<(), AppError> {
+        let mut writer_guard = lock(&self.writer)?;
+        self.install_connections(&mut writer_guard, connections)?;
+        *lock(&self.vault)? = None;
+
         Ok(())
     }
-}
 
-// Planner methods
-impl Database {
-    pub fn create_event(&self, req: CreateEventRequest) -> Result<Event, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO events (title, description, event_date, start_time, end_time, event_type, priority, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            (req.title.clone(), req.description.clone(), req.event_date.clone(), req.start_time.clone(), req.end_time.clone(), req.event_type.clone(), req.priority.clone(), now.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(Event {
-            id,
-            title: req.title,
-            description: req.description,
-            event_date: req.event_date,
-            start_time: req.start_time,
-            end_time: req.end_time,
-            event_type: req.event_type,
-            priority: req.priority,
-            created_at: now.clone(),
-            updated_at: now,
-        })
-    }
-    
-    pub fn get_events_by_date(&self, date: &str) -> Result<Vec<Event>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, title, description, event_date, start_time, end_time, event_type, priority, created_at, updated_at 
-             FROM events WHERE event_date = ?1 ORDER BY start_time"
-        )?;
-        
-        let rows = stmt.query_map([date], |row| {
-            Ok(Event {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                description: row.get(2)?,
-                event_date: row.get(3)?,
-                start_time: row.get(4)?,
-                end_time: row.get(5)?,
-                event_type: row.get(6)?,
-                priority: row.get(7)?,
-                created_at: row.get(8)?,
-                updated_at: row.get(9)?,
-            })
-        })?;
-        
-        let mut events = Vec::new();
-        for row in rows {
-            events.push(row?);
-        }
-        
-        Ok(events)
+    // Rewrites the database file with no write able to slip in: `rewrite`
+    // gets the writer and returns the finished replacement, which is renamed
+    // over `path` once every connection is closed. The file is then reopened
+    // with `key`; if it won't open, the previous file is put back and
+    // reopened instead. The vault session is kept, as it is the same vault.
+    pub(super) fn swap_database_file<F>(&self, path: &Path, key: Option<DatabaseKey>, rewrite: F) -> Result<(), AppError>
+    where
+        F: FnOnce(&Connection) -> Result<PathBuf, AppError>,
+    {
+        let mut writer_guard = lock(&self.writer)?;
+        let replacement = match writer_guard.as_ref() {
+            Some(writer) => rewrite(writer)?,
+            None => return Err(AppError::NotFound("No workspace is open".to_string())),
+        };
+
+        // Closing the last connection checkpoints the WAL into the old file,
+        // which is then kept aside until the replacement has opened
+        let previous_key = lock(&self.database_key)?.clone();
+        let previous = with_suffix(path, ".previous");
+        self.install_connections(&mut writer_guard, None)?;
+        let swapped = (|| -> Result<(), AppError> {
+            for suffix in ["-wal", "-shm"] {
+                let sidecar = with_suffix(path, suffix);
+                if sidecar.exists() {
+                    std::fs::remove_file(sidecar)?;
+                }
+            }
+            std::fs::rename(path, &previous)?;
+            if let Err(e) = std::fs::rename(&replacement, path) {
+                std::fs::rename(&previous, path)?;
+                return Err(e.into());
+            }
+            Ok(())
+        })();
+
+        let reopened = match swapped {
+            Ok(()) => match Self::open_connections(path, key) {
+                Ok(connections) => {
+                    let _ = std::fs::remove_file(&previous);
+                    Ok(connections)
+                }
+                Err(e) => {
+                    for suffix in ["", "-wal", "-shm"] {
+                        let _ = std::fs::remove_file(with_suffix(path, suffix));
+                    }
+                    std::fs::rename(&previous, path)?;
+                    Err(e)
+                }
+            },
+            Err(e) => {
+                let _ = std::fs::remove_file(&replacement);
+                Err(e)
+            }
+        };
+
+        let (connections, result) = match reopened {
+            Ok(connections) => (connections, Ok(())),
+            Err(e) => (Self::open_connections(path, previous_key)?, Err(e)),
+        };
+        self.install_connections(&mut writer_guard, Some(connections))?;
+        result
     }
-    
-    pub fn create_note(&self, req: CreateNoteRequest) -> Result<Note, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        conn.execute(
-            "INSERT INTO notes (title, content, note_date, tags, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            (req.title.clone(), req.content.clone(), req.note_date.clone(), req.tags.clone(), now.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(Note {
-            id,
-            title: req.title,
-            content: req.content,
-            note_date: req.note_date,
-            tags: req.tags,
-            created_at: now.clone(),
-            updated_at: now,
-        })
+
+    pub(super) fn database_locked(&self) -> Result<bool, AppError> {
+        Ok(lock(&self.writer)?.is_none() && lock(&self.current_workspace)?.is_some())
     }
-    
-    pub fn get_notes_by_date(&self, date: &str) -> Result<Vec<Note>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, title, content, note_date, tags, created_at, updated_at 
-             FROM notes WHERE note_date = ?1 ORDER BY created_at DESC"
-        )?;
-        
-        let rows = stmt.query_map([date], |row| {
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                note_date: row.get(3)?,
-                tags: row.get(4)?,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })?;
-        
-        let mut notes = Vec::new();
-        for row in rows {
-            notes.push(row?);
+
+    fn install_connections(&self, writer: &mut Option<Connection>, connections: Option<ConnectionSet>) -> Result<(), AppError> {
+        let (new_writer, mut readers, key) = match connections {
+            Some(set) => (Some(set.writer), set.readers.into_iter().map(Some).collect(), set.key),
+            None => (None, Vec::new(), None),
+        };
+        readers.resize_with(self.readers.len(), || None);
+
+        for (slot, reader) in self.readers.iter().zip(readers) {
+            *lock(slot)? = reader;
         }
-        
-        Ok(notes)
+        *writer = new_writer;
+        *lock(&self.database_key)? = key;
+
+        Ok(())
     }
 }
 
-// Personal Vault methods
-impl Database {
-    pub fn create_personal_account(&self, req: CreatePersonalAccountRequest) -> Result<PersonalAccount, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        // Encrypt sensitive data
-        let encrypted_password = encrypt_sensitive_data(&req.password)?;
-        let encrypted_email = encrypt_sensitive_data(&req.email)?;
-        
-        conn.execute(
-            "INSERT INTO personal_accounts (title, email, password, website, notes, category, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            (req.title.clone(), encrypted_email, encrypted_password, req.website.clone(), req.notes.clone(), req.category.clone(), now.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(PersonalAccount {
-            id,
-            title: req.title,
-            email: req.email, // Return unencrypted for display
-            password: req.password, // Return unencrypted for display
-            website: req.website,
-            notes: req.notes,
-            category: req.category,
-            created_at: now.clone(),
-            updated_at: now,
-        })
-    }
-    
-    pub fn get_personal_accounts(&self) -> Result<Vec<PersonalAccount>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, title, email, password, website, notes, category, created_at, updated_at FROM personal_accounts ORDER BY created_at DESC")?;
-        let rows = stmt.query_map([], |row| {
-            let encrypted_email: String = row.get(2)?;
-            let encrypted_password: String = row.get(3)?;
-            
-            // Decrypt sensitive data
-            let email = decrypt_sensitive_data(&encrypted_email).unwrap_or_else(|_| "Decryption failed".to_string());
-            let password = decrypt_sensitive_data(&encrypted_password).unwrap_or_else(|_| "Decryption failed".to_string());
-            
-            Ok(PersonalAccount {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                email,
-                password,
-                website: row.get(4)?,
-                notes: row.get(5)?,
-                category: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })?;
-        
-        let mut accounts = Vec::new();
-        for row in rows {
-            accounts.push(row?);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn readers_are_read_only_and_the_file_uses_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+
+        let mode: String = db.writer().unwrap().query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        assert_eq!(mode, "wal");
+        assert!(with_suffix(&dir.path().join("main.db"), "-wal").exists());
+
+        for _ in 0..READER_COUNT {
+            let reader = db.reader().unwrap();
+            assert!(reader.is_readonly(rusqlite::DatabaseName::Main).unwrap());
+            let err: AppError = reader.execute("DELETE FROM languages", []).unwrap_err().into();
+            assert!(matches!(err, AppError::Database(_)), "{:?}", err);
         }
-        
-        Ok(accounts)
+        assert!(!db.writer().unwrap().is_readonly(rusqlite::DatabaseName::Main).unwrap());
     }
-    
-    pub fn get_personal_accounts_by_category(&self, category: &str) -> Result<Vec<PersonalAccount>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, title, email, password, website, notes, category, created_at, updated_at 
-             FROM personal_accounts WHERE category = ?1 ORDER BY created_at DESC"
-        )?;
-        
-        let rows = stmt.query_map([category], |row| {
-            let encrypted_email: String = row.get(2)?;
-            let encrypted_password: String = row.get(3)?;
-            
-            // Decrypt sensitive data
-            let email = decrypt_sensitive_data(&encrypted_email).unwrap_or_else(|_| "Decryption failed".to_string());
-            let password = decrypt_sensitive_data(&encrypted_password).unwrap_or_else(|_| "Decryption failed".to_string());
-            
-            Ok(PersonalAccount {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                email,
-                password,
-                website: row.get(4)?,
-                notes: row.get(5)?,
-                category: row.get(6)?,
-                created_at: row.get(7)?,
-                updated_at: row.get(8)?,
-            })
-        })?;
-        
-        let mut accounts = Vec::new();
-        for row in rows {
-            accounts.push(row?);
-        }
-        
-        Ok(accounts)
+
+    #[test]
+    fn in_memory_databases_read_through_the_writer() {
+        let db = Database::open_in_memory().unwrap();
+        db.writer().unwrap().execute("CREATE TABLE scratch (x INTEGER)", []).unwrap();
+        let count: i64 = db.reader().unwrap().query_row("SELECT COUNT(*) FROM scratch", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
     }
-    
-    pub fn create_personal_info(&self, req: CreatePersonalInfoRequest) -> Result<PersonalInfo, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let now = chrono::Utc::now().to_rfc3339();
-        
-        // Encrypt sensitive data if marked as sensitive
-        let content = if req.is_sensitive {
-            encrypt_sensitive_data(&req.content)?
-        } else {
-            req.content.clone()
-        };
-        
-        conn.execute(
-            "INSERT INTO personal_info (title, content, category, is_sensitive, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            (req.title.clone(), content, req.category.clone(), req.is_sensitive, now.clone(), now.clone()),
-        )?;
-        
-        let id = conn.last_insert_rowid();
-        
-        Ok(PersonalInfo {
-            id,
-            title: req.title,
-            content: req.content, // Return unencrypted for display
-            category: req.category,
-            is_sensitive: req.is_sensitive,
-            created_at: now.clone(),
-            updated_at: now,
-        })
+
+    #[test]
+    fn a_poisoned_writer_fails_one_call_then_recovers() {
+        let db = Database::open_in_memory().unwrap();
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            let _writer = db.writer.lock().unwrap();
+            panic!("simulated failure while holding the writer");
+        }));
+        assert!(panicked.is_err());
+        assert!(db.writer.is_poisoned());
+
+        let err = db.writer().map(|_| ()).unwrap_err();
+        assert!(matches!(err, AppError::Database(_)), "{:?}", err);
+        assert!(db.writer().is_ok());
     }
-    
-    pub fn get_personal_info(&self) -> Result<Vec<PersonalInfo>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, title, content, category, is_sensitive, created_at, updated_at FROM personal_info ORDER BY created_at DESC")?;
-        let rows = stmt.query_map([], |row| {
-            let encrypted_content: String = row.get(2)?;
-            let is_sensitive: bool = row.get(4)?;
-            
-            // Decrypt sensitive data if needed
-            let content = if is_sensitive {
-                decrypt_sensitive_data(&encrypted_content).unwrap_or_else(|_| "Decryption failed".to_string())
-            } else {
-                encrypted_content
-            };
-            
-            Ok(PersonalInfo {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content,
-                category: row.get(3)?,
-                is_sensitive,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })?;
-        
-        let mut info = Vec::new();
-        for row in rows {
-            info.push(row?);
-        }
-        
-        Ok(info)
+
+    #[test]
+    fn opening_connections_never_creates_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.db");
+        assert!(matches!(Database::open_connections(&missing, None), Err(AppError::NotFound(_))));
+        assert!(!missing.exists());
+
+        create_database_file(&dir.path().join("nested/new.db")).unwrap();
+        assert!(Database::open_connections(&dir.path().join("nested/new.db"), None).is_ok());
     }
-    
-    pub fn get_personal_info_by_category(&self, category: &str) -> Result<Vec<PersonalInfo>, AppError> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, title, content, category, is_sensitive, created_at, updated_at 
-             FROM personal_info WHERE category = ?1 ORDER BY created_at DESC"
-        )?;
-        
-        let rows = stmt.query_map([category], |row| {
-            let encrypted_content: String = row.get(2)?;
-            let is_sensitive: bool = row.get(4)?;
-            
-            // Decrypt sensitive data if needed
-            let content = if is_sensitive {
-                decrypt_sensitive_data(&encrypted_content).unwrap_or_else(|_| "Decryption failed".to_string())
-            } else {
-                encrypted_content
-            };
-            
-            Ok(PersonalInfo {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content,
-                category: row.get(3)?,
-                is_sensitive,
-                created_at: row.get(5)?,
-                updated_at: row.get(6)?,
-            })
-        })?;
-        
-        let mut info = Vec::new();
-        for row in rows {
-            info.push(row?);
-        }
-        
-        Ok(info)
+
+    #[test]
+    fn closed_workspaces_have_no_connections() {
+        let db = Database::open_in_memory().unwrap();
+        db.close_workspace().unwrap();
+        assert!(matches!(db.writer().map(|_| ()), Err(AppError::NotFound(_))));
+        assert!(matches!(db.reader().map(|_| ()), Err(AppError::NotFound(_))));
+        assert!(!db.database_locked().unwrap());
     }
-}
\ No newline at end of file
+}
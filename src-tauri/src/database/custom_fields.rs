@@ -0,0 +1,349 @@
+use crate::encryption::{self, EncryptionService};
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::normalize_custom_value;
+use super::personal_vault::decrypt_value;
+use super::vault::{free_id, rebind_value};
+use rusqlite::types::Type;
+use rusqlite::Error::FromSqlConversionFailure;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+
+// The kind of vault entry custom fields belong to
+#[derive(Debug, Clone, Copy)]
+pub(super) enum FieldOwner {
+    Account,
+    Info,
+}
+
+impl FieldOwner {
+    fn column(self) -> &'static str {
+        match self {
+            FieldOwner::Account => "account_id",
+            FieldOwner::Info => "info_id",
+        }
+    }
+}
+
+// How hidden values come back from `load_custom_fields`
+pub(super) enum SecretValues<'a> {
+    // Left out, as in lists
+    Masked,
+    // Still encrypted, as in workspace archives
+    Stored,
+    Decrypted(&'a EncryptionService),
+}
+
+impl CustomFieldType {
+    fn as_str(self) -> &'static str {
+        match self {
+            CustomFieldType::Text => "text",
+            CustomFieldType::Hidden => "hidden",
+            CustomFieldType::Url => "url",
+            CustomFieldType::Date => "date",
+            CustomFieldType::Number => "number",
+            CustomFieldType::CardNumber => "card_number",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "text" => Some(CustomFieldType::Text),
+            "hidden" => Some(CustomFieldType::Hidden),
+            "url" => Some(CustomFieldType::Url),
+            "date" => Some(CustomFieldType::Date),
+            "number" => Some(CustomFieldType::Number),
+            "card_number" => Some(CustomFieldType::CardNumber),
+            _ => None,
+        }
+    }
+
+    // Stored encrypted and masked in lists
+    pub(super) fn is_secret(self) -> bool {
+        matches!(self, CustomFieldType::Hidden | CustomFieldType::CardNumber)
+    }
+}
+
+// Replaces all custom fields of an entry with already validated ones and
+// returns them as saved, with their values in the clear. Fields given with
+// an id are updated in place, so reveals logged against them still match;
+// saved fields left out are removed.
+pub(super) fn save_custom_fields(
+    conn: &Connection,
+    key: &EncryptionService,
+    owner: FieldOwner,
+    entry_id: i64,
+    fields: Vec<CustomFieldInput>,
+) -> Result<Vec<CustomField>, AppError> {
+    let mut stmt = conn.prepare(&format!("SELECT id FROM custom_fields WHERE {} = ?1", owner.column()))?;
+    let existing = stmt
+        .query_map([entry_id], |row| row.get::<_, i64>(0))?
+        .collect::<Result<HashSet<_>, _>>()?;
+    drop(stmt);
+
+    let mut kept = HashSet::new();
+    for id in fields.iter().filter_map(|field| field.id) {
+        if !existing.contains(&id) || !kept.insert(id) {
+            return Err(AppError::invalid_field(
+                "custom_fields",
+                format!("Custom field {} is not a field of this entry", id),
+            ));
+        }
+    }
+    for id in existing.difference(&kept) {
+        conn.execute("DELETE FROM custom_fields WHERE id = ?1", [id])?;
+    }
+
+    let mut saved = Vec::with_capacity(fields.len());
+    for (position, field) in fields.into_iter().enumerate() {
+        let label = field.label.trim().to_string();
+        let value = normalize_custom_value(field.field_type, &field.value);
+        let hint = match field.field_type {
+            CustomFieldType::CardNumber => Some(value[value.len().saturating_sub(4)..].to_string()),
+            _ => None,
+        };
+
+        let id = match field.id {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    &format!(
+                        "INSERT INTO custom_fields ({}, position, label, field_type, value) VALUES (?1, ?2, ?3, ?4, '')",
+                        owner.column()
+                    ),
+                    (entry_id, position as i64, &label, field.field_type.as_str()),
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+        // Hidden values are bound to the field's row, so they are encrypted
+        // once it exists
+        let stored = if field.field_type.is_secret() {
+            key.encrypt(&value, &encryption::aad("custom_fields", "value", id))?
+        } else {
+            value.clone()
+        };
+        conn.execute(
+            "UPDATE custom_fields SET position = ?1, label = ?2, field_type = ?3, value = ?4, hint = ?5 WHERE id = ?6",
+            (position as i64, &label, field.field_type.as_str(), &stored, &hint, id),
+        )?;
+        saved.push(CustomField {
+            id,
+            label,
+            field_type: field.field_type,
+            value: Some(value),
+            hint,
+        });
+    }
+    Ok(saved)
+}
+
+// Copies fields from a workspace archive, whose hidden values are still
+// encrypted. Fields that can't keep their archived id have those values
+// re-encrypted for the new one.
+pub(super) fn import_custom_fields(
+    conn: &Connection,
+    key: Option<&EncryptionService>,
+    owner: FieldOwner,
+    entry_id: i64,
+    fields: &[CustomField],
+) -> Result<(), AppError> {
+    for (position, field) in fields.iter().enumerate() {
+        let value = field.value.as_deref().unwrap_or_default();
+        conn.execute(
+            &format!(
+                "INSERT INTO custom_fields (id, {}, position, label, field_type, value, hint) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                owner.column()
+            ),
+            (free_id(conn, "custom_fields", field.id)?, entry_id, position as i64, &field.label, field.field_type.as_str(), value, &field.hint),
+        )?;
+        if field.field_type.is_secret() {
+            rebind_value(conn, key, "custom_fields", "value", field.id, conn.last_insert_rowid())?;
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn load_custom_fields(
+    conn: &Connection,
+    owner: FieldOwner,
+    entry_id: i64,
+    values: SecretValues,
+) -> Result<Vec<CustomField>, AppError> {
+    Ok(load_fields(conn, owner, Some(entry_id), values)?.remove(&entry_id).unwrap_or_default())
+}
+
+// Fields of every entry of one kind, by entry id, for lists
+pub(super) fn load_all_custom_fields(
+    conn: &Connection,
+    owner: FieldOwner,
+    values: SecretValues,
+) -> Result<HashMap<i64, Vec<CustomField>>, AppError> {
+    load_fields(conn, owner, None, values)
+}
+
+fn load_fields(
+    conn: &Connection,
+    owner: FieldOwner,
+    entry_id: Option<i64>,
+    values: SecretValues,
+) -> Result<HashMap<i64, Vec<CustomField>>, AppError> {
+    let column = owner.column();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {column}, id, label, field_type, value, hint FROM custom_fields
+         WHERE {column} IS NOT NULL AND (?1 IS NULL OR {column} = ?1) ORDER BY {column}, position"
+    ))?;
+    let rows = stmt
+        .query_map([entry_id], |row| {
+            let field_type: String = row.get(3)?;
+            let field_type = CustomFieldType::parse(&field_type).ok_or_else(|| {
+                FromSqlConversionFailure(3, Type::Text, format!("unknown custom field type '{}'", field_type).into())
+            })?;
+            Ok((
+                row.get::<_, i64>(0)?,
+                CustomField {
+                    id: row.get(1)?,
+                    label: row.get(2)?,
+                    field_type,
+                    value: Some(row.get(4)?),
+                    hint: row.get(5)?,
+                },
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut by_entry: HashMap<i64, Vec<CustomField>> = HashMap::new();
+    for (entry, mut field) in rows {
+        if field.field_type.is_secret() {
+            field.value = match &values {
+                SecretValues::Masked => None,
+                SecretValues::Stored => field.value,
+                SecretValues::Decrypted(key) => {
+                    let what = format!("custom field {}", field.id);
+                    let aad = encryption::aad("custom_fields", "value", field.id);
+                    Some(decrypt_value(key, field.value.as_deref().unwrap_or_default(), &aad, &what)?)
+                }
+            };
+        }
+        by_entry.entry(entry).or_default().push(field);
+    }
+    Ok(by_entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    fn input(id: Option<i64>, label: &str, field_type: CustomFieldType, value: &str) -> CustomFieldInput {
+        CustomFieldInput { id, label: label.to_string(), field_type, value: value.to_string() }
+    }
+
+    fn account(db: &Database, title: &str) -> i64 {
+        db.create_personal_account(CreatePersonalAccountRequest {
+            title: title.to_string(),
+            email: "me@example.com".to_string(),
+            password: "pw".to_string(),
+            website: None,
+            notes: None,
+            category: "web".to_string(),
+            totp_secret: None,
+            custom_fields: Vec::new(),
+        })
+        .unwrap()
+        .id
+    }
+
+    // An unlocked vault with two accounts to hang fields on
+    fn vault_with_accounts() -> (Database, i64, i64) {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        let (first, second) = (account(&db, "First"), account(&db, "Second"));
+        (db, first, second)
+    }
+
+    fn save(db: &Database, account_id: i64, fields: Vec<CustomFieldInput>) -> Result<Vec<CustomField>, AppError> {
+        let key = db.vault_key()?;
+        let conn = db.writer()?;
+        save_custom_fields(&conn, &key, FieldOwner::Account, account_id, fields)
+    }
+
+    #[test]
+    fn updates_in_place_and_removes_left_out_fields() {
+        let (db, account, _) = vault_with_accounts();
+        let saved = save(&db, account, vec![
+            input(None, "Member no.", CustomFieldType::Text, " 123 "),
+            input(None, "PIN", CustomFieldType::Hidden, "1234"),
+            input(None, "Branch", CustomFieldType::Text, "Main St"),
+        ])
+        .unwrap();
+        assert_eq!(saved[0].value.as_deref(), Some("123"));
+        let (member, pin) = (saved[0].id, saved[1].id);
+
+        let saved = save(&db, account, vec![
+            input(Some(pin), "New PIN", CustomFieldType::Hidden, "9999"),
+            input(Some(member), "Member no.", CustomFieldType::Text, "123"),
+            input(None, "Added", CustomFieldType::Text, "x"),
+        ])
+        .unwrap();
+        assert_eq!((saved[0].id, saved[1].id), (pin, member));
+
+        let key = db.vault_key().unwrap();
+        let loaded = {
+            let conn = db.reader().unwrap();
+            load_custom_fields(&conn, FieldOwner::Account, account, SecretValues::Decrypted(&key)).unwrap()
+        };
+        let fields: Vec<_> = loaded.iter().map(|f| (f.label.as_str(), f.value.as_deref().unwrap())).collect();
+        assert_eq!(fields, [("New PIN", "9999"), ("Member no.", "123"), ("Added", "x")]);
+
+        assert!(save(&db, account, Vec::new()).unwrap().is_empty());
+        assert!(db.get_personal_accounts().unwrap().iter().all(|a| a.custom_fields.is_empty()));
+    }
+
+    #[test]
+    fn refuses_fields_of_another_entry() {
+        let (db, account, other) = vault_with_accounts();
+        let theirs = save(&db, other, vec![input(None, "PIN", CustomFieldType::Hidden, "1111")]).unwrap()[0].id;
+        let mine = save(&db, account, vec![input(None, "PIN", CustomFieldType::Hidden, "2222")]).unwrap()[0].id;
+
+        let err = save(&db, account, vec![input(Some(theirs), "PIN", CustomFieldType::Hidden, "0000")]).unwrap_err();
+        assert_eq!(err.field(), Some("custom_fields"));
+        let err = save(&db, account, vec![
+            input(Some(mine), "PIN", CustomFieldType::Hidden, "1"),
+            input(Some(mine), "PIN again", CustomFieldType::Hidden, "2"),
+        ])
+        .unwrap_err();
+        assert_eq!(err.field(), Some("custom_fields"));
+
+        assert_eq!(db.reveal_secret(theirs, SecretField::CustomField).unwrap(), "1111");
+        assert_eq!(db.reveal_secret(mine, SecretField::CustomField).unwrap(), "2222");
+    }
+
+    #[test]
+    fn secret_values_are_encrypted_and_masked() {
+        let (db, account, _) = vault_with_accounts();
+        let saved = save(&db, account, vec![
+            input(None, "Card", CustomFieldType::CardNumber, "4111 1111-1111 1234"),
+            input(None, "PIN", CustomFieldType::Hidden, "1234"),
+            input(None, "Note", CustomFieldType::Text, "plain"),
+        ])
+        .unwrap();
+        assert_eq!(saved[0].value.as_deref(), Some("4111111111111234"));
+        assert_eq!(saved[0].hint.as_deref(), Some("1234"));
+        assert_eq!(saved[1].hint, None);
+
+        let stored: Vec<String> = {
+            let conn = db.reader().unwrap();
+            let mut stmt = conn.prepare("SELECT value FROM custom_fields ORDER BY position").unwrap();
+            let values = stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap();
+            values
+        };
+        assert!(!stored[0].contains("1234") && !stored[1].contains("1234"));
+        assert_eq!(stored[2], "plain");
+
+        let listed = db.get_personal_accounts().unwrap().into_iter().find(|a| a.id == account).unwrap();
+        let shown: Vec<_> = listed.custom_fields.iter().map(|f| (f.value.as_deref(), f.hint.as_deref())).collect();
+        assert_eq!(shown, [(None, Some("1234")), (None, None), (Some("plain"), None)]);
+        assert_eq!(db.reveal_secret(saved[0].id, SecretField::CustomField).unwrap(), "4111111111111234");
+    }
+}
@@ -0,0 +1,282 @@
+use crate::database::Database;
+use crate::encryption::{self, KEY_LEN, SALT_LEN};
+use crate::errors::AppError;
+use crate::models::KdfParams;
+use super::connection::{lock, with_suffix, IN_MEMORY};
+use super::migrations;
+use super::vault::open_key;
+use rusqlite::{Connection, DatabaseName, ErrorCode};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+// Whether this build links SQLCipher rather than plain SQLite
+pub(super) const SUPPORTED: bool = cfg!(feature = "sqlcipher");
+
+// First bytes of every plaintext SQLite file. SQLCipher files start with
+// their random salt instead.
+const SQLITE_HEADER: &[u8; SALT_LEN] = b"SQLite format 3\0";
+
+// Fixed, because the database key is needed before the vault header (with
+// the vault's own KDF settings) can be read
+const DATABASE_KDF: KdfParams = KdfParams {
+    memory_kib: 19 * 1024,
+    iterations: 2,
+    parallelism: 1,
+};
+
+// SQLCipher key of one database file: Argon2id of the master password with
+// the file's salt. Handed to SQLCipher as a raw key, so it doesn't run its
+// own (much slower) PBKDF2 on every connection.
+#[derive(Clone)]
+pub(super) struct DatabaseKey {
+    salt: Vec<u8>,
+    raw: Zeroizing<String>,
+}
+
+impl DatabaseKey {
+    pub(super) fn derive(password: &str, salt: &[u8]) -> Result<Self, AppError> {
+        let key = encryption::derive_key(password, salt, DATABASE_KDF)?;
+        let mut raw = Zeroizing::new(String::with_capacity(3 + 2 * (KEY_LEN + SALT_LEN)));
+        raw.push_str("x'");
+        for byte in key.iter().chain(salt) {
+            let _ = write!(raw, "{:02X}", byte);
+        }
+        raw.push('\'');
+        Ok(DatabaseKey { salt: salt.to_vec(), raw })
+    }
+
+    // Must come before anything else on a fresh connection
+    pub(super) fn apply(&self, conn: &Connection) -> Result<(), AppError> {
+        conn.pragma_update(None, "key", self.raw.as_str())?;
+        // The key is only checked on the first read
+        match conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())) {
+            Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::NotADatabase => {
+                Err(AppError::invalid_field("master_password", "Incorrect master password"))
+            }
+            result => Ok(result?),
+        }
+    }
+}
+
+impl Database {
+    // Re-writes the open workspace's database encrypted with a key derived
+    // from the master password. From then on it only opens once the vault
+    // is unlocked.
+    pub fn encrypt_database(&self, master_password: &str) -> Result<(), AppError> {
+        let live = self.convertible_database()?;
+        if lock(&self.database_key)?.is_some() {
+            return Err(AppError::validation("The database is already encrypted"));
+        }
+        open_key(master_password, &self.require_header()?, "master_password")?;
+
+        let key = DatabaseKey::derive(master_password, &encryption::generate_salt())?;
+        self.rewrite_database(&live, Some(key), |_| Ok(()))
+    }
+
+    // The reverse of `encrypt_database`; vault values stay encrypted as usual
+    pub fn decrypt_database(&self, master_password: &str) -> Result<(), AppError> {
+        let live = self.convertible_database()?;
+        if lock(&self.database_key)?.is_none() {
+            return Err(AppError::validation("The database is not encrypted"));
+        }
+        open_key(master_password, &self.require_header()?, "master_password")?;
+
+        self.rewrite_database(&live, None, |_| Ok(()))
+    }
+
+    // Opens an encrypted workspace that was left closed by `open_workspace`
+    pub(super) fn unlock_database(&self, master_password: &str) -> Result<(), AppError> {
+        let path = PathBuf::from(self.require_workspace()?.path);
+        let key = DatabaseKey::derive(master_password, &read_salt(&path)?)?;
+        let connections = Self::open_connections(&path, Some(key))?;
+        self.replace_connections(Some(connections))
+    }
+
+    // Closes an unlocked encrypted database, dropping its key with the
+    // connections. Returns whether there was one to close.
+    pub(super) fn lock_database(&self) -> Result<bool, AppError> {
+        if lock(&self.database_key)?.is_none() {
+            return Ok(false);
+        }
+        self.replace_connections(None)?;
+        Ok(true)
+    }
+
+    pub(super) fn database_encrypted(&self) -> Result<bool, AppError> {
+        Ok(self.database_locked()? || lock(&self.database_key)?.is_some())
+    }
+
+    // Key for another file of this workspace, such as a snapshot: none if it
+    // is plaintext, the current key if it shares the database's salt
+    pub(super) fn key_for_file(&self, path: &Path) -> Result<Option<DatabaseKey>, AppError> {
+        if !is_encrypted(path)? {
+            return Ok(None);
+        }
+        let salt = read_salt(path)?;
+        match lock(&self.database_key)?.as_ref() {
+            Some(key) if key.salt == salt => Ok(Some(key.clone())),
+            _ => Err(AppError::validation(format!(
+                "{} is encrypted with a different master password than the open database",
+                path.display()
+            ))),
+        }
+    }
+
+    // Copies the database into a new file under `key` (plaintext without
+    // one), lets `finish` change the copy, and swaps it in for the live file.
+    // The live file is untouched until the final rename, so a failure or
+    // crash at any point leaves the workspace as it was.
+    pub(super) fn rewrite_database<F>(&self, live: &Path, key: Option<DatabaseKey>, finish: F) -> Result<(), AppError>
+    where
+        F: FnOnce(&mut Connection) -> Result<(), AppError>,
+    {
+        if !SUPPORTED {
+            return Err(unsupported());
+        }
+        let staging = with_suffix(live, ".convert");
+
+        self.swap_database_file(live, key.clone(), |conn| {
+            if staging.exists() {
+                std::fs::remove_file(&staging)?;
+            }
+            let exported = export(conn, &staging, key.as_ref()).and_then(|()| {
+                let mut copy = Connection::open(&staging)?;
+                if let Some(key) = &key {
+                    key.apply(&copy)?;
+                }
+                finish(&mut copy)
+            });
+            match exported {
+                Ok(()) => Ok(staging.clone()),
+                Err(e) => {
+                    let _ = std::fs::remove_file(&staging);
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    fn convertible_database(&self) -> Result<PathBuf, AppError> {
+        if !SUPPORTED {
+            return Err(unsupported());
+        }
+        let workspace = self.require_workspace()?;
+        if workspace.path == IN_MEMORY {
+            return Err(AppError::validation("An in-memory database cannot be encrypted"));
+        }
+        Ok(PathBuf::from(workspace.path))
+    }
+}
+
+// New and empty files count as plaintext
+pub(super) fn is_encrypted(path: &Path) -> Result<bool, AppError> {
+    if path == Path::new(IN_MEMORY) || !path.is_file() {
+        return Ok(false);
+    }
+    let mut header = [0u8; SALT_LEN];
+    let mut file = File::open(path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header != SQLITE_HEADER),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(super) fn unsupported() -> AppError {
+    AppError::validation("This build of YOTION has no database encryption (built without the sqlcipher feature)")
+}
+
+fn read_salt(path: &Path) -> Result<Vec<u8>, AppError> {
+    let mut salt = vec![0u8; SALT_LEN];
+    File::open(path)?.read_exact(&mut salt)?;
+    Ok(salt)
+}
+
+// SQLCipher's export copies schema and rows, but not the schema version
+fn export(conn: &Connection, target: &Path, key: Option<&DatabaseKey>) -> Result<(), AppError> {
+    let key = key.map(|key| key.raw.as_str()).unwrap_or("");
+    conn.execute("ATTACH DATABASE ?1 AS converted KEY ?2", (target.to_string_lossy(), key))?;
+
+    // Rows are copied table by table, so children can come before parents
+    conn.execute("PRAGMA foreign_keys = OFF", [])?;
+    let exported = (|| -> Result<(), AppError> {
+        conn.query_row("SELECT sqlcipher_export('converted')", [], |_| Ok(()))?;
+        conn.pragma_update(Some(DatabaseName::Attached("converted")), "user_version", migrations::current_version(conn)?)?;
+        Ok(())
+    })();
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+    conn.execute("DETACH DATABASE converted", [])?;
+    exported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plaintext_and_empty_files_are_not_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plain.db");
+        let db = Database::open(&path).unwrap();
+        assert!(!is_encrypted(&path).unwrap());
+        // Nothing to close, so the workspace stays open
+        assert!(!db.lock_database().unwrap());
+        assert!(!db.database_locked().unwrap());
+
+        let empty = dir.path().join("empty.db");
+        File::create(&empty).unwrap();
+        assert!(!is_encrypted(&empty).unwrap());
+
+        let salted = dir.path().join("salted.db");
+        std::fs::write(&salted, [7u8; 64]).unwrap();
+        assert!(is_encrypted(&salted).unwrap());
+    }
+
+    #[cfg(feature = "sqlcipher")]
+    #[test]
+    fn encrypted_database_round_trip() {
+        const MASTER_PASSWORD: &str = "correct horse battery staple";
+        let language = || crate::models::CreateLanguageRequest {
+            name: "Spanish".to_string(),
+            code: "es".to_string(),
+            flag_emoji: "🇪🇸".to_string(),
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.db");
+        let db = Database::open(&path).unwrap();
+        db.setup_vault(MASTER_PASSWORD).unwrap();
+        db.create_language(language()).unwrap();
+        db.encrypt_database(MASTER_PASSWORD).unwrap();
+        assert!(is_encrypted(&path).unwrap());
+        drop(db);
+
+        // Reopened, nothing can be read until the vault is unlocked
+        let db = Database::open(&path).unwrap();
+        assert!(db.database_locked().unwrap());
+        assert!(matches!(db.get_languages(), Err(AppError::VaultLocked)));
+        let err = db.unlock_vault("not the password").unwrap_err();
+        assert_eq!(err.field(), Some("master_password"));
+        db.unlock_vault(MASTER_PASSWORD).unwrap();
+        assert_eq!(db.get_languages().unwrap().len(), 1);
+
+        // Locking the vault closes the database again
+        db.lock_vault().unwrap();
+        assert!(db.database_locked().unwrap());
+        assert!(lock(&db.database_key).unwrap().is_none());
+        assert!(matches!(db.get_languages(), Err(AppError::VaultLocked)));
+        // Background maintenance skips a locked database rather than failing
+        assert!(db.run_scheduled_backup().unwrap().is_none());
+        assert_eq!(db.purge_expired_trash().unwrap(), 0);
+
+        db.unlock_vault(MASTER_PASSWORD).unwrap();
+        db.decrypt_database(MASTER_PASSWORD).unwrap();
+        assert!(!is_encrypted(&path).unwrap());
+        db.lock_vault().unwrap();
+        assert!(!db.database_locked().unwrap());
+        assert_eq!(db.get_languages().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,439 @@
+use crate::database::Database;
+use crate::encryption;
+use crate::errors::AppError;
+use crate::models::*;
+use super::connection::with_suffix;
+use super::personal_vault::decrypt_value;
+use super::secret_access::log_secret_access;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// Plaintext bytes per encrypted chunk, so no single row or decryption is large
+const CHUNK_SIZE: usize = 256 * 1024;
+// Largest single file that can be attached
+const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+// Largest total of all attachments in a workspace; every byte is re-encrypted
+// when the master password changes
+const MAX_TOTAL_ATTACHMENT_BYTES: u64 = 250 * 1024 * 1024;
+
+impl Database {
+    // Encrypts the file at `path` into the database; the original is left
+    // where it is
+    pub fn add_info_attachment(&self, info_id: i64, path: &str) -> Result<InfoAttachment, AppError> {
+        let path = PathBuf::from(path.trim());
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| AppError::invalid_field("path", "Choose a file to attach"))?;
+        let mut file = File::open(&path).map_err(|e| AppError::invalid_field("path", format!("Can't open the file: {}", e)))?;
+        let size = file.metadata()?.len();
+        if size > MAX_ATTACHMENT_BYTES {
+            return Err(AppError::invalid_field("path", format!("Attachments can be at most {} MB", MAX_ATTACHMENT_BYTES / (1024 * 1024))));
+        }
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        require_info(&tx, info_id)?;
+        let stored: i64 = tx.query_row("SELECT COALESCE(SUM(size_bytes), 0) FROM info_attachments", [], |row| row.get(0))?;
+        if stored as u64 + size > MAX_TOTAL_ATTACHMENT_BYTES {
+            return Err(AppError::invalid_field(
+                "path",
+                format!("Attachments can take at most {} MB in total", MAX_TOTAL_ATTACHMENT_BYTES / (1024 * 1024)),
+            ));
+        }
+
+        // Name, sizes and checksum are filled in once every chunk is written
+        let now = Utc::now().to_rfc3339();
+        tx.execute(
+            "INSERT INTO info_attachments (info_id, file_name, size_bytes, checksum, chunk_count, created_at)
+             VALUES (?1, '', 0, '', 0, ?2)",
+            (info_id, &now),
+        )?;
+        let id = tx.last_insert_rowid();
+
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let mut total = 0u64;
+        let mut chunks = 0i64;
+        loop {
+            let read = read_chunk(&mut file, &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            total += read as u64;
+            // The file may have grown since its size was checked
+            if total > size {
+                return Err(AppError::invalid_field("path", "The file changed while it was being attached"));
+            }
+            hasher.update(&buffer[..read]);
+            tx.execute(
+                "INSERT INTO info_attachment_chunks (attachment_id, chunk_index, data) VALUES (?1, ?2, '')",
+                (id, chunks),
+            )?;
+            let chunk_id = tx.last_insert_rowid();
+            tx.execute(
+                "UPDATE info_attachment_chunks SET data = ?1 WHERE id = ?2",
+                (key.encrypt_bytes(&buffer[..read], &encryption::aad("info_attachment_chunks", "data", chunk_id))?, chunk_id),
+            )?;
+            chunks += 1;
+        }
+
+        let aad = |column| encryption::aad("info_attachments", column, id);
+        tx.execute(
+            "UPDATE info_attachments SET file_name = ?1, size_bytes = ?2, checksum = ?3, chunk_count = ?4 WHERE id = ?5",
+            (key.encrypt(&file_name, &aad("file_name"))?, total as i64, key.encrypt(&hex(&hasher.finalize()), &aad("checksum"))?, chunks, id),
+        )?;
+        tx.commit()?;
+
+        Ok(InfoAttachment {
+            id,
+            info_id,
+            file_name,
+            size_bytes: total as i64,
+            created_at: now,
+        })
+    }
+
+    pub fn list_info_attachments(&self, info_id: i64) -> Result<Vec<InfoAttachment>, AppError> {
+        let key = self.vault_key()?;
+        let conn = self.reader()?;
+        require_info(&conn, info_id)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, file_name, size_bytes, created_at FROM info_attachments WHERE info_id = ?1 ORDER BY created_at, id",
+        )?;
+        let rows = stmt
+            .query_map([info_id], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?, row.get::<_, String>(3)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, file_name, size_bytes, created_at)| {
+                Ok(InfoAttachment {
+                    id,
+                    info_id,
+                    file_name: decrypt_value(
+                        &key,
+                        &file_name,
+                        &encryption::aad("info_attachments", "file_name", id),
+                        &format!("name of attachment {}", id),
+                    )?,
+                    size_bytes,
+                    created_at,
+                })
+            })
+            .collect()
+    }
+
+    // Decrypts an attachment to `path`, or into it under the original name if
+    // it is a folder. Existing files are never overwritten. The export is
+    // recorded in the secret access log. Returns the file written.
+    pub fn export_info_attachment(&self, id: i64, path: &str) -> Result<String, AppError> {
+        let conn = self.writer()?;
+        let key = self.vault_key()?;
+
+        let (encrypted_name, checksum, chunk_count, title): (String, String, i64, String) = conn
+            .query_row(
+                "SELECT a.file_name, a.checksum, a.chunk_count, i.title FROM info_attachments a
+                 JOIN personal_info i ON i.id = a.info_id
+                 WHERE a.id = ?1 AND i.deleted_at IS NULL",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Attachment {} not found", id)))?;
+        let aad = |column| encryption::aad("info_attachments", column, id);
+        let file_name = decrypt_value(&key, &encrypted_name, &aad("file_name"), &format!("name of attachment {}", id))?;
+        let checksum = decrypt_value(&key, &checksum, &aad("checksum"), &format!("checksum of attachment {}", id))?;
+
+        let mut target = PathBuf::from(path.trim());
+        if target.as_os_str().is_empty() {
+            return Err(AppError::invalid_field("path", "Choose where to save the attachment"));
+        }
+        if target.is_dir() {
+            target.push(&file_name);
+        }
+        if target.exists() {
+            return Err(AppError::invalid_field("path", format!("{} already exists", target.display())));
+        }
+
+        // Written under a temporary name so a failed export leaves no
+        // partial document behind
+        let partial = with_suffix(&target, ".partial");
+        let written = (|| -> Result<(), AppError> {
+            let mut out = create_private(&partial)?;
+            let mut hasher = Sha256::new();
+            let mut stmt = conn.prepare(
+                "SELECT id, chunk_index, data FROM info_attachment_chunks WHERE attachment_id = ?1 ORDER BY chunk_index",
+            )?;
+            let mut rows = stmt.query([id])?;
+            let mut expected = 0i64;
+            while let Some(row) = rows.next()? {
+                let index: i64 = row.get(1)?;
+                if index != expected {
+                    return Err(damaged(id));
+                }
+                let data: String = row.get(2)?;
+                let aad = encryption::aad("info_attachment_chunks", "data", row.get(0)?);
+                let plaintext = key.decrypt_bytes(&data, &aad).map_err(|_| damaged(id))?;
+                hasher.update(&plaintext);
+                out.write_all(&plaintext)?;
+                expected += 1;
+            }
+            if expected != chunk_count || hex(&hasher.finalize()) != checksum {
+                return Err(damaged(id));
+            }
+            out.sync_all()?;
+            Ok(())
+        })();
+        let written = written.and_then(|()| move_without_overwriting(&partial, &target));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+
+        log_secret_access(&conn, SecretField::Attachment, id, &format!("{} · {}", title, file_name))?;
+        Ok(target.to_string_lossy().to_string())
+    }
+
+    pub fn delete_info_attachment(&self, id: i64) -> Result<(), AppError> {
+        let conn = self.writer()?;
+        self.vault_key()?;
+        // Chunks go with it through the foreign key
+        let deleted = conn.execute("DELETE FROM info_attachments WHERE id = ?1", [id])?;
+        if deleted == 0 {
+            return Err(AppError::NotFound(format!("Attachment {} not found", id)));
+        }
+        Ok(())
+    }
+}
+
+// Entries in the trash can't be given attachments or have theirs listed
+fn require_info(conn: &Connection, info_id: i64) -> Result<(), AppError> {
+    let exists = conn
+        .query_row("SELECT 1 FROM personal_info WHERE id = ?1 AND deleted_at IS NULL", [info_id], |_| Ok(()))
+        .optional()?
+        .is_some();
+    if !exists {
+        return Err(AppError::NotFound(format!("Personal info {} not found", info_id)));
+    }
+    Ok(())
+}
+
+// Fills `buffer` unless the file ends first, so chunks are full-sized
+fn read_chunk(file: &mut File, buffer: &mut [u8]) -> Result<usize, AppError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+// Readable only by the current user where the platform allows it
+pub(super) fn create_private(path: &Path) -> Result<File, AppError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    Ok(options.open(path)?)
+}
+
+fn damaged(id: i64) -> AppError {
+    AppError::Encryption(format!("Attachment {} is damaged or was tampered with; nothing was exported", id))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// Moves the finished export into place unless something has appeared at
+// `target` since it was checked; a rename would silently replace it. A hard
+// link fails if the target exists. Where links aren't supported (e.g. FAT
+// drives) the target is checked again just before renaming.
+pub(super) fn move_without_overwriting(partial: &Path, target: &Path) -> Result<(), AppError> {
+    match std::fs::hard_link(partial, target) {
+        Ok(()) => {
+            std::fs::remove_file(partial)?;
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists || target.exists() => {
+            Err(AppError::invalid_field("path", format!("{} already exists", target.display())))
+        }
+        Err(_) => {
+            std::fs::rename(partial, target)?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked_with_info() -> (Database, i64) {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        let info = db
+            .create_personal_info(CreatePersonalInfoRequest {
+                title: "Passport".to_string(),
+                content: "Scans".to_string(),
+                category: "documents".to_string(),
+                is_sensitive: true,
+                custom_fields: Vec::new(),
+            })
+            .unwrap();
+        (db, info.id)
+    }
+
+    // Bytes that differ from chunk to chunk, so swapped chunks are noticed
+    fn contents(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn add(db: &Database, info_id: i64, dir: &Path, name: &str, data: &[u8]) -> InfoAttachment {
+        let source = dir.join(name);
+        std::fs::write(&source, data).unwrap();
+        db.add_info_attachment(info_id, source.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn multi_chunk_files_round_trip() {
+        let (db, info_id) = unlocked_with_info();
+        let dir = tempfile::tempdir().unwrap();
+        let data = contents(CHUNK_SIZE * 2 + 100);
+        let attachment = add(&db, info_id, dir.path(), "scan.pdf", &data);
+        assert_eq!(attachment.size_bytes, data.len() as i64);
+
+        let chunks: i64 = db
+            .reader()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM info_attachment_chunks WHERE attachment_id = ?1", [attachment.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(chunks, 3);
+        let listed = db.list_info_attachments(info_id).unwrap();
+        assert_eq!(listed[0].file_name, "scan.pdf");
+
+        let out = dir.path().join("out");
+        std::fs::create_dir(&out).unwrap();
+        let written = db.export_info_attachment(attachment.id, out.to_str().unwrap()).unwrap();
+        assert_eq!(Path::new(&written), out.join("scan.pdf"));
+        assert_eq!(std::fs::read(&written).unwrap(), data);
+        let log = db.list_secret_access(None).unwrap();
+        assert_eq!((log[0].field, log[0].title.as_str()), (SecretField::Attachment, "Passport · scan.pdf"));
+
+        // Exporting again finds the earlier file and leaves it alone
+        let err = db.export_info_attachment(attachment.id, out.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.field(), Some("path"));
+        assert_eq!(std::fs::read(&written).unwrap(), data);
+        assert!(!with_suffix(&out.join("scan.pdf"), ".partial").exists());
+    }
+
+    #[test]
+    fn tampering_is_reported_as_damage() {
+        let (db, info_id) = unlocked_with_info();
+        let dir = tempfile::tempdir().unwrap();
+        let attachment = add(&db, info_id, dir.path(), "scan.pdf", &contents(CHUNK_SIZE + 10));
+        let id = attachment.id;
+        let export = |name: &str| {
+            let target = dir.path().join(name);
+            let result = db.export_info_attachment(id, target.to_str().unwrap());
+            if result.is_err() {
+                assert!(!target.exists() && !with_suffix(&target, ".partial").exists());
+            }
+            result
+        };
+        let tamper = |sql: &str| {
+            db.writer().unwrap().execute(sql, [id]).unwrap();
+        };
+
+        let swap = || {
+            tamper("UPDATE info_attachment_chunks SET chunk_index = -1 WHERE attachment_id = ?1 AND chunk_index = 0");
+            tamper("UPDATE info_attachment_chunks SET chunk_index = 0 WHERE attachment_id = ?1 AND chunk_index = 1");
+            tamper("UPDATE info_attachment_chunks SET chunk_index = 1 WHERE attachment_id = ?1 AND chunk_index = -1");
+        };
+        swap();
+        assert!(matches!(export("swapped"), Err(AppError::Encryption(_))));
+        swap();
+        assert!(export("restored").is_ok());
+
+        // Each chunk's data only decrypts in its own row
+        tamper(
+            "UPDATE info_attachment_chunks SET data = (SELECT data FROM info_attachment_chunks WHERE attachment_id = ?1 AND chunk_index = 0)
+             WHERE attachment_id = ?1 AND chunk_index = 1",
+        );
+        assert!(matches!(export("moved"), Err(AppError::Encryption(_))));
+
+        tamper("UPDATE info_attachment_chunks SET data = 'garbage' WHERE attachment_id = ?1 AND chunk_index = 1");
+        assert!(matches!(export("garbled"), Err(AppError::Encryption(_))));
+        tamper("DELETE FROM info_attachment_chunks WHERE attachment_id = ?1 AND chunk_index = 1");
+        assert!(matches!(export("truncated"), Err(AppError::Encryption(_))));
+    }
+
+    #[test]
+    fn a_changed_checksum_is_damage() {
+        let (db, info_id) = unlocked_with_info();
+        let dir = tempfile::tempdir().unwrap();
+        let id = add(&db, info_id, dir.path(), "note.txt", b"hello").id;
+        let aad = encryption::aad("info_attachments", "checksum", id);
+        let checksum = db.vault_key().unwrap().encrypt(&hex(&Sha256::digest(b"other")), &aad).unwrap();
+        db.writer()
+            .unwrap()
+            .execute("UPDATE info_attachments SET checksum = ?1 WHERE id = ?2", (checksum, id))
+            .unwrap();
+
+        let err = db.export_info_attachment(id, dir.path().join("out.txt").to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, AppError::Encryption(_)), "{:?}", err);
+        assert!(!dir.path().join("out.txt").exists());
+    }
+
+    #[test]
+    fn enforces_the_size_caps() {
+        let (db, info_id) = unlocked_with_info();
+        let dir = tempfile::tempdir().unwrap();
+        let big = dir.path().join("big.iso");
+        File::create(&big).unwrap().set_len(MAX_ATTACHMENT_BYTES + 1).unwrap();
+        let err = db.add_info_attachment(info_id, big.to_str().unwrap()).unwrap_err();
+        assert_eq!(err.field(), Some("path"));
+        assert!(err.message().contains("at most 25 MB"));
+
+        // Pretend the workspace is nearly full rather than storing 250 MB
+        let first = add(&db, info_id, dir.path(), "a.txt", b"a");
+        db.writer()
+            .unwrap()
+            .execute("UPDATE info_attachments SET size_bytes = ?1 WHERE id = ?2", (MAX_TOTAL_ATTACHMENT_BYTES as i64 - 4, first.id))
+            .unwrap();
+        add(&db, info_id, dir.path(), "b.txt", b"bbbb");
+        std::fs::write(dir.path().join("c.txt"), b"c").unwrap();
+        let err = db.add_info_attachment(info_id, dir.path().join("c.txt").to_str().unwrap()).unwrap_err();
+        assert!(err.message().contains("in total"));
+        assert_eq!(db.list_info_attachments(info_id).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn trashed_entries_and_a_locked_vault_are_refused() {
+        let (db, info_id) = unlocked_with_info();
+        let dir = tempfile::tempdir().unwrap();
+        let id = add(&db, info_id, dir.path(), "note.txt", b"hello").id;
+
+        db.lock_vault().unwrap();
+        let target = dir.path().join("out.txt");
+        assert!(matches!(db.export_info_attachment(id, target.to_str().unwrap()), Err(AppError::VaultLocked)));
+
+        db.unlock_vault("correct horse battery staple").unwrap();
+        db.delete_personal_info(info_id).unwrap();
+        assert!(matches!(db.export_info_attachment(id, target.to_str().unwrap()), Err(AppError::NotFound(_))));
+        assert!(matches!(db.list_info_attachments(info_id), Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use chrono::Utc;
+use rusqlite::OptionalExtension;
+
+impl Database {
+    pub fn create_language(&self, req: CreateLanguageRequest) -> Result<Language, AppError> {
+        req.validate()?;
+
+        let name = sanitize_string(req.name);
+        let code = sanitize_string(req.code);
+        let flag_emoji = sanitize_string(req.flag_emoji);
+
+        let conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+
+        // Names and codes are unique across the trash too
+        let trashed: Option<String> = conn
+            .query_row(
+                "SELECT CASE WHEN name = ?1 THEN 'name' ELSE 'code' END FROM languages
+                 WHERE (name = ?1 OR code = ?2) AND deleted_at IS NOT NULL",
+                (&name, &code),
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(field) = trashed {
+            return Err(AppError::Duplicate { table: "languages".to_string(), field, in_trash: true });
+        }
+
+        conn.execute(
+            "INSERT INTO languages (name, code, flag_emoji, created_at) VALUES (?1, ?2, ?3, ?4)",
+            (&name, &code, &flag_emoji, &now),
+        )?;
+
+        let id = conn.last_insert_rowid();
+
+        Ok(Language {
+            id,
+            name,
+            code,
+            flag_emoji,
+            created_at: now,
+        })
+    }
+
+    pub fn get_languages(&self) -> Result<Vec<Language>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, code, flag_emoji, created_at FROM languages WHERE deleted_at IS NULL ORDER BY name"
+        )?;
+
+        let language_iter = stmt.query_map([], |row| {
+            Ok(Language {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                code: row.get(2)?,
+                flag_emoji: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+
+        let mut languages = Vec::new();
+        for language in language_iter {
+            languages.push(language?);
+        }
+
+        Ok(languages)
+    }
+
+    // Moves the language, its vocabulary and their flashcards to the trash
+    pub fn delete_language(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::Language, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str, code: &str) -> CreateLanguageRequest {
+        CreateLanguageRequest { name: name.to_string(), code: code.to_string(), flag_emoji: "🏳".to_string() }
+    }
+
+    #[test]
+    fn creates_and_lists_languages_by_name() {
+        let db = Database::open_in_memory().unwrap();
+        let spanish = db.create_language(request("  Spanish ", "es")).unwrap();
+        db.create_language(request("German", "de")).unwrap();
+        assert_eq!(spanish.name, "Spanish");
+
+        let names: Vec<_> = db.get_languages().unwrap().into_iter().map(|l| l.name).collect();
+        assert_eq!(names, ["German", "Spanish"]);
+
+        db.delete_language(spanish.id).unwrap();
+        assert_eq!(db.get_languages().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let db = Database::open_in_memory().unwrap();
+        let cases = [
+            (request(" ", "es"), "name"),
+            (request(&"x".repeat(51), "es"), "name"),
+            (request("Spanish", "ES"), "code"),
+            (request("Spanish", "esp"), "code"),
+            (CreateLanguageRequest { flag_emoji: String::new(), ..request("Spanish", "es") }, "flag_emoji"),
+        ];
+        for (req, field) in cases {
+            let err = db.create_language(req).unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+            assert_eq!(err.field(), Some(field));
+        }
+        assert!(db.get_languages().unwrap().is_empty());
+    }
+
+    #[test]
+    fn names_stay_taken_while_in_the_trash() {
+        let db = Database::open_in_memory().unwrap();
+        let spanish = db.create_language(request("Spanish", "es")).unwrap();
+        let err = db.create_language(request("Spanish", "sp")).unwrap_err();
+        assert!(matches!(err, AppError::Duplicate { in_trash: false, .. }), "{:?}", err);
+
+        db.delete_language(spanish.id).unwrap();
+        let err = db.create_language(request("Castilian", "es")).unwrap_err();
+        assert!(matches!(err, AppError::Duplicate { in_trash: true, .. }), "{:?}", err);
+        assert_eq!(err.field(), Some("code"));
+    }
+}
@@ -0,0 +1,234 @@
+use rusqlite::Connection;
+use crate::errors::AppError;
+
+// A single forward-only schema change, applied when the database's
+// `PRAGMA user_version` is below `version`.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+// Ordered list of every migration. Never edit or reorder an entry that has
+// shipped; append a new one with the next version number instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial schema",
+        sql: include_str!("../schema.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "Soft delete",
+        sql: include_str!("../migrations/0002_soft_delete.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "Revision history",
+        sql: include_str!("../migrations/0003_revisions.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "Vault master password",
+        sql: include_str!("../migrations/0004_vault.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "Password strength",
+        sql: include_str!("../migrations/0005_password_strength.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "TOTP secrets",
+        sql: include_str!("../migrations/0006_totp.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "Password history",
+        sql: include_str!("../migrations/0007_password_history.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "Secret access log",
+        sql: include_str!("../migrations/0008_secret_access_log.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "Info attachments",
+        sql: include_str!("../migrations/0009_info_attachments.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "Custom fields",
+        sql: include_str!("../migrations/0010_custom_fields.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "Flashcard schedulers",
+        sql: include_str!("../migrations/0011_scheduler.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "Review log details",
+        sql: include_str!("../migrations/0012_review_log_details.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "Vault legacy upgrade flag",
+        sql: include_str!("../migrations/0013_vault_legacy_upgrade.sql"),
+    },
+];
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn current_version(conn: &Connection) -> Result<i64, AppError> {
+    let version = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+// Brings the database up to `latest_version()`, one transaction per
+// migration so a failure leaves the schema at the last good version.
+pub fn run(conn: &mut Connection) -> Result<(), AppError> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+
+    if current > latest {
+        return Err(AppError::Migration(format!(
+            "Database schema version {} is newer than this version of YOTION supports ({}). Please update the app.",
+            current, latest
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql).map_err(|e| {
+            AppError::Migration(format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Applies the migrations up to and including `version`, as an older build
+    // would have left the database
+    fn migrate_to(conn: &Connection, version: i64) {
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current_version(conn).unwrap() && m.version <= version) {
+            conn.execute_batch(migration.sql).unwrap();
+            conn.pragma_update(None, "user_version", migration.version).unwrap();
+        }
+    }
+
+    fn has_column(conn: &Connection, table: &str, column: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+            [table, column],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn versions_count_up_from_one() {
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, index as i64 + 1, "{}", migration.description);
+        }
+        assert_eq!(latest_version(), MIGRATIONS.len() as i64);
+    }
+
+    // A column (or a table's key) that each migration from 2 on brings in
+    #[test]
+    fn each_migration_adds_its_schema() {
+        let added = [
+            (2, "notes", "deleted_at"),
+            (3, "revisions", "entity_id"),
+            (4, "vault", "verifier"),
+            (5, "personal_accounts", "password_strength"),
+            (6, "personal_accounts", "totp_secret"),
+            (7, "password_history", "account_id"),
+            (8, "secret_access_log", "item_id"),
+            (9, "info_attachment_chunks", "data"),
+            (10, "custom_fields", "field_type"),
+            (11, "flashcards", "stability"),
+            (12, "review_log", "prev_ease_factor"),
+            (13, "vault", "legacy_upgraded"),
+        ];
+        assert_eq!(added.len() as i64, latest_version() - 1);
+
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 1);
+        for (version, table, column) in added {
+            assert!(!has_column(&conn, table, column), "{}.{} before migration {}", table, column, version);
+            migrate_to(&conn, version);
+            assert!(has_column(&conn, table, column), "{}.{} after migration {}", table, column, version);
+        }
+    }
+
+    #[test]
+    fn upgrades_keep_existing_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 1);
+        conn.execute_batch(
+            "INSERT INTO languages (name, code, flag_emoji, created_at) VALUES ('German', 'de', 'x', '2024-01-01');
+             INSERT INTO vocabulary (language_id, word, translation, created_at) VALUES (1, 'Haus', 'house', '2024-01-01');
+             INSERT INTO flashcards (vocabulary_id, ease_factor, interval_days, repetitions, next_review, created_at)
+                 VALUES (1, 2.6, 6, 2, '2024-01-08', '2024-01-01');
+             INSERT INTO personal_accounts (title, email, password, category, created_at, updated_at)
+                 VALUES ('Bank', 'e', 'p', 'banking', '2024-01-01', '2024-01-01');",
+        )
+        .unwrap();
+        migrate_to(&conn, 11);
+        conn.execute_batch(
+            "INSERT INTO review_log (flashcard_id, reviewed_at, quality) VALUES (1, '2024-01-01', 4);
+             INSERT INTO review_log (flashcard_id, reviewed_at, quality, elapsed_days) VALUES (1, '2024-01-02', 5, 1);",
+        )
+        .unwrap();
+
+        run(&mut conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+
+        let (word, deleted_at): (String, Option<String>) = conn
+            .query_row("SELECT word, deleted_at FROM vocabulary", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((word.as_str(), deleted_at), ("Haus", None));
+        let strength: Option<i64> = conn.query_row("SELECT password_strength FROM personal_accounts", [], |row| row.get(0)).unwrap();
+        assert_eq!(strength, None);
+
+        // Only each card's latest logged review knows the state it left
+        let after: Vec<(Option<f64>, Option<i64>)> = conn
+            .prepare("SELECT new_ease_factor, new_interval_days FROM review_log ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(after, vec![(None, None), (Some(2.6), Some(6))]);
+    }
+
+    #[test]
+    fn running_again_changes_nothing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn).unwrap();
+        run(&mut conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn refuses_newer_databases() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+        assert!(matches!(run(&mut conn), Err(AppError::Migration(_))));
+        assert_eq!(current_version(&conn).unwrap(), latest_version() + 1);
+    }
+}
@@ -0,0 +1,729 @@
+use crate::database::Database;
+use crate::encryption::{self, EncryptionService};
+use crate::errors::AppError;
+use crate::models::*;
+use crate::passwords::estimate_strength;
+use crate::totp::TotpConfig;
+use crate::validation::*;
+use super::custom_fields::{load_all_custom_fields, load_custom_fields, save_custom_fields, FieldOwner, SecretValues};
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+
+// Earlier passwords kept per account
+const MAX_PASSWORD_HISTORY: i64 = 20;
+
+impl Database {
+    pub fn create_personal_account(&self, req: CreatePersonalAccountRequest) -> Result<PersonalAccount, AppError> {
+        req.validate()?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        let account = insert_personal_account(&tx, &key, req)?;
+        tx.commit()?;
+        Ok(account)
+    }
+
+    pub fn get_personal_accounts(&self) -> Result<Vec<PersonalAccountSummary>, AppError> {
+        let key = self.vault_key()?;
+        let conn = self.reader()?;
+        list_personal_accounts(&conn, &key, None)
+    }
+
+    pub fn get_personal_accounts_by_category(&self, category: &str) -> Result<Vec<PersonalAccountSummary>, AppError> {
+        let key = self.vault_key()?;
+        let conn = self.reader()?;
+        list_personal_accounts(&conn, &key, Some(category))
+    }
+
+    // Replaces every field of an account, the password only if one is given.
+    // A new password sends the old one to the account's password history.
+    pub fn update_personal_account(&self, id: i64, req: UpdatePersonalAccountRequest) -> Result<PersonalAccount, AppError> {
+        req.validate()?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        let account = write_personal_account(&tx, &key, id, req)?;
+        tx.commit()?;
+        Ok(account)
+    }
+
+    // Earlier passwords of an account, most recently replaced first
+    pub fn get_password_history(&self, account_id: i64) -> Result<Vec<PasswordHistoryEntry>, AppError> {
+        let key = self.vault_key()?;
+        let conn = self.reader()?;
+        read_account_summary(&conn, &key, account_id)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, account_id, replaced_at FROM password_history
+             WHERE account_id = ?1 ORDER BY replaced_at DESC, id DESC"
+        )?;
+        let entries = stmt
+            .query_map([account_id], |row| {
+                Ok(PasswordHistoryEntry {
+                    id: row.get(0)?,
+                    account_id: row.get(1)?,
+                    replaced_at: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    // Makes an earlier password current again. The password it replaces
+    // goes into the history in its place.
+    pub fn restore_password(&self, account_id: i64, history_id: i64) -> Result<PersonalAccountSummary, AppError> {
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+
+        let current = read_personal_account(&tx, &key, account_id)?;
+        let encrypted_password: String = tx
+            .query_row(
+                "SELECT password FROM password_history WHERE id = ?1 AND account_id = ?2",
+                (history_id, account_id),
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Password history entry {} not found", history_id)))?;
+        let password = decrypt_value(
+            &key,
+            &encrypted_password,
+            &encryption::aad("password_history", "password", history_id),
+            &format!("password history entry {}", history_id),
+        )?;
+        let password_strength = estimate_strength(&password).score;
+
+        tx.execute("DELETE FROM password_history WHERE id = ?1", [history_id])?;
+        push_password_history(&tx, &key, account_id, &current.password, &now)?;
+        tx.execute(
+            "UPDATE personal_accounts SET password = ?1, password_strength = ?2, updated_at = ?3 WHERE id = ?4",
+            (key.encrypt(&password, &encryption::aad("personal_accounts", "password", account_id))?, password_strength, &now, account_id),
+        )?;
+        let account = read_account_summary(&tx, &key, account_id)?;
+        tx.commit()?;
+
+        Ok(account)
+    }
+
+    // Current code for the account's 2FA secret
+    pub fn get_totp_code(&self, id: i64) -> Result<TotpCode, AppError> {
+        let key = self.vault_key()?;
+        let conn = self.reader()?;
+        let encrypted: Option<String> = conn
+            .query_row(
+                "SELECT totp_secret FROM personal_accounts WHERE id = ?1 AND deleted_at IS NULL",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound("Personal account not found".to_string()))?;
+        let encrypted = encrypted.ok_or_else(|| AppError::NotFound("This account has no 2FA secret".to_string()))?;
+
+        let config = TotpConfig::parse(&decrypt_value(
+            &key,
+            &encrypted,
+            &encryption::aad("personal_accounts", "totp_secret", id),
+            &format!("2FA secret of personal account {}", id),
+        )?)?;
+        let now = u64::try_from(Utc::now().timestamp()).unwrap_or(0);
+        Ok(config.code_at(now))
+    }
+
+    pub fn delete_personal_account(&self, id: i64) -> Result<(), AppError> {
+        self.vault_key()?;
+        self.move_to_trash(TrashKind::PersonalAccount, id)
+    }
+
+    pub fn create_personal_info(&self, req: CreatePersonalInfoRequest) -> Result<PersonalInfo, AppError> {
+        req.validate()?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        let info = insert_personal_info(&tx, &key, req)?;
+        tx.commit()?;
+        Ok(info)
+    }
+
+    pub fn get_personal_info(&self) -> Result<Vec<PersonalInfoSummary>, AppError> {
+        self.vault_key()?;
+        let conn = self.reader()?;
+        list_personal_info(&conn, None)
+    }
+
+    pub fn get_personal_info_by_category(&self, category: &str) -> Result<Vec<PersonalInfoSummary>, AppError> {
+        self.vault_key()?;
+        let conn = self.reader()?;
+        list_personal_info(&conn, Some(category))
+    }
+
+    pub fn update_personal_info(&self, id: i64, req: CreatePersonalInfoRequest) -> Result<PersonalInfo, AppError> {
+        req.validate()?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        let info = write_personal_info(&tx, &key, id, req)?;
+        tx.commit()?;
+        Ok(info)
+    }
+
+    pub fn delete_personal_info(&self, id: i64) -> Result<(), AppError> {
+        self.vault_key()?;
+        self.move_to_trash(TrashKind::PersonalInfo, id)
+    }
+}
+
+// Scores accounts saved before strength was recorded. Needs the key, so it
+// runs when the vault is set up or unlocked.
+pub(super) fn fill_password_strength(conn: &Connection, key: &EncryptionService) -> Result<(), AppError> {
+    let mut stmt = conn.prepare("SELECT id, password FROM personal_accounts WHERE password_strength IS NULL")?;
+    let unscored = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (id, encrypted_password) in unscored {
+        // Leave values this key can't read for a later unlock
+        if let Ok(password) = key.decrypt(&encrypted_password, &encryption::aad("personal_accounts", "password", id)) {
+            conn.execute(
+                "UPDATE personal_accounts SET password_strength = ?1 WHERE id = ?2",
+                (estimate_strength(&password).score, id),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Saves an already validated account, encrypting its secrets
+pub(super) fn insert_personal_account(
+    conn: &Connection,
+    key: &EncryptionService,
+    req: CreatePersonalAccountRequest,
+) -> Result<PersonalAccount, AppError> {
+    let title = sanitize_string(req.title);
+    let email = sanitize_string(req.email);
+    let website = sanitize_optional_string(req.website);
+    let notes = sanitize_optional_string(req.notes);
+    let category = sanitize_string(req.category);
+    let password_strength = estimate_strength(&req.password).score;
+    let totp_secret = normalize_totp_secret(req.totp_secret.as_deref())?;
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO personal_accounts (title, email, password, website, notes, category, password_strength, created_at, updated_at)
+         VALUES (?1, '', '', ?2, ?3, ?4, ?5, ?6, ?7)",
+        (&title, &website, &notes, &category, password_strength, &now, &now),
+    )?;
+    let id = conn.last_insert_rowid();
+    write_account_secrets(conn, key, id, &email, &req.password, totp_secret.as_deref())?;
+    let custom_fields = save_custom_fields(conn, key, FieldOwner::Account, id, req.custom_fields)?;
+
+    Ok(PersonalAccount {
+        id,
+        title,
+        email, // Return unencrypted for display
+        password: req.password, // Return unencrypted for display
+        website,
+        notes,
+        category,
+        password_strength: Some(password_strength),
+        totp_secret,
+        custom_fields,
+        created_at: now.clone(),
+        updated_at: now,
+    })
+}
+
+// `update_personal_account` for an already validated request, in the
+// caller's transaction
+pub(super) fn write_personal_account(
+    conn: &Connection,
+    key: &EncryptionService,
+    id: i64,
+    req: UpdatePersonalAccountRequest,
+) -> Result<PersonalAccount, AppError> {
+    let title = sanitize_string(req.title);
+    let email = sanitize_string(req.email);
+    let website = sanitize_optional_string(req.website);
+    let notes = sanitize_optional_string(req.notes);
+    let category = sanitize_string(req.category);
+    let totp_secret = normalize_totp_secret(req.totp_secret.as_deref())?;
+    let now = Utc::now().to_rfc3339();
+
+    let current = read_personal_account(conn, key, id)?;
+    let password = req.password.unwrap_or_else(|| current.password.clone());
+    if current.password != password {
+        push_password_history(conn, key, id, &current.password, &now)?;
+    }
+    let password_strength = estimate_strength(&password).score;
+
+    conn.execute(
+        "UPDATE personal_accounts SET title = ?1, website = ?2, notes = ?3, category = ?4, password_strength = ?5, updated_at = ?6
+         WHERE id = ?7",
+        (&title, &website, &notes, &category, password_strength, &now, id),
+    )?;
+    write_account_secrets(conn, key, id, &email, &password, totp_secret.as_deref())?;
+    let custom_fields = save_custom_fields(conn, key, FieldOwner::Account, id, req.custom_fields)?;
+
+    Ok(PersonalAccount {
+        id,
+        title,
+        email,
+        password,
+        website,
+        notes,
+        category,
+        password_strength: Some(password_strength),
+        totp_secret,
+        custom_fields,
+        created_at: current.created_at,
+        updated_at: now,
+    })
+}
+
+// Encrypts an account's secrets for its row
+fn write_account_secrets(
+    conn: &Connection,
+    key: &EncryptionService,
+    id: i64,
+    email: &str,
+    password: &str,
+    totp_secret: Option<&str>,
+) -> Result<(), AppError> {
+    let aad = |column| encryption::aad("personal_accounts", column, id);
+    conn.execute(
+        "UPDATE personal_accounts SET email = ?1, password = ?2, totp_secret = ?3 WHERE id = ?4",
+        (
+            key.encrypt(email, &aad("email"))?,
+            key.encrypt(password, &aad("password"))?,
+            totp_secret.map(|secret| key.encrypt(secret, &aad("totp_secret"))).transpose()?,
+            id,
+        ),
+    )?;
+    Ok(())
+}
+
+// Saves already validated info, encrypting its content if it is sensitive
+pub(super) fn insert_personal_info(
+    conn: &Connection,
+    key: &EncryptionService,
+    req: CreatePersonalInfoRequest,
+) -> Result<PersonalInfo, AppError> {
+    let title = sanitize_string(req.title);
+    let category = sanitize_string(req.category);
+    let now = Utc::now().to_rfc3339();
+
+    conn.execute(
+        "INSERT INTO personal_info (title, content, category, is_sensitive, created_at, updated_at)
+         VALUES (?1, '', ?2, ?3, ?4, ?5)",
+        (&title, &category, &req.is_sensitive, &now, &now),
+    )?;
+
+    let id = conn.last_insert_rowid();
+    // Encrypt sensitive data if marked as sensitive
+    conn.execute(
+        "UPDATE personal_info SET content = ?1 WHERE id = ?2",
+        (stored_info_content(key, id, &req.content, req.is_sensitive)?, id),
+    )?;
+    let custom_fields = save_custom_fields(conn, key, FieldOwner::Info, id, req.custom_fields)?;
+
+    Ok(PersonalInfo {
+        id,
+        title,
+        content: req.content, // Return unencrypted for display
+        category,
+        is_sensitive: req.is_sensitive,
+        custom_fields,
+        created_at: now.clone(),
+        updated_at: now,
+    })
+}
+
+pub(super) fn write_personal_info(
+    conn: &Connection,
+    key: &EncryptionService,
+    id: i64,
+    req: CreatePersonalInfoRequest,
+) -> Result<PersonalInfo, AppError> {
+    let title = sanitize_string(req.title);
+    let category = sanitize_string(req.category);
+    let now = Utc::now().to_rfc3339();
+
+    let created_at: String = conn
+        .query_row(
+            "SELECT created_at FROM personal_info WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Personal info {} not found", id)))?;
+
+    conn.execute(
+        "UPDATE personal_info SET title = ?1, content = ?2, category = ?3, is_sensitive = ?4, updated_at = ?5 WHERE id = ?6",
+        (&title, stored_info_content(key, id, &req.content, req.is_sensitive)?, &category, &req.is_sensitive, &now, id),
+    )?;
+    let custom_fields = save_custom_fields(conn, key, FieldOwner::Info, id, req.custom_fields)?;
+
+    Ok(PersonalInfo {
+        id,
+        title,
+        content: req.content,
+        category,
+        is_sensitive: req.is_sensitive,
+        custom_fields,
+        created_at,
+        updated_at: now,
+    })
+}
+
+fn stored_info_content(key: &EncryptionService, id: i64, content: &str, is_sensitive: bool) -> Result<String, AppError> {
+    if is_sensitive {
+        key.encrypt(content, &encryption::aad("personal_info", "content", id))
+    } else {
+        Ok(content.to_string())
+    }
+}
+
+// Stores 2FA secrets as canonical otpauth URIs; blank means none
+fn normalize_totp_secret(secret: Option<&str>) -> Result<Option<String>, AppError> {
+    match secret.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(secret) => Ok(Some(TotpConfig::parse(secret)?.to_uri())),
+        None => Ok(None),
+    }
+}
+
+pub(super) fn read_personal_account(conn: &Connection, key: &EncryptionService, id: i64) -> Result<PersonalAccount, AppError> {
+    let account = conn
+        .query_row(
+            "SELECT id, title, email, password, website, notes, category, password_strength, totp_secret, created_at, updated_at
+             FROM personal_accounts WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |row| {
+                Ok(PersonalAccount {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    email: row.get(2)?,
+                    password: row.get(3)?,
+                    website: row.get(4)?,
+                    notes: row.get(5)?,
+                    category: row.get(6)?,
+                    password_strength: row.get(7)?,
+                    totp_secret: row.get(8)?,
+                    custom_fields: Vec::new(),
+                    created_at: row.get(9)?,
+                    updated_at: row.get(10)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Personal account {} not found", id)))?;
+
+    let aad = |column| encryption::aad("personal_accounts", column, id);
+    Ok(PersonalAccount {
+        email: decrypt_value(key, &account.email, &aad("email"), &format!("email of personal account {}", id))?,
+        password: decrypt_value(key, &account.password, &aad("password"), &format!("password of personal account {}", id))?,
+        totp_secret: account
+            .totp_secret
+            .as_deref()
+            .map(|secret| decrypt_value(key, secret, &aad("totp_secret"), &format!("2FA secret of personal account {}", id)))
+            .transpose()?,
+        custom_fields: load_custom_fields(conn, FieldOwner::Account, id, SecretValues::Decrypted(key))?,
+        ..account
+    })
+}
+
+// With its content and custom fields decrypted
+pub(super) fn read_personal_info(conn: &Connection, key: &EncryptionService, id: i64) -> Result<PersonalInfo, AppError> {
+    let info = conn
+        .query_row(
+            "SELECT id, title, content, category, is_sensitive, created_at, updated_at
+             FROM personal_info WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+            |row| {
+                Ok(PersonalInfo {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get(2)?,
+                    category: row.get(3)?,
+                    is_sensitive: row.get(4)?,
+                    custom_fields: Vec::new(),
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Personal info {} not found", id)))?;
+
+    let content = if info.is_sensitive {
+        decrypt_value(
+            key,
+            &info.content,
+            &encryption::aad("personal_info", "content", id),
+            &format!("content of personal info {}", id),
+        )?
+    } else {
+        info.content
+    };
+    Ok(PersonalInfo {
+        content,
+        custom_fields: load_custom_fields(conn, FieldOwner::Info, id, SecretValues::Decrypted(key))?,
+        ..info
+    })
+}
+
+// Decrypts a stored value, naming it in the error so a damaged entry can be
+// told apart from a wrong key. `aad` is where it is stored; see
+// `encryption::aad`.
+pub(super) fn decrypt_value(key: &EncryptionService, value: &str, aad: &str, what: &str) -> Result<String, AppError> {
+    key.decrypt(value, aad)
+        .map_err(|e| AppError::Encryption(format!("Can't decrypt the {}: {}", what, e.message())))
+}
+
+const ACCOUNT_SUMMARY_COLUMNS: &str =
+    "id, title, email, website, notes, category, password_strength, totp_secret IS NOT NULL, created_at, updated_at";
+
+// Leaves `email` encrypted; see `decrypt_summary`
+fn map_account_summary(row: &rusqlite::Row) -> rusqlite::Result<PersonalAccountSummary> {
+    Ok(PersonalAccountSummary {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        email: row.get(2)?,
+        website: row.get(3)?,
+        notes: row.get(4)?,
+        category: row.get(5)?,
+        password_strength: row.get(6)?,
+        has_totp: row.get(7)?,
+        custom_fields: Vec::new(),
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn decrypt_summary(key: &EncryptionService, account: PersonalAccountSummary) -> Result<PersonalAccountSummary, AppError> {
+    Ok(PersonalAccountSummary {
+        email: decrypt_value(
+            key,
+            &account.email,
+            &encryption::aad("personal_accounts", "email", account.id),
+            &format!("email of personal account {}", account.id),
+        )?,
+        ..account
+    })
+}
+
+fn list_personal_accounts(
+    conn: &Connection,
+    key: &EncryptionService,
+    category: Option<&str>,
+) -> Result<Vec<PersonalAccountSummary>, AppError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM personal_accounts
+         WHERE (?1 IS NULL OR category = ?1) AND deleted_at IS NULL ORDER BY created_at DESC",
+        ACCOUNT_SUMMARY_COLUMNS
+    ))?;
+    let accounts = stmt
+        .query_map([category], map_account_summary)?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut fields = load_all_custom_fields(conn, FieldOwner::Account, SecretValues::Masked)?;
+
+    accounts
+        .into_iter()
+        .map(|account| {
+            let custom_fields = fields.remove(&account.id).unwrap_or_default();
+            decrypt_summary(key, PersonalAccountSummary { custom_fields, ..account })
+        })
+        .collect()
+}
+
+fn read_account_summary(conn: &Connection, key: &EncryptionService, id: i64) -> Result<PersonalAccountSummary, AppError> {
+    let account = conn
+        .query_row(
+            &format!("SELECT {} FROM personal_accounts WHERE id = ?1 AND deleted_at IS NULL", ACCOUNT_SUMMARY_COLUMNS),
+            [id],
+            map_account_summary,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Personal account {} not found", id)))?;
+    let custom_fields = load_custom_fields(conn, FieldOwner::Account, id, SecretValues::Masked)?;
+    decrypt_summary(key, PersonalAccountSummary { custom_fields, ..account })
+}
+
+// Sensitive content stays out of the list; it is revealed one entry at a time
+fn list_personal_info(conn: &Connection, category: Option<&str>) -> Result<Vec<PersonalInfoSummary>, AppError> {
+    let mut fields = load_all_custom_fields(conn, FieldOwner::Info, SecretValues::Masked)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, title, content, category, is_sensitive, created_at, updated_at,
+                (SELECT COUNT(*) FROM info_attachments a WHERE a.info_id = personal_info.id)
+         FROM personal_info WHERE (?1 IS NULL OR category = ?1) AND deleted_at IS NULL ORDER BY created_at DESC"
+    )?;
+    let info = stmt
+        .query_map([category], |row| {
+            let id = row.get(0)?;
+            let is_sensitive: bool = row.get(4)?;
+            Ok(PersonalInfoSummary {
+                id,
+                title: row.get(1)?,
+                content: if is_sensitive { None } else { Some(row.get(2)?) },
+                category: row.get(3)?,
+                is_sensitive,
+                custom_fields: fields.remove(&id).unwrap_or_default(),
+                attachment_count: row.get(7)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(info)
+}
+
+// Adds the password an account is giving up to its history, dropping the
+// oldest entries beyond `MAX_PASSWORD_HISTORY`. It is encrypted again for
+// the history row rather than copied, as values are bound to their row.
+fn push_password_history(
+    conn: &Connection,
+    key: &EncryptionService,
+    account_id: i64,
+    password: &str,
+    replaced_at: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO password_history (account_id, password, replaced_at) VALUES (?1, '', ?2)",
+        (account_id, replaced_at),
+    )?;
+    let id = conn.last_insert_rowid();
+    conn.execute(
+        "UPDATE password_history SET password = ?1 WHERE id = ?2",
+        (key.encrypt(password, &encryption::aad("password_history", "password", id))?, id),
+    )?;
+    conn.execute(
+        "DELETE FROM password_history WHERE account_id = ?1 AND id NOT IN (
+             SELECT id FROM password_history WHERE account_id = ?1 ORDER BY replaced_at DESC, id DESC LIMIT ?2
+         )",
+        (account_id, MAX_PASSWORD_HISTORY),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        db
+    }
+
+    fn account(password: &str) -> CreatePersonalAccountRequest {
+        CreatePersonalAccountRequest {
+            title: "Bank".to_string(),
+            email: "me@example.com".to_string(),
+            password: password.to_string(),
+            website: None,
+            notes: None,
+            category: "banking".to_string(),
+            totp_secret: None,
+            custom_fields: Vec::new(),
+        }
+    }
+
+    fn update(password: Option<&str>) -> UpdatePersonalAccountRequest {
+        UpdatePersonalAccountRequest {
+            title: "Bank".to_string(),
+            email: "me@example.com".to_string(),
+            password: password.map(str::to_string),
+            website: None,
+            notes: Some("Renamed".to_string()),
+            category: "banking".to_string(),
+            totp_secret: None,
+            custom_fields: Vec::new(),
+        }
+    }
+
+    fn revealed_history(db: &Database, account_id: i64) -> Vec<String> {
+        db.get_password_history(account_id)
+            .unwrap()
+            .into_iter()
+            .map(|entry| db.reveal_secret(entry.id, SecretField::PreviousPassword).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn only_a_new_password_goes_to_the_history() {
+        let db = unlocked();
+        let id = db.create_personal_account(account("first")).unwrap().id;
+
+        let kept = db.update_personal_account(id, update(None)).unwrap();
+        assert_eq!(kept.password, "first");
+        assert_eq!(kept.notes.as_deref(), Some("Renamed"));
+        db.update_personal_account(id, update(Some("first"))).unwrap();
+        assert!(db.get_password_history(id).unwrap().is_empty());
+
+        db.update_personal_account(id, update(Some("second"))).unwrap();
+        db.update_personal_account(id, update(Some("third"))).unwrap();
+        assert_eq!(revealed_history(&db, id), ["second", "first"]);
+        assert_eq!(db.reveal_secret(id, SecretField::Password).unwrap(), "third");
+
+        let err = db.update_personal_account(id, update(Some(" "))).unwrap_err();
+        assert_eq!(err.field(), Some("password"));
+    }
+
+    #[test]
+    fn restoring_swaps_the_current_password_into_the_history() {
+        let db = unlocked();
+        let id = db.create_personal_account(account("first")).unwrap().id;
+        db.update_personal_account(id, update(Some("second"))).unwrap();
+
+        let first = db.get_password_history(id).unwrap().remove(0);
+        db.restore_password(id, first.id).unwrap();
+        assert_eq!(db.reveal_secret(id, SecretField::Password).unwrap(), "first");
+        assert_eq!(revealed_history(&db, id), ["second"]);
+
+        let err = db.restore_password(id, first.id).unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let db = unlocked();
+        let id = db.create_personal_account(account("password 0")).unwrap().id;
+        let changes = MAX_PASSWORD_HISTORY + 5;
+        for n in 1..=changes {
+            db.update_personal_account(id, update(Some(&format!("password {}", n)))).unwrap();
+        }
+
+        let history = revealed_history(&db, id);
+        assert_eq!(history.len() as i64, MAX_PASSWORD_HISTORY);
+        assert_eq!(history[0], format!("password {}", changes - 1));
+        assert_eq!(history.last().unwrap(), &format!("password {}", changes - MAX_PASSWORD_HISTORY));
+
+        // A restore at the cap keeps it there, dropping nothing else
+        let oldest = db.get_password_history(id).unwrap().pop().unwrap();
+        db.restore_password(id, oldest.id).unwrap();
+        let history = revealed_history(&db, id);
+        assert_eq!(history.len() as i64, MAX_PASSWORD_HISTORY);
+        assert_eq!(history[0], format!("password {}", changes));
+    }
+
+    #[test]
+    fn secrets_only_decrypt_in_their_own_row() {
+        let db = unlocked();
+        let bank = db.create_personal_account(account("bank password")).unwrap().id;
+        let shop = db.create_personal_account(account("shop password")).unwrap().id;
+        db.writer()
+            .unwrap()
+            .execute(
+                "UPDATE personal_accounts SET password = (SELECT password FROM personal_accounts WHERE id = ?1) WHERE id = ?2",
+                (bank, shop),
+            )
+            .unwrap();
+
+        let err = db.reveal_secret(shop, SecretField::Password).unwrap_err();
+        assert!(matches!(err, AppError::Encryption(_)), "{:?}", err);
+        assert_eq!(db.reveal_secret(bank, SecretField::Password).unwrap(), "bank password");
+    }
+}
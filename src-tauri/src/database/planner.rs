@@ -1,28 +1,29 @@
+use crate::database::Database;
+use crate::errors::AppError;
 use crate::models::*;
-use crate::db::Database;
 use crate::validation::*;
-use anyhow::Result;
+use super::revisions::{record_baseline, record_revision};
 use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
 
 impl Database {
-    pub fn create_event(&self, req: CreateEventRequest) -> Result<Event> {
-validate_not_empty(&req.title, "Event title")?;
-validate_string_length(&req.title, "Event title", 1, 200)?;
-validate_date_format(&req.event_date)?;
-validate_priority(&req.priority)?;
+    pub fn create_event(&self, req: CreateEventRequest) -> Result<Event, AppError> {
+        req.validate()?;
 
         let title = sanitize_string(req.title);
         let description = sanitize_optional_string(req.description);
+        let start_time = sanitize_optional_string(req.start_time);
+        let end_time = sanitize_optional_string(req.end_time);
         let event_type = sanitize_string(req.event_type);
         let priority = sanitize_string(req.priority);
 
-        let conn = self.conn.lock().unwrap();
+        let conn = self.writer()?;
         let now = Utc::now().to_rfc3339();
 
         conn.execute(
             "INSERT INTO events (title, description, event_date, start_time, end_time, event_type, priority, created_at, updated_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            (&title, &description, &req.event_date, &req.start_time, &req.end_time, &event_type, &priority, &now, &now),
+            (&title, &description, &req.event_date, &start_time, &end_time, &event_type, &priority, &now, &now),
         )?;
 
         let id = conn.last_insert_rowid();
@@ -32,8 +33,8 @@ validate_priority(&req.priority)?;
             title,
             description,
             event_date: req.event_date,
-            start_time: req.start_time,
-            end_time: req.end_time,
+            start_time,
+            end_time,
             event_type,
             priority,
             created_at: now.clone(),
@@ -41,13 +42,13 @@ validate_priority(&req.priority)?;
         })
     }
 
-    pub fn get_events_by_date(&self, date: &str) -> Result<Vec<Event>> {
-validate_date_format(date)?;
+    pub fn get_events_by_date(&self, date: &str) -> Result<Vec<Event>, AppError> {
+        validate_date_format(date)?;
 
-        let conn = self.conn.lock().unwrap();
+        let conn = self.reader()?;
         let mut stmt = conn.prepare(
             "SELECT id, title, description, event_date, start_time, end_time, event_type, priority, created_at, updated_at
-             FROM events WHERE event_date = ?1 ORDER BY start_time ASC"
+             FROM events WHERE event_date = ?1 AND deleted_at IS NULL ORDER BY start_time"
         )?;
 
         let event_iter = stmt.query_map([date], |row| {
@@ -73,45 +74,83 @@ validate_date_format(date)?;
         Ok(events)
     }
 
-    pub fn create_note(&self, req: CreateNoteRequest) -> Result<Note> {
-validate_not_empty(&req.title, "Note title")?;
-validate_string_length(&req.title, "Note title", 1, 200)?;
-validate_not_empty(&req.content, "Note content")?;
-validate_date_format(&req.note_date)?;
+    pub fn delete_event(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::Event, id)
+    }
+
+    pub fn create_note(&self, req: CreateNoteRequest) -> Result<Note, AppError> {
+        req.validate()?;
 
         let title = sanitize_string(req.title);
         let content = req.content;
         let tags = sanitize_optional_string(req.tags);
 
-        let conn = self.conn.lock().unwrap();
+        let mut conn = self.writer()?;
         let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
 
-        conn.execute(
+        tx.execute(
             "INSERT INTO notes (title, content, note_date, tags, created_at, updated_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
             (&title, &content, &req.note_date, &tags, &now, &now),
         )?;
 
-        let id = conn.last_insert_rowid();
+        let note = Note {
+            id: tx.last_insert_rowid(),
+            title,
+            content,
+            note_date: req.note_date,
+            tags,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::Note, note.id, &note)?;
+        tx.commit()?;
+
+        Ok(note)
+    }
+
+    pub fn update_note(&self, id: i64, req: CreateNoteRequest) -> Result<Note, AppError> {
+        req.validate()?;
+
+        let title = sanitize_string(req.title);
+        let content = req.content;
+        let tags = sanitize_optional_string(req.tags);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
 
-        Ok(Note {
+        let current = read_note(&tx, id)?;
+        record_baseline(&tx, RevisionKind::Note, id, &current)?;
+
+        tx.execute(
+            "UPDATE notes SET title = ?1, content = ?2, note_date = ?3, tags = ?4, updated_at = ?5 WHERE id = ?6",
+            (&title, &content, &req.note_date, &tags, &now, id),
+        )?;
+
+        let note = Note {
             id,
             title,
             content,
             note_date: req.note_date,
             tags,
-            created_at: now.clone(),
+            created_at: current.created_at,
             updated_at: now,
-        })
+        };
+        record_revision(&tx, RevisionKind::Note, id, &note)?;
+        tx.commit()?;
+
+        Ok(note)
     }
 
-    pub fn get_notes_by_date(&self, date: &str) -> Result<Vec<Note>> {
-validate_date_format(date)?;
+    pub fn get_notes_by_date(&self, date: &str) -> Result<Vec<Note>, AppError> {
+        validate_date_format(date)?;
 
-        let conn = self.conn.lock().unwrap();
+        let conn = self.reader()?;
         let mut stmt = conn.prepare(
             "SELECT id, title, content, note_date, tags, created_at, updated_at
-             FROM notes WHERE note_date = ?1 ORDER BY created_at DESC"
+             FROM notes WHERE note_date = ?1 AND deleted_at IS NULL ORDER BY created_at DESC"
         )?;
 
         let note_iter = stmt.query_map([date], |row| {
@@ -133,4 +172,29 @@ validate_date_format(date)?;
 
         Ok(notes)
     }
+
+    pub fn delete_note(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::Note, id)
+    }
+}
+
+fn read_note(conn: &Connection, id: i64) -> Result<Note, AppError> {
+    conn.query_row(
+        "SELECT id, title, content, note_date, tags, created_at, updated_at
+         FROM notes WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+        |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                note_date: row.get(3)?,
+                tags: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Note {} not found", id)))
 }
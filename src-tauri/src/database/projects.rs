@@ -0,0 +1,427 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use super::revisions::{record_baseline, record_revision};
+use super::trash::require_active;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+
+impl Database {
+    pub fn create_project(&self, req: CreateProjectRequest) -> Result<Project, AppError> {
+        req.validate()?;
+
+        let name = sanitize_string(req.name);
+        let description = sanitize_optional_string(req.description);
+        let status = sanitize_string(req.status);
+        let priority = sanitize_string(req.priority);
+        let start_date = sanitize_optional_string(req.start_date);
+        let end_date = sanitize_optional_string(req.end_date);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO projects (name, description, status, priority, start_date, end_date, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (&name, &description, &status, &priority, &start_date, &end_date, &now, &now),
+        )?;
+
+        let project = Project {
+            id: tx.last_insert_rowid(),
+            name,
+            description,
+            status,
+            priority,
+            start_date,
+            end_date,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::Project, project.id, &project)?;
+        tx.commit()?;
+
+        Ok(project)
+    }
+
+    pub fn update_project(&self, id: i64, req: CreateProjectRequest) -> Result<Project, AppError> {
+        req.validate()?;
+
+        let name = sanitize_string(req.name);
+        let description = sanitize_optional_string(req.description);
+        let status = sanitize_string(req.status);
+        let priority = sanitize_string(req.priority);
+        let start_date = sanitize_optional_string(req.start_date);
+        let end_date = sanitize_optional_string(req.end_date);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+
+        let current = read_project(&tx, id)?;
+        record_baseline(&tx, RevisionKind::Project, id, &current)?;
+
+        tx.execute(
+            "UPDATE projects SET name = ?1, description = ?2, status = ?3, priority = ?4, start_date = ?5, end_date = ?6, updated_at = ?7
+             WHERE id = ?8",
+            (&name, &description, &status, &priority, &start_date, &end_date, &now, id),
+        )?;
+
+        let project = Project {
+            id,
+            name,
+            description,
+            status,
+            priority,
+            start_date,
+            end_date,
+            created_at: current.created_at,
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::Project, id, &project)?;
+        tx.commit()?;
+
+        Ok(project)
+    }
+
+    pub fn get_projects(&self) -> Result<Vec<Project>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, description, status, priority, start_date, end_date, created_at, updated_at
+             FROM projects WHERE deleted_at IS NULL ORDER BY created_at DESC"
+        )?;
+
+        let project_iter = stmt.query_map([], |row| {
+            Ok(Project {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                status: row.get(3)?,
+                priority: row.get(4)?,
+                start_date: row.get(5)?,
+                end_date: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        })?;
+
+        let mut projects = Vec::new();
+        for project in project_iter {
+            projects.push(project?);
+        }
+
+        Ok(projects)
+    }
+
+    // Moves the project and its tasks to the trash
+    pub fn delete_project(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::Project, id)
+    }
+
+    pub fn create_task(&self, req: CreateTaskRequest) -> Result<Task, AppError> {
+        req.validate()?;
+
+        let title = sanitize_string(req.title);
+        let description = sanitize_optional_string(req.description);
+        let status = sanitize_string(req.status);
+        let priority = sanitize_string(req.priority);
+        let due_date = sanitize_optional_string(req.due_date);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let completed_at = if status == "completed" { Some(now.clone()) } else { None };
+        let tx = conn.transaction()?;
+        require_active(&tx, TrashKind::Project, req.project_id, "project_id")?;
+
+        tx.execute(
+            "INSERT INTO tasks (project_id, title, description, status, priority, due_date, completed_at, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (&req.project_id, &title, &description, &status, &priority, &due_date, &completed_at, &now, &now),
+        )?;
+
+        let task = Task {
+            id: tx.last_insert_rowid(),
+            project_id: req.project_id,
+            title,
+            description,
+            status,
+            priority,
+            due_date,
+            completed_at,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::Task, task.id, &task)?;
+        tx.commit()?;
+
+        Ok(task)
+    }
+
+    pub fn update_task(&self, id: i64, req: CreateTaskRequest) -> Result<Task, AppError> {
+        req.validate()?;
+
+        let title = sanitize_string(req.title);
+        let description = sanitize_optional_string(req.description);
+        let status = sanitize_string(req.status);
+        let priority = sanitize_string(req.priority);
+        let due_date = sanitize_optional_string(req.due_date);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        require_active(&tx, TrashKind::Project, req.project_id, "project_id")?;
+
+        let current = read_task(&tx, id)?;
+        record_baseline(&tx, RevisionKind::Task, id, &current)?;
+        let completed_at = completion_time(&current, &status, &now);
+
+        tx.execute(
+            "UPDATE tasks SET project_id = ?1, title = ?2, description = ?3, status = ?4, priority = ?5, due_date = ?6, completed_at = ?7, updated_at = ?8
+             WHERE id = ?9",
+            (&req.project_id, &title, &description, &status, &priority, &due_date, &completed_at, &now, id),
+        )?;
+
+        let task = Task {
+            id,
+            project_id: req.project_id,
+            title,
+            description,
+            status,
+            priority,
+            due_date,
+            completed_at,
+            created_at: current.created_at,
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::Task, id, &task)?;
+        tx.commit()?;
+
+        Ok(task)
+    }
+
+    pub fn get_tasks_by_project(&self, project_id: i64) -> Result<Vec<Task>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, project_id, title, description, status, priority, due_date, completed_at, created_at, updated_at
+             FROM tasks WHERE project_id = ?1 AND deleted_at IS NULL ORDER BY created_at DESC"
+        )?;
+
+        let task_iter = stmt.query_map([project_id], |row| {
+            Ok(Task {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                title: row.get(2)?,
+                description: row.get(3)?,
+                status: row.get(4)?,
+                priority: row.get(5)?,
+                due_date: row.get(6)?,
+                completed_at: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+            })
+        })?;
+
+        let mut tasks = Vec::new();
+        for task in task_iter {
+            tasks.push(task?);
+        }
+
+        Ok(tasks)
+    }
+
+    pub fn delete_task(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::Task, id)
+    }
+
+    pub fn update_task_status(&self, task_id: i64, status: &str) -> Result<(), AppError> {
+        validate_status(status, TASK_STATUSES)?;
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+
+        let current = read_task(&tx, task_id)?;
+        record_baseline(&tx, RevisionKind::Task, task_id, &current)?;
+        let completed_at = completion_time(&current, status, &now);
+
+        tx.execute(
+            "UPDATE tasks SET status = ?1, completed_at = ?2, updated_at = ?3 WHERE id = ?4",
+            (status, &completed_at, &now, task_id),
+        )?;
+
+        let task = Task {
+            status: status.to_string(),
+            completed_at,
+            updated_at: now,
+            ..current
+        };
+        record_revision(&tx, RevisionKind::Task, task_id, &task)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+// A task keeps its original completion time while it stays completed
+fn completion_time(current: &Task, status: &str, now: &str) -> Option<String> {
+    match (status, current.status.as_str()) {
+        ("completed", "completed") => current.completed_at.clone(),
+        ("completed", _) => Some(now.to_string()),
+        _ => None,
+    }
+}
+
+fn read_project(conn: &Connection, id: i64) -> Result<Project, AppError> {
+    conn.query_row(
+        "SELECT id, name, description, status, priority, start_date, end_date, created_at, updated_at
+         FROM projects WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+        |row| {
+            Ok(Project {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                status: row.get(3)?,
+                priority: row.get(4)?,
+                start_date: row.get(5)?,
+                end_date: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Project {} not found", id)))
+}
+
+fn read_task(conn: &Connection, id: i64) -> Result<Task, AppError> {
+    conn.query_row(
+        "SELECT id, project_id, title, description, status, priority, due_date, completed_at, created_at, updated_at
+         FROM tasks WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+        |row| {
+            Ok(Task {
+                id: row.get(0)?,
+                project_id: row.get(1)?,
+                title: row.get(2)?,
+                description: row.get(3)?,
+                status: row.get(4)?,
+                priority: row.get(5)?,
+                due_date: row.get(6)?,
+                completed_at: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Task {} not found", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(name: &str) -> CreateProjectRequest {
+        CreateProjectRequest {
+            name: name.to_string(),
+            description: None,
+            status: "active".to_string(),
+            priority: "medium".to_string(),
+            start_date: Some("2024-03-01".to_string()),
+            end_date: Some(String::new()),
+        }
+    }
+
+    fn task(project_id: i64, status: &str) -> CreateTaskRequest {
+        CreateTaskRequest {
+            project_id,
+            title: "Write docs".to_string(),
+            description: None,
+            status: status.to_string(),
+            priority: "high".to_string(),
+            due_date: None,
+        }
+    }
+
+    #[test]
+    fn creates_updates_and_lists_projects() {
+        let db = Database::open_in_memory().unwrap();
+        let created = db.create_project(project(" Website ")).unwrap();
+        assert_eq!(created.name, "Website");
+        assert_eq!(created.end_date, None);
+
+        let updated = db
+            .update_project(created.id, CreateProjectRequest { status: "on-hold".to_string(), ..project("Website") })
+            .unwrap();
+        assert_eq!(updated.created_at, created.created_at);
+
+        let projects = db.get_projects().unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].status, "on-hold");
+
+        let err = db.update_project(created.id + 1, project("Missing")).unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn rejects_invalid_projects() {
+        let db = Database::open_in_memory().unwrap();
+        let cases = [
+            (project("  "), "name"),
+            (CreateProjectRequest { status: "done".to_string(), ..project("Website") }, "status"),
+            (CreateProjectRequest { priority: "urgent".to_string(), ..project("Website") }, "priority"),
+            (CreateProjectRequest { start_date: Some("2024-02-30".to_string()), ..project("Website") }, "start_date"),
+            (CreateProjectRequest { end_date: Some("01/03/2024".to_string()), ..project("Website") }, "end_date"),
+            (CreateProjectRequest { end_date: Some("2024-02-01".to_string()), ..project("Website") }, "end_date"),
+        ];
+        for (req, field) in cases {
+            let err = db.create_project(req).unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+            assert_eq!(err.field(), Some(field));
+        }
+        assert!(db.get_projects().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tracks_task_completion_time() {
+        let db = Database::open_in_memory().unwrap();
+        let project_id = db.create_project(project("Website")).unwrap().id;
+        let created = db.create_task(task(project_id, "todo")).unwrap();
+        assert_eq!(created.completed_at, None);
+
+        db.update_task_status(created.id, "completed").unwrap();
+        let completed = db.get_tasks_by_project(project_id).unwrap().remove(0);
+        assert!(completed.completed_at.is_some());
+
+        // Editing a completed task keeps when it was completed
+        let edited = db.update_task(created.id, task(project_id, "completed")).unwrap();
+        assert_eq!(edited.completed_at, completed.completed_at);
+
+        let reopened = db.update_task(created.id, task(project_id, "in-progress")).unwrap();
+        assert_eq!(reopened.completed_at, None);
+    }
+
+    #[test]
+    fn rejects_invalid_tasks() {
+        let db = Database::open_in_memory().unwrap();
+        let project_id = db.create_project(project("Website")).unwrap().id;
+        let cases = [
+            (CreateTaskRequest { title: String::new(), ..task(project_id, "todo") }, "title"),
+            (task(project_id, "done"), "status"),
+            (CreateTaskRequest { priority: "LOW".to_string(), ..task(project_id, "todo") }, "priority"),
+            (CreateTaskRequest { due_date: Some("tomorrow".to_string()), ..task(project_id, "todo") }, "due_date"),
+        ];
+        for (req, field) in cases {
+            let err = db.create_task(req).unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+            assert_eq!(err.field(), Some(field));
+        }
+
+        let err = db.create_task(task(project_id + 1, "todo")).unwrap_err();
+        assert_eq!(err.field(), Some("project_id"));
+        assert!(matches!(db.update_task_status(1, "done").unwrap_err(), AppError::Validation { .. }));
+        assert!(db.get_tasks_by_project(project_id).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,267 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use rusqlite::{Connection, OptionalExtension};
+
+// Card state a review replaced, as logged with it
+struct PreviousState {
+    ease_factor: Option<f64>,
+    interval_days: Option<i64>,
+    repetitions: Option<i64>,
+    stability: Option<f64>,
+    difficulty: Option<f64>,
+    next_review: Option<String>,
+    last_reviewed: Option<String>,
+}
+
+impl Database {
+    // A card's logged reviews, newest first
+    pub fn get_flashcard_history(&self, flashcard_id: i64) -> Result<Vec<ReviewLogEntry>, AppError> {
+        let conn = self.reader()?;
+        require_flashcard(&conn, flashcard_id)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, flashcard_id, reviewed_at, quality, duration_ms, elapsed_days,
+                    prev_ease_factor, new_ease_factor, prev_interval_days, new_interval_days
+             FROM review_log WHERE flashcard_id = ?1
+             ORDER BY reviewed_at DESC, id DESC",
+        )?;
+        let entries = stmt
+            .query_map([flashcard_id], |row| {
+                Ok(ReviewLogEntry {
+                    id: row.get(0)?,
+                    flashcard_id: row.get(1)?,
+                    reviewed_at: row.get(2)?,
+                    quality: row.get(3)?,
+                    duration_ms: row.get(4)?,
+                    elapsed_days: row.get(5)?,
+                    prev_ease_factor: row.get(6)?,
+                    new_ease_factor: row.get(7)?,
+                    prev_interval_days: row.get(8)?,
+                    new_interval_days: row.get(9)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    // Takes back the most recent review, of the given card or of any card
+    // not in the trash: the card gets its earlier state back and the review
+    // leaves the log. Returns the restored card.
+    pub fn undo_last_review(&self, flashcard_id: Option<i64>) -> Result<Flashcard, AppError> {
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+        if let Some(id) = flashcard_id {
+            require_flashcard(&tx, id)?;
+        }
+
+        let last = tx
+            .query_row(
+                "SELECT r.id, r.flashcard_id, r.prev_ease_factor, r.prev_interval_days, r.prev_repetitions,
+                        r.prev_stability, r.prev_difficulty, r.prev_next_review, r.prev_last_reviewed
+                 FROM review_log r JOIN flashcards f ON f.id = r.flashcard_id
+                 WHERE f.deleted_at IS NULL AND (?1 IS NULL OR r.flashcard_id = ?1)
+                 ORDER BY r.reviewed_at DESC, r.id DESC LIMIT 1",
+                [flashcard_id],
+                |row| {
+                    let previous = PreviousState {
+                        ease_factor: row.get(2)?,
+                        interval_days: row.get(3)?,
+                        repetitions: row.get(4)?,
+                        stability: row.get(5)?,
+                        difficulty: row.get(6)?,
+                        next_review: row.get(7)?,
+                        last_reviewed: row.get(8)?,
+                    };
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, previous))
+                },
+            )
+            .optional()?;
+        let Some((log_id, card_id, previous)) = last else {
+            return Err(AppError::NotFound("There is no review to undo".to_string()));
+        };
+
+        let (Some(ease_factor), Some(interval_days), Some(repetitions), Some(next_review)) =
+            (previous.ease_factor, previous.interval_days, previous.repetitions, previous.next_review)
+        else {
+            return Err(AppError::validation(
+                "This review was logged before reviews could be undone".to_string(),
+            ));
+        };
+
+        tx.execute(
+            "UPDATE flashcards SET ease_factor = ?1, interval_days = ?2, repetitions = ?3, stability = ?4, difficulty = ?5,
+                    next_review = ?6, last_reviewed = ?7
+             WHERE id = ?8",
+            (
+                ease_factor,
+                interval_days,
+                repetitions,
+                previous.stability,
+                previous.difficulty,
+                &next_review,
+                &previous.last_reviewed,
+                card_id,
+            ),
+        )?;
+        tx.execute("DELETE FROM review_log WHERE id = ?1", [log_id])?;
+
+        let card = tx.query_row(
+            "SELECT id, vocabulary_id, ease_factor, interval_days, repetitions, next_review, last_reviewed, stability, difficulty, created_at
+             FROM flashcards WHERE id = ?1",
+            [card_id],
+            |row| {
+                Ok(Flashcard {
+                    id: row.get(0)?,
+                    vocabulary_id: row.get(1)?,
+                    ease_factor: row.get(2)?,
+                    interval_days: row.get(3)?,
+                    repetitions: row.get(4)?,
+                    next_review: row.get(5)?,
+                    last_reviewed: row.get(6)?,
+                    stability: row.get(7)?,
+                    difficulty: row.get(8)?,
+                    created_at: row.get(9)?,
+                })
+            },
+        )?;
+        tx.commit()?;
+
+        Ok(card)
+    }
+}
+
+fn require_flashcard(conn: &Connection, flashcard_id: i64) -> Result<(), AppError> {
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM flashcards WHERE id = ?1 AND deleted_at IS NULL",
+            [flashcard_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match exists {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound(format!("Flashcard {} not found", flashcard_id))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A database with one word per given spelling, returning their card ids
+    fn cards(words: &[&str]) -> (Database, Vec<i64>) {
+        let db = Database::open_in_memory().unwrap();
+        let language = db
+            .create_language(CreateLanguageRequest {
+                name: "Spanish".to_string(),
+                code: "es".to_string(),
+                flag_emoji: "🇪🇸".to_string(),
+            })
+            .unwrap();
+        for word in words {
+            db.create_vocabulary(CreateVocabularyRequest {
+                language_id: language.id,
+                word: word.to_string(),
+                translation: "-".to_string(),
+                pronunciation: None,
+                example_sentence: None,
+                difficulty_level: 1,
+            })
+            .unwrap();
+        }
+        let ids = {
+            let conn = db.reader().unwrap();
+            let mut stmt = conn.prepare("SELECT id FROM flashcards ORDER BY vocabulary_id").unwrap();
+            let ids = stmt.query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap();
+            ids
+        };
+        (db, ids)
+    }
+
+    fn review(db: &Database, flashcard_id: i64, quality: i64) {
+        db.review_flashcard(FlashcardReviewRequest { flashcard_id, quality, duration_ms: None }).unwrap();
+    }
+
+    // Scheduling state of a card, comparable across reviews
+    fn state(db: &Database, flashcard_id: i64) -> serde_json::Value {
+        db.reader()
+            .unwrap()
+            .query_row(
+                "SELECT ease_factor, interval_days, repetitions, stability, difficulty, next_review, last_reviewed
+                 FROM flashcards WHERE id = ?1",
+                [flashcard_id],
+                |row| {
+                    Ok(serde_json::json!([
+                        row.get::<_, f64>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, Option<f64>>(3)?,
+                        row.get::<_, Option<f64>>(4)?,
+                        row.get::<_, String>(5)?,
+                        row.get::<_, Option<String>>(6)?,
+                    ]))
+                },
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn undo_restores_the_previous_state_and_drops_the_review() {
+        let (db, ids) = cards(&["casa"]);
+        let card = ids[0];
+        let fresh = state(&db, card);
+        review(&db, card, 5);
+        let after_first = state(&db, card);
+        review(&db, card, 2);
+        assert_eq!(db.get_flashcard_history(card).unwrap().len(), 2);
+
+        let restored = db.undo_last_review(Some(card)).unwrap();
+        assert_eq!(restored.id, card);
+        assert_eq!(state(&db, card), after_first);
+        let history = db.get_flashcard_history(card).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].quality, 5);
+
+        db.undo_last_review(None).unwrap();
+        assert_eq!(state(&db, card), fresh);
+        assert!(db.get_flashcard_history(card).unwrap().is_empty());
+    }
+
+    #[test]
+    fn nothing_to_undo_is_not_found() {
+        let (db, ids) = cards(&["casa"]);
+        assert!(matches!(db.undo_last_review(None), Err(AppError::NotFound(_))));
+        assert!(matches!(db.undo_last_review(Some(ids[0])), Err(AppError::NotFound(_))));
+        assert!(matches!(db.undo_last_review(Some(999)), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn undo_without_a_card_takes_the_latest_review_of_a_card_not_in_the_trash() {
+        let (db, ids) = cards(&["casa", "agua"]);
+        review(&db, ids[0], 4);
+        review(&db, ids[1], 4);
+        assert_eq!(db.undo_last_review(None).unwrap().id, ids[1]);
+
+        review(&db, ids[1], 4);
+        let agua = db.undo_last_review(Some(ids[1])).unwrap();
+        review(&db, ids[1], 4);
+        db.delete_vocabulary(agua.vocabulary_id).unwrap();
+        assert_eq!(db.undo_last_review(None).unwrap().id, ids[0]);
+        assert!(matches!(db.undo_last_review(None), Err(AppError::NotFound(_))));
+        assert!(matches!(db.undo_last_review(Some(ids[1])), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn reviews_logged_without_the_earlier_state_cannot_be_undone() {
+        let (db, ids) = cards(&["casa"]);
+        review(&db, ids[0], 4);
+        let before = state(&db, ids[0]);
+        db.writer().unwrap().execute("UPDATE review_log SET prev_ease_factor = NULL", []).unwrap();
+
+        let err = db.undo_last_review(Some(ids[0])).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert_eq!(state(&db, ids[0]), before);
+        assert_eq!(db.get_flashcard_history(ids[0]).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,396 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use chrono::Utc;
+use rusqlite::types::Type;
+use rusqlite::Error::FromSqlConversionFailure;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use similar::{ChangeTag, TextDiff};
+
+impl RevisionKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            RevisionKind::CodeSnippet => "code_snippet",
+            RevisionKind::Note => "note",
+            RevisionKind::Task => "task",
+            RevisionKind::Project => "project",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "code_snippet" => Some(RevisionKind::CodeSnippet),
+            "note" => Some(RevisionKind::Note),
+            "task" => Some(RevisionKind::Task),
+            "project" => Some(RevisionKind::Project),
+            _ => None,
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            RevisionKind::CodeSnippet => "code_snippets",
+            RevisionKind::Note => "notes",
+            RevisionKind::Task => "tasks",
+            RevisionKind::Project => "projects",
+        }
+    }
+
+    // The multi-line field shown after the other fields in a diff
+    fn body_field(self) -> &'static str {
+        match self {
+            RevisionKind::CodeSnippet => "code",
+            RevisionKind::Note => "content",
+            RevisionKind::Task | RevisionKind::Project => "description",
+        }
+    }
+}
+
+impl Database {
+    // Oldest first; the last revision matches the current state
+    pub fn list_revisions(&self, kind: RevisionKind, entity_id: i64) -> Result<Vec<Revision>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, entity_type, entity_id, snapshot, created_at
+             FROM revisions WHERE entity_type = ?1 AND entity_id = ?2 ORDER BY id"
+        )?;
+
+        let revision_iter = stmt.query_map((kind.as_str(), entity_id), map_revision)?;
+
+        let mut revisions = Vec::new();
+        for revision in revision_iter {
+            revisions.push(revision?);
+        }
+
+        Ok(revisions)
+    }
+
+    // Line diff from one revision to another of the same entity
+    pub fn diff_revisions(&self, from_id: i64, to_id: i64) -> Result<Vec<DiffLine>, AppError> {
+        let (from, to) = {
+            let conn = self.reader()?;
+            (get_revision(&conn, from_id)?, get_revision(&conn, to_id)?)
+        };
+        if from.kind != to.kind || from.entity_id != to.entity_id {
+            return Err(AppError::validation("Revisions belong to different items".to_string()));
+        }
+
+        let old = render(from.kind, &from.snapshot);
+        let new = render(to.kind, &to.snapshot);
+
+        let lines = TextDiff::from_lines(&old, &new)
+            .iter_all_changes()
+            .map(|change| DiffLine {
+                op: match change.tag() {
+                    ChangeTag::Equal => DiffOp::Equal,
+                    ChangeTag::Insert => DiffOp::Insert,
+                    ChangeTag::Delete => DiffOp::Delete,
+                },
+                text: change.value().trim_end_matches('\n').to_string(),
+            })
+            .collect();
+
+        Ok(lines)
+    }
+
+    // Saves an older revision's contents as the current state, which is
+    // itself recorded as a new revision
+    pub fn restore_revision(&self, revision_id: i64) -> Result<(), AppError> {
+        let revision = get_revision(&*self.reader()?, revision_id)?;
+        let invalid = |e: serde_json::Error| AppError::Database(format!("Revision {} is unreadable: {}", revision_id, e));
+
+        match revision.kind {
+            RevisionKind::CodeSnippet => {
+                let snippet: CodeSnippet = serde_json::from_value(revision.snapshot).map_err(invalid)?;
+                self.update_code_snippet(snippet.id, CreateCodeSnippetRequest {
+                    tech_space_id: snippet.tech_space_id,
+                    title: snippet.title,
+                    description: snippet.description,
+                    code: snippet.code,
+                    language: snippet.language,
+                    tags: snippet.tags,
+                })?;
+            }
+            RevisionKind::Note => {
+                let note: Note = serde_json::from_value(revision.snapshot).map_err(invalid)?;
+                self.update_note(note.id, CreateNoteRequest {
+                    title: note.title,
+                    content: note.content,
+                    note_date: note.note_date,
+                    tags: note.tags,
+                })?;
+            }
+            RevisionKind::Task => {
+                let task: Task = serde_json::from_value(revision.snapshot).map_err(invalid)?;
+                self.update_task(task.id, CreateTaskRequest {
+                    project_id: task.project_id,
+                    title: task.title,
+                    description: task.description,
+                    status: task.status,
+                    priority: task.priority,
+                    due_date: task.due_date,
+                })?;
+            }
+            RevisionKind::Project => {
+                let project: Project = serde_json::from_value(revision.snapshot).map_err(invalid)?;
+                self.update_project(project.id, CreateProjectRequest {
+                    name: project.name,
+                    description: project.description,
+                    status: project.status,
+                    priority: project.priority,
+                    start_date: project.start_date,
+                    end_date: project.end_date,
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Appends a snapshot of an entity's saved state
+pub(super) fn record_revision<T: Serialize>(
+    conn: &Connection,
+    kind: RevisionKind,
+    entity_id: i64,
+    entity: &T,
+) -> Result<(), AppError> {
+    let snapshot = serde_json::to_string(entity).map_err(|e| AppError::Database(e.to_string()))?;
+    conn.execute(
+        "INSERT INTO revisions (entity_type, entity_id, snapshot, created_at) VALUES (?1, ?2, ?3, ?4)",
+        (kind.as_str(), entity_id, &snapshot, Utc::now().to_rfc3339()),
+    )?;
+    Ok(())
+}
+
+// Records the state before an edit for entities with no history yet
+// (created before revisions existed, or imported)
+pub(super) fn record_baseline<T: Serialize>(
+    conn: &Connection,
+    kind: RevisionKind,
+    entity_id: i64,
+    entity: &T,
+) -> Result<(), AppError> {
+    let has_history: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM revisions WHERE entity_type = ?1 AND entity_id = ?2)",
+        (kind.as_str(), entity_id),
+        |row| row.get(0),
+    )?;
+    if !has_history {
+        record_revision(conn, kind, entity_id, entity)?;
+    }
+    Ok(())
+}
+
+// Drops the history of entities that have been permanently deleted
+pub(super) fn prune_orphan_revisions(conn: &Connection) -> Result<(), AppError> {
+    for kind in [RevisionKind::CodeSnippet, RevisionKind::Note, RevisionKind::Task, RevisionKind::Project] {
+        conn.execute(
+            &format!(
+                "DELETE FROM revisions WHERE entity_type = ?1 AND entity_id NOT IN (SELECT id FROM {})",
+                kind.table()
+            ),
+            [kind.as_str()],
+        )?;
+    }
+    Ok(())
+}
+
+fn get_revision(conn: &Connection, id: i64) -> Result<Revision, AppError> {
+    conn.query_row(
+        "SELECT id, entity_type, entity_id, snapshot, created_at FROM revisions WHERE id = ?1",
+        [id],
+        map_revision,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Revision {} not found", id)))
+}
+
+fn map_revision(row: &rusqlite::Row) -> rusqlite::Result<Revision> {
+    let entity_type: String = row.get(1)?;
+    let snapshot: String = row.get(3)?;
+
+    let kind = RevisionKind::parse(&entity_type).ok_or_else(|| {
+        FromSqlConversionFailure(1, Type::Text, format!("unknown revision type '{}'", entity_type).into())
+    })?;
+    let snapshot = serde_json::from_str(&snapshot)
+        .map_err(|e| FromSqlConversionFailure(3, Type::Text, Box::new(e)))?;
+
+    Ok(Revision {
+        id: row.get(0)?,
+        kind,
+        entity_id: row.get(2)?,
+        snapshot,
+        created_at: row.get(4)?,
+    })
+}
+
+// Text form used for diffs: one "field: value" line per short field, then
+// the body split into its own lines
+fn render(kind: RevisionKind, snapshot: &Value) -> String {
+    let body = kind.body_field();
+    let mut text = String::new();
+
+    if let Some(fields) = snapshot.as_object() {
+        for (key, value) in fields {
+            if matches!(key.as_str(), "id" | "created_at" | "updated_at") || key == body {
+                continue;
+            }
+            text.push_str(&format!("{}: {}\n", key, plain(value)));
+        }
+        text.push('\n');
+        text.push_str(&plain(fields.get(body).unwrap_or(&Value::Null)));
+        text.push('\n');
+    }
+
+    text
+}
+
+fn plain(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(title: &str, content: &str, tags: Option<&str>) -> CreateNoteRequest {
+        CreateNoteRequest {
+            title: title.to_string(),
+            content: content.to_string(),
+            note_date: "2024-03-01".to_string(),
+            tags: tags.map(str::to_string),
+        }
+    }
+
+    fn snapshot_field(revision: &Revision, field: &str) -> String {
+        plain(&revision.snapshot[field])
+    }
+
+    #[test]
+    fn records_a_revision_per_save() {
+        let db = Database::open_in_memory().unwrap();
+        let created = db.create_note(note("Groceries", "milk", None)).unwrap();
+        db.update_note(created.id, note("Groceries", "milk\neggs", None)).unwrap();
+
+        let revisions = db.list_revisions(RevisionKind::Note, created.id).unwrap();
+        let contents: Vec<_> = revisions.iter().map(|r| snapshot_field(r, "content")).collect();
+        assert_eq!(contents, ["milk", "milk\neggs"]);
+        assert!(revisions.iter().all(|r| r.kind == RevisionKind::Note && r.entity_id == created.id));
+
+        let project = db
+            .create_project(CreateProjectRequest {
+                name: "Website".to_string(),
+                description: None,
+                status: "active".to_string(),
+                priority: "medium".to_string(),
+                start_date: None,
+                end_date: None,
+            })
+            .unwrap();
+        let task = db
+            .create_task(CreateTaskRequest {
+                project_id: project.id,
+                title: "Write docs".to_string(),
+                description: None,
+                status: "todo".to_string(),
+                priority: "high".to_string(),
+                due_date: None,
+            })
+            .unwrap();
+        db.update_task_status(task.id, "completed").unwrap();
+        let statuses: Vec<_> = db
+            .list_revisions(RevisionKind::Task, task.id)
+            .unwrap()
+            .iter()
+            .map(|r| snapshot_field(r, "status"))
+            .collect();
+        assert_eq!(statuses, ["todo", "completed"]);
+        assert_eq!(db.list_revisions(RevisionKind::Project, project.id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn edits_without_history_keep_the_earlier_state() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.create_note(note("Groceries", "milk", None)).unwrap().id;
+        db.writer().unwrap().execute("DELETE FROM revisions", []).unwrap();
+
+        db.update_note(id, note("Groceries", "bread", None)).unwrap();
+        let contents: Vec<_> = db
+            .list_revisions(RevisionKind::Note, id)
+            .unwrap()
+            .iter()
+            .map(|r| snapshot_field(r, "content"))
+            .collect();
+        assert_eq!(contents, ["milk", "bread"]);
+    }
+
+    #[test]
+    fn diffs_fields_then_body_lines() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.create_note(note("Groceries", "milk\neggs", None)).unwrap().id;
+        db.update_note(id, note("Shopping", "milk\nbread\neggs", Some("home"))).unwrap();
+        let revisions = db.list_revisions(RevisionKind::Note, id).unwrap();
+
+        let diff: Vec<_> = db
+            .diff_revisions(revisions[0].id, revisions[1].id)
+            .unwrap()
+            .into_iter()
+            .map(|line| (line.op, line.text))
+            .collect();
+        let expected = [
+            (DiffOp::Equal, "note_date: 2024-03-01"),
+            (DiffOp::Delete, "tags: "),
+            (DiffOp::Delete, "title: Groceries"),
+            (DiffOp::Insert, "tags: home"),
+            (DiffOp::Insert, "title: Shopping"),
+            (DiffOp::Equal, ""),
+            (DiffOp::Equal, "milk"),
+            (DiffOp::Insert, "bread"),
+            (DiffOp::Equal, "eggs"),
+        ];
+        assert_eq!(diff, expected.map(|(op, text)| (op, text.to_string())));
+
+        let other = db.create_note(note("Other", "x", None)).unwrap().id;
+        let other = db.list_revisions(RevisionKind::Note, other).unwrap()[0].id;
+        assert!(matches!(db.diff_revisions(revisions[0].id, other), Err(AppError::Validation { .. })));
+        assert!(matches!(db.diff_revisions(revisions[0].id, 999), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn restoring_saves_the_old_state_as_a_new_revision() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.create_note(note("Groceries", "milk", None)).unwrap().id;
+        db.update_note(id, note("Shopping", "bread", Some("home"))).unwrap();
+        let first = db.list_revisions(RevisionKind::Note, id).unwrap()[0].id;
+
+        db.restore_revision(first).unwrap();
+        let revisions = db.list_revisions(RevisionKind::Note, id).unwrap();
+        assert_eq!(revisions.len(), 3);
+        let current = &revisions[2];
+        assert_eq!(snapshot_field(current, "title"), "Groceries");
+        assert_eq!(snapshot_field(current, "content"), "milk");
+        assert_eq!(current.snapshot["tags"], Value::Null);
+        let notes = db.get_notes_by_date("2024-03-01").unwrap();
+        assert_eq!((notes[0].title.as_str(), notes[0].content.as_str()), ("Groceries", "milk"));
+
+        assert!(matches!(db.restore_revision(999), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn purged_entities_lose_their_history() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.create_note(note("Groceries", "milk", None)).unwrap().id;
+        let conn = db.writer().unwrap();
+        conn.execute("DELETE FROM notes WHERE id = ?1", [id]).unwrap();
+        prune_orphan_revisions(&conn).unwrap();
+        drop(conn);
+        assert!(db.list_revisions(RevisionKind::Note, id).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::scheduler::{self, DEFAULT_RETENTION};
+use crate::validation::*;
+use super::trash::require_active;
+use chrono::Utc;
+use rusqlite::types::Type;
+use rusqlite::Error::FromSqlConversionFailure;
+use rusqlite::{Connection, OptionalExtension};
+
+// Fewest reviews, not counting each card's first, worth fitting FSRS to
+const MIN_FIT_REVIEWS: usize = 100;
+
+impl SchedulerKind {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            SchedulerKind::Sm2 => "sm2",
+            SchedulerKind::Fsrs => "fsrs",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sm2" => Some(SchedulerKind::Sm2),
+            "fsrs" => Some(SchedulerKind::Fsrs),
+            _ => None,
+        }
+    }
+}
+
+impl Database {
+    pub fn get_scheduler_settings(&self, language_id: i64) -> Result<SchedulerSettings, AppError> {
+        let conn = self.reader()?;
+        require_active(&conn, TrashKind::Language, language_id, "language_id")?;
+        load_scheduler_settings(&conn, language_id)
+    }
+
+    pub fn update_scheduler_settings(
+        &self,
+        language_id: i64,
+        req: UpdateSchedulerSettingsRequest,
+    ) -> Result<SchedulerSettings, AppError> {
+        req.validate()?;
+
+        let conn = self.writer()?;
+        require_active(&conn, TrashKind::Language, language_id, "language_id")?;
+
+        conn.execute(
+            "INSERT INTO scheduler_settings (language_id, scheduler, desired_retention) VALUES (?1, ?2, ?3)
+             ON CONFLICT(language_id) DO UPDATE SET scheduler = excluded.scheduler, desired_retention = excluded.desired_retention",
+            (language_id, req.scheduler.as_str(), req.desired_retention),
+        )?;
+        if req.reset_weights {
+            conn.execute(
+                "UPDATE scheduler_settings SET fsrs_weights = NULL, fitted_at = NULL WHERE language_id = ?1",
+                [language_id],
+            )?;
+        }
+
+        load_scheduler_settings(&conn, language_id)
+    }
+
+    // Fits the language's FSRS parameters to its review log. Only cards whose
+    // every review was logged count. The new parameters are saved if they
+    // predict the reviews better than the current ones; the scheduler choice
+    // is left alone.
+    pub fn fit_fsrs_parameters(&self, language_id: i64) -> Result<FsrsFitReport, AppError> {
+        let (settings, histories) = {
+            let conn = self.reader()?;
+            require_active(&conn, TrashKind::Language, language_id, "language_id")?;
+            (load_scheduler_settings(&conn, language_id)?, review_histories(&conn, language_id)?)
+        };
+
+        let reviews: usize = histories.iter().map(|history| history.len() - 1).sum();
+        if reviews < MIN_FIT_REVIEWS {
+            return Err(AppError::validation(format!(
+                "Fitting needs at least {} repeat reviews of this language's flashcards; there are {}",
+                MIN_FIT_REVIEWS, reviews
+            )));
+        }
+
+        let fit = scheduler::fit(&histories, settings.fsrs_weights.as_deref());
+
+        let conn = self.writer()?;
+        require_active(&conn, TrashKind::Language, language_id, "language_id")?;
+        if fit.log_loss_after < fit.log_loss_before {
+            let weights = serde_json::to_string(&fit.weights).map_err(|e| AppError::Database(e.to_string()))?;
+            conn.execute(
+                "INSERT INTO scheduler_settings (language_id, scheduler, desired_retention, fsrs_weights, fitted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(language_id) DO UPDATE SET fsrs_weights = excluded.fsrs_weights, fitted_at = excluded.fitted_at",
+                (
+                    language_id,
+                    settings.scheduler.as_str(),
+                    settings.desired_retention,
+                    &weights,
+                    Utc::now().to_rfc3339(),
+                ),
+            )?;
+        }
+
+        Ok(FsrsFitReport {
+            settings: load_scheduler_settings(&conn, language_id)?,
+            cards: histories.len(),
+            reviews: fit.reviews,
+            log_loss_before: fit.log_loss_before,
+            log_loss_after: fit.log_loss_after,
+        })
+    }
+}
+
+// A language's settings, or the defaults if it has none
+pub(super) fn load_scheduler_settings(conn: &Connection, language_id: i64) -> Result<SchedulerSettings, AppError> {
+    let row = conn
+        .query_row(
+            "SELECT scheduler, desired_retention, fsrs_weights, fitted_at FROM scheduler_settings WHERE language_id = ?1",
+            [language_id],
+            |row| {
+                let scheduler: String = row.get(0)?;
+                let scheduler = SchedulerKind::parse(&scheduler).ok_or_else(|| {
+                    FromSqlConversionFailure(0, Type::Text, format!("unknown scheduler '{}'", scheduler).into())
+                })?;
+                let fsrs_weights = row
+                    .get::<_, Option<String>>(2)?
+                    .map(|weights| serde_json::from_str::<Vec<f64>>(&weights))
+                    .transpose()
+                    .map_err(|e| FromSqlConversionFailure(2, Type::Text, Box::new(e)))?;
+                Ok(SchedulerSettings {
+                    language_id,
+                    scheduler,
+                    desired_retention: row.get(1)?,
+                    fsrs_weights,
+                    fitted_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(row.unwrap_or(SchedulerSettings {
+        language_id,
+        scheduler: SchedulerKind::Sm2,
+        desired_retention: DEFAULT_RETENTION,
+        fsrs_weights: None,
+        fitted_at: None,
+    }))
+}
+
+// Each card's logged reviews as (days since the previous review, quality),
+// oldest first, for cards whose first review was logged
+fn review_histories(conn: &Connection, language_id: i64) -> Result<Vec<Vec<(f64, i64)>>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT r.flashcard_id, r.elapsed_days, r.quality
+         FROM review_log r
+         JOIN flashcards f ON f.id = r.flashcard_id
+         JOIN vocabulary v ON v.id = f.vocabulary_id
+         WHERE v.language_id = ?1
+         ORDER BY r.flashcard_id, r.reviewed_at, r.id",
+    )?;
+    let rows = stmt
+        .query_map([language_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<f64>>(1)?, row.get::<_, i64>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut histories: Vec<Vec<(f64, i64)>> = Vec::new();
+    let mut current: Option<i64> = None;
+    let mut complete = false;
+    for (flashcard_id, elapsed_days, quality) in rows {
+        if current != Some(flashcard_id) {
+            current = Some(flashcard_id);
+            complete = elapsed_days.is_none();
+            if complete {
+                histories.push(Vec::new());
+            }
+        }
+        if complete {
+            if let Some(history) = histories.last_mut() {
+                history.push((elapsed_days.unwrap_or(0.0), quality));
+            }
+        }
+    }
+    // A lone first review predicts nothing
+    histories.retain(|history| history.len() > 1);
+
+    Ok(histories)
+}
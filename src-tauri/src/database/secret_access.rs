@@ -0,0 +1,279 @@
+use crate::database::Database;
+use crate::encryption;
+use crate::errors::AppError;
+use crate::models::*;
+use super::personal_vault::decrypt_value;
+use chrono::Utc;
+use rusqlite::types::Type;
+use rusqlite::Error::FromSqlConversionFailure;
+use rusqlite::{Connection, OptionalExtension};
+
+// Entries `list_secret_access` returns when no limit is given
+const DEFAULT_ACCESS_LOG_LIMIT: u32 = 200;
+
+impl SecretField {
+    fn as_str(self) -> &'static str {
+        match self {
+            SecretField::Password => "password",
+            SecretField::TotpSecret => "totp_secret",
+            SecretField::PreviousPassword => "previous_password",
+            SecretField::Content => "content",
+            SecretField::Attachment => "attachment",
+            SecretField::CustomField => "custom_field",
+            SecretField::BundledAccount => "bundled_account",
+            SecretField::BundledInfo => "bundled_info",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "password" => Some(SecretField::Password),
+            "totp_secret" => Some(SecretField::TotpSecret),
+            "previous_password" => Some(SecretField::PreviousPassword),
+            "content" => Some(SecretField::Content),
+            "attachment" => Some(SecretField::Attachment),
+            "custom_field" => Some(SecretField::CustomField),
+            "bundled_account" => Some(SecretField::BundledAccount),
+            "bundled_info" => Some(SecretField::BundledInfo),
+            _ => None,
+        }
+    }
+
+    // What the id refers to, as shown in errors
+    fn item_label(self) -> &'static str {
+        match self {
+            SecretField::Password | SecretField::TotpSecret | SecretField::BundledAccount => "Personal account",
+            SecretField::PreviousPassword => "Password history entry",
+            SecretField::Content | SecretField::BundledInfo => "Personal info",
+            SecretField::Attachment => "Attachment",
+            SecretField::CustomField => "Custom field",
+        }
+    }
+
+    // Table and column the value `query` selects is stored in, which it is
+    // bound to when encrypted
+    fn stored_in(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            SecretField::Password => Some(("personal_accounts", "password")),
+            SecretField::TotpSecret => Some(("personal_accounts", "totp_secret")),
+            SecretField::PreviousPassword => Some(("password_history", "password")),
+            SecretField::Content => Some(("personal_info", "content")),
+            SecretField::CustomField => Some(("custom_fields", "value")),
+            SecretField::Attachment | SecretField::BundledAccount | SecretField::BundledInfo => None,
+        }
+    }
+
+    // Selects (title, stored value, whether the value is encrypted) for the
+    // item with id ?1; attachments and bundled entries are written to files
+    // instead
+    fn query(self) -> Option<&'static str> {
+        let query = match self {
+            SecretField::Password => {
+                "SELECT title, password, 1 FROM personal_accounts WHERE id = ?1 AND deleted_at IS NULL"
+            }
+            SecretField::TotpSecret => {
+                "SELECT title, totp_secret, 1 FROM personal_accounts WHERE id = ?1 AND deleted_at IS NULL"
+            }
+            SecretField::PreviousPassword => {
+                "SELECT a.title, h.password, 1 FROM password_history h
+                 JOIN personal_accounts a ON a.id = h.account_id
+                 WHERE h.id = ?1 AND a.deleted_at IS NULL"
+            }
+            SecretField::Content => {
+                "SELECT title, content, is_sensitive FROM personal_info WHERE id = ?1 AND deleted_at IS NULL"
+            }
+            // Titled by entry and label, as fields have no title of their own
+            SecretField::CustomField => {
+                "SELECT COALESCE(a.title, i.title) || ' · ' || f.label, f.value, f.field_type IN ('hidden', 'card_number')
+                 FROM custom_fields f
+                 LEFT JOIN personal_accounts a ON a.id = f.account_id
+                 LEFT JOIN personal_info i ON i.id = f.info_id
+                 WHERE f.id = ?1 AND COALESCE(a.deleted_at, i.deleted_at) IS NULL"
+            }
+            SecretField::Attachment | SecretField::BundledAccount | SecretField::BundledInfo => return None,
+        };
+        Some(query)
+    }
+}
+
+impl Database {
+    // Decrypts a single value for display and records the access
+    pub fn reveal_secret(&self, id: i64, field: SecretField) -> Result<String, AppError> {
+        let (query, (table, column)) = field
+            .query()
+            .zip(field.stored_in())
+            .ok_or_else(|| AppError::invalid_field("field", "Attachments and bundled entries are exported to a file, not revealed"))?;
+        let conn = self.writer()?;
+        let key = self.vault_key()?;
+
+        let (title, stored, encrypted): (String, Option<String>, bool) = conn
+            .query_row(query, [id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("{} {} not found", field.item_label(), id)))?;
+        // Only the 2FA secret is optional
+        let stored = stored.ok_or_else(|| AppError::NotFound("This account has no 2FA secret".to_string()))?;
+
+        let value = if encrypted {
+            let what = format!("{} of {} {}", field.as_str().replace('_', " "), field.item_label().to_lowercase(), id);
+            decrypt_value(&key, &stored, &encryption::aad(table, column, id), &what)?
+        } else {
+            stored
+        };
+
+        log_secret_access(&conn, field, id, &title)?;
+        Ok(value)
+    }
+
+    // Most recent reveals first
+    pub fn list_secret_access(&self, limit: Option<u32>) -> Result<Vec<SecretAccess>, AppError> {
+        let limit = limit.unwrap_or(DEFAULT_ACCESS_LOG_LIMIT);
+        if limit == 0 {
+            return Err(AppError::invalid_field("limit", "Limit must be at least 1"));
+        }
+
+        self.vault_key()?;
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, field, item_id, title, accessed_at FROM secret_access_log
+             ORDER BY accessed_at DESC, id DESC LIMIT ?1",
+        )?;
+        let entries = stmt
+            .query_map([limit], |row| {
+                let field: String = row.get(1)?;
+                let field = SecretField::parse(&field).ok_or_else(|| {
+                    FromSqlConversionFailure(1, Type::Text, format!("unknown secret field '{}'", field).into())
+                })?;
+                Ok(SecretAccess {
+                    id: row.get(0)?,
+                    field,
+                    item_id: row.get(2)?,
+                    title: row.get(3)?,
+                    accessed_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+}
+
+pub(super) fn log_secret_access(conn: &Connection, field: SecretField, id: i64, title: &str) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO secret_access_log (field, item_id, title, accessed_at) VALUES (?1, ?2, ?3, ?4)",
+        (field.as_str(), id, title, Utc::now().to_rfc3339()),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unlocked() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        db
+    }
+
+    fn hidden(label: &str, value: &str) -> CustomFieldInput {
+        CustomFieldInput { id: None, label: label.to_string(), field_type: CustomFieldType::Hidden, value: value.to_string() }
+    }
+
+    fn account(db: &Database) -> PersonalAccount {
+        db.create_personal_account(CreatePersonalAccountRequest {
+            title: "Bank".to_string(),
+            email: "me@example.com".to_string(),
+            password: "hunter2".to_string(),
+            website: None,
+            notes: None,
+            category: "banking".to_string(),
+            totp_secret: Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_string()),
+            custom_fields: vec![hidden("PIN", "1234")],
+        })
+        .unwrap()
+    }
+
+    fn info(db: &Database, is_sensitive: bool) -> PersonalInfo {
+        db.create_personal_info(CreatePersonalInfoRequest {
+            title: if is_sensitive { "Passport" } else { "Shoe size" }.to_string(),
+            content: "X1234567".to_string(),
+            category: "documents".to_string(),
+            is_sensitive,
+            custom_fields: Vec::new(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn lists_leave_sensitive_values_out() {
+        let db = unlocked();
+        account(&db);
+        info(&db, true);
+        info(&db, false);
+
+        let accounts = db.get_personal_accounts().unwrap();
+        assert!(accounts[0].has_totp);
+        assert_eq!(accounts[0].custom_fields[0].value, None);
+
+        let infos = db.get_personal_info().unwrap();
+        let content = |title: &str| infos.iter().find(|i| i.title == title).unwrap().content.clone();
+        assert_eq!(content("Passport"), None);
+        assert_eq!(content("Shoe size").as_deref(), Some("X1234567"));
+        assert!(db.list_secret_access(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn each_reveal_is_logged_once() {
+        let db = unlocked();
+        let account = account(&db);
+        let passport = info(&db, true);
+        let pin = account.custom_fields[0].id;
+
+        assert_eq!(db.reveal_secret(account.id, SecretField::Password).unwrap(), "hunter2");
+        assert!(db.reveal_secret(account.id, SecretField::TotpSecret).unwrap().starts_with("otpauth://"));
+        assert_eq!(db.reveal_secret(pin, SecretField::CustomField).unwrap(), "1234");
+        assert_eq!(db.reveal_secret(passport.id, SecretField::Content).unwrap(), "X1234567");
+
+        let log: Vec<_> = db
+            .list_secret_access(None)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.field, entry.item_id, entry.title))
+            .collect();
+        assert_eq!(
+            log,
+            [
+                (SecretField::Content, passport.id, "Passport".to_string()),
+                (SecretField::CustomField, pin, "Bank · PIN".to_string()),
+                (SecretField::TotpSecret, account.id, "Bank".to_string()),
+                (SecretField::Password, account.id, "Bank".to_string()),
+            ]
+        );
+        assert_eq!(db.list_secret_access(Some(1)).unwrap().len(), 1);
+        assert_eq!(db.list_secret_access(Some(0)).unwrap_err().field(), Some("limit"));
+    }
+
+    #[test]
+    fn trashed_and_missing_items_are_not_revealed() {
+        let db = unlocked();
+        let account = account(&db);
+        let pin = account.custom_fields[0].id;
+        db.delete_personal_account(account.id).unwrap();
+
+        for (id, field) in [(account.id, SecretField::Password), (pin, SecretField::CustomField), (999, SecretField::Content)] {
+            let err = db.reveal_secret(id, field).unwrap_err();
+            assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+        }
+        let err = db.reveal_secret(account.id, SecretField::Attachment).unwrap_err();
+        assert_eq!(err.field(), Some("field"));
+        assert!(db.list_secret_access(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reveals_need_the_unlocked_vault() {
+        let db = unlocked();
+        let account = account(&db);
+        db.lock_vault().unwrap();
+        assert!(matches!(db.reveal_secret(account.id, SecretField::Password), Err(AppError::VaultLocked)));
+        assert!(matches!(db.list_secret_access(None), Err(AppError::VaultLocked)));
+    }
+}
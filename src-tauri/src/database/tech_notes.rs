@@ -0,0 +1,333 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use super::revisions::{record_baseline, record_revision};
+use super::trash::require_active;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+
+impl Database {
+    pub fn create_tech_space(&self, req: CreateTechSpaceRequest) -> Result<TechSpace, AppError> {
+        req.validate()?;
+
+        let name = sanitize_string(req.name);
+        let description = sanitize_optional_string(req.description);
+        let icon = sanitize_string(req.icon);
+
+        let conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+
+        let in_trash: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM tech_spaces WHERE name = ?1 AND deleted_at IS NOT NULL)",
+            [&name],
+            |row| row.get(0),
+        )?;
+        if in_trash {
+            return Err(AppError::Duplicate { table: "tech_spaces".to_string(), field: "name".to_string(), in_trash: true });
+        }
+
+        conn.execute(
+            "INSERT INTO tech_spaces (name, description, icon, created_at) VALUES (?1, ?2, ?3, ?4)",
+            (&name, &description, &icon, &now),
+        )?;
+
+        let id = conn.last_insert_rowid();
+
+        Ok(TechSpace {
+            id,
+            name,
+            description,
+            icon,
+            created_at: now,
+        })
+    }
+
+    pub fn get_tech_spaces(&self) -> Result<Vec<TechSpace>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, name, description, icon, created_at FROM tech_spaces WHERE deleted_at IS NULL ORDER BY name"
+        )?;
+
+        let tech_space_iter = stmt.query_map([], |row| {
+            Ok(TechSpace {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                icon: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?;
+
+        let mut tech_spaces = Vec::new();
+        for tech_space in tech_space_iter {
+            tech_spaces.push(tech_space?);
+        }
+
+        Ok(tech_spaces)
+    }
+
+    // Moves the space and its snippets to the trash
+    pub fn delete_tech_space(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::TechSpace, id)
+    }
+
+    pub fn create_code_snippet(&self, req: CreateCodeSnippetRequest) -> Result<CodeSnippet, AppError> {
+        req.validate()?;
+
+        let title = sanitize_string(req.title);
+        let description = sanitize_optional_string(req.description);
+        let code = req.code;
+        let language = sanitize_string(req.language);
+        let tags = sanitize_optional_string(req.tags);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        require_active(&tx, TrashKind::TechSpace, req.tech_space_id, "tech_space_id")?;
+
+        tx.execute(
+            "INSERT INTO code_snippets (tech_space_id, title, description, code, language, tags, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (&req.tech_space_id, &title, &description, &code, &language, &tags, &now, &now),
+        )?;
+
+        let snippet = CodeSnippet {
+            id: tx.last_insert_rowid(),
+            tech_space_id: req.tech_space_id,
+            title,
+            description,
+            code,
+            language,
+            tags,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::CodeSnippet, snippet.id, &snippet)?;
+        tx.commit()?;
+
+        Ok(snippet)
+    }
+
+    pub fn update_code_snippet(&self, id: i64, req: CreateCodeSnippetRequest) -> Result<CodeSnippet, AppError> {
+        req.validate()?;
+
+        let title = sanitize_string(req.title);
+        let description = sanitize_optional_string(req.description);
+        let code = req.code;
+        let language = sanitize_string(req.language);
+        let tags = sanitize_optional_string(req.tags);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now().to_rfc3339();
+        let tx = conn.transaction()?;
+        require_active(&tx, TrashKind::TechSpace, req.tech_space_id, "tech_space_id")?;
+
+        let current = read_code_snippet(&tx, id)?;
+        record_baseline(&tx, RevisionKind::CodeSnippet, id, &current)?;
+
+        tx.execute(
+            "UPDATE code_snippets SET tech_space_id = ?1, title = ?2, description = ?3, code = ?4, language = ?5, tags = ?6, updated_at = ?7
+             WHERE id = ?8",
+            (&req.tech_space_id, &title, &description, &code, &language, &tags, &now, id),
+        )?;
+
+        let snippet = CodeSnippet {
+            id,
+            tech_space_id: req.tech_space_id,
+            title,
+            description,
+            code,
+            language,
+            tags,
+            created_at: current.created_at,
+            updated_at: now,
+        };
+        record_revision(&tx, RevisionKind::CodeSnippet, id, &snippet)?;
+        tx.commit()?;
+
+        Ok(snippet)
+    }
+
+    pub fn get_code_snippets_by_tech_space(&self, tech_space_id: i64) -> Result<Vec<CodeSnippet>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, tech_space_id, title, description, code, language, tags, created_at, updated_at
+             FROM code_snippets WHERE tech_space_id = ?1 AND deleted_at IS NULL ORDER BY created_at DESC"
+        )?;
+
+        let snippet_iter = stmt.query_map([tech_space_id], |row| {
+            Ok(CodeSnippet {
+                id: row.get(0)?,
+                tech_space_id: row.get(1)?,
+                title: row.get(2)?,
+                description: row.get(3)?,
+                code: row.get(4)?,
+                language: row.get(5)?,
+                tags: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        })?;
+
+        let mut snippets = Vec::new();
+        for snippet in snippet_iter {
+            snippets.push(snippet?);
+        }
+
+        Ok(snippets)
+    }
+
+    pub fn delete_code_snippet(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::CodeSnippet, id)
+    }
+
+    pub fn search_code_snippets(&self, query: &str, limit: i64) -> Result<Vec<CodeSnippet>, AppError> {
+        let conn = self.reader()?;
+        let search_term = format!("%{}%", query.trim());
+
+        let mut stmt = conn.prepare(
+            "SELECT id, tech_space_id, title, description, code, language, tags, created_at, updated_at
+             FROM code_snippets
+             WHERE deleted_at IS NULL
+               AND (title LIKE ?1 OR description LIKE ?1 OR code LIKE ?1 OR tags LIKE ?1)
+             ORDER BY title
+             LIMIT ?2"
+        )?;
+
+        let snippet_iter = stmt.query_map((&search_term, limit), |row| {
+            Ok(CodeSnippet {
+                id: row.get(0)?,
+                tech_space_id: row.get(1)?,
+                title: row.get(2)?,
+                description: row.get(3)?,
+                code: row.get(4)?,
+                language: row.get(5)?,
+                tags: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        })?;
+
+        let mut snippets = Vec::new();
+        for snippet in snippet_iter {
+            snippets.push(snippet?);
+        }
+
+        Ok(snippets)
+    }
+}
+
+fn read_code_snippet(conn: &Connection, id: i64) -> Result<CodeSnippet, AppError> {
+    conn.query_row(
+        "SELECT id, tech_space_id, title, description, code, language, tags, created_at, updated_at
+         FROM code_snippets WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+        |row| {
+            Ok(CodeSnippet {
+                id: row.get(0)?,
+                tech_space_id: row.get(1)?,
+                title: row.get(2)?,
+                description: row.get(3)?,
+                code: row.get(4)?,
+                language: row.get(5)?,
+                tags: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Code snippet {} not found", id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (Database, i64) {
+        let db = Database::open_in_memory().unwrap();
+        let space = db
+            .create_tech_space(CreateTechSpaceRequest {
+                name: "Rust".to_string(),
+                description: Some(String::new()),
+                icon: "🦀".to_string(),
+            })
+            .unwrap();
+        (db, space.id)
+    }
+
+    fn snippet(tech_space_id: i64, title: &str) -> CreateCodeSnippetRequest {
+        CreateCodeSnippetRequest {
+            tech_space_id,
+            title: title.to_string(),
+            description: None,
+            code: "fn main() {}\n".to_string(),
+            language: "rust".to_string(),
+            tags: Some(" cli ".to_string()),
+        }
+    }
+
+    #[test]
+    fn creates_and_lists_spaces() {
+        let (db, space_id) = setup();
+        let spaces = db.get_tech_spaces().unwrap();
+        assert_eq!(spaces.len(), 1);
+        assert_eq!(spaces[0].id, space_id);
+        assert_eq!(spaces[0].description, None);
+
+        let err = db
+            .create_tech_space(CreateTechSpaceRequest { name: " ".to_string(), description: None, icon: "x".to_string() })
+            .unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert_eq!(err.field(), Some("name"));
+        let err = db
+            .create_tech_space(CreateTechSpaceRequest { name: "Go".to_string(), description: None, icon: String::new() })
+            .unwrap_err();
+        assert_eq!(err.field(), Some("icon"));
+    }
+
+    #[test]
+    fn creates_updates_and_lists_snippets() {
+        let (db, space_id) = setup();
+        let created = db.create_code_snippet(snippet(space_id, " Hello ")).unwrap();
+        assert_eq!(created.title, "Hello");
+        assert_eq!(created.tags.as_deref(), Some("cli"));
+        // Code is stored as written, whitespace included
+        assert_eq!(created.code, "fn main() {}\n");
+
+        let updated = db
+            .update_code_snippet(created.id, CreateCodeSnippetRequest { tags: None, ..snippet(space_id, "Hello, world") })
+            .unwrap();
+        assert_eq!(updated.created_at, created.created_at);
+        assert_eq!(updated.tags, None);
+
+        let listed = db.get_code_snippets_by_tech_space(space_id).unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].title, "Hello, world");
+        assert_eq!(db.search_code_snippets("world", 10).unwrap().len(), 1);
+
+        let err = db.update_code_snippet(created.id + 1, snippet(space_id, "Missing")).unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn rejects_invalid_snippets() {
+        let (db, space_id) = setup();
+        let cases = [
+            (snippet(space_id, ""), "title"),
+            (snippet(space_id, &"x".repeat(201)), "title"),
+            (CreateCodeSnippetRequest { code: "  ".to_string(), ..snippet(space_id, "Hello") }, "code"),
+            (CreateCodeSnippetRequest { language: String::new(), ..snippet(space_id, "Hello") }, "language"),
+        ];
+        for (req, field) in cases {
+            let err = db.create_code_snippet(req).unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+            assert_eq!(err.field(), Some(field));
+        }
+
+        let err = db.create_code_snippet(snippet(space_id + 1, "Hello")).unwrap_err();
+        assert_eq!(err.field(), Some("tech_space_id"));
+        assert!(db.get_code_snippets_by_tech_space(space_id).unwrap().is_empty());
+    }
+}
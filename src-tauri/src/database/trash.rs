@@ -0,0 +1,460 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use super::connection::lock;
+use super::revisions::prune_orphan_revisions;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+
+const TRASH_KINDS: &[TrashKind] = &[
+    TrashKind::Language,
+    TrashKind::Vocabulary,
+    TrashKind::TechSpace,
+    TrashKind::CodeSnippet,
+    TrashKind::Project,
+    TrashKind::Task,
+    TrashKind::Event,
+    TrashKind::Note,
+    TrashKind::PersonalAccount,
+    TrashKind::PersonalInfo,
+];
+
+impl TrashKind {
+    fn table(self) -> &'static str {
+        match self {
+            TrashKind::Language => "languages",
+            TrashKind::Vocabulary => "vocabulary",
+            TrashKind::TechSpace => "tech_spaces",
+            TrashKind::CodeSnippet => "code_snippets",
+            TrashKind::Project => "projects",
+            TrashKind::Task => "tasks",
+            TrashKind::Event => "events",
+            TrashKind::Note => "notes",
+            TrashKind::PersonalAccount => "personal_accounts",
+            TrashKind::PersonalInfo => "personal_info",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TrashKind::Language => "Language",
+            TrashKind::Vocabulary => "Vocabulary",
+            TrashKind::TechSpace => "Tech space",
+            TrashKind::CodeSnippet => "Code snippet",
+            TrashKind::Project => "Project",
+            TrashKind::Task => "Task",
+            TrashKind::Event => "Event",
+            TrashKind::Note => "Note",
+            TrashKind::PersonalAccount => "Personal account",
+            TrashKind::PersonalInfo => "Personal info",
+        }
+    }
+
+    fn title_column(self) -> &'static str {
+        match self {
+            TrashKind::Language | TrashKind::TechSpace | TrashKind::Project => "name",
+            TrashKind::Vocabulary => "word",
+            _ => "title",
+        }
+    }
+
+    // Vault entries can only be restored or purged with the vault unlocked
+    fn in_vault(self) -> bool {
+        matches!(self, TrashKind::PersonalAccount | TrashKind::PersonalInfo)
+    }
+
+    // The owning entity and the column pointing at it
+    fn parent(self) -> Option<(TrashKind, &'static str)> {
+        match self {
+            TrashKind::Vocabulary => Some((TrashKind::Language, "language_id")),
+            TrashKind::CodeSnippet => Some((TrashKind::TechSpace, "tech_space_id")),
+            TrashKind::Task => Some((TrashKind::Project, "project_id")),
+            _ => None,
+        }
+    }
+
+    // Dependent rows that follow this entity into and out of the trash, as
+    // (table, condition on the parent id ?2)
+    fn dependents(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            TrashKind::Language => &[
+                ("vocabulary", "language_id = ?2"),
+                ("flashcards", "vocabulary_id IN (SELECT id FROM vocabulary WHERE language_id = ?2)"),
+            ],
+            TrashKind::Vocabulary => &[("flashcards", "vocabulary_id = ?2")],
+            TrashKind::TechSpace => &[("code_snippets", "tech_space_id = ?2")],
+            TrashKind::Project => &[("tasks", "project_id = ?2")],
+            _ => &[],
+        }
+    }
+}
+
+impl Database {
+    // Soft-deletes an entity and everything that depends on it, stamping
+    // them all with the same time so they can be restored as one
+    pub(super) fn move_to_trash(&self, kind: TrashKind, id: i64) -> Result<(), AppError> {
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+        let now = Utc::now().to_rfc3339();
+
+        let trashed = tx.execute(
+            &format!("UPDATE {} SET deleted_at = ?1 WHERE id = ?2 AND deleted_at IS NULL", kind.table()),
+            (&now, id),
+        )?;
+        if trashed == 0 {
+            return Err(AppError::NotFound(format!("{} {} not found", kind.label(), id)));
+        }
+
+        for (table, condition) in kind.dependents() {
+            tx.execute(
+                &format!("UPDATE {} SET deleted_at = ?1 WHERE {} AND deleted_at IS NULL", table, condition),
+                (&now, id),
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Items whose owner is not itself in the trash, most recently deleted first
+    pub fn list_trash(&self) -> Result<Vec<TrashItem>, AppError> {
+        let conn = self.reader()?;
+
+        let mut items = Vec::new();
+        for &kind in TRASH_KINDS {
+            let sql = match kind.parent() {
+                Some((parent, column)) => format!(
+                    "SELECT c.id, c.{title}, c.deleted_at FROM {table} c
+                     JOIN {parent} p ON p.id = c.{column}
+                     WHERE c.deleted_at IS NOT NULL AND p.deleted_at IS NULL",
+                    title = kind.title_column(),
+                    table = kind.table(),
+                    parent = parent.table(),
+                    column = column,
+                ),
+                None => format!(
+                    "SELECT id, {}, deleted_at FROM {} WHERE deleted_at IS NOT NULL",
+                    kind.title_column(),
+                    kind.table()
+                ),
+            };
+
+            let mut stmt = conn.prepare(&sql)?;
+            let item_iter = stmt.query_map([], |row| {
+                Ok(TrashItem {
+                    kind,
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    deleted_at: row.get(2)?,
+                })
+            })?;
+
+            for item in item_iter {
+                items.push(item?);
+            }
+        }
+
+        items.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        Ok(items)
+    }
+
+    // Brings an item back together with whatever was trashed alongside it
+    pub fn restore_trash_item(&self, kind: TrashKind, id: i64) -> Result<(), AppError> {
+        if kind.in_vault() {
+            self.vault_key()?;
+        }
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+
+        let deleted_at = trashed_at(&tx, kind, id)?;
+
+        if let Some((parent, column)) = kind.parent() {
+            let parent_trashed: bool = tx.query_row(
+                &format!(
+                    "SELECT p.deleted_at IS NOT NULL FROM {} c JOIN {} p ON p.id = c.{} WHERE c.id = ?1",
+                    kind.table(),
+                    parent.table(),
+                    column
+                ),
+                [id],
+                |row| row.get(0),
+            )?;
+            if parent_trashed {
+                return Err(AppError::validation(format!(
+                    "Restore its {} first",
+                    parent.label().to_lowercase()
+                )));
+            }
+        }
+
+        tx.execute(&format!("UPDATE {} SET deleted_at = NULL WHERE id = ?1", kind.table()), [id])?;
+        for (table, condition) in kind.dependents() {
+            tx.execute(
+                &format!("UPDATE {} SET deleted_at = NULL WHERE {} AND deleted_at = ?1", table, condition),
+                (&deleted_at, id),
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    // Permanently deletes a trashed item; foreign keys cascade to its dependents
+    pub fn purge_trash_item(&self, kind: TrashKind, id: i64) -> Result<(), AppError> {
+        if kind.in_vault() {
+            self.vault_key()?;
+        }
+        let conn = self.writer()?;
+        trashed_at(&conn, kind, id)?;
+        conn.execute(&format!("DELETE FROM {} WHERE id = ?1", kind.table()), [id])?;
+        prune_orphan_revisions(&conn)?;
+        Ok(())
+    }
+
+    pub fn empty_trash(&self) -> Result<usize, AppError> {
+        self.purge_trash_before(None)
+    }
+
+    // Called periodically; purges items older than the workspace's retention
+    pub fn purge_expired_trash(&self) -> Result<usize, AppError> {
+        let workspace = match lock(&self.current_workspace)?.clone() {
+            Some(workspace) => workspace,
+            None => return Ok(0),
+        };
+        // An encrypted workspace can't be read until the vault is unlocked
+        if workspace.trash.retention_days == 0 || self.database_locked()? {
+            return Ok(0);
+        }
+
+        let cutoff = Utc::now() - chrono::Duration::days(workspace.trash.retention_days);
+        self.purge_trash_before(Some(cutoff.to_rfc3339()))
+    }
+
+    pub fn get_trash_settings(&self) -> Result<TrashSettings, AppError> {
+        Ok(self.require_workspace()?.trash)
+    }
+
+    pub fn update_trash_settings(&self, settings: TrashSettings) -> Result<TrashSettings, AppError> {
+        settings.validate()?;
+        let id = self.require_workspace()?.id;
+
+        let workspace = {
+            let mut registry = lock(&self.registry)?;
+            let workspace = registry.get_mut(id)?;
+            workspace.trash = settings.clone();
+            let workspace = workspace.clone();
+            registry.save()?;
+            workspace
+        };
+        *lock(&self.current_workspace)? = Some(workspace);
+
+        Ok(settings)
+    }
+
+    // Owners are deleted before their dependents, so rows removed by the
+    // cascade are not counted
+    fn purge_trash_before(&self, cutoff: Option<String>) -> Result<usize, AppError> {
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+
+        let mut purged = 0;
+        for &kind in TRASH_KINDS {
+            purged += tx.execute(
+                &format!(
+                    "DELETE FROM {} WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)",
+                    kind.table()
+                ),
+                [&cutoff],
+            )?;
+        }
+        prune_orphan_revisions(&tx)?;
+
+        tx.commit()?;
+        Ok(purged)
+    }
+}
+
+fn trashed_at(conn: &Connection, kind: TrashKind, id: i64) -> Result<String, AppError> {
+    let deleted_at: Option<Option<String>> = conn
+        .query_row(
+            &format!("SELECT deleted_at FROM {} WHERE id = ?1", kind.table()),
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match deleted_at {
+        Some(Some(deleted_at)) => Ok(deleted_at),
+        Some(None) => Err(AppError::validation(format!("{} {} is not in the trash", kind.label(), id))),
+        None => Err(AppError::NotFound(format!("{} {} not found", kind.label(), id))),
+    }
+}
+
+// For inserts that reference an owner through `field`: it must exist and
+// not be in the trash
+pub(super) fn require_active(conn: &Connection, kind: TrashKind, id: i64, field: &str) -> Result<(), AppError> {
+    let active: Option<bool> = conn
+        .query_row(
+            &format!("SELECT deleted_at IS NULL FROM {} WHERE id = ?1", kind.table()),
+            [id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match active {
+        Some(true) => Ok(()),
+        Some(false) => Err(AppError::MissingReference {
+            field: Some(field.to_string()),
+            message: format!("{} {} is in the trash", kind.label(), id),
+        }),
+        None => Err(AppError::MissingReference {
+            field: Some(field.to_string()),
+            message: format!("{} {} not found", kind.label(), id),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Spanish with the words "casa" and "agua"
+    fn setup() -> (Database, i64, Vec<i64>) {
+        let db = Database::open_in_memory().unwrap();
+        let language_id = db
+            .create_language(CreateLanguageRequest {
+                name: "Spanish".to_string(),
+                code: "es".to_string(),
+                flag_emoji: "🇪🇸".to_string(),
+            })
+            .unwrap()
+            .id;
+        let words = ["casa", "agua"]
+            .into_iter()
+            .map(|word| {
+                db.create_vocabulary(CreateVocabularyRequest {
+                    language_id,
+                    word: word.to_string(),
+                    translation: "-".to_string(),
+                    pronunciation: None,
+                    example_sentence: None,
+                    difficulty_level: 1,
+                })
+                .unwrap()
+                .id
+            })
+            .collect();
+        (db, language_id, words)
+    }
+
+    fn count(db: &Database, sql: &str) -> i64 {
+        db.reader().unwrap().query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn restore_brings_back_only_what_was_trashed_together() {
+        let (db, language_id, words) = setup();
+        db.delete_vocabulary(words[0]).unwrap();
+        db.delete_language(language_id).unwrap();
+
+        // Only the language is listed; its words are trashed under it
+        let trash = db.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!((trash[0].kind, trash[0].id), (TrashKind::Language, language_id));
+
+        db.restore_trash_item(TrashKind::Language, language_id).unwrap();
+        let words_left: Vec<_> = db.get_vocabulary_by_language(language_id).unwrap().into_iter().map(|v| v.id).collect();
+        assert_eq!(words_left, [words[1]]);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM flashcards WHERE deleted_at IS NULL"), 1);
+
+        // The word deleted on its own earlier is still in the trash
+        let trash = db.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!((trash[0].kind, trash[0].id), (TrashKind::Vocabulary, words[0]));
+    }
+
+    #[test]
+    fn children_need_their_parent_restored_first() {
+        let (db, language_id, words) = setup();
+        db.delete_language(language_id).unwrap();
+
+        let err = db.restore_trash_item(TrashKind::Vocabulary, words[0]).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert_eq!(err.message(), "Restore its language first");
+
+        let err = db.restore_trash_item(TrashKind::Language, language_id + 1).unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn purge_deletes_the_item_and_its_dependents() {
+        let (db, language_id, words) = setup();
+        let err = db.purge_trash_item(TrashKind::Language, language_id).unwrap_err();
+        assert_eq!(err.message(), format!("Language {} is not in the trash", language_id));
+
+        db.delete_vocabulary(words[0]).unwrap();
+        db.delete_language(language_id).unwrap();
+        db.purge_trash_item(TrashKind::Language, language_id).unwrap();
+
+        assert!(db.list_trash().unwrap().is_empty());
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM languages"), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM vocabulary"), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM flashcards"), 0);
+    }
+
+    #[test]
+    fn expired_items_are_purged_after_the_retention() {
+        let (db, language_id, words) = setup();
+        db.update_trash_settings(TrashSettings { retention_days: 30 }).unwrap();
+        db.delete_vocabulary(words[0]).unwrap();
+        db.delete_vocabulary(words[1]).unwrap();
+
+        let long_ago = (Utc::now() - chrono::Duration::days(31)).to_rfc3339();
+        db.writer()
+            .unwrap()
+            .execute("UPDATE vocabulary SET deleted_at = ?1 WHERE id = ?2", (&long_ago, words[0]))
+            .unwrap();
+
+        assert_eq!(db.purge_expired_trash().unwrap(), 1);
+        let trash: Vec<_> = db.list_trash().unwrap().into_iter().map(|item| item.id).collect();
+        assert_eq!(trash, [words[1]]);
+
+        // No retention keeps everything
+        db.update_trash_settings(TrashSettings { retention_days: 0 }).unwrap();
+        db.writer()
+            .unwrap()
+            .execute("UPDATE vocabulary SET deleted_at = ?1", [&long_ago])
+            .unwrap();
+        assert_eq!(db.purge_expired_trash().unwrap(), 0);
+        assert_eq!(db.get_vocabulary_by_language(language_id).unwrap().len(), 0);
+        assert_eq!(db.empty_trash().unwrap(), 1);
+    }
+
+    #[test]
+    fn vault_entries_need_the_vault_unlocked() {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        let id = db
+            .create_personal_info(CreatePersonalInfoRequest {
+                title: "Passport".to_string(),
+                content: "X1234567".to_string(),
+                category: "documents".to_string(),
+                is_sensitive: true,
+                custom_fields: Vec::new(),
+            })
+            .unwrap()
+            .id;
+        db.delete_personal_info(id).unwrap();
+        db.lock_vault().unwrap();
+
+        let err = db.restore_trash_item(TrashKind::PersonalInfo, id).unwrap_err();
+        assert!(matches!(err, AppError::VaultLocked), "{:?}", err);
+        let err = db.purge_trash_item(TrashKind::PersonalInfo, id).unwrap_err();
+        assert!(matches!(err, AppError::VaultLocked), "{:?}", err);
+
+        db.unlock_vault("correct horse battery staple").unwrap();
+        db.restore_trash_item(TrashKind::PersonalInfo, id).unwrap();
+        assert_eq!(db.get_personal_info().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,532 @@
+use crate::database::Database;
+use crate::encryption::{self, EncryptionService};
+use crate::errors::{log_error, AppError};
+use crate::models::*;
+use crate::validation::*;
+use super::connection::lock;
+use super::file_encryption::{DatabaseKey, SUPPORTED};
+use super::personal_vault::fill_password_strength;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Plaintext of the verifier; decrypting it proves the master password
+const VERIFIER: &str = "yotion-vault";
+
+// The unlocked key, dropped (and so wiped) when the vault locks
+pub(super) struct VaultSession {
+    key: Arc<EncryptionService>,
+    last_used: Instant,
+}
+
+impl VaultSession {
+    fn new(key: EncryptionService) -> Self {
+        VaultSession {
+            key: Arc::new(key),
+            last_used: Instant::now(),
+        }
+    }
+}
+
+// Every encrypted column as (table, column, filter on the rows that hold
+// ciphertext)
+const ENCRYPTED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("personal_accounts", "email", ""),
+    ("personal_accounts", "password", ""),
+    ("personal_accounts", "totp_secret", " WHERE totp_secret IS NOT NULL"),
+    ("personal_info", "content", " WHERE is_sensitive"),
+    ("password_history", "password", ""),
+    ("info_attachments", "file_name", ""),
+    ("info_attachments", "checksum", ""),
+    ("info_attachment_chunks", "data", ""),
+    ("custom_fields", "value", " WHERE field_type IN ('hidden', 'card_number')"),
+];
+
+impl Database {
+    pub fn get_vault_status(&self) -> Result<VaultStatus, AppError> {
+        // An encrypted database can't be read at all until it is unlocked,
+        // and it can only have been encrypted with a master password set
+        if self.database_locked()? {
+            return Ok(VaultStatus {
+                initialized: true,
+                unlocked: false,
+                database_encrypted: true,
+                database_encryption_available: SUPPORTED,
+            });
+        }
+        let initialized = read_header(&*self.reader()?)?.is_some();
+        Ok(VaultStatus {
+            initialized,
+            unlocked: self.session_key(false)?.is_some(),
+            database_encrypted: self.database_encrypted()?,
+            database_encryption_available: SUPPORTED,
+        })
+    }
+
+    // First-time setup: sets the master password, re-encrypts the values
+    // stored before the vault had one and leaves the vault unlocked
+    pub fn setup_vault(&self, master_password: &str) -> Result<(), AppError> {
+        validate_password_strength(master_password).field("master_password")?;
+
+        let kdf = KdfParams::default();
+        let key = EncryptionService::derive(master_password, &encryption::generate_salt(), kdf)?;
+        let now = Utc::now().to_rfc3339();
+
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+        if read_header(&tx)?.is_some() {
+            return Err(AppError::validation("The vault already has a master password"));
+        }
+        write_header(&tx, &VaultHeader {
+            kdf,
+            salt: encryption::encode(key.salt()),
+            verifier: encrypt_verifier(&key)?,
+            created_at: now.clone(),
+            updated_at: now,
+        })?;
+        upgrade_legacy_values(&tx, &key)?;
+        fill_password_strength(&tx, &key)?;
+        tx.commit()?;
+
+        *lock(&self.vault)? = Some(VaultSession::new(key));
+        Ok(())
+    }
+
+    pub fn unlock_vault(&self, master_password: &str) -> Result<(), AppError> {
+        if self.database_locked()? {
+            self.unlock_database(master_password)?;
+        }
+        let header = self.require_header()?;
+        let key = open_key(master_password, &header, "master_password")?;
+
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+        ensure_unchanged(&tx, &header)?;
+        // Only the first unlock after the upgrade, or after an import that
+        // couldn't do it, still has old values to pick up
+        if !legacy_values_upgraded(&tx)? {
+            upgrade_legacy_values(&tx, &key)?;
+        }
+        fill_password_strength(&tx, &key)?;
+        tx.commit()?;
+
+        *lock(&self.vault)? = Some(VaultSession::new(key));
+        Ok(())
+    }
+
+    pub fn change_master_password(&self, current_password: &str, new_password: &str) -> Result<(), AppError> {
+        validate_password_strength(new_password).field("new_password")?;
+        self.rekey_vault(current_password, new_password)
+    }
+
+    // Keeps the master password but moves the vault to a fresh salt, and so a
+    // fresh key, e.g. after a copy of the database may have leaked
+    pub fn rotate_vault_key(&self, master_password: &str) -> Result<(), AppError> {
+        self.rekey_vault(master_password, master_password)
+    }
+
+    // Re-encrypts every vault value under a key derived from `new_password`.
+    // It all happens in one transaction: if the app is killed midway SQLite
+    // rolls it back on the next open and the old password keeps working.
+    fn rekey_vault(&self, current_password: &str, new_password: &str) -> Result<(), AppError> {
+        let header = self.require_header()?;
+        let old_key = open_key(current_password, &header, "current_password")?;
+
+        // Rotation also brings older vaults up to the current Argon2id cost
+        let kdf = KdfParams::default();
+        let new_key = EncryptionService::derive(new_password, &encryption::generate_salt(), kdf)?;
+
+        // An encrypted database is rekeyed in a copy under a key from the new
+        // password, which then replaces the file, so the vault and the file
+        // can never end up on different passwords
+        if lock(&self.database_key)?.is_some() {
+            let live = PathBuf::from(self.require_workspace()?.path);
+            let database_key = DatabaseKey::derive(new_password, &encryption::generate_salt())?;
+            let rewritten = self.rewrite_database(&live, Some(database_key), |conn| {
+                rekey_values(conn, &header, &old_key, &new_key, kdf)?;
+                *lock(&self.vault)? = Some(VaultSession::new(new_key));
+                Ok(())
+            });
+            // The session may already hold the new key
+            if rewritten.is_err() {
+                *lock(&self.vault)? = None;
+            }
+            return rewritten;
+        }
+
+        let mut conn = self.writer()?;
+        rekey_values(&mut conn, &header, &old_key, &new_key, kdf)?;
+
+        // Swapped while the writer is still held; vault writes look up the key
+        // only once they have the writer, so none can use the old one
+        *lock(&self.vault)? = Some(VaultSession::new(new_key));
+        Ok(())
+    }
+
+    // An encrypted database is closed as well, dropping its key, so it has
+    // to be unlocked again before anything in it can be read
+    pub fn lock_vault(&self) -> Result<(), AppError> {
+        if !self.lock_database()? {
+            *lock(&self.vault)? = None;
+        }
+        Ok(())
+    }
+
+    // Called periodically so an idle key doesn't stay in memory until the
+    // next vault call. Returns whether the vault was locked.
+    pub fn lock_idle_vault(&self) -> Result<bool, AppError> {
+        let was_unlocked = lock(&self.vault)?.is_some();
+        if self.session_key(false)?.is_some() {
+            return Ok(false);
+        }
+        // The session may already have ended on a vault call, which leaves
+        // an encrypted database open
+        let database_open = lock(&self.database_key)?.is_some();
+        if was_unlocked || database_open {
+            self.lock_vault()?;
+        }
+        Ok(was_unlocked || database_open)
+    }
+
+    pub fn get_vault_settings(&self) -> Result<VaultSettings, AppError> {
+        Ok(self.require_workspace()?.vault)
+    }
+
+    pub fn update_vault_settings(&self, settings: VaultSettings) -> Result<VaultSettings, AppError> {
+        settings.validate()?;
+        let id = self.require_workspace()?.id;
+
+        let workspace = {
+            let mut registry = lock(&self.registry)?;
+            let workspace = registry.get_mut(id)?;
+            workspace.vault = settings.clone();
+            let workspace = workspace.clone();
+            registry.save()?;
+            workspace
+        };
+        *lock(&self.current_workspace)? = Some(workspace);
+
+        Ok(settings)
+    }
+
+    pub(super) fn require_header(&self) -> Result<VaultHeader, AppError> {
+        read_header(&*self.reader()?)?
+            .ok_or_else(|| AppError::NotFound("The vault has no master password yet".to_string()))
+    }
+
+    // Key for reading and writing vault values; each use restarts the idle timer
+    pub(super) fn vault_key(&self) -> Result<Arc<EncryptionService>, AppError> {
+        self.session_key(true)?.ok_or(AppError::VaultLocked)
+    }
+
+    // The session's key unless it has been idle past the auto-lock timeout,
+    // in which case the session ends here
+    fn session_key(&self, touch: bool) -> Result<Option<Arc<EncryptionService>>, AppError> {
+        let auto_lock_minutes = match lock(&self.current_workspace)?.as_ref() {
+            Some(workspace) => workspace.vault.auto_lock_minutes,
+            None => 0,
+        };
+        let timeout = Duration::from_secs(auto_lock_minutes.max(0) as u64 * 60);
+
+        let mut session = lock(&self.vault)?;
+        match session.as_mut() {
+            Some(active) if auto_lock_minutes == 0 || active.last_used.elapsed() < timeout => {
+                if touch {
+                    active.last_used = Instant::now();
+                }
+                Ok(Some(active.key.clone()))
+            }
+            _ => {
+                *session = None;
+                Ok(None)
+            }
+        }
+    }
+}
+
+// Derives the key for `password` and checks it against the verifier
+pub(super) fn open_key(password: &str, header: &VaultHeader, field: &str) -> Result<EncryptionService, AppError> {
+    let key = EncryptionService::derive(password, &encryption::decode(&header.salt)?, header.kdf)?;
+    if key.decrypt(&header.verifier, &verifier_aad()).ok().as_deref() != Some(VERIFIER) {
+        return Err(AppError::invalid_field(field, "Incorrect master password"));
+    }
+    Ok(key)
+}
+
+fn encrypt_verifier(key: &EncryptionService) -> Result<String, AppError> {
+    key.encrypt(VERIFIER, &verifier_aad())
+}
+
+fn verifier_aad() -> String {
+    encryption::aad("vault", "verifier", 1)
+}
+
+// Guards against the master password changing while a key was being derived
+fn ensure_unchanged(conn: &Connection, header: &VaultHeader) -> Result<(), AppError> {
+    match read_header(conn)? {
+        Some(current) if current.salt == header.salt => Ok(()),
+        _ => Err(AppError::validation("The master password was changed in the meantime; please try again")),
+    }
+}
+
+pub(super) fn read_header(conn: &Connection) -> Result<Option<VaultHeader>, AppError> {
+    let header = conn
+        .query_row(
+            "SELECT kdf_memory_kib, kdf_iterations, kdf_parallelism, salt, verifier, created_at, updated_at
+             FROM vault WHERE id = 1",
+            [],
+            |row| {
+                Ok(VaultHeader {
+                    kdf: KdfParams {
+                        memory_kib: row.get(0)?,
+                        iterations: row.get(1)?,
+                        parallelism: row.get(2)?,
+                    },
+                    salt: row.get(3)?,
+                    verifier: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(header)
+}
+
+pub(super) fn write_header(conn: &Connection, header: &VaultHeader) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO vault (id, kdf_memory_kib, kdf_iterations, kdf_parallelism, salt, verifier, created_at, updated_at)
+         VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        (
+            header.kdf.memory_kib,
+            header.kdf.iterations,
+            header.kdf.parallelism,
+            &header.salt,
+            &header.verifier,
+            &header.created_at,
+            &header.updated_at,
+        ),
+    )?;
+    Ok(())
+}
+
+pub(super) fn legacy_values_upgraded(conn: &Connection) -> Result<bool, AppError> {
+    let upgraded = conn
+        .query_row("SELECT legacy_upgraded FROM vault WHERE id = 1", [], |row| row.get(0))
+        .optional()?;
+    Ok(upgraded.unwrap_or(false))
+}
+
+pub(super) fn set_legacy_values_upgraded(conn: &Connection, upgraded: bool) -> Result<(), AppError> {
+    conn.execute("UPDATE vault SET legacy_upgraded = ?1 WHERE id = 1", [upgraded])?;
+    Ok(())
+}
+
+// Re-encrypts the old values and records that it is done
+pub(super) fn upgrade_legacy_values(conn: &Connection, key: &EncryptionService) -> Result<(), AppError> {
+    reencrypt_legacy_values(conn, key)?;
+    set_legacy_values_upgraded(conn, true)
+}
+
+// Upgrade of values written by older versions, including those in the
+// trash: XOR-obfuscated with the old built-in key, or encrypted without being
+// bound to their row
+fn reencrypt_legacy_values(conn: &Connection, key: &EncryptionService) -> Result<(), AppError> {
+    for (table, column, filter) in ENCRYPTED_COLUMNS {
+        for id in encrypted_ids(conn, table, filter)? {
+            let value = encrypted_value(conn, table, column, id)?;
+            let aad = encryption::aad(table, column, id);
+            let plaintext = if encryption::is_legacy(&value) {
+                encryption::decrypt_legacy(&value).map(String::into_bytes)
+            } else if encryption::is_unbound(&value) {
+                key.decrypt_bytes(&value, &aad)
+            } else {
+                continue;
+            };
+            // Unreadable values are left as they are rather than blocking the
+            // vault, but not silently
+            let plaintext = match plaintext {
+                Ok(plaintext) => plaintext,
+                Err(e) => {
+                    log_error(&format!("{}.{} of row {} can't be upgraded and was left as it is", table, column, id), &e);
+                    continue;
+                }
+            };
+            conn.execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column),
+                (key.encrypt_bytes(&plaintext, &aad)?, id),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn rekey_values(
+    conn: &mut Connection,
+    header: &VaultHeader,
+    old_key: &EncryptionService,
+    new_key: &EncryptionService,
+    kdf: KdfParams,
+) -> Result<(), AppError> {
+    let tx = conn.transaction()?;
+    ensure_unchanged(&tx, header)?;
+    reencrypt_legacy_values(&tx, old_key)?;
+    reencrypt_values(&tx, old_key, new_key)?;
+    write_header(&tx, &VaultHeader {
+        kdf,
+        salt: encryption::encode(new_key.salt()),
+        verifier: encrypt_verifier(new_key)?,
+        created_at: header.created_at.clone(),
+        updated_at: Utc::now().to_rfc3339(),
+    })?;
+    set_legacy_values_upgraded(&tx, true)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn reencrypt_values(conn: &Connection, old_key: &EncryptionService, new_key: &EncryptionService) -> Result<(), AppError> {
+    for (table, column, filter) in ENCRYPTED_COLUMNS {
+        for id in encrypted_ids(conn, table, filter)? {
+            let value = encrypted_value(conn, table, column, id)?;
+            if encryption::is_legacy(&value) {
+                continue;
+            }
+            let aad = encryption::aad(table, column, id);
+            let plaintext = old_key.decrypt_bytes(&value, &aad).map_err(|e| {
+                AppError::Encryption(format!("{}.{} of row {} can't be read ({}); nothing was changed", table, column, id, e.message()))
+            })?;
+            conn.execute(
+                &format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column),
+                (new_key.encrypt_bytes(&plaintext, &aad)?, id),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// Values are then read one at a time, as attachments make a column too big
+// to hold in memory at once
+fn encrypted_ids(conn: &Connection, table: &str, filter: &str) -> Result<Vec<i64>, AppError> {
+    let mut stmt = conn.prepare(&format!("SELECT id FROM {}{}", table, filter))?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+// `id` if no row of `table` has it yet. Imported vault rows keep their
+// archived id where they can, since their values are bound to it.
+pub(super) fn free_id(conn: &Connection, table: &str, id: i64) -> Result<Option<i64>, AppError> {
+    let taken: bool = conn.query_row(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?1)", table), [id], |row| row.get(0))?;
+    Ok((!taken).then_some(id))
+}
+
+// Binds a value that was encrypted for row `from` to row `to`, which it has
+// been copied into. Legacy and unbound values are left to the next upgrade.
+pub(super) fn rebind_value(
+    conn: &Connection,
+    key: Option<&EncryptionService>,
+    table: &str,
+    column: &str,
+    from: i64,
+    to: i64,
+) -> Result<(), AppError> {
+    let value: Option<String> =
+        conn.query_row(&format!("SELECT {} FROM {} WHERE id = ?1", column, table), [to], |row| row.get(0))?;
+    let Some(value) = value.filter(|v| from != to && !encryption::is_legacy(v) && !encryption::is_unbound(v)) else {
+        return Ok(());
+    };
+    let key = key.ok_or_else(|| {
+        AppError::validation("Unlock the vault first; some archived vault entries need new ids, which means re-encrypting them")
+    })?;
+    let plaintext = key.decrypt_bytes(&value, &encryption::aad(table, column, from)).map_err(|e| {
+        AppError::Encryption(format!("{}.{} of archived row {} can't be read ({})", table, column, from, e.message()))
+    })?;
+    conn.execute(
+        &format!("UPDATE {} SET {} = ?1 WHERE id = ?2", table, column),
+        (key.encrypt_bytes(&plaintext, &encryption::aad(table, column, to))?, to),
+    )?;
+    Ok(())
+}
+
+fn encrypted_value(conn: &Connection, table: &str, column: &str, id: i64) -> Result<String, AppError> {
+    let value = conn.query_row(&format!("SELECT {} FROM {} WHERE id = ?1", column, table), [id], |row| row.get(0))?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER_PASSWORD: &str = "correct horse battery staple";
+
+    fn unlocked() -> Database {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault(MASTER_PASSWORD).unwrap();
+        db
+    }
+
+    fn account() -> CreatePersonalAccountRequest {
+        CreatePersonalAccountRequest {
+            title: "Bank".to_string(),
+            email: "me@example.com".to_string(),
+            password: "hunter2".to_string(),
+            website: None,
+            notes: None,
+            category: "banking".to_string(),
+            totp_secret: None,
+            custom_fields: Vec::new(),
+        }
+    }
+
+    // Makes the session look idle for `idle`
+    fn backdate_session(db: &Database, idle: Duration) {
+        let mut session = lock(&db.vault).unwrap();
+        let session = session.as_mut().unwrap();
+        session.last_used = Instant::now().checked_sub(idle).unwrap();
+    }
+
+    #[test]
+    fn locked_vault_rejects_reads_until_unlocked() {
+        let db = unlocked();
+        let id = db.create_personal_account(account()).unwrap().id;
+
+        db.lock_vault().unwrap();
+        assert!(!db.get_vault_status().unwrap().unlocked);
+        assert!(matches!(db.get_personal_accounts(), Err(AppError::VaultLocked)));
+        assert!(matches!(db.reveal_secret(id, SecretField::Password), Err(AppError::VaultLocked)));
+
+        let err = db.unlock_vault("wrong password").unwrap_err();
+        assert_eq!(err.field(), Some("master_password"));
+        db.unlock_vault(MASTER_PASSWORD).unwrap();
+        assert_eq!(db.reveal_secret(id, SecretField::Password).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn idle_vault_locks_after_the_timeout() {
+        let db = unlocked();
+        db.update_vault_settings(VaultSettings { auto_lock_minutes: 1 }).unwrap();
+
+        backdate_session(&db, Duration::from_secs(30));
+        assert!(!db.lock_idle_vault().unwrap());
+        db.get_personal_accounts().unwrap();
+
+        backdate_session(&db, Duration::from_secs(61));
+        assert!(db.lock_idle_vault().unwrap());
+        assert!(lock(&db.vault).unwrap().is_none());
+        assert!(matches!(db.get_personal_accounts(), Err(AppError::VaultLocked)));
+        // Already locked, so nothing more to report
+        assert!(!db.lock_idle_vault().unwrap());
+    }
+
+    #[test]
+    fn zero_minutes_never_locks() {
+        let db = unlocked();
+        db.update_vault_settings(VaultSettings { auto_lock_minutes: 0 }).unwrap();
+
+        backdate_session(&db, Duration::from_secs(24 * 60 * 60));
+        assert!(!db.lock_idle_vault().unwrap());
+        db.get_personal_accounts().unwrap();
+    }
+}
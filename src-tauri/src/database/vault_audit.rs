@@ -0,0 +1,242 @@
+use crate::database::Database;
+use crate::encryption;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::passwords::estimate_strength;
+use crate::validation::*;
+use super::personal_vault::decrypt_value;
+use chrono::{DateTime, Datelike, Utc};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+impl Database {
+    // Password health of every account in the vault. Nothing is written, and
+    // passwords never leave this function.
+    pub fn audit_vault(&self, req: VaultAuditRequest) -> Result<VaultAuditReport, AppError> {
+        req.validate()?;
+
+        let key = self.vault_key()?;
+        let conn = self.reader()?;
+        // The password last changed when it replaced the newest history entry
+        let mut stmt = conn.prepare(
+            "SELECT a.id, a.title, a.website, a.category, a.password,
+                    COALESCE((SELECT MAX(h.replaced_at) FROM password_history h WHERE h.account_id = a.id), a.created_at)
+             FROM personal_accounts a WHERE a.deleted_at IS NULL ORDER BY a.title COLLATE NOCASE",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(stmt);
+        drop(conn);
+
+        let now = Utc::now();
+        let mut accounts = Vec::with_capacity(rows.len());
+        // Accounts by the SHA-1 of their password, for reuse and breach checks
+        let mut by_hash: HashMap<String, Vec<i64>> = HashMap::new();
+        for (id, title, website, category, encrypted_password, changed_at) in rows {
+            let password = decrypt_value(
+                &key,
+                &encrypted_password,
+                &encryption::aad("personal_accounts", "password", id),
+                &format!("password of personal account {}", id),
+            )?;
+            by_hash.entry(sha1_hex(&password)).or_default().push(id);
+
+            let changed = DateTime::parse_from_rfc3339(&changed_at)
+                .map_err(|e| AppError::Database(format!("Invalid password change time on account {}: {}", id, e)))?;
+            accounts.push(AccountAudit {
+                id,
+                title,
+                website,
+                category,
+                issues: Vec::new(),
+                strength: estimate_strength(&password),
+                reused_with: Vec::new(),
+                months_since_change: months_between(changed.with_timezone(&Utc), now),
+                breach_count: None,
+            });
+        }
+
+        let breaches = match req.breach_list_path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            Some(path) => Some(find_breached(path, &by_hash).field("breach_list_path")?),
+            None => None,
+        };
+        let hash_of: HashMap<i64, &String> = by_hash
+            .iter()
+            .flat_map(|(hash, ids)| ids.iter().map(move |id| (*id, hash)))
+            .collect();
+
+        for account in &mut accounts {
+            let hash = hash_of[&account.id];
+            account.reused_with = by_hash[hash].iter().copied().filter(|id| *id != account.id).collect();
+            if !account.reused_with.is_empty() {
+                account.issues.push(AuditIssue::Reused);
+            }
+            if account.strength.score < 2 {
+                account.issues.push(AuditIssue::Weak);
+            }
+            if account.months_since_change >= i64::from(req.stale_after_months) {
+                account.issues.push(AuditIssue::Stale);
+            }
+            if let Some(breaches) = &breaches {
+                account.breach_count = Some(breaches.get(hash).copied().unwrap_or(0));
+                if account.breach_count > Some(0) {
+                    account.issues.push(AuditIssue::Breached);
+                }
+            }
+        }
+
+        let count = |issue: AuditIssue| accounts.iter().filter(|a| a.issues.contains(&issue)).count();
+        Ok(VaultAuditReport {
+            total_accounts: accounts.len(),
+            reused: count(AuditIssue::Reused),
+            weak: count(AuditIssue::Weak),
+            stale: count(AuditIssue::Stale),
+            breached: count(AuditIssue::Breached),
+            breach_list_checked: breaches.is_some(),
+            accounts,
+        })
+    }
+}
+
+fn sha1_hex(password: &str) -> String {
+    Sha1::digest(password.as_bytes()).iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+// Whole calendar months, so an account changed on the 15th is a month old
+// on the 15th of the next month
+fn months_between(from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+    let from = from.date_naive();
+    let to = to.date_naive();
+    let mut months = (to.year() - from.year()) as i64 * 12 + to.month() as i64 - from.month() as i64;
+    if to.day() < from.day() {
+        months -= 1;
+    }
+    months.max(0)
+}
+
+// Streams the list once, since the full Pwned Passwords file runs to tens of
+// gigabytes. Returns the count for each vault hash found in it; lines without
+// a count count once.
+fn find_breached(path: &str, by_hash: &HashMap<String, Vec<i64>>) -> Result<HashMap<String, u64>, AppError> {
+    let file = File::open(path).map_err(|e| AppError::validation(format!("Can't open the breach list: {}", e)))?;
+    let mut found = HashMap::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        let (hash, count) = line.split_once(':').unwrap_or((line, "1"));
+        if hash.len() != 40 {
+            continue;
+        }
+        let hash = hash.to_ascii_uppercase();
+        if by_hash.contains_key(&hash) {
+            found.insert(hash, count.trim().parse().unwrap_or(1));
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // SHA-1 of "password"
+    const PASSWORD_SHA1: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    fn at(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 12, 0, 0).unwrap()
+    }
+
+    fn account(db: &Database, title: &str, password: &str) -> i64 {
+        db.create_personal_account(CreatePersonalAccountRequest {
+            title: title.to_string(),
+            email: "me@example.com".to_string(),
+            password: password.to_string(),
+            website: None,
+            notes: None,
+            category: "web".to_string(),
+            totp_secret: None,
+            custom_fields: Vec::new(),
+        })
+        .unwrap()
+        .id
+    }
+
+    fn audit(db: &Database, breach_list_path: Option<String>) -> VaultAuditReport {
+        db.audit_vault(VaultAuditRequest { stale_after_months: 12, breach_list_path }).unwrap()
+    }
+
+    #[test]
+    fn months_count_from_the_day_of_the_month() {
+        assert_eq!(months_between(at(2024, 1, 15), at(2024, 2, 14)), 0);
+        assert_eq!(months_between(at(2024, 1, 15), at(2024, 2, 15)), 1);
+        assert_eq!(months_between(at(2023, 11, 30), at(2024, 2, 29)), 2);
+        assert_eq!(months_between(at(2023, 12, 31), at(2024, 12, 31)), 12);
+        assert_eq!(months_between(at(2024, 3, 1), at(2024, 2, 1)), 0);
+    }
+
+    #[test]
+    fn breach_lists_match_with_and_without_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwned.txt");
+        let by_hash: HashMap<String, Vec<i64>> = [(PASSWORD_SHA1.to_string(), vec![1]), (sha1_hex("hunter2"), vec![2])].into();
+        let other = sha1_hex("not in the vault");
+
+        std::fs::write(&path, format!("{}:3861493\n{}\nnot a hash\n:5\n", PASSWORD_SHA1.to_lowercase(), other)).unwrap();
+        let found = find_breached(path.to_str().unwrap(), &by_hash).unwrap();
+        assert_eq!(found, [(PASSWORD_SHA1.to_string(), 3861493)].into());
+
+        std::fs::write(&path, format!("{}\n{}:many\n", sha1_hex("hunter2"), PASSWORD_SHA1)).unwrap();
+        let found = find_breached(path.to_str().unwrap(), &by_hash).unwrap();
+        assert_eq!(found, [(PASSWORD_SHA1.to_string(), 1), (sha1_hex("hunter2"), 1)].into());
+
+        let err = find_breached(dir.path().join("missing.txt").to_str().unwrap(), &by_hash).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn groups_reused_passwords_and_flags_issues() {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault("correct horse battery staple").unwrap();
+        let mail = account(&db, "Mail", "password");
+        let shop = account(&db, "Shop", "password");
+        let bank = account(&db, "Bank", "Tr0ub4dor&3-horse-staple-battery");
+        db.writer()
+            .unwrap()
+            .execute("UPDATE personal_accounts SET created_at = '2020-01-01T00:00:00+00:00' WHERE id = ?1", [bank])
+            .unwrap();
+
+        let report = audit(&db, None);
+        assert_eq!((report.total_accounts, report.reused, report.weak, report.stale), (3, 2, 2, 1));
+        assert!(!report.breach_list_checked);
+        let titles: Vec<_> = report.accounts.iter().map(|a| a.title.as_str()).collect();
+        assert_eq!(titles, ["Bank", "Mail", "Shop"]);
+        assert_eq!(report.accounts[0].issues, [AuditIssue::Stale]);
+        assert_eq!(report.accounts[1].reused_with, [shop]);
+        assert_eq!(report.accounts[2].reused_with, [mail]);
+        assert_eq!(report.accounts[1].issues, [AuditIssue::Reused, AuditIssue::Weak]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pwned.txt");
+        std::fs::write(&path, format!("{}:42\n", PASSWORD_SHA1)).unwrap();
+        let report = audit(&db, Some(path.to_string_lossy().to_string()));
+        assert_eq!(report.breached, 2);
+        assert_eq!(report.accounts[0].breach_count, Some(0));
+        assert_eq!(report.accounts[1].breach_count, Some(42));
+
+        let err = db.audit_vault(VaultAuditRequest { stale_after_months: 12, breach_list_path: Some("/nonexistent".to_string()) }).unwrap_err();
+        assert_eq!(err.field(), Some("breach_list_path"));
+    }
+}
@@ -0,0 +1,351 @@
+use crate::bundle::{self, Recipients};
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use super::account_import::{known_accounts, match_key, skip_reason};
+use super::connection::with_suffix;
+use super::info_attachments::{create_private, move_without_overwriting};
+use super::personal_vault::{
+    insert_personal_account, insert_personal_info, read_personal_account, read_personal_info, write_personal_account,
+    write_personal_info,
+};
+use super::secret_access::log_secret_access;
+use chrono::Utc;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+impl Database {
+    // Writes the chosen vault entries, decrypted, into a bundle sealed for
+    // `req.recipients`, for handing the vault to someone or keeping a cold
+    // copy. Existing files are never overwritten, and every exported entry
+    // is recorded in the secret access log.
+    pub fn export_vault_bundle(&self, req: VaultBundleExportRequest) -> Result<VaultBundleExport, AppError> {
+        let path = Path::new(req.path.trim());
+        if !path.is_absolute() {
+            return Err(AppError::invalid_field("path", "Export path must be absolute"));
+        }
+        if path.exists() {
+            return Err(AppError::invalid_field("path", format!("{} already exists", path.display())));
+        }
+        let mut account_ids = req.account_ids;
+        let mut info_ids = req.info_ids;
+        account_ids.sort_unstable();
+        account_ids.dedup();
+        info_ids.sort_unstable();
+        info_ids.dedup();
+        if account_ids.is_empty() && info_ids.is_empty() {
+            return Err(AppError::validation("Choose at least one entry to export"));
+        }
+        let recipients = match &req.recipients {
+            BundleRecipients::Passphrase { passphrase } => {
+                validate_password_strength(passphrase).field("passphrase")?;
+                Recipients::Passphrase(passphrase)
+            }
+            BundleRecipients::PublicKeys { public_keys } => {
+                if public_keys.is_empty() {
+                    return Err(AppError::invalid_field("public_keys", "Add at least one public key"));
+                }
+                let keys = public_keys
+                    .iter()
+                    .map(|key| {
+                        bundle::parse_public_key(key).ok_or_else(|| {
+                            AppError::invalid_field("public_keys", format!("'{}' is not an X25519 public key (age1…)", key.trim()))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Recipients::PublicKeys(keys)
+            }
+        };
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        let contents = VaultBundleContents {
+            exported_at: Utc::now().to_rfc3339(),
+            personal_accounts: account_ids
+                .iter()
+                .map(|&id| read_personal_account(&tx, &key, id))
+                .collect::<Result<Vec<_>, _>>()?,
+            personal_info: info_ids
+                .iter()
+                .map(|&id| read_personal_info(&tx, &key, id))
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        let plaintext = Zeroizing::new(serde_json::to_vec(&contents).map_err(|e| AppError::Io(e.to_string()))?);
+        let sealed = bundle::seal(&plaintext, &recipients)?;
+
+        // Written under a temporary name so a failed export leaves nothing
+        // behind. The access log is committed before the file is moved into
+        // place, so no bundle exists without its entries being recorded.
+        let partial = with_suffix(path, ".partial");
+        let written = create_private(&partial)
+            .and_then(|mut out| {
+                out.write_all(sealed.as_bytes())?;
+                out.sync_all()?;
+                Ok(())
+            })
+            .and_then(|()| {
+                for account in &contents.personal_accounts {
+                    log_secret_access(&tx, SecretField::BundledAccount, account.id, &account.title)?;
+                }
+                for info in &contents.personal_info {
+                    log_secret_access(&tx, SecretField::BundledInfo, info.id, &info.title)?;
+                }
+                tx.commit()?;
+                move_without_overwriting(&partial, path)
+            });
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+
+        Ok(VaultBundleExport {
+            path: path.to_string_lossy().to_string(),
+            personal_accounts: contents.personal_accounts.len(),
+            personal_info: contents.personal_info.len(),
+        })
+    }
+
+    // Reads a bundle back into the vault, re-encrypted with this vault's key.
+    // Entries matching one already in the vault are handled as `req.conflict`
+    // says; invalid ones are skipped. Either everything is imported or
+    // nothing is.
+    pub fn import_vault_bundle(&self, req: VaultBundleImportRequest) -> Result<VaultBundleImportReport, AppError> {
+        let data = std::fs::read_to_string(req.path.trim())?;
+        let plaintext = bundle::open(&data, &req.unlock)?;
+        let contents: VaultBundleContents = serde_json::from_slice(&plaintext)
+            .map_err(|e| AppError::validation(format!("The bundle's contents are not valid: {}", e)))?;
+
+        let mut conn = self.writer()?;
+        let key = self.vault_key()?;
+        let tx = conn.transaction()?;
+        let mut report = VaultBundleImportReport {
+            dry_run: req.dry_run,
+            entries: Vec::new(),
+        };
+
+        let mut known_accounts = known_accounts(&tx, &key)?;
+        for account in contents.personal_accounts {
+            let account_key = match_key(&account.title, account.website.as_deref(), &account.email);
+            let existing = known_accounts.get(&account_key).copied();
+            let entry = import_entry(BundleEntryKind::PersonalAccount, &account.title, existing, req.conflict);
+
+            let account_req = CreatePersonalAccountRequest {
+                title: account.title,
+                email: account.email,
+                password: account.password,
+                website: account.website,
+                notes: account.notes,
+                category: account.category,
+                totp_secret: account.totp_secret,
+                custom_fields: field_inputs(account.custom_fields),
+            };
+            if let Err(e) = account_req.validate() {
+                report.entries.push(BundleImportEntry {
+                    outcome: BundleImportOutcome::Skipped,
+                    reason: Some(skip_reason(&e)),
+                    ..entry
+                });
+                continue;
+            }
+
+            if !req.dry_run {
+                match (entry.outcome, existing) {
+                    (BundleImportOutcome::Added, _) => {
+                        let id = insert_personal_account(&tx, &key, account_req)?.id;
+                        known_accounts.entry(account_key).or_insert(id);
+                    }
+                    (BundleImportOutcome::Replaced, Some(id)) => {
+                        let update = UpdatePersonalAccountRequest {
+                            title: account_req.title,
+                            email: account_req.email,
+                            password: Some(account_req.password),
+                            website: account_req.website,
+                            notes: account_req.notes,
+                            category: account_req.category,
+                            totp_secret: account_req.totp_secret,
+                            custom_fields: account_req.custom_fields,
+                        };
+                        write_personal_account(&tx, &key, id, update)?;
+                    }
+                    _ => {}
+                }
+            }
+            report.entries.push(entry);
+        }
+
+        let mut known_info = known_info(&tx)?;
+        for info in contents.personal_info {
+            let info_key = info_match_key(&info.title, &info.category);
+            let existing = known_info.get(&info_key).copied();
+            let entry = import_entry(BundleEntryKind::PersonalInfo, &info.title, existing, req.conflict);
+
+            let info_req = CreatePersonalInfoRequest {
+                title: info.title,
+                content: info.content,
+                category: info.category,
+                is_sensitive: info.is_sensitive,
+                custom_fields: field_inputs(info.custom_fields),
+            };
+            if let Err(e) = info_req.validate() {
+                report.entries.push(BundleImportEntry {
+                    outcome: BundleImportOutcome::Skipped,
+                    reason: Some(skip_reason(&e)),
+                    ..entry
+                });
+                continue;
+            }
+
+            if !req.dry_run {
+                match (entry.outcome, existing) {
+                    (BundleImportOutcome::Added, _) => {
+                        let id = insert_personal_info(&tx, &key, info_req)?.id;
+                        known_info.entry(info_key).or_insert(id);
+                    }
+                    (BundleImportOutcome::Replaced, Some(id)) => {
+                        write_personal_info(&tx, &key, id, info_req)?;
+                    }
+                    _ => {}
+                }
+            }
+            report.entries.push(entry);
+        }
+
+        if !req.dry_run {
+            tx.commit()?;
+        }
+        Ok(report)
+    }
+}
+
+// What becomes of a bundle entry that matched `existing`, if anything
+fn import_entry(kind: BundleEntryKind, title: &str, existing: Option<i64>, conflict: BundleConflict) -> BundleImportEntry {
+    let (outcome, reason) = match (existing, conflict) {
+        (None, _) | (Some(_), BundleConflict::KeepBoth) => (BundleImportOutcome::Added, None),
+        (Some(_), BundleConflict::Skip) => {
+            (BundleImportOutcome::Skipped, Some("An entry like this is already in the vault".to_string()))
+        }
+        (Some(_), BundleConflict::Replace) => (BundleImportOutcome::Replaced, None),
+    };
+    BundleImportEntry {
+        kind,
+        title: title.trim().to_string(),
+        outcome,
+        existing_id: existing,
+        reason,
+    }
+}
+
+fn known_info(conn: &Connection) -> Result<HashMap<(String, String), i64>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, title, category FROM personal_info WHERE deleted_at IS NULL")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut known = HashMap::new();
+    for (id, title, category) in rows {
+        known.entry(info_match_key(&title, &category)).or_insert(id);
+    }
+    Ok(known)
+}
+
+fn info_match_key(title: &str, category: &str) -> (String, String) {
+    (title.trim().to_lowercase(), category.trim().to_lowercase())
+}
+
+fn field_inputs(fields: Vec<CustomField>) -> Vec<CustomFieldInput> {
+    fields
+        .into_iter()
+        .map(|field| CustomFieldInput {
+            id: None,
+            label: field.label,
+            field_type: field.field_type,
+            value: field.value.unwrap_or_default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn vault_with_account() -> (Database, i64) {
+        let db = Database::open_in_memory().unwrap();
+        db.setup_vault(PASSPHRASE).unwrap();
+        let id = db
+            .create_personal_account(CreatePersonalAccountRequest {
+                title: "Bank".to_string(),
+                email: "me@example.com".to_string(),
+                password: "hunter2".to_string(),
+                website: Some("https://bank.example".to_string()),
+                notes: None,
+                category: "banking".to_string(),
+                totp_secret: None,
+                custom_fields: Vec::new(),
+            })
+            .unwrap()
+            .id;
+        (db, id)
+    }
+
+    fn export_request(path: &Path, account_id: i64) -> VaultBundleExportRequest {
+        VaultBundleExportRequest {
+            path: path.to_string_lossy().to_string(),
+            account_ids: vec![account_id],
+            info_ids: Vec::new(),
+            recipients: BundleRecipients::Passphrase { passphrase: PASSPHRASE.to_string() },
+        }
+    }
+
+    #[test]
+    fn export_is_logged_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.bundle");
+        let (db, id) = vault_with_account();
+
+        let export = db.export_vault_bundle(export_request(&path, id)).unwrap();
+        assert_eq!(export.personal_accounts, 1);
+        assert!(!with_suffix(&path, ".partial").exists());
+
+        let log = db.list_secret_access(None).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!((log[0].field, log[0].item_id, log[0].title.as_str()), (SecretField::BundledAccount, id, "Bank"));
+
+        let report = db
+            .import_vault_bundle(VaultBundleImportRequest {
+                path: path.to_string_lossy().to_string(),
+                unlock: BundleUnlock::Passphrase { passphrase: PASSPHRASE.to_string() },
+                conflict: BundleConflict::KeepBoth,
+                dry_run: false,
+            })
+            .unwrap();
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(db.get_personal_accounts().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn never_overwrites_an_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vault.bundle");
+        std::fs::write(&path, b"keep me").unwrap();
+        let (db, id) = vault_with_account();
+
+        let err = db.export_vault_bundle(export_request(&path, id)).unwrap_err();
+        assert_eq!(err.field(), Some("path"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"keep me");
+        assert!(db.list_secret_access(None).unwrap().is_empty());
+
+        // A file that appears while the bundle is being written is kept too
+        let partial = with_suffix(&path, ".partial");
+        std::fs::write(&partial, b"bundle").unwrap();
+        let err = move_without_overwriting(&partial, &path).unwrap_err();
+        assert_eq!(err.field(), Some("path"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"keep me");
+    }
+}
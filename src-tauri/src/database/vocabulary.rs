@@ -0,0 +1,350 @@
+use crate::database::Database;
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use crate::scheduler::{self, CardState};
+use super::scheduler_settings::load_scheduler_settings;
+use super::trash::require_active;
+use chrono::{DateTime, Utc};
+
+impl Database {
+    pub fn create_vocabulary(&self, req: CreateVocabularyRequest) -> Result<Vocabulary, AppError> {
+        req.validate()?;
+
+        let word = sanitize_string(req.word);
+        let translation = sanitize_string(req.translation);
+        let pronunciation = sanitize_optional_string(req.pronunciation);
+        let example_sentence = sanitize_optional_string(req.example_sentence);
+
+        let mut conn = self.writer()?;
+        let now = Utc::now();
+        let created_at = now.to_rfc3339();
+        let next_review = (now + chrono::Duration::days(1)).to_rfc3339();
+
+        // The word and its flashcard are created together or not at all
+        let tx = conn.transaction()?;
+        require_active(&tx, TrashKind::Language, req.language_id, "language_id")?;
+
+        tx.execute(
+            "INSERT INTO vocabulary (language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (&req.language_id, &word, &translation, &pronunciation, &example_sentence, &req.difficulty_level, &created_at),
+        )?;
+
+        let vocab_id = tx.last_insert_rowid();
+
+        tx.execute(
+            "INSERT INTO flashcards (vocabulary_id, ease_factor, interval_days, repetitions, next_review, created_at)
+             VALUES (?1, 2.5, 1, 0, ?2, ?3)",
+            (vocab_id, &next_review, &created_at),
+        )?;
+
+        tx.commit()?;
+
+        Ok(Vocabulary {
+            id: vocab_id,
+            language_id: req.language_id,
+            word,
+            translation,
+            pronunciation,
+            example_sentence,
+            difficulty_level: req.difficulty_level,
+            created_at,
+        })
+    }
+
+    pub fn get_vocabulary_by_language(&self, language_id: i64) -> Result<Vec<Vocabulary>, AppError> {
+        let conn = self.reader()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at
+             FROM vocabulary WHERE language_id = ?1 AND deleted_at IS NULL ORDER BY word"
+        )?;
+
+        let vocab_iter = stmt.query_map([language_id], |row| {
+            Ok(Vocabulary {
+                id: row.get(0)?,
+                language_id: row.get(1)?,
+                word: row.get(2)?,
+                translation: row.get(3)?,
+                pronunciation: row.get(4)?,
+                example_sentence: row.get(5)?,
+                difficulty_level: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+
+        let mut vocabulary = Vec::new();
+        for vocab in vocab_iter {
+            vocabulary.push(vocab?);
+        }
+
+        Ok(vocabulary)
+    }
+
+    // Moves the word and its flashcard to the trash
+    pub fn delete_vocabulary(&self, id: i64) -> Result<(), AppError> {
+        self.move_to_trash(TrashKind::Vocabulary, id)
+    }
+
+    pub fn search_vocabulary(&self, query: &str, limit: i64) -> Result<Vec<Vocabulary>, AppError> {
+        let conn = self.reader()?;
+        let search_term = format!("%{}%", query.trim());
+
+        let mut stmt = conn.prepare(
+            "SELECT id, language_id, word, translation, pronunciation, example_sentence, difficulty_level, created_at
+             FROM vocabulary
+             WHERE deleted_at IS NULL
+               AND (word LIKE ?1 OR translation LIKE ?1 OR example_sentence LIKE ?1)
+             ORDER BY word
+             LIMIT ?2"
+        )?;
+
+        let vocab_iter = stmt.query_map((&search_term, limit), |row| {
+            Ok(Vocabulary {
+                id: row.get(0)?,
+                language_id: row.get(1)?,
+                word: row.get(2)?,
+                translation: row.get(3)?,
+                pronunciation: row.get(4)?,
+                example_sentence: row.get(5)?,
+                difficulty_level: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+
+        let mut vocabulary = Vec::new();
+        for vocab in vocab_iter {
+            vocabulary.push(vocab?);
+        }
+
+        Ok(vocabulary)
+    }
+
+    pub fn get_due_flashcards(&self, limit: i64) -> Result<Vec<(Flashcard, Vocabulary)>, AppError> {
+        let conn = self.reader()?;
+        let now = Utc::now().to_rfc3339();
+
+        let mut stmt = conn.prepare(
+            "SELECT f.id, f.vocabulary_id, f.ease_factor, f.interval_days, f.repetitions, f.next_review, f.last_reviewed, f.stability, f.difficulty, f.created_at,
+                    v.id, v.language_id, v.word, v.translation, v.pronunciation, v.example_sentence, v.difficulty_level, v.created_at
+             FROM flashcards f
+             JOIN vocabulary v ON f.vocabulary_id = v.id
+             WHERE f.next_review <= ?1 AND f.deleted_at IS NULL
+             ORDER BY f.next_review
+             LIMIT ?2"
+        )?;
+
+        let flashcard_iter = stmt.query_map((&now, limit), |row| {
+            let flashcard = Flashcard {
+                id: row.get(0)?,
+                vocabulary_id: row.get(1)?,
+                ease_factor: row.get(2)?,
+                interval_days: row.get(3)?,
+                repetitions: row.get(4)?,
+                next_review: row.get(5)?,
+                last_reviewed: row.get(6)?,
+                stability: row.get(7)?,
+                difficulty: row.get(8)?,
+                created_at: row.get(9)?,
+            };
+
+            let vocabulary = Vocabulary {
+                id: row.get(10)?,
+                language_id: row.get(11)?,
+                word: row.get(12)?,
+                translation: row.get(13)?,
+                pronunciation: row.get(14)?,
+                example_sentence: row.get(15)?,
+                difficulty_level: row.get(16)?,
+                created_at: row.get(17)?,
+            };
+
+            Ok((flashcard, vocabulary))
+        })?;
+
+        let mut flashcards = Vec::new();
+        for item in flashcard_iter {
+            flashcards.push(item?);
+        }
+
+        Ok(flashcards)
+    }
+
+    // Schedules the card's next review with its language's scheduler and
+    // logs the review, with the card's state before it
+    pub fn review_flashcard(&self, req: FlashcardReviewRequest) -> Result<(), AppError> {
+        req.validate()?;
+
+        let mut conn = self.writer()?;
+        let tx = conn.transaction()?;
+
+        let (language_id, card, next_review, last_reviewed): (i64, CardState, String, Option<String>) = tx
+            .query_row(
+                "SELECT v.language_id, f.ease_factor, f.interval_days, f.repetitions, f.stability, f.difficulty,
+                        f.next_review, f.last_reviewed
+                 FROM flashcards f JOIN vocabulary v ON v.id = f.vocabulary_id
+                 WHERE f.id = ?1 AND f.deleted_at IS NULL",
+                [req.flashcard_id],
+                |row| {
+                    let card = CardState {
+                        ease_factor: row.get(1)?,
+                        interval_days: row.get(2)?,
+                        repetitions: row.get(3)?,
+                        stability: row.get(4)?,
+                        difficulty: row.get(5)?,
+                    };
+                    Ok((row.get(0)?, card, row.get(6)?, row.get(7)?))
+                },
+            )
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => {
+                    AppError::NotFound(format!("Flashcard {} not found", req.flashcard_id))
+                }
+                other => other.into(),
+            })?;
+
+        let now = Utc::now();
+        let elapsed_days = last_reviewed
+            .as_deref()
+            .and_then(|last| DateTime::parse_from_rfc3339(last).ok())
+            .map(|last| (now - last.with_timezone(&Utc)).num_seconds().max(0) as f64 / 86_400.0);
+
+        let settings = load_scheduler_settings(&tx, language_id)?;
+        let next = scheduler::for_settings(&settings).review(&card, req.quality, elapsed_days);
+
+        let new_next_review = (now + chrono::Duration::days(next.interval_days)).to_rfc3339();
+        let now = now.to_rfc3339();
+
+        tx.execute(
+            "UPDATE flashcards SET ease_factor = ?1, interval_days = ?2, repetitions = ?3, stability = ?4, difficulty = ?5,
+                    next_review = ?6, last_reviewed = ?7
+             WHERE id = ?8",
+            (
+                next.ease_factor,
+                next.interval_days,
+                next.repetitions,
+                next.stability,
+                next.difficulty,
+                &new_next_review,
+                &now,
+                req.flashcard_id,
+            ),
+        )?;
+        // The card's state before the review is kept so it can be undone
+        tx.execute(
+            "INSERT INTO review_log (flashcard_id, reviewed_at, quality, elapsed_days, duration_ms,
+                                     prev_ease_factor, prev_interval_days, prev_repetitions, prev_stability, prev_difficulty,
+                                     prev_next_review, prev_last_reviewed, new_ease_factor, new_interval_days)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            (
+                req.flashcard_id,
+                &now,
+                req.quality,
+                elapsed_days,
+                req.duration_ms,
+                card.ease_factor,
+                card.interval_days,
+                card.repetitions,
+                card.stability,
+                card.difficulty,
+                &next_review,
+                &last_reviewed,
+                next.ease_factor,
+                next.interval_days,
+            ),
+        )?;
+
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (Database, i64) {
+        let db = Database::open_in_memory().unwrap();
+        let language = db
+            .create_language(CreateLanguageRequest {
+                name: "Spanish".to_string(),
+                code: "es".to_string(),
+                flag_emoji: "🇪🇸".to_string(),
+            })
+            .unwrap();
+        (db, language.id)
+    }
+
+    fn request(language_id: i64, word: &str, translation: &str) -> CreateVocabularyRequest {
+        CreateVocabularyRequest {
+            language_id,
+            word: word.to_string(),
+            translation: translation.to_string(),
+            pronunciation: Some("  ".to_string()),
+            example_sentence: None,
+            difficulty_level: 2,
+        }
+    }
+
+    #[test]
+    fn creates_words_with_a_flashcard() {
+        let (db, language_id) = setup();
+        let casa = db.create_vocabulary(request(language_id, " casa ", "house")).unwrap();
+        db.create_vocabulary(request(language_id, "agua", "water")).unwrap();
+        assert_eq!(casa.word, "casa");
+        assert_eq!(casa.pronunciation, None);
+
+        let words: Vec<_> = db.get_vocabulary_by_language(language_id).unwrap().into_iter().map(|v| v.word).collect();
+        assert_eq!(words, ["agua", "casa"]);
+
+        let found = db.search_vocabulary("hous", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, casa.id);
+
+        let cards: i64 = db
+            .reader()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM flashcards WHERE vocabulary_id = ?1", [casa.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cards, 1);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let (db, language_id) = setup();
+        let cases = [
+            (request(language_id, "", "house"), "word"),
+            (request(language_id, "casa", "  "), "translation"),
+            (request(language_id, "casa", &"x".repeat(501)), "translation"),
+            (CreateVocabularyRequest { difficulty_level: 6, ..request(language_id, "casa", "house") }, "difficulty_level"),
+        ];
+        for (req, field) in cases {
+            let err = db.create_vocabulary(req).unwrap_err();
+            assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+            assert_eq!(err.field(), Some(field));
+        }
+        assert!(db.get_vocabulary_by_language(language_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn needs_an_active_language() {
+        let (db, language_id) = setup();
+        let err = db.create_vocabulary(request(language_id + 1, "casa", "house")).unwrap_err();
+        assert!(matches!(err, AppError::MissingReference { .. }), "{:?}", err);
+        assert_eq!(err.field(), Some("language_id"));
+
+        db.delete_language(language_id).unwrap();
+        let err = db.create_vocabulary(request(language_id, "casa", "house")).unwrap_err();
+        assert_eq!(err.field(), Some("language_id"));
+    }
+
+    #[test]
+    fn rejects_out_of_range_review_quality() {
+        let (db, _) = setup();
+        let err = db
+            .review_flashcard(FlashcardReviewRequest { flashcard_id: 1, quality: 0, duration_ms: None })
+            .unwrap_err();
+        assert_eq!(err.field(), Some("quality"));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::database::Database;
+use super::connection::{create_database_file, lock, with_suffix};
+use super::file_encryption::{is_encrypted, unsupported, SUPPORTED};
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::*;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Known workspaces, persisted as JSON next to the default database so it
+// survives independently of whichever workspace file is open. A detached
+// registry has no file and only lasts as long as its `Database`.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct WorkspaceRegistry {
+    #[serde(skip)]
+    file: Option<PathBuf>,
+    #[serde(skip)]
+    data_dir: PathBuf,
+    pub workspaces: Vec<Workspace>,
+    pub active_id: Option<i64>,
+    next_id: i64,
+}
+
+impl WorkspaceRegistry {
+    pub fn load(file: PathBuf) -> Result<Self, AppError> {
+        let mut registry = if file.exists() {
+            let data = std::fs::read_to_string(&file)?;
+            serde_json::from_str::<WorkspaceRegistry>(&data)
+                .map_err(|e| AppError::Io(format!("Invalid workspace registry: {}", e)))?
+        } else {
+            WorkspaceRegistry::default()
+        };
+        registry.data_dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+        registry.file = Some(file);
+        Ok(registry)
+    }
+
+    pub fn detached(data_dir: PathBuf) -> Self {
+        WorkspaceRegistry {
+            data_dir,
+            ..Default::default()
+        }
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Io(e.to_string()))?;
+
+        // Write-then-rename so a crash never leaves a truncated registry
+        let tmp = file.with_extension("json.tmp");
+        std::fs::write(&tmp, data)?;
+        std::fs::rename(&tmp, file)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, name: &str, path: &str) -> Result<Workspace, AppError> {
+        self.check_available(name, path)?;
+
+        self.next_id += 1;
+        let workspace = Workspace {
+            id: self.next_id,
+            name: name.to_string(),
+            path: path.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            last_opened_at: None,
+            backup: BackupSettings::default(),
+            trash: TrashSettings::default(),
+            vault: VaultSettings::default(),
+        };
+
+        self.workspaces.push(workspace.clone());
+        self.save()?;
+        Ok(workspace)
+    }
+
+    pub fn get(&self, id: i64) -> Result<&Workspace, AppError> {
+        self.workspaces
+            .iter()
+            .find(|w| w.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("Workspace {} not found", id)))
+    }
+
+    pub fn get_mut(&mut self, id: i64) -> Result<&mut Workspace, AppError> {
+        self.workspaces
+            .iter_mut()
+            .find(|w| w.id == id)
+            .ok_or_else(|| AppError::NotFound(format!("Workspace {} not found", id)))
+    }
+
+    // Folder holding the registry, default database and default backups
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    // Names and paths are unique among registered workspaces
+    fn check_available(&self, name: &str, path: &str) -> Result<(), AppError> {
+        if self.find_by_name(name).is_some() {
+            return Err(AppError::Duplicate { table: "workspaces".to_string(), field: "name".to_string(), in_trash: false });
+        }
+        if self.workspaces.iter().any(|w| Path::new(&w.path) == Path::new(path)) {
+            return Err(AppError::Duplicate { table: "workspaces".to_string(), field: "path".to_string(), in_trash: false });
+        }
+        Ok(())
+    }
+
+    fn find_by_name(&self, name: &str) -> Option<&Workspace> {
+        self.workspaces.iter().find(|w| w.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Database {
+    pub fn create_workspace(&self, req: CreateWorkspaceRequest) -> Result<Workspace, AppError> {
+        req.validate()?;
+
+        let name = sanitize_string(req.name);
+        let mut path = PathBuf::from(req.path.trim());
+
+        // A folder means "put the workspace database in here", under a file
+        // name made safe from the display name
+        if path.is_dir() {
+            path.push(format!("{}.db", file_stem_for(&name)));
+        }
+
+        // A taken name or path is refused before anything is written
+        let path_str = path.to_string_lossy().to_string();
+        lock(&self.registry)?.check_available(&name, &path_str)?;
+
+        // Initialise the file up front so a bad location fails before
+        // registering; a file this call created is removed again on failure
+        let existed = path.exists();
+        create_database_file(&path)?;
+        let registered = (|| {
+            if !is_encrypted(&path)? {
+                Self::open_connections(&path, None)?;
+            }
+            lock(&self.registry)?.add(&name, &path_str)
+        })();
+        let workspace = match registered {
+            Ok(workspace) => workspace,
+            Err(e) => {
+                if !existed {
+                    for suffix in ["", "-wal", "-shm"] {
+                        let _ = std::fs::remove_file(with_suffix(&path, suffix));
+                    }
+                }
+                return Err(e);
+            }
+        };
+        self.open_workspace(workspace.id)
+    }
+
+    pub fn list_workspaces(&self) -> Result<Vec<Workspace>, AppError> {
+        let registry = lock(&self.registry)?;
+        let mut workspaces = registry.workspaces.clone();
+        workspaces.sort_by_key(|w| w.name.to_lowercase());
+        Ok(workspaces)
+    }
+
+    pub fn get_current_workspace(&self) -> Result<Option<Workspace>, AppError> {
+        Ok(lock(&self.current_workspace)?.clone())
+    }
+
+    pub fn open_workspace(&self, id: i64) -> Result<Workspace, AppError> {
+        let path = lock(&self.registry)?.get(id)?.path.clone();
+
+        // Open the new database before dropping the old one so a failure
+        // leaves the current workspace usable. An encrypted one stays closed
+        // until the vault is unlocked, since its key comes from the master
+        // password.
+        let connections = if is_encrypted(Path::new(&path))? {
+            if !SUPPORTED {
+                return Err(unsupported());
+            }
+            None
+        } else {
+            Some(Self::open_connections(Path::new(&path), None)?)
+        };
+
+        let workspace = {
+            let mut registry = lock(&self.registry)?;
+            let workspace = registry.get_mut(id)?;
+            workspace.last_opened_at = Some(Utc::now().to_rfc3339());
+            let workspace = workspace.clone();
+            registry.active_id = Some(id);
+            registry.save()?;
+            workspace
+        };
+
+        self.replace_connections(connections)?;
+        *lock(&self.current_workspace)? = Some(workspace.clone());
+
+        Ok(workspace)
+    }
+
+    pub fn close_workspace(&self) -> Result<(), AppError> {
+        self.replace_connections(None)?;
+        *lock(&self.current_workspace)? = None;
+
+        let mut registry = lock(&self.registry)?;
+        registry.active_id = None;
+        registry.save()
+    }
+
+    pub fn rename_workspace(&self, id: i64, name: String) -> Result<Workspace, AppError> {
+        validate_not_empty(&name, "Workspace name").field("name")?;
+        validate_string_length(name.trim(), "Workspace name", 1, 100).field("name")?;
+        let name = sanitize_string(name);
+
+        let workspace = {
+            let mut registry = lock(&self.registry)?;
+            if registry.find_by_name(&name).is_some_and(|w| w.id != id) {
+                return Err(AppError::Duplicate { table: "workspaces".to_string(), field: "name".to_string(), in_trash: false });
+            }
+            let workspace = registry.get_mut(id)?;
+            workspace.name = name;
+            let workspace = workspace.clone();
+            registry.save()?;
+            workspace
+        };
+
+        let mut current = lock(&self.current_workspace)?;
+        if current.as_ref().is_some_and(|w| w.id == id) {
+            *current = Some(workspace.clone());
+        }
+
+        Ok(workspace)
+    }
+}
+
+// A workspace name cut down to letters, digits, '-' and '_' for use as a file
+// name, so separators and ".." can't take the file out of the chosen folder
+fn file_stem_for(name: &str) -> String {
+    let mut stem = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            stem.push(c);
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+    if stem.is_empty() {
+        "workspace".to_string()
+    } else {
+        stem.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(name: &str, path: &Path) -> CreateWorkspaceRequest {
+        CreateWorkspaceRequest { name: name.to_string(), path: path.to_string_lossy().to_string() }
+    }
+
+    #[test]
+    fn creates_lists_renames_and_switches() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+
+        let notes = db.create_workspace(request("Work notes", dir.path())).unwrap();
+        assert_eq!(Path::new(&notes.path), dir.path().join("Work-notes.db"));
+        assert_eq!(db.get_current_workspace().unwrap().unwrap().id, notes.id);
+
+        let names: Vec<_> = db.list_workspaces().unwrap().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, ["main", "Work notes"]);
+
+        let renamed = db.rename_workspace(notes.id, " Journal ".to_string()).unwrap();
+        assert_eq!(renamed.name, "Journal");
+        assert_eq!(db.get_current_workspace().unwrap().unwrap().name, "Journal");
+        let err = db.rename_workspace(notes.id, "MAIN".to_string()).unwrap_err();
+        assert!(matches!(err, AppError::Duplicate { .. }), "{:?}", err);
+        assert_eq!(err.field(), Some("name"));
+
+        let main = db.list_workspaces().unwrap().into_iter().find(|w| w.name == "main").unwrap();
+        db.open_workspace(main.id).unwrap();
+        assert_eq!(db.get_current_workspace().unwrap().unwrap().id, main.id);
+    }
+
+    #[test]
+    fn refuses_taken_names_and_paths_without_leaving_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open(dir.path().join("main.db")).unwrap();
+        db.create_workspace(request("Notes", &dir.path().join("notes.db"))).unwrap();
+
+        let other = dir.path().join("other.db");
+        let err = db.create_workspace(request("notes", &other)).unwrap_err();
+        assert!(matches!(err, AppError::Duplicate { .. }), "{:?}", err);
+        assert_eq!(err.field(), Some("name"));
+        assert!(!other.exists());
+
+        let err = db.create_workspace(request("Other", &dir.path().join("notes.db"))).unwrap_err();
+        assert_eq!(err.field(), Some("path"));
+        assert_eq!(db.list_workspaces().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let db = Database::open_in_memory().unwrap();
+        let err = db.create_workspace(request(" ", Path::new("/tmp"))).unwrap_err();
+        assert_eq!(err.field(), Some("name"));
+        let err = db.create_workspace(request("Notes", Path::new("relative/notes.db"))).unwrap_err();
+        assert!(matches!(err, AppError::Validation { .. }), "{:?}", err);
+        assert_eq!(err.field(), Some("path"));
+    }
+
+    #[test]
+    fn file_stems_stay_inside_the_folder() {
+        assert_eq!(file_stem_for("Work notes"), "Work-notes");
+        assert_eq!(file_stem_for("../../etc/passwd"), "etc-passwd");
+        assert_eq!(file_stem_for("a / b \\ c"), "a-b-c");
+        assert_eq!(file_stem_for("snake_case.v2"), "snake_case-v2");
+        assert_eq!(file_stem_for("Über Café"), "Über-Café");
+        assert_eq!(file_stem_for("..."), "workspace");
+        assert_eq!(file_stem_for(""), "workspace");
+    }
+}
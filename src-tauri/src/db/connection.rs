@@ -1,212 +0,0 @@
-use rusqlite::Connection;
-use anyhow::Result;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-
-pub struct Database {
-    pub conn: Arc<Mutex<Connection>>,
-}
-
-impl Database {
-    pub fn new() -> Result<Self> {
-        let app_data_dir = dirs::data_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("yotion");
-
-        std::fs::create_dir_all(&app_data_dir)?;
-        let db_path = app_data_dir.join("yotion.db");
-
-        let conn = Connection::open(db_path)?;
-        conn.execute("PRAGMA foreign_keys = ON", [])?;
-
-        let db = Database {
-            conn: Arc::new(Mutex::new(conn))
-        };
-
-        db.create_tables()?;
-        Ok(db)
-    }
-
-    fn create_tables(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS languages (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                code TEXT NOT NULL UNIQUE,
-                flag_emoji TEXT NOT NULL,
-                created_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS vocabulary (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                language_id INTEGER NOT NULL,
-                word TEXT NOT NULL,
-                translation TEXT NOT NULL,
-                pronunciation TEXT,
-                example_sentence TEXT,
-                difficulty_level INTEGER NOT NULL DEFAULT 1,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (language_id) REFERENCES languages(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS flashcards (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                vocabulary_id INTEGER NOT NULL,
-                ease_factor REAL NOT NULL DEFAULT 2.5,
-                interval_days INTEGER NOT NULL DEFAULT 1,
-                repetitions INTEGER NOT NULL DEFAULT 0,
-                next_review TEXT NOT NULL,
-                last_reviewed TEXT,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (vocabulary_id) REFERENCES vocabulary(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tech_spaces (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                description TEXT,
-                icon TEXT NOT NULL,
-                created_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS code_snippets (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                tech_space_id INTEGER NOT NULL,
-                title TEXT NOT NULL,
-                description TEXT,
-                code TEXT NOT NULL,
-                language TEXT NOT NULL,
-                tags TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY (tech_space_id) REFERENCES tech_spaces(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS projects (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                description TEXT,
-                status TEXT NOT NULL DEFAULT 'active',
-                priority TEXT NOT NULL DEFAULT 'medium',
-                start_date TEXT,
-                end_date TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tasks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                project_id INTEGER NOT NULL,
-                title TEXT NOT NULL,
-                description TEXT,
-                status TEXT NOT NULL DEFAULT 'todo',
-                priority TEXT NOT NULL DEFAULT 'medium',
-                due_date TEXT,
-                completed_at TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS events (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                description TEXT,
-                event_date TEXT NOT NULL,
-                start_time TEXT,
-                end_time TEXT,
-                event_type TEXT NOT NULL DEFAULT 'event',
-                priority TEXT NOT NULL DEFAULT 'medium',
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS notes (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                content TEXT NOT NULL,
-                note_date TEXT NOT NULL,
-                tags TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS personal_accounts (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                email TEXT NOT NULL,
-                password TEXT NOT NULL,
-                website TEXT,
-                notes TEXT,
-                category TEXT NOT NULL DEFAULT 'email',
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS personal_info (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                title TEXT NOT NULL,
-                content TEXT NOT NULL,
-                category TEXT NOT NULL DEFAULT 'general',
-                is_sensitive INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_vocabulary_language_id ON vocabulary(language_id)",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_flashcards_next_review ON flashcards(next_review)",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_code_snippets_tech_space_id ON code_snippets(tech_space_id)",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_tasks_project_id ON tasks(project_id)",
-            [],
-        )?;
-
-        Ok(())
-    }
-}
@@ -1,9 +0,0 @@
-pub mod connection;
-pub mod language;
-pub mod vocabulary;
-pub mod tech_notes;
-pub mod projects;
-pub mod planner;
-pub mod personal_vault;
-
-pub use connection::Database;
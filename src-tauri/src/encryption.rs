@@ -1,63 +1,246 @@
 use crate::errors::AppError;
+use crate::models::KdfParams;
+use argon2::{Algorithm, Argon2, Params, Version};
 use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
 
-// Simple encryption for demo purposes
-// In production, use proper encryption libraries like aes-gcm or chacha20poly1305
+// Encrypted values are stored as a self-describing envelope:
+//
+//     v2$xchacha20poly1305$<salt>$<nonce>$<ciphertext>
+//
+// with each part base64 encoded. The salt is the vault's Argon2id salt, so it
+// identifies which master password (key) a value was written with. The row
+// and column holding the value (see `aad`) are authenticated with it, so a
+// value copied into another row or column fails to decrypt. v1 envelopes were
+// written without that binding and are still read.
+const ENVELOPE_VERSION: &str = "v2";
+const UNBOUND_VERSION: &str = "v1";
+const ALGORITHM: &str = "xchacha20poly1305";
+pub const KEY_LEN: usize = 32;
+pub const SALT_LEN: usize = 16;
 
+// Key used to XOR vault data before envelopes existed. Only kept so those
+// rows can be read once and re-encrypted.
+const LEGACY_KEY: &[u8] = b"yotion-secret-key-2024";
+
+// The cipher wipes its copy of the key when dropped
 pub struct EncryptionService {
-    key: String,
+    cipher: XChaCha20Poly1305,
+    salt: Vec<u8>,
 }
 
 impl EncryptionService {
-    pub fn new(key: String) -> Self {
-        Self { key }
-    }
-
-    pub fn encrypt(&self, data: &str) -> Result<String, AppError> {
-        // Simple XOR encryption for demo
-        let key_bytes = self.key.as_bytes();
-        let data_bytes = data.as_bytes();
-        let mut encrypted = Vec::new();
-        
-        for (i, &byte) in data_bytes.iter().enumerate() {
-            let key_byte = key_bytes[i % key_bytes.len()];
-            encrypted.push(byte ^ key_byte);
+    // Derives the vault key from the master password with Argon2id
+    pub fn derive(password: &str, salt: &[u8], kdf: KdfParams) -> Result<Self, AppError> {
+        let key = derive_key(password, salt, kdf)?;
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(key.as_ref().into()),
+            salt: salt.to_vec(),
+        })
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    // `aad` names where the value is stored; see `aad`
+    pub fn encrypt(&self, data: &str, aad: &str) -> Result<String, AppError> {
+        self.encrypt_bytes(data.as_bytes(), aad)
+    }
+
+    // Same envelope as `encrypt`, for binary data such as attachment chunks
+    pub fn encrypt_bytes(&self, data: &[u8], aad: &str) -> Result<String, AppError> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self.cipher
+            .encrypt(&nonce, Payload { msg: data, aad: aad.as_bytes() })
+            .map_err(|_| AppError::Encryption("Encryption failed".to_string()))?;
+
+        Ok([
+            ENVELOPE_VERSION,
+            ALGORITHM,
+            &general_purpose::STANDARD.encode(&self.salt),
+            &general_purpose::STANDARD.encode(nonce),
+            &general_purpose::STANDARD.encode(ciphertext),
+        ]
+        .join("$"))
+    }
+
+    // `aad` must match the one the value was encrypted with; v1 envelopes
+    // ignore it
+    pub fn decrypt(&self, envelope: &str, aad: &str) -> Result<String, AppError> {
+        String::from_utf8(self.decrypt_bytes(envelope, aad)?).map_err(|e| AppError::Encryption(e.to_string()))
+    }
+
+    pub fn decrypt_bytes(&self, envelope: &str, aad: &str) -> Result<Vec<u8>, AppError> {
+        let parts: Vec<&str> = envelope.split('$').collect();
+        let (aad, salt, nonce, ciphertext) = match parts.as_slice() {
+            [ENVELOPE_VERSION, ALGORITHM, salt, nonce, ciphertext] => (aad, salt, nonce, ciphertext),
+            [UNBOUND_VERSION, ALGORITHM, salt, nonce, ciphertext] => ("", salt, nonce, ciphertext),
+            _ => return Err(AppError::Encryption("Unsupported encrypted value".to_string())),
+        };
+        let (salt, nonce, ciphertext) = (decode(salt)?, decode(nonce)?, decode(ciphertext)?);
+        if salt != self.salt {
+            return Err(AppError::Encryption("Value was encrypted with a different master password".to_string()));
         }
-        
-        Ok(general_purpose::STANDARD.encode(encrypted))
-    }
-
-    pub fn decrypt(&self, encrypted_data: &str) -> Result<String, AppError> {
-        let encrypted_bytes = general_purpose::STANDARD.decode(encrypted_data)
-            .map_err(|e| AppError::Encryption(e.to_string()))?;
-        
-        let key_bytes = self.key.as_bytes();
-        let mut decrypted = Vec::new();
-        
-        for (i, &byte) in encrypted_bytes.iter().enumerate() {
-            let key_byte = key_bytes[i % key_bytes.len()];
-            decrypted.push(byte ^ key_byte);
+        if nonce.len() != XNonce::default().len() {
+            return Err(AppError::Encryption("Malformed nonce".to_string()));
         }
-        
-        String::from_utf8(decrypted)
-            .map_err(|e| AppError::Encryption(e.to_string()))
+
+        self.cipher
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: aad.as_bytes() })
+            .map_err(|_| AppError::Encryption("Decryption failed; the data may have been tampered with".to_string()))
     }
 }
 
-// Global encryption service instance
-lazy_static::lazy_static! {
-    pub static ref ENCRYPTION_SERVICE: EncryptionService = {
-        EncryptionService::new("yotion-secret-key-2024".to_string())
-    };
+// Associated data binding a value to the row and column it is stored in,
+// e.g. "personal_accounts.password:12"
+pub fn aad(table: &str, column: &str, id: i64) -> String {
+    format!("{}.{}:{}", table, column, id)
 }
 
-pub fn encrypt_sensitive_data(data: &str) -> Result<String, AppError> {
-    ENCRYPTION_SERVICE.encrypt(data)
+// Written before values were bound to their row; see `aad`
+pub fn is_unbound(value: &str) -> bool {
+    value.split('$').next() == Some(UNBOUND_VERSION)
 }
 
-pub fn decrypt_sensitive_data(encrypted_data: &str) -> Result<String, AppError> {
-    ENCRYPTION_SERVICE.decrypt(encrypted_data)
+// Argon2id hash of the master password, used directly as a key
+pub fn derive_key(password: &str, salt: &[u8], kdf: KdfParams) -> Result<Zeroizing<[u8; KEY_LEN]>, AppError> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN))
+        .map_err(|e| AppError::Encryption(e.to_string()))?;
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| AppError::Encryption(e.to_string()))?;
+    Ok(key)
 }
 
-// Type alias for encrypted data
-pub type EncryptedData = String;
\ No newline at end of file
+pub fn generate_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+// Values written before envelopes existed are bare base64, which never
+// contains a '$'
+pub fn is_legacy(value: &str) -> bool {
+    !value.contains('$')
+}
+
+pub fn decrypt_legacy(encrypted_data: &str) -> Result<String, AppError> {
+    let encrypted_bytes = decode(encrypted_data)?;
+    let decrypted: Vec<u8> = encrypted_bytes
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| byte ^ LEGACY_KEY[i % LEGACY_KEY.len()])
+        .collect();
+
+    String::from_utf8(decrypted)
+        .map_err(|e| AppError::Encryption(e.to_string()))
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+pub fn decode(data: &str) -> Result<Vec<u8>, AppError> {
+    general_purpose::STANDARD.decode(data)
+        .map_err(|e| AppError::Encryption(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Argon2's smallest settings; the real cost doesn't matter here
+    const CHEAP: KdfParams = KdfParams { memory_kib: 8, iterations: 1, parallelism: 1 };
+
+    fn key(password: &str, salt: &[u8]) -> EncryptionService {
+        EncryptionService::derive(password, salt, CHEAP).unwrap()
+    }
+
+    #[test]
+    fn envelope_round_trip() {
+        let salt = generate_salt();
+        let service = key("master", &salt);
+        let envelope = service.encrypt("hunter2", "a.b:1").unwrap();
+
+        let parts: Vec<&str> = envelope.split('$').collect();
+        assert_eq!(&parts[..2], &[ENVELOPE_VERSION, ALGORITHM]);
+        assert_eq!(decode(parts[2]).unwrap(), salt);
+        assert!(!is_legacy(&envelope));
+        assert_eq!(service.decrypt(&envelope, "a.b:1").unwrap(), "hunter2");
+
+        // A fresh nonce each time
+        assert_ne!(service.encrypt("hunter2", "a.b:1").unwrap(), envelope);
+
+        let bytes = [0u8, 159, 146, 150, 255];
+        assert_eq!(service.decrypt_bytes(&service.encrypt_bytes(&bytes, "a.b:1").unwrap(), "a.b:1").unwrap(), bytes);
+    }
+
+    #[test]
+    fn same_password_and_salt_give_the_same_key() {
+        let salt = generate_salt();
+        let envelope = key("master", &salt).encrypt("hunter2", "a.b:1").unwrap();
+        assert_eq!(key("master", &salt).decrypt(&envelope, "a.b:1").unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn refuses_other_keys_and_tampering() {
+        let salt = generate_salt();
+        let service = key("master", &salt);
+        let envelope = service.encrypt("hunter2", "a.b:1").unwrap();
+
+        let other_salt = key("master", &generate_salt()).decrypt(&envelope, "a.b:1").unwrap_err();
+        assert!(other_salt.to_string().contains("different master password"));
+        assert!(key("wrong", &salt).decrypt(&envelope, "a.b:1").is_err());
+
+        let mut parts: Vec<String> = envelope.split('$').map(str::to_string).collect();
+        let mut ciphertext = decode(&parts[4]).unwrap();
+        ciphertext[0] ^= 1;
+        parts[4] = encode(&ciphertext);
+        assert!(service.decrypt(&parts.join("$"), "a.b:1").is_err());
+
+        assert!(service.decrypt("v2$xchacha20poly1305$a$b$c", "a.b:1").is_err());
+        assert!(service.decrypt(&envelope.replacen("v2", "v9", 1), "a.b:1").is_err());
+    }
+
+    #[test]
+    fn values_only_decrypt_where_they_were_stored() {
+        let service = key("master", &generate_salt());
+        let envelope = service.encrypt("hunter2", &aad("personal_accounts", "password", 1)).unwrap();
+        assert_eq!(aad("personal_accounts", "password", 1), "personal_accounts.password:1");
+
+        for elsewhere in [aad("personal_accounts", "password", 2), aad("personal_accounts", "email", 1), String::new()] {
+            let err = service.decrypt(&envelope, &elsewhere).unwrap_err();
+            assert!(err.to_string().contains("tampered"), "{}", err);
+        }
+        // Relabelling it as unbound doesn't get around that
+        assert!(service.decrypt(&envelope.replacen("v2", "v1", 1), "").is_err());
+    }
+
+    #[test]
+    fn reads_unbound_values() {
+        let salt = generate_salt();
+        let service = key("master", &salt);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = service.cipher.encrypt(&nonce, b"hunter2".as_slice()).unwrap();
+        let envelope = [UNBOUND_VERSION, ALGORITHM, &encode(&salt), &encode(&nonce), &encode(&ciphertext)].join("$");
+
+        assert!(is_unbound(&envelope) && !is_legacy(&envelope));
+        assert!(!is_unbound(&service.encrypt("hunter2", "a.b:1").unwrap()));
+        assert_eq!(service.decrypt(&envelope, "a.b:1").unwrap(), "hunter2");
+        assert_eq!(service.decrypt(&envelope, "").unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn reads_legacy_values() {
+        let legacy: Vec<u8> = "hunter2".bytes().enumerate().map(|(i, b)| b ^ LEGACY_KEY[i % LEGACY_KEY.len()]).collect();
+        let legacy = encode(&legacy);
+        assert!(is_legacy(&legacy));
+        assert_eq!(decrypt_legacy(&legacy).unwrap(), "hunter2");
+        assert!(decrypt_legacy("not base64!").is_err());
+    }
+}
@@ -1,31 +1,179 @@
+use rusqlite::ffi;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
 use std::fmt;
 
 #[derive(Debug)]
 pub enum AppError {
     Database(String),
-    Validation(String),
+    // SQLite could not get a lock in time; retrying usually succeeds
+    Busy,
+    Validation { field: Option<String>, message: String },
     NotFound(String),
     Encryption(String),
     Io(String),
+    Migration(String),
+    // Vault data was requested without the master password having been given,
+    // or after the session timed out
+    VaultLocked,
+    // A unique column already holds this value, possibly on a trashed row
+    Duplicate { table: String, field: String, in_trash: bool },
+    // The row points at a parent that doesn't exist or is in the trash
+    MissingReference { field: Option<String>, message: String },
 }
 
-impl fmt::Display for AppError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl AppError {
+    pub fn validation(message: impl Into<String>) -> Self {
+        AppError::Validation { field: None, message: message.into() }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        AppError::Validation { field: Some(field.to_string()), message: message.into() }
+    }
+
+    // Names the request field a validation error came from, unless it
+    // already names one
+    pub fn with_field(self, name: &str) -> Self {
+        match self {
+            AppError::Validation { field: None, message } => AppError::invalid_field(name, message),
+            other => other,
+        }
+    }
+
+    // Broad category the frontend can branch on
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Database(_) | AppError::Busy => "database",
+            AppError::Validation { .. } => "validation",
+            AppError::NotFound(_) => "not_found",
+            AppError::Encryption(_) => "encryption",
+            AppError::Io(_) => "io",
+            AppError::Migration(_) => "migration",
+            AppError::VaultLocked => "vault",
+            AppError::Duplicate { .. } | AppError::MissingReference { .. } => "constraint",
+        }
+    }
+
+    // Stable identifier for the specific failure
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database_error",
+            AppError::Busy => "busy",
+            AppError::Validation { .. } => "invalid_value",
+            AppError::NotFound(_) => "not_found",
+            AppError::Encryption(_) => "encryption_failed",
+            AppError::Io(_) => "io_error",
+            AppError::Migration(_) => "migration_failed",
+            AppError::VaultLocked => "vault_locked",
+            AppError::Duplicate { .. } => "duplicate",
+            AppError::MissingReference { .. } => "missing_reference",
+        }
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            AppError::Validation { field, .. } | AppError::MissingReference { field, .. } => field.as_deref(),
+            AppError::Duplicate { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    // Human-readable text without the category prefix used by `Display`
+    pub fn message(&self) -> String {
         match self {
-            AppError::Database(msg) => write!(f, "Database error: {}", msg),
-            AppError::Validation(msg) => write!(f, "Validation error: {}", msg),
-            AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
-            AppError::Encryption(msg) => write!(f, "Encryption error: {}", msg),
-            AppError::Io(msg) => write!(f, "IO error: {}", msg),
+            AppError::Database(msg)
+            | AppError::NotFound(msg)
+            | AppError::Encryption(msg)
+            | AppError::Io(msg)
+            | AppError::Migration(msg) => msg.clone(),
+            AppError::Busy => "The database is busy, please try again".to_string(),
+            AppError::VaultLocked => "The vault is locked".to_string(),
+            AppError::Validation { message, .. } | AppError::MissingReference { message, .. } => message.clone(),
+            AppError::Duplicate { table, field, in_trash } => {
+                let item = table.trim_end_matches('s').replace('_', " ");
+                if *in_trash {
+                    format!("A {} with this {} is in the trash; restore or purge it first", item, field)
+                } else {
+                    format!("A {} with this {} already exists", item, field)
+                }
+            }
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            AppError::Duplicate { table, in_trash, .. } => Some(json!({ "table": table, "in_trash": in_trash })),
+            _ => None,
         }
     }
 }
 
+// Reports a failure that has no caller to return it to, such as one in a
+// background task or one that is deliberately skipped over
+pub fn log_error(context: &str, error: &AppError) {
+    eprintln!("{}: {}", context, error);
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = match self {
+            AppError::Database(_) | AppError::Busy => "Database error",
+            AppError::Validation { .. } => "Validation error",
+            AppError::NotFound(_) => "Not found",
+            AppError::Encryption(_) => "Encryption error",
+            AppError::Io(_) => "IO error",
+            AppError::Migration(_) => "Migration error",
+            AppError::VaultLocked => "Vault error",
+            AppError::Duplicate { .. } | AppError::MissingReference { .. } => "Constraint violation",
+        };
+        write!(f, "{}: {}", prefix, self.message())
+    }
+}
+
 impl std::error::Error for AppError {}
 
+// Sent to the frontend as `{ kind, code, field, message, details }`
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AppError", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("field", &self.field())?;
+        state.serialize_field("message", &self.message())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
 impl From<rusqlite::Error> for AppError {
     fn from(err: rusqlite::Error) -> Self {
-        AppError::Database(err.to_string())
+        if let rusqlite::Error::SqliteFailure(failure, Some(msg)) = &err {
+            match failure.extended_code {
+                ffi::SQLITE_CONSTRAINT_UNIQUE | ffi::SQLITE_CONSTRAINT_PRIMARYKEY => {
+                    // "UNIQUE constraint failed: languages.name"
+                    let column = msg.rsplit(": ").next().and_then(|cols| cols.split(", ").next());
+                    if let Some((table, field)) = column.and_then(|c| c.split_once('.')) {
+                        return AppError::Duplicate {
+                            table: table.to_string(),
+                            field: field.to_string(),
+                            in_trash: false,
+                        };
+                    }
+                }
+                ffi::SQLITE_CONSTRAINT_FOREIGNKEY => {
+                    return AppError::MissingReference {
+                        field: None,
+                        message: "A referenced item does not exist".to_string(),
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy) | Some(rusqlite::ErrorCode::DatabaseLocked) => AppError::Busy,
+            _ => AppError::Database(err.to_string()),
+        }
     }
 }
 
@@ -33,4 +181,93 @@ impl From<std::io::Error> for AppError {
     fn from(err: std::io::Error) -> Self {
         AppError::Io(err.to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn schema() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE languages (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+             CREATE TABLE vocabulary (id INTEGER PRIMARY KEY, language_id INTEGER NOT NULL REFERENCES languages(id));
+             INSERT INTO languages (id, name) VALUES (1, 'German');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn to_json(err: &AppError) -> Value {
+        serde_json::to_value(err).unwrap()
+    }
+
+    #[test]
+    fn unique_violations_name_the_column() {
+        let conn = schema();
+        let err: AppError = conn.execute("INSERT INTO languages (name) VALUES ('German')", []).unwrap_err().into();
+        assert!(matches!(&err, AppError::Duplicate { table, field, in_trash: false } if table == "languages" && field == "name"), "{:?}", err);
+        assert_eq!(err.message(), "A language with this name already exists");
+
+        let err: AppError = conn.execute("INSERT INTO languages (id, name) VALUES (1, 'French')", []).unwrap_err().into();
+        assert_eq!(err.field(), Some("id"));
+    }
+
+    #[test]
+    fn foreign_key_violations_are_missing_references() {
+        let conn = schema();
+        let err: AppError = conn.execute("INSERT INTO vocabulary (language_id) VALUES (99)", []).unwrap_err().into();
+        assert!(matches!(err, AppError::MissingReference { field: None, .. }), "{:?}", err);
+        assert_eq!(err.code(), "missing_reference");
+    }
+
+    #[test]
+    fn a_held_lock_is_busy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("busy.db");
+        let holder = Connection::open(&path).unwrap();
+        holder.execute_batch("CREATE TABLE t (x INTEGER); BEGIN EXCLUSIVE;").unwrap();
+
+        let other = Connection::open(&path).unwrap();
+        other.busy_timeout(std::time::Duration::ZERO).unwrap();
+        let err: AppError = other.execute("INSERT INTO t VALUES (1)", []).unwrap_err().into();
+        assert!(matches!(err, AppError::Busy), "{:?}", err);
+        assert_eq!((err.kind(), err.code()), ("database", "busy"));
+
+        let err: AppError = rusqlite::Error::QueryReturnedNoRows.into();
+        assert!(matches!(err, AppError::Database(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn serializes_for_the_frontend() {
+        let err = AppError::Duplicate { table: "languages".to_string(), field: "name".to_string(), in_trash: true };
+        assert_eq!(
+            to_json(&err),
+            json!({
+                "kind": "constraint",
+                "code": "duplicate",
+                "field": "name",
+                "message": "A language with this name is in the trash; restore or purge it first",
+                "details": { "table": "languages", "in_trash": true },
+            })
+        );
+        assert_eq!(
+            to_json(&AppError::invalid_field("title", "Title is required")),
+            json!({ "kind": "validation", "code": "invalid_value", "field": "title", "message": "Title is required", "details": null })
+        );
+        assert_eq!(
+            to_json(&AppError::VaultLocked),
+            json!({ "kind": "vault", "code": "vault_locked", "field": null, "message": "The vault is locked", "details": null })
+        );
+        assert_eq!(AppError::NotFound("Language 3 not found".to_string()).to_string(), "Not found: Language 3 not found");
+    }
+
+    #[test]
+    fn with_field_only_fills_in_a_missing_field() {
+        assert_eq!(AppError::validation("Too long").with_field("title").field(), Some("title"));
+        assert_eq!(AppError::invalid_field("name", "Too long").with_field("title").field(), Some("name"));
+        assert!(matches!(AppError::Busy.with_field("title"), AppError::Busy));
+    }
+}
@@ -0,0 +1,434 @@
+use crate::errors::AppError;
+use crate::models::{AccountImportFormat, CreatePersonalAccountRequest};
+use serde_json::Value;
+use std::collections::HashMap;
+
+// One entry of an export file, either mapped to an account or with the
+// reason it can't be imported
+#[derive(Debug)]
+pub struct ImportRow {
+    pub row: usize,
+    pub title: Option<String>,
+    pub account: Result<CreatePersonalAccountRequest, String>,
+}
+
+// Fields of a login as the exporting password manager had them
+#[derive(Default)]
+struct Login {
+    title: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+    url: Option<String>,
+    notes: Option<String>,
+    totp: Option<String>,
+}
+
+// Header names (lowercase) each CSV format uses for a field; the first one
+// present in the file wins
+struct CsvColumns {
+    title: &'static [&'static str],
+    username: &'static [&'static str],
+    password: &'static [&'static str],
+    url: &'static [&'static str],
+    notes: &'static [&'static str],
+    totp: &'static [&'static str],
+    // Column that must hold "login" for the row to be one
+    kind: Option<&'static str>,
+}
+
+const BITWARDEN_CSV: CsvColumns = CsvColumns {
+    title: &["name"],
+    username: &["login_username"],
+    password: &["login_password"],
+    url: &["login_uri"],
+    notes: &["notes"],
+    totp: &["login_totp"],
+    kind: Some("type"),
+};
+
+// KeePassXC and KeePass 2 name their columns differently
+const KEEPASS_CSV: CsvColumns = CsvColumns {
+    title: &["title", "account"],
+    username: &["username", "user name", "login name"],
+    password: &["password"],
+    url: &["url", "web site"],
+    notes: &["notes", "comments"],
+    totp: &["totp"],
+    kind: None,
+};
+
+const ONE_PASSWORD_CSV: CsvColumns = CsvColumns {
+    title: &["title"],
+    username: &["username"],
+    password: &["password"],
+    url: &["url", "website", "urls"],
+    notes: &["notes", "notesplain"],
+    totp: &["otpauth", "one-time password"],
+    kind: None,
+};
+
+const CHROMIUM_CSV: CsvColumns = CsvColumns {
+    title: &["name"],
+    username: &["username"],
+    password: &["password"],
+    url: &["url"],
+    notes: &["note", "notes"],
+    totp: &[],
+    kind: None,
+};
+
+const FIREFOX_CSV: CsvColumns = CsvColumns {
+    title: &[],
+    username: &["username"],
+    password: &["password"],
+    url: &["url"],
+    notes: &[],
+    totp: &[],
+    kind: None,
+};
+
+// Maps every entry of an export into an account request. Fails only if the
+// file as a whole can't be read as the given format.
+pub fn parse(format: AccountImportFormat, data: &str, category: &str) -> Result<Vec<ImportRow>, AppError> {
+    // Excel and some exporters start the file with a byte order mark
+    let data = data.trim_start_matches('\u{feff}');
+    let logins = match format {
+        AccountImportFormat::BitwardenJson => parse_bitwarden_json(data)?,
+        AccountImportFormat::BitwardenCsv => parse_csv(data, &BITWARDEN_CSV)?,
+        AccountImportFormat::KeepassXml => parse_keepass_xml(data)?,
+        AccountImportFormat::KeepassCsv => parse_csv(data, &KEEPASS_CSV)?,
+        AccountImportFormat::OnePasswordCsv => parse_csv(data, &ONE_PASSWORD_CSV)?,
+        AccountImportFormat::ChromiumCsv => parse_csv(data, &CHROMIUM_CSV)?,
+        AccountImportFormat::FirefoxCsv => parse_csv(data, &FIREFOX_CSV)?,
+    };
+
+    Ok(logins
+        .into_iter()
+        .map(|(row, login)| match login {
+            Ok(login) => ImportRow {
+                row,
+                title: login.title.clone(),
+                account: to_request(login, category),
+            },
+            Err(reason) => ImportRow { row, title: None, account: Err(reason) },
+        })
+        .collect())
+}
+
+fn to_request(login: Login, category: &str) -> Result<CreatePersonalAccountRequest, String> {
+    let password = login.password.ok_or("No password")?;
+    let username = login.username.ok_or("No username")?;
+    // Browsers don't keep titles, so fall back to the site
+    let title = login
+        .title
+        .or_else(|| login.url.as_deref().map(site).filter(|site| !site.is_empty()))
+        .unwrap_or_else(|| username.clone());
+
+    Ok(CreatePersonalAccountRequest {
+        title,
+        email: username,
+        password,
+        website: login.url,
+        notes: login.notes,
+        category: category.to_string(),
+        totp_secret: login.totp,
+        custom_fields: Vec::new(),
+    })
+}
+
+// Host part of a URL without "www.", used to match logins for the same site
+pub fn site(url: &str) -> String {
+    let url = url.trim().to_lowercase();
+    let rest = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    host.trim_start_matches("www.").to_string()
+}
+
+// Empty strings in exports mean "not set"
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+type ParsedLogins = Vec<(usize, Result<Login, String>)>;
+
+fn parse_csv(data: &str, columns: &CsvColumns) -> Result<ParsedLogins, AppError> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| AppError::validation(format!("Not a valid CSV file: {}", e)))?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let position = |names: &[&str]| names.iter().find_map(|name| headers.iter().position(|h| h == name));
+
+    let password = position(columns.password)
+        .ok_or_else(|| AppError::validation("The file has no password column; check the export format"))?;
+    let username = position(columns.username);
+    let title = position(columns.title);
+    let url = position(columns.url);
+    let notes = position(columns.notes);
+    let totp = position(columns.totp);
+    let kind = columns.kind.and_then(|kind| position(&[kind]));
+
+    let mut logins = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::validation(format!("Not a valid CSV file: {}", e)))?;
+        let row = record.position().map_or(0, |p| p.line() as usize);
+        let field = |index: Option<usize>| non_empty(index.and_then(|i| record.get(i)));
+
+        if let Some(kind) = field(kind) {
+            if kind != "login" {
+                logins.push((row, Err(format!("Not a login ({})", kind))));
+                continue;
+            }
+        }
+        logins.push((
+            row,
+            Ok(Login {
+                title: field(title),
+                username: field(username),
+                password: field(Some(password)),
+                url: field(url),
+                notes: field(notes),
+                totp: field(totp),
+            }),
+        ));
+    }
+    Ok(logins)
+}
+
+fn parse_bitwarden_json(data: &str) -> Result<ParsedLogins, AppError> {
+    let export: Value = serde_json::from_str(data)
+        .map_err(|e| AppError::validation(format!("Not a valid Bitwarden export: {}", e)))?;
+    if export["encrypted"].as_bool() == Some(true) {
+        return Err(AppError::validation(
+            "Encrypted Bitwarden exports can't be read; export as unencrypted JSON instead",
+        ));
+    }
+    let items = export["items"]
+        .as_array()
+        .ok_or_else(|| AppError::validation("Not a valid Bitwarden export: no items"))?;
+
+    Ok(items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let row = index + 1;
+            // Type 1 is a login; the others are notes, cards and identities
+            if item["type"].as_i64() != Some(1) {
+                return (row, Err("Not a login".to_string()));
+            }
+            let login = &item["login"];
+            let url = login["uris"]
+                .as_array()
+                .and_then(|uris| uris.iter().find_map(|uri| non_empty(uri["uri"].as_str())));
+            (
+                row,
+                Ok(Login {
+                    title: non_empty(item["name"].as_str()),
+                    username: non_empty(login["username"].as_str()),
+                    password: non_empty(login["password"].as_str()),
+                    url,
+                    notes: non_empty(item["notes"].as_str()),
+                    totp: non_empty(login["totp"].as_str()),
+                }),
+            )
+        })
+        .collect())
+}
+
+fn parse_keepass_xml(data: &str) -> Result<ParsedLogins, AppError> {
+    let document = roxmltree::Document::parse(data)
+        .map_err(|e| AppError::validation(format!("Not a valid KeePass XML export: {}", e)))?;
+    let root = document.root_element();
+    if !root.has_tag_name("KeePassFile") {
+        return Err(AppError::validation("Not a valid KeePass XML export: no KeePassFile element"));
+    }
+
+    // Deleted entries sit in the recycle bin group until it is emptied
+    let recycle_bin = root
+        .descendants()
+        .find(|node| node.has_tag_name("RecycleBinUUID"))
+        .and_then(|node| non_empty(node.text()));
+    let in_recycle_bin = |entry: roxmltree::Node| {
+        entry.ancestors().filter(|node| node.has_tag_name("Group")).any(|group| {
+            let uuid = group.children().find(|child| child.has_tag_name("UUID")).and_then(|uuid| non_empty(uuid.text()));
+            uuid.is_some() && uuid == recycle_bin
+        })
+    };
+
+    let mut logins = Vec::new();
+    let entries = root
+        .descendants()
+        .filter(|node| node.has_tag_name("Entry"))
+        // Old versions of an entry are kept inside it
+        .filter(|entry| !entry.ancestors().any(|node| node.has_tag_name("History")));
+    for (index, entry) in entries.enumerate() {
+        let row = index + 1;
+        if in_recycle_bin(entry) {
+            logins.push((row, Err("In the recycle bin".to_string())));
+            continue;
+        }
+
+        let strings: HashMap<String, String> = entry
+            .children()
+            .filter(|child| child.has_tag_name("String"))
+            .filter_map(|string| {
+                let text = |name: &str| string.children().find(|child| child.has_tag_name(name)).and_then(|n| n.text());
+                Some((text("Key")?.to_string(), text("Value").unwrap_or("").to_string()))
+            })
+            .collect();
+        let get = |key: &str| non_empty(strings.get(key).map(String::as_str));
+        logins.push((
+            row,
+            Ok(Login {
+                title: get("Title"),
+                username: get("UserName"),
+                password: get("Password"),
+                url: get("URL"),
+                notes: get("Notes"),
+                // KeePassXC keeps the otpauth URI in an "otp" attribute
+                totp: get("otp"),
+            }),
+        ));
+    }
+    Ok(logins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accounts(format: AccountImportFormat, data: &str) -> Vec<ImportRow> {
+        parse(format, data, "imported").unwrap()
+    }
+
+    fn ok(row: &ImportRow) -> &CreatePersonalAccountRequest {
+        row.account.as_ref().unwrap()
+    }
+
+    #[test]
+    fn reads_bitwarden_json() {
+        let data = r#"{"encrypted": false, "items": [
+            {"type": 1, "name": "Mail", "notes": "", "login": {"username": "me@example.com", "password": "pw1",
+             "totp": "JBSWY3DPEHPK3PXP", "uris": [{"uri": ""}, {"uri": "https://mail.example.com/login"}]}},
+            {"type": 2, "name": "A note"},
+            {"type": 1, "name": "No password", "login": {"username": "me", "password": null}}
+        ]}"#;
+        let rows = accounts(AccountImportFormat::BitwardenJson, data);
+        assert_eq!(rows.len(), 3);
+
+        let mail = ok(&rows[0]);
+        assert_eq!((mail.title.as_str(), mail.email.as_str(), mail.password.as_str()), ("Mail", "me@example.com", "pw1"));
+        assert_eq!(mail.website.as_deref(), Some("https://mail.example.com/login"));
+        assert_eq!(mail.notes, None);
+        assert_eq!(mail.totp_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        assert_eq!(mail.category, "imported");
+        assert_eq!(rows[1].account.as_ref().unwrap_err(), "Not a login");
+        assert_eq!((rows[2].row, rows[2].account.as_ref().unwrap_err().as_str()), (3, "No password"));
+
+        let err = parse(AccountImportFormat::BitwardenJson, r#"{"encrypted": true, "items": []}"#, "x").unwrap_err();
+        assert!(err.message().contains("Encrypted"));
+        assert!(parse(AccountImportFormat::BitwardenJson, "[]", "x").is_err());
+    }
+
+    #[test]
+    fn reads_bitwarden_csv() {
+        let data = "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n\
+                    ,,login,Mail,\"multi\nline\",,0,https://mail.example.com,me@example.com,pw1,\n\
+                    ,,note,Secret note,text,,0,,,,\n";
+        let rows = accounts(AccountImportFormat::BitwardenCsv, data);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(ok(&rows[0]).notes.as_deref(), Some("multi\nline"));
+        assert_eq!(ok(&rows[0]).totp_secret, None);
+        assert_eq!(rows[0].row, 2);
+        // Line numbers count the line break inside the quoted notes
+        assert_eq!(rows[1].row, 4);
+        assert_eq!(rows[1].account.as_ref().unwrap_err(), "Not a login (note)");
+    }
+
+    #[test]
+    fn keepass_xml_skips_the_recycle_bin_and_history() {
+        let data = r#"<?xml version="1.0" encoding="utf-8"?>
+            <KeePassFile>
+              <Meta><RecycleBinUUID>YmluYmluYmluYmluYmluYg==</RecycleBinUUID></Meta>
+              <Root><Group><UUID>cm9vdHJvb3Ryb290cm9vdA==</UUID>
+                <Entry>
+                  <String><Key>Title</Key><Value>Mail</Value></String>
+                  <String><Key>UserName</Key><Value>me@example.com</Value></String>
+                  <String><Key>Password</Key><Value ProtectValue="True">pw-new</Value></String>
+                  <String><Key>URL</Key><Value>https://mail.example.com</Value></String>
+                  <String><Key>otp</Key><Value>otpauth://totp/Mail?secret=JBSWY3DPEHPK3PXP</Value></String>
+                  <History><Entry>
+                    <String><Key>Title</Key><Value>Mail</Value></String>
+                    <String><Key>Password</Key><Value>pw-old</Value></String>
+                  </Entry></History>
+                </Entry>
+                <Group><UUID>YmluYmluYmluYmluYmluYg==</UUID><Name>Recycle Bin</Name>
+                  <Entry>
+                    <String><Key>Title</Key><Value>Deleted</Value></String>
+                    <String><Key>Password</Key><Value>pw</Value></String>
+                  </Entry>
+                </Group>
+              </Group></Root>
+            </KeePassFile>"#;
+        let rows = accounts(AccountImportFormat::KeepassXml, data);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(ok(&rows[0]).password, "pw-new");
+        assert_eq!(ok(&rows[0]).totp_secret.as_deref(), Some("otpauth://totp/Mail?secret=JBSWY3DPEHPK3PXP"));
+        assert_eq!(rows[1].account.as_ref().unwrap_err(), "In the recycle bin");
+
+        let err = parse(AccountImportFormat::KeepassXml, "<Database/>", "x").unwrap_err();
+        assert!(err.message().contains("KeePassFile"));
+    }
+
+    #[test]
+    fn reads_keepass_and_one_password_csv() {
+        let rows = accounts(AccountImportFormat::KeepassCsv, "\"Account\",\"Login Name\",\"Password\",\"Web Site\",\"Comments\"\n\"Mail\",\"me\",\"pw\",\"https://mail.example.com\",\"\"\n");
+        assert_eq!((ok(&rows[0]).title.as_str(), ok(&rows[0]).email.as_str()), ("Mail", "me"));
+
+        let rows = accounts(
+            AccountImportFormat::OnePasswordCsv,
+            "Title,Url,Username,Password,OTPAuth,Favorite,Archived,Tags,Notes\nMail,https://mail.example.com,me,pw,otpauth://totp/x?secret=JBSWY3DPEHPK3PXP,false,false,,hi\n",
+        );
+        let mail = ok(&rows[0]);
+        assert_eq!(mail.notes.as_deref(), Some("hi"));
+        assert!(mail.totp_secret.as_deref().unwrap().starts_with("otpauth://"));
+    }
+
+    #[test]
+    fn browsers_fall_back_to_the_site_for_a_title() {
+        let rows = accounts(AccountImportFormat::ChromiumCsv, "name,url,username,password,note\n,https://www.Example.com/login,me,pw,\nBank,https://bank.example.com,me,pw,memo\n");
+        assert_eq!(ok(&rows[0]).title, "example.com");
+        assert_eq!(ok(&rows[1]).title, "Bank");
+        assert_eq!(ok(&rows[1]).notes.as_deref(), Some("memo"));
+
+        let rows = accounts(
+            AccountImportFormat::FirefoxCsv,
+            "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n\
+             \"https://user@shop.example.com:443/cart\",\"me\",\"pw\",,\"\",\"{1}\",\"1\",\"1\",\"1\"\n\
+             \"\",\"me\",\"pw\",,\"\",\"{2}\",\"1\",\"1\",\"1\"\n",
+        );
+        assert_eq!(ok(&rows[0]).title, "shop.example.com:443");
+        assert_eq!(ok(&rows[1]).title, "me");
+    }
+
+    #[test]
+    fn ignores_a_byte_order_mark_and_needs_a_password_column() {
+        let rows = accounts(AccountImportFormat::ChromiumCsv, "\u{feff}name,url,username,password\nMail,,me,pw\n");
+        assert_eq!(ok(&rows[0]).title, "Mail");
+
+        let err = parse(AccountImportFormat::ChromiumCsv, "name,url,username\nMail,,me\n", "x").unwrap_err();
+        assert!(err.message().contains("no password column"));
+        let rows = accounts(AccountImportFormat::ChromiumCsv, "name,url,username,password\nMail,,,pw\n");
+        assert_eq!(rows[0].account.as_ref().unwrap_err(), "No username");
+    }
+
+    #[test]
+    fn sites_ignore_scheme_user_path_and_www() {
+        assert_eq!(site("https://www.Example.com/login?next=/"), "example.com");
+        assert_eq!(site("user:pw@mail.example.com#top"), "mail.example.com");
+        assert_eq!(site("  "), "");
+    }
+}
@@ -1,18 +1,71 @@
+// Storage and business rules, usable without Tauri: open a `Database` with
+// `Database::open` or `Database::open_in_memory` and call its methods.
 pub mod database;
-mod commands;
-mod models;
-mod errors;
+pub mod models;
+pub mod errors;
 mod validation;
 mod encryption;
+pub mod passwords;
+pub mod totp;
+mod importers;
+pub mod bundle;
+pub mod scheduler;
+#[cfg(feature = "app")]
+mod commands;
+
+pub use database::Database;
+pub use errors::AppError;
 
-use database::Database;
+#[cfg(feature = "app")]
 use commands::*;
+#[cfg(feature = "app")]
+use errors::log_error;
+#[cfg(feature = "app")]
+use std::time::Duration;
+#[cfg(feature = "app")]
+use tauri::{Emitter, Manager};
 
+// How often the background thread runs scheduled backups and trash purging
+#[cfg(feature = "app")]
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// How often an idle vault is checked for auto-lock
+#[cfg(feature = "app")]
+const VAULT_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[cfg(feature = "app")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(Database::new().expect("Failed to initialize database"))
+        .manage(Database::new().unwrap_or_else(|e| {
+            log_error("Failed to initialize database", &e);
+            Database::without_workspace(&e)
+        }))
+        .setup(|app| {
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                if let Err(e) = handle.state::<Database>().run_scheduled_backup() {
+                    log_error("Scheduled backup failed", &e);
+                }
+                if let Err(e) = handle.state::<Database>().purge_expired_trash() {
+                    log_error("Trash purge failed", &e);
+                }
+                std::thread::sleep(MAINTENANCE_INTERVAL);
+            });
+
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(VAULT_LOCK_CHECK_INTERVAL);
+                match handle.state::<Database>().lock_idle_vault() {
+                    Ok(true) => {
+                        let _ = handle.emit("vault-locked", ());
+                    }
+                    Ok(false) => {}
+                    Err(e) => log_error("Vault auto-lock failed", &e),
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             create_language,
@@ -21,28 +74,97 @@ pub fn run() {
             create_vocabulary,
             get_vocabulary_by_language,
             search_vocabulary,
+            delete_vocabulary,
             get_due_flashcards,
             review_flashcard,
+            get_flashcard_history,
+            undo_last_review,
+            get_scheduler_settings,
+            update_scheduler_settings,
+            fit_fsrs_parameters,
             create_tech_space,
             get_tech_spaces,
+            delete_tech_space,
             create_code_snippet,
+            update_code_snippet,
             get_code_snippets_by_tech_space,
             search_code_snippets,
+            delete_code_snippet,
             create_project,
+            update_project,
             get_projects,
+            delete_project,
             create_task,
+            update_task,
             get_tasks_by_project,
             update_task_status,
+            delete_task,
             create_event,
             get_events_by_date,
+            delete_event,
             create_note,
+            update_note,
             get_notes_by_date,
+            delete_note,
             create_personal_account,
             get_personal_accounts,
             get_personal_accounts_by_category,
+            update_personal_account,
+            get_password_history,
+            restore_password,
+            reveal_secret,
+            list_secret_access,
+            add_info_attachment,
+            list_info_attachments,
+            export_info_attachment,
+            delete_info_attachment,
+            delete_personal_account,
+            get_totp_code,
+            import_accounts,
+            export_vault_bundle,
+            import_vault_bundle,
+            generate_bundle_key_pair,
+            audit_vault,
+            generate_password,
+            estimate_password_strength,
             create_personal_info,
             get_personal_info,
-            get_personal_info_by_category
+            get_personal_info_by_category,
+            update_personal_info,
+            delete_personal_info,
+            get_vault_status,
+            setup_vault,
+            unlock_vault,
+            lock_vault,
+            change_master_password,
+            rotate_vault_key,
+            encrypt_database,
+            decrypt_database,
+            get_vault_settings,
+            update_vault_settings,
+            create_workspace,
+            list_workspaces,
+            get_current_workspace,
+            get_startup_error,
+            open_workspace,
+            close_workspace,
+            rename_workspace,
+            get_backup_settings,
+            update_backup_settings,
+            create_backup,
+            list_backups,
+            restore_backup,
+            export_workspace,
+            import_workspace,
+            list_trash,
+            restore_trash_item,
+            purge_trash_item,
+            empty_trash,
+            get_trash_settings,
+            update_trash_settings,
+            list_revisions,
+            diff_revisions,
+            restore_revision
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,14 @@
+-- Soft delete: rows with a deleted_at timestamp are in the trash. Rows
+-- trashed together (a language and its vocabulary, say) share one timestamp
+-- so they can be restored together.
+ALTER TABLE languages ADD COLUMN deleted_at TEXT;
+ALTER TABLE vocabulary ADD COLUMN deleted_at TEXT;
+ALTER TABLE flashcards ADD COLUMN deleted_at TEXT;
+ALTER TABLE tech_spaces ADD COLUMN deleted_at TEXT;
+ALTER TABLE code_snippets ADD COLUMN deleted_at TEXT;
+ALTER TABLE projects ADD COLUMN deleted_at TEXT;
+ALTER TABLE tasks ADD COLUMN deleted_at TEXT;
+ALTER TABLE events ADD COLUMN deleted_at TEXT;
+ALTER TABLE notes ADD COLUMN deleted_at TEXT;
+ALTER TABLE personal_accounts ADD COLUMN deleted_at TEXT;
+ALTER TABLE personal_info ADD COLUMN deleted_at TEXT;
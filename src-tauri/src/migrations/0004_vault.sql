@@ -0,0 +1,15 @@
+-- Master password settings for the personal vault (at most one row). The key
+-- itself is never stored: it is re-derived from the master password with
+-- Argon2id, and `verifier` is a known value encrypted with it so a wrong
+-- password can be rejected. Existing XOR-obfuscated values are re-encrypted
+-- the first time the vault is set up or unlocked.
+CREATE TABLE IF NOT EXISTS vault (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    kdf_memory_kib INTEGER NOT NULL,
+    kdf_iterations INTEGER NOT NULL,
+    kdf_parallelism INTEGER NOT NULL,
+    salt TEXT NOT NULL,
+    verifier TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Strength score (0-4) of each account's password, computed when it is saved
+-- so the vault can flag weak passwords without decrypting them. Existing rows
+-- are scored the next time the vault is unlocked.
+ALTER TABLE personal_accounts ADD COLUMN password_strength INTEGER;
@@ -0,0 +1,3 @@
+-- Encrypted otpauth:// URI with the account's 2FA seed, so codes can be
+-- generated from the vault instead of keeping seeds in `notes`
+ALTER TABLE personal_accounts ADD COLUMN totp_secret TEXT;
@@ -0,0 +1,11 @@
+-- Passwords an account had before its current one, encrypted like the
+-- current one, so a change can be looked up or undone
+CREATE TABLE IF NOT EXISTS password_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER NOT NULL,
+    password TEXT NOT NULL,
+    replaced_at TEXT NOT NULL,
+    FOREIGN KEY (account_id) REFERENCES personal_accounts(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_account ON password_history(account_id);
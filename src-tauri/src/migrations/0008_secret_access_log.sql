@@ -0,0 +1,23 @@
+-- Every time a vault secret is decrypted for display. Rows can only be
+-- added: the triggers reject any change to or removal of an entry.
+CREATE TABLE IF NOT EXISTS secret_access_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    field TEXT NOT NULL,
+    item_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    accessed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_secret_access_log_accessed ON secret_access_log(accessed_at);
+
+CREATE TRIGGER IF NOT EXISTS secret_access_log_no_update
+BEFORE UPDATE ON secret_access_log
+BEGIN
+    SELECT RAISE(ABORT, 'The secret access log is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS secret_access_log_no_delete
+BEFORE DELETE ON secret_access_log
+BEGIN
+    SELECT RAISE(ABORT, 'The secret access log is append-only');
+END;
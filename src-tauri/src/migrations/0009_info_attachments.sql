@@ -0,0 +1,24 @@
+-- Files kept with personal info entries. The name, the checksum of the
+-- contents and every chunk of the contents are encrypted with the vault key;
+-- only the size is stored in the clear, to enforce the limits.
+CREATE TABLE IF NOT EXISTS info_attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    info_id INTEGER NOT NULL,
+    file_name TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    checksum TEXT NOT NULL,
+    chunk_count INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (info_id) REFERENCES personal_info(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_info_attachments_info ON info_attachments(info_id);
+
+CREATE TABLE IF NOT EXISTS info_attachment_chunks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    attachment_id INTEGER NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    UNIQUE (attachment_id, chunk_index),
+    FOREIGN KEY (attachment_id) REFERENCES info_attachments(id) ON DELETE CASCADE
+);
@@ -0,0 +1,19 @@
+-- User-defined fields of vault entries, each belonging to an account or to a
+-- personal info entry. Hidden fields and card numbers are encrypted one by
+-- one; `hint` keeps the last four digits of a card number for lists.
+CREATE TABLE IF NOT EXISTS custom_fields (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_id INTEGER,
+    info_id INTEGER,
+    position INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    field_type TEXT NOT NULL,
+    value TEXT NOT NULL,
+    hint TEXT,
+    CHECK ((account_id IS NULL) <> (info_id IS NULL)),
+    FOREIGN KEY (account_id) REFERENCES personal_accounts(id) ON DELETE CASCADE,
+    FOREIGN KEY (info_id) REFERENCES personal_info(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_custom_fields_account ON custom_fields(account_id);
+CREATE INDEX IF NOT EXISTS idx_custom_fields_info ON custom_fields(info_id);
@@ -0,0 +1,29 @@
+-- Spaced repetition scheduler of each language's flashcards; languages
+-- without a row use SM-2. `fsrs_weights` is a JSON array of FSRS parameters
+-- fitted from the review log, NULL for the defaults.
+CREATE TABLE IF NOT EXISTS scheduler_settings (
+    language_id INTEGER PRIMARY KEY,
+    scheduler TEXT NOT NULL DEFAULT 'sm2',
+    desired_retention REAL NOT NULL DEFAULT 0.9,
+    fsrs_weights TEXT,
+    fitted_at TEXT,
+    FOREIGN KEY (language_id) REFERENCES languages(id) ON DELETE CASCADE
+);
+
+-- FSRS memory state, NULL until the card is reviewed under FSRS
+ALTER TABLE flashcards ADD COLUMN stability REAL;
+ALTER TABLE flashcards ADD COLUMN difficulty REAL;
+
+-- Every flashcard review, which FSRS parameters are fitted to.
+-- `elapsed_days` is the time since the card's previous review, NULL for its
+-- first one.
+CREATE TABLE IF NOT EXISTS review_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    flashcard_id INTEGER NOT NULL,
+    reviewed_at TEXT NOT NULL,
+    quality INTEGER NOT NULL,
+    elapsed_days REAL,
+    FOREIGN KEY (flashcard_id) REFERENCES flashcards(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_review_log_flashcard ON review_log(flashcard_id, reviewed_at);
@@ -0,0 +1,22 @@
+-- What each logged review changed, so reviews can be analysed and the last
+-- one undone. `duration_ms` is how long the answer took, NULL if the client
+-- did not time it. The `prev_` columns hold the card's state before the
+-- review; they are NULL for reviews logged before this migration, which
+-- cannot be undone.
+ALTER TABLE review_log ADD COLUMN duration_ms INTEGER;
+ALTER TABLE review_log ADD COLUMN prev_ease_factor REAL;
+ALTER TABLE review_log ADD COLUMN prev_interval_days INTEGER;
+ALTER TABLE review_log ADD COLUMN prev_repetitions INTEGER;
+ALTER TABLE review_log ADD COLUMN prev_stability REAL;
+ALTER TABLE review_log ADD COLUMN prev_difficulty REAL;
+ALTER TABLE review_log ADD COLUMN prev_next_review TEXT;
+ALTER TABLE review_log ADD COLUMN prev_last_reviewed TEXT;
+ALTER TABLE review_log ADD COLUMN new_ease_factor REAL;
+ALTER TABLE review_log ADD COLUMN new_interval_days INTEGER;
+
+-- Earlier rows still know the state the review left behind when it was the
+-- card's latest
+UPDATE review_log SET
+    new_ease_factor = (SELECT f.ease_factor FROM flashcards f WHERE f.id = review_log.flashcard_id),
+    new_interval_days = (SELECT f.interval_days FROM flashcards f WHERE f.id = review_log.flashcard_id)
+WHERE id IN (SELECT MAX(id) FROM review_log GROUP BY flashcard_id);
@@ -0,0 +1,5 @@
+-- Set once the values still XOR-obfuscated with the old built-in key, or
+-- encrypted without being bound to their row, have been re-encrypted, so
+-- later unlocks skip the scan of every encrypted column. Cleared when an
+-- import may have brought in more.
+ALTER TABLE vault ADD COLUMN legacy_upgraded INTEGER NOT NULL DEFAULT 0;
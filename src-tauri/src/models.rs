@@ -50,13 +50,98 @@ pub struct Flashcard {
     pub repetitions: i64,
     pub next_review: String,
     pub last_reviewed: Option<String>,
+    // FSRS memory state; absent until the card is reviewed under FSRS
+    #[serde(default)]
+    pub stability: Option<f64>,
+    #[serde(default)]
+    pub difficulty: Option<f64>,
     pub created_at: String,
 }
 
+// `quality` runs from 1 to 5; below 3 counts as forgotten. `duration_ms` is
+// how long the answer took, if the client timed it.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FlashcardReviewRequest {
     pub flashcard_id: i64,
     pub quality: i64,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+}
+
+// One logged review. `elapsed_days` is the time since the card's previous
+// review, absent for its first; the `prev_` values are absent for reviews
+// logged before they were recorded, and the `new_` ones for some of those.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewLogEntry {
+    pub id: i64,
+    pub flashcard_id: i64,
+    pub reviewed_at: String,
+    pub quality: i64,
+    pub duration_ms: Option<i64>,
+    pub elapsed_days: Option<f64>,
+    pub prev_ease_factor: Option<f64>,
+    pub new_ease_factor: Option<f64>,
+    pub prev_interval_days: Option<i64>,
+    pub new_interval_days: Option<i64>,
+}
+
+// A review log row in a workspace archive, with the card state undo needs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedReview {
+    pub flashcard_id: i64,
+    pub reviewed_at: String,
+    pub quality: i64,
+    pub elapsed_days: Option<f64>,
+    pub duration_ms: Option<i64>,
+    pub prev_ease_factor: Option<f64>,
+    pub prev_interval_days: Option<i64>,
+    pub prev_repetitions: Option<i64>,
+    pub prev_stability: Option<f64>,
+    pub prev_difficulty: Option<f64>,
+    pub prev_next_review: Option<String>,
+    pub prev_last_reviewed: Option<String>,
+    pub new_ease_factor: Option<f64>,
+    pub new_interval_days: Option<i64>,
+}
+
+// Scheduler models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulerKind {
+    Sm2,
+    Fsrs,
+}
+
+// How a language's flashcards are scheduled. `desired_retention` is the
+// chance of still knowing a card when it comes due, used by FSRS only;
+// `fsrs_weights` are absent until fitted from the review history.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SchedulerSettings {
+    pub language_id: i64,
+    pub scheduler: SchedulerKind,
+    pub desired_retention: f64,
+    pub fsrs_weights: Option<Vec<f64>>,
+    pub fitted_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSchedulerSettingsRequest {
+    pub scheduler: SchedulerKind,
+    pub desired_retention: f64,
+    // Go back to the default FSRS parameters
+    #[serde(default)]
+    pub reset_weights: bool,
+}
+
+// Log loss is how badly the parameters predicted whether each reviewed card
+// was remembered; the fitted weights are only kept if they do better
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FsrsFitReport {
+    pub settings: SchedulerSettings,
+    pub cards: usize,
+    pub reviews: usize,
+    pub log_loss_before: f64,
+    pub log_loss_after: f64,
 }
 
 // Tech Space models
@@ -205,6 +290,14 @@ pub struct PersonalAccount {
     pub website: Option<String>,
     pub notes: Option<String>,
     pub category: String,
+    // Strength score of the password when it was saved
+    #[serde(default)]
+    pub password_strength: Option<i64>,
+    // otpauth:// URI holding the 2FA seed, encrypted like the password
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -217,6 +310,224 @@ pub struct CreatePersonalAccountRequest {
     pub website: Option<String>,
     pub notes: Option<String>,
     pub category: String,
+    // An otpauth:// URI or a bare base32 secret
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    // Replace the account's custom fields, in this order
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldInput>,
+}
+
+// Like `CreatePersonalAccountRequest`, but without a password the account
+// keeps its current one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdatePersonalAccountRequest {
+    pub title: String,
+    pub email: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    pub website: Option<String>,
+    pub notes: Option<String>,
+    pub category: String,
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldInput>,
+}
+
+// An account as listed, without its password or 2FA secret; those are
+// fetched one at a time with `reveal_secret`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonalAccountSummary {
+    pub id: i64,
+    pub title: String,
+    pub email: String,
+    pub website: Option<String>,
+    pub notes: Option<String>,
+    pub category: String,
+    pub password_strength: Option<i64>,
+    pub has_totp: bool,
+    pub custom_fields: Vec<CustomField>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// A password an account used before. The password itself is revealed with
+// `SecretField::PreviousPassword` and the entry's id.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PasswordHistoryEntry {
+    pub id: i64,
+    pub account_id: i64,
+    pub replaced_at: String,
+}
+
+// A password history row in a workspace archive, the password encrypted as
+// stored
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchivedPassword {
+    pub id: i64,
+    pub account_id: i64,
+    pub password: String,
+    pub replaced_at: String,
+}
+
+// Password generator models
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PasswordPolicy {
+    Random {
+        length: usize,
+        lowercase: bool,
+        uppercase: bool,
+        digits: bool,
+        symbols: bool,
+        // Leaves out look-alikes such as 0/O and 1/l/I
+        exclude_ambiguous: bool,
+    },
+    // Words from the bundled wordlist
+    Passphrase {
+        words: usize,
+        separator: String,
+        capitalize: bool,
+        include_number: bool,
+    },
+}
+
+// `score` runs from 0 (very weak) to 4 (very strong)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PasswordStrength {
+    pub score: i64,
+    pub label: String,
+    pub entropy_bits: f64,
+    pub feedback: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    pub strength: PasswordStrength,
+}
+
+// TOTP models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpCode {
+    pub code: String,
+    pub seconds_remaining: u64,
+    pub period: u64,
+    pub digits: u32,
+    pub algorithm: TotpAlgorithm,
+}
+
+// Vault audit models
+#[derive(Debug, Deserialize)]
+pub struct VaultAuditRequest {
+    // Passwords not changed for this long are reported as stale
+    #[serde(default = "default_stale_after_months")]
+    pub stale_after_months: u32,
+    // Pwned Passwords style file: one uppercase SHA-1 hash per line,
+    // optionally followed by ":<count>"
+    #[serde(default)]
+    pub breach_list_path: Option<String>,
+}
+
+fn default_stale_after_months() -> u32 {
+    12
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditIssue {
+    Reused,
+    Weak,
+    Stale,
+    Breached,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountAudit {
+    pub id: i64,
+    pub title: String,
+    pub website: Option<String>,
+    pub category: String,
+    pub issues: Vec<AuditIssue>,
+    pub strength: PasswordStrength,
+    // Other accounts with the same password
+    pub reused_with: Vec<i64>,
+    pub months_since_change: i64,
+    // Times the password appears in the breach list, if it was checked
+    pub breach_count: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultAuditReport {
+    pub total_accounts: usize,
+    pub reused: usize,
+    pub weak: usize,
+    pub stale: usize,
+    pub breached: usize,
+    pub breach_list_checked: bool,
+    pub accounts: Vec<AccountAudit>,
+}
+
+// Account import models
+// Export formats of other password managers that can be imported
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountImportFormat {
+    BitwardenJson,
+    BitwardenCsv,
+    KeepassXml,
+    KeepassCsv,
+    OnePasswordCsv,
+    ChromiumCsv,
+    FirefoxCsv,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AccountImportRequest {
+    pub path: String,
+    pub format: AccountImportFormat,
+    // Given to every imported account
+    pub category: String,
+    // Only report what would be imported
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// `row` is the line number in CSV files and the position of the entry in
+// JSON and XML files
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportedAccount {
+    pub row: usize,
+    pub title: String,
+    pub username: String,
+    pub website: Option<String>,
+    pub has_totp: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkippedImportRow {
+    pub row: usize,
+    pub title: Option<String>,
+    pub reason: String,
+    // Existing account with the same website and username
+    pub duplicate_of: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountImportReport {
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub imported: Vec<ImportedAccount>,
+    pub skipped: Vec<SkippedImportRow>,
 }
 
 // Personal Info models
@@ -227,14 +538,462 @@ pub struct PersonalInfo {
     pub content: String,
     pub category: String,
     pub is_sensitive: bool,
+    #[serde(default)]
+    pub custom_fields: Vec<CustomField>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+// Sensitive content is left out of lists, like account passwords
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonalInfoSummary {
+    pub id: i64,
+    pub title: String,
+    pub content: Option<String>,
+    pub category: String,
+    pub is_sensitive: bool,
+    pub custom_fields: Vec<CustomField>,
+    pub attachment_count: i64,
     pub created_at: String,
     pub updated_at: String,
 }
 
+// A file kept with a personal info entry. Its contents only ever leave the
+// database through `export_info_attachment`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InfoAttachment {
+    pub id: i64,
+    pub info_id: i64,
+    pub file_name: String,
+    pub size_bytes: i64,
+    pub created_at: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreatePersonalInfoRequest {
     pub title: String,
     pub content: String,
     pub category: String,
     pub is_sensitive: bool,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub custom_fields: Vec<CustomFieldInput>,
+}
+
+// Custom field models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomFieldType {
+    Text,
+    // Encrypted, and left out of lists
+    Hidden,
+    Url,
+    // YYYY-MM-DD
+    Date,
+    Number,
+    // Digits only, Luhn-checked; encrypted like hidden fields
+    CardNumber,
+}
+
+// A user-defined field of an account or personal info entry
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomField {
+    pub id: i64,
+    pub label: String,
+    pub field_type: CustomFieldType,
+    // None in lists for hidden fields and card numbers, which are fetched
+    // with `SecretField::CustomField` and the field's id
+    pub value: Option<String>,
+    // Last four digits of a card number, shown in place of the value
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CustomFieldInput {
+    // The saved field this one replaces, kept so its id stays the same;
+    // none for a new field
+    #[serde(default)]
+    pub id: Option<i64>,
+    pub label: String,
+    pub field_type: CustomFieldType,
+    pub value: String,
+}
+
+// Vault bundle models
+// Who can open an exported vault bundle: anyone with the passphrase, or the
+// holder of the secret key to any of the X25519 public keys (`age1…`)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BundleRecipients {
+    Passphrase { passphrase: String },
+    PublicKeys { public_keys: Vec<String> },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VaultBundleExportRequest {
+    pub path: String,
+    #[serde(default)]
+    pub account_ids: Vec<i64>,
+    #[serde(default)]
+    pub info_ids: Vec<i64>,
+    pub recipients: BundleRecipients,
+}
+
+// How to open a bundle: its passphrase, or the `AGE-SECRET-KEY-1…` secret key
+// to one of its public keys
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BundleUnlock {
+    Passphrase { passphrase: String },
+    SecretKey { secret_key: String },
+}
+
+// What happens to a bundle entry that matches one already in the vault
+// (same website and username for accounts, same title and category for info)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleConflict {
+    Skip,
+    // Overwrites the existing entry; a changed password goes to its history
+    Replace,
+    KeepBoth,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VaultBundleImportRequest {
+    pub path: String,
+    pub unlock: BundleUnlock,
+    pub conflict: BundleConflict,
+    // Only report what would be imported
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+// An X25519 key pair in age's encoding; the secret key opens bundles
+// exported to the public key
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleKeyPair {
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+// The decrypted contents of a bundle, with every value in the clear
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultBundleContents {
+    pub exported_at: String,
+    #[serde(default)]
+    pub personal_accounts: Vec<PersonalAccount>,
+    #[serde(default)]
+    pub personal_info: Vec<PersonalInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultBundleExport {
+    pub path: String,
+    pub personal_accounts: usize,
+    pub personal_info: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleEntryKind {
+    PersonalAccount,
+    PersonalInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BundleImportOutcome {
+    Added,
+    Replaced,
+    Skipped,
+}
+
+// `existing_id` is the vault entry the bundle entry matched, if any
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BundleImportEntry {
+    pub kind: BundleEntryKind,
+    pub title: String,
+    pub outcome: BundleImportOutcome,
+    pub existing_id: Option<i64>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultBundleImportReport {
+    pub dry_run: bool,
+    pub entries: Vec<BundleImportEntry>,
+}
+
+// Secret access models
+// A value `reveal_secret` can decrypt, and what the id passed with it refers to
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretField {
+    // Of a personal account
+    Password,
+    TotpSecret,
+    // Of a password history entry
+    PreviousPassword,
+    // Of a personal info entry
+    Content,
+    // Of an info attachment; written to a file by `export_info_attachment`
+    // rather than revealed
+    Attachment,
+    // Of a hidden or card number custom field
+    CustomField,
+    // Of a personal account or info entry written to a vault bundle by
+    // `export_vault_bundle` rather than revealed
+    BundledAccount,
+    BundledInfo,
+}
+
+// One entry of the append-only log of revealed secrets. The title is kept as
+// it was, so entries stay readable after the item is renamed or purged.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecretAccess {
+    pub id: i64,
+    pub field: SecretField,
+    pub item_id: i64,
+    pub title: String,
+    pub accessed_at: String,
+}
+
+// Vault models
+// Argon2id cost settings, stored with the vault so they can be raised later
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+// Everything needed to re-derive the vault key from the master password;
+// `verifier` is a known value encrypted with that key
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultHeader {
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub verifier: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub initialized: bool,
+    pub unlocked: bool,
+    // The whole database file is encrypted with SQLCipher
+    pub database_encrypted: bool,
+    // Whether this build can encrypt the database at all
+    pub database_encryption_available: bool,
+}
+
+// Minutes without vault activity before it locks itself; 0 never locks
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultSettings {
+    pub auto_lock_minutes: i64,
+}
+
+impl Default for VaultSettings {
+    fn default() -> Self {
+        Self { auto_lock_minutes: 5 }
+    }
+}
+
+// Workspace models
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    pub id: i64,
+    pub name: String,
+    pub path: String,
+    pub created_at: String,
+    pub last_opened_at: Option<String>,
+    #[serde(default)]
+    pub backup: BackupSettings,
+    #[serde(default)]
+    pub trash: TrashSettings,
+    #[serde(default)]
+    pub vault: VaultSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWorkspaceRequest {
+    pub name: String,
+    pub path: String,
+}
+
+// Backup models
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupSettings {
+    pub folder: Option<String>,
+    pub interval_hours: i64,
+    pub keep_daily: i64,
+    pub keep_weekly: i64,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            folder: None,
+            interval_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub path: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+}
+
+// Trash models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Language,
+    Vocabulary,
+    TechSpace,
+    CodeSnippet,
+    Project,
+    Task,
+    Event,
+    Note,
+    PersonalAccount,
+    PersonalInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashItem {
+    pub kind: TrashKind,
+    pub id: i64,
+    pub title: String,
+    pub deleted_at: String,
+}
+
+// Days an item stays in the trash before it is purged; 0 keeps it forever
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrashSettings {
+    pub retention_days: i64,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+// Revision models
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionKind {
+    CodeSnippet,
+    Note,
+    Task,
+    Project,
+}
+
+// `snapshot` is the entity as it was saved, e.g. a `Note`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Revision {
+    pub id: i64,
+    pub kind: RevisionKind,
+    pub entity_id: i64,
+    pub snapshot: serde_json::Value,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+// Workspace archive models
+//
+// A workspace archive is a single JSON document (see docs/workspace-archive.md).
+// Rows keep their original ids so references between them can be resolved on
+// import; vault entries carry their stored, still-encrypted values.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceArchive {
+    pub format: String,
+    pub version: i64,
+    pub schema_version: i64,
+    pub exported_at: String,
+    #[serde(default)]
+    pub languages: Vec<Language>,
+    #[serde(default)]
+    pub vocabulary: Vec<Vocabulary>,
+    #[serde(default)]
+    pub flashcards: Vec<Flashcard>,
+    #[serde(default)]
+    pub scheduler_settings: Vec<SchedulerSettings>,
+    #[serde(default)]
+    pub review_log: Vec<ArchivedReview>,
+    #[serde(default)]
+    pub tech_spaces: Vec<TechSpace>,
+    #[serde(default)]
+    pub code_snippets: Vec<CodeSnippet>,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub events: Vec<Event>,
+    #[serde(default)]
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub personal_accounts: Vec<PersonalAccount>,
+    #[serde(default)]
+    pub password_history: Vec<ArchivedPassword>,
+    #[serde(default)]
+    pub personal_info: Vec<PersonalInfo>,
+    // Needed to decrypt the vault entries; absent if no master password was set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault: Option<VaultHeader>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    Merge,
+    Replace,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ImportSummary {
+    pub languages: usize,
+    pub vocabulary: usize,
+    pub flashcards: usize,
+    pub tech_spaces: usize,
+    pub code_snippets: usize,
+    pub projects: usize,
+    pub tasks: usize,
+    pub events: usize,
+    pub notes: usize,
+    pub personal_accounts: usize,
+    pub personal_info: usize,
+    pub merged_languages: usize,
+    pub merged_tech_spaces: usize,
+}
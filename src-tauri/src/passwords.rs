@@ -0,0 +1,351 @@
+use crate::errors::AppError;
+use crate::models::*;
+use crate::validation::Validator;
+use rand::rngs::OsRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+// BIP-39 English wordlist: 2048 short, distinct words, so each word of a
+// passphrase adds 11 bits
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()-_=+[]{};:,.<>?/~";
+const AMBIGUOUS: &str = "0Oo1lI|";
+
+// Passwords that appear at the top of every leaked-password list, most common
+// first. Matched after undoing simple substitutions such as 0 for o.
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "123456", "qwerty", "letmein", "welcome", "admin", "iloveyou", "monkey", "dragon",
+    "football", "baseball", "master", "sunshine", "shadow", "princess", "trustno1", "superman",
+    "batman", "starwars", "passw0rd", "login", "abc123", "secret", "hello", "freedom", "whatever",
+    "qazwsx", "michael", "charlie", "jordan", "hunter", "ashley", "mustang", "access", "pokemon",
+];
+
+const KEYBOARD_ROWS: &[&str] = &["qwertyuiop", "asdfghjkl", "zxcvbnm", "1234567890"];
+
+// Longest password the app stores or estimates, in characters
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+const LABELS: [&str; 5] = ["Very weak", "Weak", "Fair", "Strong", "Very strong"];
+
+fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| WORDLIST.lines().map(str::trim).filter(|w| !w.is_empty()).collect())
+}
+
+fn dictionary() -> &'static HashSet<&'static str> {
+    static DICTIONARY: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    DICTIONARY.get_or_init(|| wordlist().iter().copied().collect())
+}
+
+// Longest piece the word, common password and keyboard matchers can find, so
+// they only look that far ahead
+fn longest_known_piece() -> usize {
+    static LONGEST: OnceLock<usize> = OnceLock::new();
+    *LONGEST.get_or_init(|| {
+        wordlist()
+            .iter()
+            .chain(COMMON_PASSWORDS)
+            .chain(KEYBOARD_ROWS)
+            .map(|piece| piece.chars().count())
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+pub fn generate_password(policy: &PasswordPolicy) -> Result<GeneratedPassword, AppError> {
+    policy.validate()?;
+
+    let password = match policy {
+        PasswordPolicy::Random { length, lowercase, uppercase, digits, symbols, exclude_ambiguous } => {
+            let classes: Vec<Vec<char>> = [(*lowercase, LOWERCASE), (*uppercase, UPPERCASE), (*digits, DIGITS), (*symbols, SYMBOLS)]
+                .iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, set)| set.chars().filter(|c| !exclude_ambiguous || !AMBIGUOUS.contains(*c)).collect())
+                .collect();
+            let pool: Vec<char> = classes.concat();
+
+            // One character from every chosen class, the rest from all of them
+            let mut chars: Vec<char> = classes.iter().map(|class| pick(class)).collect();
+            while chars.len() < *length {
+                chars.push(pick(&pool));
+            }
+            chars.shuffle(&mut OsRng);
+            chars.into_iter().collect()
+        }
+        PasswordPolicy::Passphrase { words, separator, capitalize, include_number } => {
+            let mut chosen: Vec<String> = (0..*words)
+                .map(|_| {
+                    let word = pick(wordlist());
+                    if *capitalize {
+                        capitalized(word)
+                    } else {
+                        word.to_string()
+                    }
+                })
+                .collect();
+            if *include_number {
+                let index = OsRng.gen_range(0..chosen.len());
+                chosen[index].push(pick(&DIGITS.chars().collect::<Vec<_>>()));
+            }
+            chosen.join(separator)
+        }
+    };
+
+    Ok(GeneratedPassword {
+        strength: estimate_strength(&password),
+        password,
+    })
+}
+
+fn pick<T: Copy>(items: &[T]) -> T {
+    *items.choose(&mut OsRng).expect("password character pools are never empty")
+}
+
+fn capitalized(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pattern {
+    Character,
+    Common,
+    Word,
+    Sequence,
+    Repeat,
+    Keyboard,
+    Year,
+}
+
+// Estimates how many guesses an attacker who knows the usual tricks would
+// need, in bits. The password is split into the cheapest run of pieces
+// (dictionary words, common passwords, sequences, repeats, keyboard rows,
+// years or single characters), each costed separately.
+pub fn estimate_strength(password: &str) -> PasswordStrength {
+    let chars: Vec<char> = password.chars().collect();
+    let n = chars.len();
+    let char_bits = pool_size(&chars).log2();
+
+    // best[i] is the cheapest cost of the first i characters, with the
+    // pattern and start of the last piece
+    let mut best: Vec<(f64, Pattern, usize)> = vec![(f64::INFINITY, Pattern::Character, 0); n + 1];
+    best[0].0 = 0.0;
+    for start in 0..n {
+        let base = best[start].0;
+        let mut consider = |end: usize, bits: f64, pattern: Pattern| {
+            if base + bits < best[end].0 {
+                best[end] = (base + bits, pattern, start);
+            }
+        };
+        consider(start + 1, char_bits, Pattern::Character);
+        for (end, bits, pattern) in matches_from(&chars, start) {
+            consider(end, bits, pattern);
+        }
+    }
+
+    let mut patterns = Vec::new();
+    let mut end = n;
+    while end > 0 {
+        let (_, pattern, start) = best[end];
+        patterns.push(pattern);
+        end = start;
+    }
+
+    let entropy_bits = if n == 0 { 0.0 } else { best[n].0 };
+    let score = match entropy_bits {
+        bits if bits < 28.0 => 0,
+        bits if bits < 36.0 => 1,
+        bits if bits < 60.0 => 2,
+        bits if bits < 80.0 => 3,
+        _ => 4,
+    };
+
+    PasswordStrength {
+        score,
+        label: LABELS[score as usize].to_string(),
+        entropy_bits: (entropy_bits * 10.0).round() / 10.0,
+        feedback: feedback(&chars, &patterns, score),
+    }
+}
+
+// Size of the character set a brute-force attack would have to cover
+fn pool_size(chars: &[char]) -> f64 {
+    let mut size: f64 = 0.0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        size += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        size += 26.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        size += 10.0;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        size += 33.0;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        size += 100.0;
+    }
+    size.max(1.0)
+}
+
+// Every recognised piece starting at `start`, as (end, bits, pattern)
+fn matches_from(chars: &[char], start: usize) -> Vec<(usize, f64, Pattern)> {
+    let mut found = Vec::new();
+    let n = chars.len();
+
+    for end in (start + 3)..=n.min(start + longest_known_piece()) {
+        let piece = &chars[start..end];
+        let lower: String = piece.iter().flat_map(|c| c.to_lowercase()).collect();
+        let plain: String = lower.chars().map(unleet).collect();
+        // A bit for each capital or substitution the attacker has to try
+        let variations = piece.iter().filter(|c| c.is_uppercase()).count()
+            + lower.chars().zip(plain.chars()).filter(|(a, b)| a != b).count();
+
+        if let Some(rank) = COMMON_PASSWORDS.iter().position(|p| *p == plain) {
+            found.push((end, ((rank + 1) as f64).log2() + 1.0 + variations as f64, Pattern::Common));
+        }
+        if dictionary().contains(plain.as_str()) {
+            found.push((end, (wordlist().len() as f64).log2() + variations as f64, Pattern::Word));
+        }
+        if end - start >= 4 && KEYBOARD_ROWS.iter().any(|row| row.contains(&lower) || row.contains(&reversed(&lower))) {
+            found.push((end, 26f64.log2() + ((end - start) as f64).log2() + 1.0, Pattern::Keyboard));
+        }
+    }
+
+    // Runs of one character, or of characters stepping up or down by one
+    let first = chars.get(start).copied();
+    if let Some(first) = first {
+        let class_bits = class_size(first).log2();
+        let mut repeat_end = start + 1;
+        while repeat_end < n && chars[repeat_end] == first {
+            repeat_end += 1;
+        }
+        if repeat_end - start >= 3 {
+            found.push((repeat_end, class_bits + ((repeat_end - start) as f64).log2(), Pattern::Repeat));
+        }
+
+        if start + 1 < n {
+            let step = chars[start + 1] as i64 - first as i64;
+            if step == 1 || step == -1 {
+                let mut seq_end = start + 2;
+                while seq_end < n && chars[seq_end] as i64 - chars[seq_end - 1] as i64 == step {
+                    seq_end += 1;
+                }
+                if seq_end - start >= 3 {
+                    found.push((seq_end, class_bits + ((seq_end - start) as f64).log2() + 1.0, Pattern::Sequence));
+                }
+            }
+        }
+    }
+
+    // Years from 1900 to 2099
+    if start + 4 <= n {
+        let year: String = chars[start..start + 4].iter().collect();
+        if let Ok(year) = year.parse::<u32>() {
+            if (1900..2100).contains(&year) {
+                found.push((start + 4, 200f64.log2(), Pattern::Year));
+            }
+        }
+    }
+
+    found
+}
+
+fn class_size(c: char) -> f64 {
+    if c.is_ascii_digit() {
+        10.0
+    } else if c.is_ascii_alphabetic() {
+        26.0
+    } else {
+        33.0
+    }
+}
+
+fn unleet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        other => other,
+    }
+}
+
+fn reversed(s: &str) -> String {
+    s.chars().rev().collect()
+}
+
+fn feedback(chars: &[char], patterns: &[Pattern], score: i64) -> Vec<String> {
+    let mut feedback = Vec::new();
+    let has = |pattern: Pattern| patterns.contains(&pattern);
+
+    if has(Pattern::Common) {
+        feedback.push("Avoid common passwords, even with letters swapped for symbols".to_string());
+    }
+    if has(Pattern::Sequence) {
+        feedback.push("Avoid sequences like abc or 123".to_string());
+    }
+    if has(Pattern::Repeat) {
+        feedback.push("Avoid repeated characters".to_string());
+    }
+    if has(Pattern::Keyboard) {
+        feedback.push("Avoid keyboard patterns like qwerty".to_string());
+    }
+    if has(Pattern::Year) {
+        feedback.push("Avoid years and dates".to_string());
+    }
+    if score < 3 {
+        let words = patterns.iter().filter(|p| **p == Pattern::Word).count();
+        if words > 0 {
+            feedback.push("Add more random words".to_string());
+        } else if chars.len() < 12 {
+            feedback.push("Use at least 12 characters".to_string());
+        }
+        let classes = [
+            chars.iter().any(|c| c.is_ascii_lowercase()),
+            chars.iter().any(|c| c.is_ascii_uppercase()),
+            chars.iter().any(|c| c.is_ascii_digit()),
+            chars.iter().any(|c| !c.is_ascii_alphanumeric()),
+        ];
+        if words == 0 && classes.iter().filter(|present| **present).count() < 3 {
+            feedback.push("Mix upper- and lowercase letters, digits and symbols".to_string());
+        }
+    }
+
+    feedback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_common_pieces() {
+        let strength = estimate_strength("P@ssw0rd1990");
+        assert!(strength.score <= 1);
+        assert!(strength.feedback.iter().any(|f| f.contains("common passwords")));
+        assert!(strength.feedback.iter().any(|f| f.contains("years")));
+        assert!(estimate_strength("qwertyuiop").score == 0);
+    }
+
+    #[test]
+    fn long_passwords_stay_fast() {
+        let password: String = "correct horse battery staple ".repeat(36).chars().take(MAX_PASSWORD_LENGTH).collect();
+        let started = std::time::Instant::now();
+        let strength = estimate_strength(&password);
+        assert_eq!(strength.score, 4);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,39 @@
+// Spaced repetition schedulers: given a card's state and a review, each works
+// out the card's next state, including how many days until it is due again
+mod fsrs;
+mod sm2;
+
+pub use fsrs::{fit, Fsrs, FsrsFit, DEFAULT_WEIGHTS};
+pub use sm2::Sm2;
+
+use crate::models::{SchedulerKind, SchedulerSettings};
+
+// Default and range of the desired retention setting
+pub const DEFAULT_RETENTION: f64 = 0.9;
+pub const MIN_RETENTION: f64 = 0.7;
+pub const MAX_RETENTION: f64 = 0.97;
+
+// What the schedulers keep per card. SM-2 uses the ease factor and
+// repetitions, FSRS the stability (in days) and difficulty (1 to 10); both
+// keep the interval up to date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CardState {
+    pub ease_factor: f64,
+    pub interval_days: i64,
+    pub repetitions: i64,
+    pub stability: Option<f64>,
+    pub difficulty: Option<f64>,
+}
+
+pub trait Scheduler {
+    // The card's state after a review of `quality` (1 to 5), `elapsed_days`
+    // after its previous review; None if it was never reviewed
+    fn review(&self, card: &CardState, quality: i64, elapsed_days: Option<f64>) -> CardState;
+}
+
+pub fn for_settings(settings: &SchedulerSettings) -> Box<dyn Scheduler> {
+    match settings.scheduler {
+        SchedulerKind::Sm2 => Box::new(Sm2),
+        SchedulerKind::Fsrs => Box::new(Fsrs::new(settings.fsrs_weights.as_deref(), settings.desired_retention)),
+    }
+}
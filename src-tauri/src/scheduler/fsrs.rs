@@ -0,0 +1,334 @@
+use super::{CardState, Scheduler, DEFAULT_RETENTION};
+
+// Free Spaced Repetition Scheduler (FSRS-4.5). Each card has a stability, the
+// days until its chance of being remembered drops to 90%, and a difficulty
+// from 1 to 10 that sets how fast the stability grows. Cards come due when
+// that chance reaches the desired retention.
+
+// FSRS-4.5's defaults, fitted by its authors to a large set of Anki reviews
+pub const DEFAULT_WEIGHTS: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461, 2.1072, 0.0793, 0.3246,
+    1.587, 0.2272, 2.8755,
+];
+
+// Ranges the weights are kept within while fitting, as in the reference
+// optimizer
+const WEIGHT_BOUNDS: [(f64, f64); 17] = [
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (1.0, 10.0),
+    (0.1, 4.0),
+    (0.1, 4.0),
+    (0.0, 0.75),
+    (0.0, 4.5),
+    (0.0, 0.8),
+    (0.01, 3.5),
+    (0.1, 5.0),
+    (0.01, 0.2),
+    (0.01, 0.9),
+    (0.01, 4.0),
+    (0.0, 1.0),
+    (1.0, 6.0),
+];
+
+// Forgetting curve R(t) = (1 + FACTOR * t / S) ^ DECAY, which is 90% at t = S
+const DECAY: f64 = -0.5;
+const FACTOR: f64 = 19.0 / 81.0;
+const MIN_STABILITY: f64 = 0.01;
+const MAX_INTERVAL_DAYS: f64 = 36500.0;
+// Retention SM-2 intervals are taken to aim for when converting a card
+const SM2_RETENTION: f64 = 0.9;
+
+// Adam steps while fitting; each moves a weight by at most about
+// `FIT_STEP` of its range
+const FIT_ITERATIONS: usize = 250;
+const FIT_STEP: f64 = 0.004;
+
+#[derive(Debug, Clone, Copy)]
+struct Memory {
+    stability: f64,
+    difficulty: f64,
+}
+
+pub struct Fsrs {
+    weights: [f64; 17],
+    desired_retention: f64,
+}
+
+impl Fsrs {
+    // Uses the default weights unless 17 are given
+    pub fn new(weights: Option<&[f64]>, desired_retention: f64) -> Self {
+        let weights = weights
+            .and_then(|weights| <[f64; 17]>::try_from(weights).ok())
+            .unwrap_or(DEFAULT_WEIGHTS);
+        Self { weights, desired_retention }
+    }
+
+    fn first(&self, rating: f64) -> Memory {
+        Memory {
+            stability: self.weights[rating as usize - 1].max(MIN_STABILITY),
+            difficulty: self.initial_difficulty(rating).clamp(1.0, 10.0),
+        }
+    }
+
+    fn initial_difficulty(&self, rating: f64) -> f64 {
+        self.weights[4] - (rating - 3.0) * self.weights[5]
+    }
+
+    fn next(&self, memory: Memory, rating: f64, elapsed_days: f64) -> Memory {
+        let w = &self.weights;
+        let Memory { stability, difficulty } = memory;
+        let r = retrievability(elapsed_days, stability);
+
+        let stability = if rating == 1.0 {
+            let forgotten = w[11] * difficulty.powf(-w[12]) * ((stability + 1.0).powf(w[13]) - 1.0) * (w[14] * (1.0 - r)).exp();
+            forgotten.min(stability)
+        } else {
+            let hard_penalty = if rating == 2.0 { w[15] } else { 1.0 };
+            let easy_bonus = if rating == 4.0 { w[16] } else { 1.0 };
+            let growth = w[8].exp() * (11.0 - difficulty) * stability.powf(-w[9]) * ((w[10] * (1.0 - r)).exp() - 1.0);
+            stability * (1.0 + growth * hard_penalty * easy_bonus)
+        };
+        // Drifts back towards the difficulty of a first "good" answer
+        let difficulty = w[7] * self.initial_difficulty(3.0) + (1.0 - w[7]) * (difficulty - w[6] * (rating - 3.0));
+
+        Memory {
+            stability: stability.max(MIN_STABILITY),
+            difficulty: difficulty.clamp(1.0, 10.0),
+        }
+    }
+
+    // A card scheduled by SM-2 so far: its interval is taken as the
+    // stability, and the difficulty is the one whose stability growth matches
+    // its ease factor
+    fn converted_from_sm2(&self, ease_factor: f64, interval_days: i64) -> Memory {
+        let w = &self.weights;
+        let stability = (interval_days as f64).max(1.0);
+        let growth = w[8].exp() * stability.powf(-w[9]) * ((w[10] * (1.0 - SM2_RETENTION)).exp() - 1.0);
+        Memory {
+            stability,
+            difficulty: (11.0 - (ease_factor - 1.0) / growth).clamp(1.0, 10.0),
+        }
+    }
+
+    fn interval(&self, stability: f64) -> i64 {
+        let days = stability / FACTOR * (self.desired_retention.powf(1.0 / DECAY) - 1.0);
+        days.round().clamp(1.0, MAX_INTERVAL_DAYS) as i64
+    }
+}
+
+impl Scheduler for Fsrs {
+    fn review(&self, card: &CardState, quality: i64, elapsed_days: Option<f64>) -> CardState {
+        let rating = rating(quality);
+        let memory = match (elapsed_days, card.stability, card.difficulty) {
+            (None, _, _) => self.first(rating),
+            (Some(elapsed), Some(stability), Some(difficulty)) => self.next(Memory { stability, difficulty }, rating, elapsed),
+            (Some(elapsed), _, _) => self.next(self.converted_from_sm2(card.ease_factor, card.interval_days), rating, elapsed),
+        };
+
+        CardState {
+            ease_factor: card.ease_factor,
+            interval_days: self.interval(memory.stability),
+            repetitions: if rating == 1.0 { 0 } else { card.repetitions + 1 },
+            stability: Some(memory.stability),
+            difficulty: Some(memory.difficulty),
+        }
+    }
+}
+
+pub struct FsrsFit {
+    pub weights: Vec<f64>,
+    // Reviews whose outcome was predicted, i.e. all but each card's first
+    pub reviews: usize,
+    pub log_loss_before: f64,
+    pub log_loss_after: f64,
+}
+
+// Fits the weights to complete review histories, each one card's reviews
+// oldest first as (days since the previous review, quality), starting from
+// `weights` (or the defaults). The log loss is per predicted review.
+pub fn fit(histories: &[Vec<(f64, i64)>], weights: Option<&[f64]>) -> FsrsFit {
+    let reviews: usize = histories.iter().map(|history| history.len().saturating_sub(1)).sum();
+    let loss = |weights: &[f64; 17]| log_loss(weights, histories) / reviews.max(1) as f64;
+
+    let mut weights = Fsrs::new(weights, DEFAULT_RETENTION).weights;
+    for (weight, (low, high)) in weights.iter_mut().zip(WEIGHT_BOUNDS) {
+        *weight = weight.clamp(low, high);
+    }
+    let log_loss_before = loss(&weights);
+    let mut best = (log_loss_before, weights);
+
+    // Adam, with gradients by central differences
+    let (beta1, beta2, epsilon) = (0.9, 0.999, 1e-8);
+    let mut first_moment = [0.0; 17];
+    let mut second_moment = [0.0; 17];
+    for step in 1..=FIT_ITERATIONS {
+        let mut gradient = [0.0; 17];
+        for (i, (low, high)) in WEIGHT_BOUNDS.iter().copied().enumerate() {
+            let h = (high - low) * 1e-4;
+            let mut up = weights;
+            up[i] = (weights[i] + h).min(high);
+            let mut down = weights;
+            down[i] = (weights[i] - h).max(low);
+            gradient[i] = (loss(&up) - loss(&down)) / (up[i] - down[i]);
+        }
+
+        for (i, (low, high)) in WEIGHT_BOUNDS.iter().copied().enumerate() {
+            first_moment[i] = beta1 * first_moment[i] + (1.0 - beta1) * gradient[i];
+            second_moment[i] = beta2 * second_moment[i] + (1.0 - beta2) * gradient[i] * gradient[i];
+            let corrected_first = first_moment[i] / (1.0 - beta1.powi(step as i32));
+            let corrected_second = second_moment[i] / (1.0 - beta2.powi(step as i32));
+            let change = FIT_STEP * (high - low) * corrected_first / (corrected_second.sqrt() + epsilon);
+            weights[i] = (weights[i] - change).clamp(low, high);
+        }
+
+        let current = loss(&weights);
+        if current < best.0 {
+            best = (current, weights);
+        }
+    }
+
+    FsrsFit {
+        weights: best.1.to_vec(),
+        reviews,
+        log_loss_before,
+        log_loss_after: best.0,
+    }
+}
+
+// Total binary cross-entropy of the predicted chance of remembering each
+// card against whether it was remembered
+fn log_loss(weights: &[f64; 17], histories: &[Vec<(f64, i64)>]) -> f64 {
+    let fsrs = Fsrs {
+        weights: *weights,
+        desired_retention: DEFAULT_RETENTION,
+    };
+    let mut total = 0.0;
+    for history in histories {
+        let mut memory: Option<Memory> = None;
+        for &(elapsed_days, quality) in history {
+            let rating = rating(quality);
+            memory = Some(match memory {
+                None => fsrs.first(rating),
+                Some(memory) => {
+                    let r = retrievability(elapsed_days, memory.stability).clamp(1e-4, 1.0 - 1e-4);
+                    total -= if rating > 1.0 { r.ln() } else { (1.0 - r).ln() };
+                    fsrs.next(memory, rating, elapsed_days)
+                }
+            });
+        }
+    }
+    total
+}
+
+// FSRS grades answers Again, Hard, Good or Easy (1 to 4); qualities 1 and 2
+// are both Again
+fn rating(quality: i64) -> f64 {
+    match quality {
+        ..=2 => 1.0,
+        3 => 2.0,
+        4 => 3.0,
+        _ => 4.0,
+    }
+}
+
+fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+    (1.0 + FACTOR * elapsed_days.max(0.0) / stability).powf(DECAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW: CardState = CardState { ease_factor: 2.5, interval_days: 1, repetitions: 0, stability: None, difficulty: None };
+
+    #[test]
+    fn first_review_uses_the_initial_weights() {
+        let fsrs = Fsrs::new(None, 0.9);
+        for (quality, stability) in [(1, DEFAULT_WEIGHTS[0]), (3, DEFAULT_WEIGHTS[1]), (4, DEFAULT_WEIGHTS[2]), (5, DEFAULT_WEIGHTS[3])] {
+            assert_eq!(fsrs.review(&NEW, quality, None).stability, Some(stability));
+        }
+        let good = fsrs.review(&NEW, 4, None);
+        assert_eq!(good.difficulty, Some(DEFAULT_WEIGHTS[4]));
+        // At 90% retention the interval is the stability
+        assert_eq!(good.interval_days, DEFAULT_WEIGHTS[2].round() as i64);
+        assert_eq!(good.repetitions, 1);
+    }
+
+    #[test]
+    fn the_forgetting_curve_is_ninety_percent_at_the_stability() {
+        assert!((retrievability(7.0, 7.0) - 0.9).abs() < 1e-9);
+        assert_eq!(retrievability(0.0, 7.0), 1.0);
+        assert!(retrievability(14.0, 7.0) < 0.9);
+    }
+
+    #[test]
+    fn recall_grows_stability_and_a_lapse_shrinks_it() {
+        let fsrs = Fsrs::new(None, 0.9);
+        let good = fsrs.review(&NEW, 4, None);
+        let easy = fsrs.review(&good, 5, Some(good.interval_days as f64));
+        let again_good = fsrs.review(&good, 4, Some(good.interval_days as f64));
+        let hard = fsrs.review(&good, 3, Some(good.interval_days as f64));
+        assert!(easy.stability > again_good.stability);
+        assert!(again_good.stability > hard.stability);
+        assert!(hard.stability > good.stability);
+
+        let lapse = fsrs.review(&again_good, 1, Some(again_good.interval_days as f64));
+        assert!(lapse.stability < again_good.stability);
+        assert!(lapse.difficulty > again_good.difficulty);
+        assert_eq!(lapse.repetitions, 0);
+    }
+
+    #[test]
+    fn higher_retention_means_shorter_intervals() {
+        let card = CardState { stability: Some(20.0), difficulty: Some(5.0), ..NEW };
+        let relaxed = Fsrs::new(None, 0.8).review(&card, 4, Some(20.0));
+        let strict = Fsrs::new(None, 0.95).review(&card, 4, Some(20.0));
+        assert_eq!(relaxed.stability, strict.stability);
+        assert!(strict.interval_days < relaxed.interval_days);
+    }
+
+    #[test]
+    fn converts_sm2_cards() {
+        let fsrs = Fsrs::new(None, 0.9);
+        let easy_card = fsrs.converted_from_sm2(2.8, 10);
+        let hard_card = fsrs.converted_from_sm2(1.4, 10);
+        assert_eq!(easy_card.stability, 10.0);
+        assert!(easy_card.difficulty < hard_card.difficulty);
+        assert!((1.0..=10.0).contains(&hard_card.difficulty));
+
+        let sm2_card = CardState { ease_factor: 2.5, interval_days: 10, repetitions: 3, stability: None, difficulty: None };
+        let reviewed = fsrs.review(&sm2_card, 4, Some(10.0));
+        assert!(reviewed.stability.unwrap() > 10.0);
+        assert_eq!(reviewed.ease_factor, 2.5);
+    }
+
+    #[test]
+    fn ignores_weights_of_the_wrong_length() {
+        let card = Fsrs::new(Some(&[1.0; 5]), 0.9).review(&NEW, 4, None);
+        assert_eq!(card.stability, Some(DEFAULT_WEIGHTS[2]));
+    }
+
+    #[test]
+    fn fitting_does_not_make_predictions_worse() {
+        // Cards that are remembered far more reliably than the defaults expect
+        let histories: Vec<Vec<(f64, i64)>> = (0..30)
+            .map(|card| {
+                let mut history = vec![(0.0, 4)];
+                for (step, gap) in [3.0, 10.0, 30.0, 90.0].into_iter().enumerate() {
+                    history.push((gap, if (card + step) % 10 == 0 { 1 } else { 4 }));
+                }
+                history
+            })
+            .collect();
+        let fit = fit(&histories, None);
+        assert_eq!(fit.reviews, 120);
+        assert_eq!(fit.weights.len(), 17);
+        assert!(fit.log_loss_after <= fit.log_loss_before);
+        for (weight, (low, high)) in fit.weights.iter().zip(WEIGHT_BOUNDS) {
+            assert!((low..=high).contains(weight));
+        }
+    }
+}
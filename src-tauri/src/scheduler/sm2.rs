@@ -0,0 +1,73 @@
+use super::{CardState, Scheduler};
+
+// SuperMemo 2: the ease factor grows or shrinks with each answer, and the
+// interval goes 1 day, 6 days, then the previous interval times the ease
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+    fn review(&self, card: &CardState, quality: i64, _elapsed_days: Option<f64>) -> CardState {
+        let quality = quality as f64;
+        let ease_factor = if quality >= 3.0 {
+            card.ease_factor + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02))
+        } else {
+            card.ease_factor.max(1.3)
+        };
+
+        let (interval_days, repetitions) = if quality < 3.0 {
+            (1, 0)
+        } else {
+            let interval = match card.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (card.interval_days as f64 * ease_factor) as i64,
+            };
+            (interval, card.repetitions + 1)
+        };
+
+        // FSRS state would be stale after this review; it is rebuilt from
+        // the ease and interval if the language switches back
+        CardState {
+            ease_factor,
+            interval_days,
+            repetitions,
+            stability: None,
+            difficulty: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEW: CardState = CardState { ease_factor: 2.5, interval_days: 1, repetitions: 0, stability: None, difficulty: None };
+
+    #[test]
+    fn intervals_go_one_six_then_times_the_ease() {
+        let first = Sm2.review(&NEW, 5, None);
+        assert_eq!((first.interval_days, first.repetitions), (1, 1));
+        assert!((first.ease_factor - 2.6).abs() < 1e-9);
+
+        let second = Sm2.review(&first, 5, Some(1.0));
+        assert_eq!((second.interval_days, second.repetitions), (6, 2));
+        assert!((second.ease_factor - 2.7).abs() < 1e-9);
+
+        let third = Sm2.review(&second, 5, Some(6.0));
+        assert_eq!((third.interval_days, third.repetitions), (16, 3));
+    }
+
+    #[test]
+    fn ease_follows_the_answer() {
+        assert!((Sm2.review(&NEW, 4, None).ease_factor - 2.5).abs() < 1e-9);
+        assert!((Sm2.review(&NEW, 3, None).ease_factor - 2.36).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_lapse_starts_over_and_clears_fsrs_state() {
+        let learnt = CardState { ease_factor: 2.2, interval_days: 40, repetitions: 5, stability: Some(30.0), difficulty: Some(4.0) };
+        let lapsed = Sm2.review(&learnt, 2, Some(40.0));
+        assert_eq!((lapsed.interval_days, lapsed.repetitions), (1, 0));
+        assert!((lapsed.ease_factor - 2.2).abs() < 1e-9);
+        assert_eq!((lapsed.stability, lapsed.difficulty), (None, None));
+    }
+}
@@ -0,0 +1,320 @@
+use crate::errors::AppError;
+use crate::models::{TotpAlgorithm, TotpCode};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+// RFC 4226 asks for at least 128 bits, but 80-bit secrets are what most
+// services hand out
+const MIN_SECRET_BYTES: usize = 10;
+
+// What is needed to compute codes for one account, as found in an
+// `otpauth://totp/...` URI
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotpConfig {
+    pub secret: Vec<u8>,
+    pub algorithm: TotpAlgorithm,
+    pub digits: u32,
+    pub period: u64,
+    pub issuer: Option<String>,
+    pub account_name: Option<String>,
+}
+
+impl TotpConfig {
+    // Accepts either an otpauth URI or a bare base32 secret, which gets the
+    // usual defaults (SHA1, 6 digits, 30 seconds)
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        let input = input.trim();
+        let Some(rest) = strip_prefix_ignore_case(input, "otpauth://") else {
+            return Ok(Self {
+                secret: decode_secret(input)?,
+                algorithm: TotpAlgorithm::Sha1,
+                digits: 6,
+                period: 30,
+                issuer: None,
+                account_name: None,
+            });
+        };
+
+        let Some(rest) = strip_prefix_ignore_case(rest, "totp/") else {
+            return Err(AppError::validation("Only time-based (otpauth://totp/) codes are supported"));
+        };
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let label = percent_decode(label)?;
+        // The label is "Issuer:account" or just "account"
+        let (label_issuer, account_name) = match label.split_once(':') {
+            Some((issuer, account)) => (Some(issuer.trim().to_string()), account.trim().to_string()),
+            None => (None, label.trim().to_string()),
+        };
+
+        let mut config = Self {
+            secret: Vec::new(),
+            algorithm: TotpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+            issuer: label_issuer,
+            account_name: Some(account_name).filter(|name| !name.is_empty()),
+        };
+        let mut secret = None;
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value)?;
+            match name.to_ascii_lowercase().as_str() {
+                "secret" => secret = Some(decode_secret(&value)?),
+                "issuer" => config.issuer = Some(value).filter(|issuer| !issuer.is_empty()),
+                "algorithm" => config.algorithm = parse_algorithm(&value)?,
+                "digits" => {
+                    config.digits = value.parse().map_err(|_| AppError::validation("Digits must be 6 or 8"))?
+                }
+                "period" => {
+                    config.period = value.parse().map_err(|_| AppError::validation("Period must be a number of seconds"))?
+                }
+                // Unknown parameters (image, color, ...) are ignored
+                _ => {}
+            }
+        }
+        config.secret = secret.ok_or_else(|| AppError::validation("The otpauth URI has no secret"))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        if self.digits != 6 && self.digits != 8 {
+            return Err(AppError::validation("Digits must be 6 or 8"));
+        }
+        if !(1..=600).contains(&self.period) {
+            return Err(AppError::validation("Period must be between 1 and 600 seconds"));
+        }
+        Ok(())
+    }
+
+    // Canonical form stored (encrypted) in the vault
+    pub fn to_uri(&self) -> String {
+        let label = match (&self.issuer, &self.account_name) {
+            (Some(issuer), Some(account)) => format!("{}:{}", percent_encode(issuer), percent_encode(account)),
+            (Some(name), None) | (None, Some(name)) => percent_encode(name),
+            (None, None) => String::new(),
+        };
+        let mut uri = format!(
+            "otpauth://totp/{}?secret={}&algorithm={}&digits={}&period={}",
+            label,
+            encode_base32(&self.secret),
+            algorithm_name(self.algorithm),
+            self.digits,
+            self.period
+        );
+        if let Some(issuer) = &self.issuer {
+            uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+        }
+        uri
+    }
+
+    // RFC 6238 code for the time step containing `unix_time`
+    pub fn code_at(&self, unix_time: u64) -> TotpCode {
+        let counter = unix_time / self.period;
+        let mac = match self.algorithm {
+            TotpAlgorithm::Sha1 => sign::<Hmac<Sha1>>(&self.secret, counter),
+            TotpAlgorithm::Sha256 => sign::<Hmac<Sha256>>(&self.secret, counter),
+            TotpAlgorithm::Sha512 => sign::<Hmac<Sha512>>(&self.secret, counter),
+        };
+
+        // Dynamic truncation from RFC 4226
+        let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+        let value = u32::from_be_bytes([mac[offset] & 0x7f, mac[offset + 1], mac[offset + 2], mac[offset + 3]]);
+        let code = value % 10u32.pow(self.digits);
+
+        TotpCode {
+            code: format!("{:0width$}", code, width = self.digits as usize),
+            seconds_remaining: self.period - unix_time % self.period,
+            period: self.period,
+            digits: self.digits,
+            algorithm: self.algorithm,
+        }
+    }
+}
+
+fn sign<M: Mac + hmac::digest::KeyInit>(secret: &[u8], counter: u64) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn parse_algorithm(value: &str) -> Result<TotpAlgorithm, AppError> {
+    match value.to_ascii_uppercase().replace('-', "").as_str() {
+        "SHA1" => Ok(TotpAlgorithm::Sha1),
+        "SHA256" => Ok(TotpAlgorithm::Sha256),
+        "SHA512" => Ok(TotpAlgorithm::Sha512),
+        _ => Err(AppError::validation("Algorithm must be SHA1, SHA256 or SHA512")),
+    }
+}
+
+fn algorithm_name(algorithm: TotpAlgorithm) -> &'static str {
+    match algorithm {
+        TotpAlgorithm::Sha1 => "SHA1",
+        TotpAlgorithm::Sha256 => "SHA256",
+        TotpAlgorithm::Sha512 => "SHA512",
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value
+        .get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+// Base32 as in RFC 4648, ignoring case, spaces, dashes and padding since
+// secrets are often shown in groups for reading aloud
+fn decode_secret(value: &str) -> Result<Vec<u8>, AppError> {
+    let mut bytes = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in value.chars().filter(|c| !matches!(c, ' ' | '-' | '=')) {
+        let index = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())
+            .ok_or_else(|| AppError::validation("The secret must be base32 (letters A-Z and digits 2-7)"))?;
+        buffer = (buffer << 5) | index as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    if bytes.len() < MIN_SECRET_BYTES {
+        return Err(AppError::validation("The secret is too short; it should be at least 16 base32 characters"));
+    }
+    Ok(bytes)
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> Result<String, AppError> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = value
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| AppError::validation("The otpauth URI is not properly encoded"))?;
+                decoded.push(hex);
+                i += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| AppError::validation("The otpauth URI is not valid UTF-8"))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'@' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(secret: &[u8], algorithm: TotpAlgorithm) -> TotpConfig {
+        TotpConfig {
+            secret: secret.to_vec(),
+            algorithm,
+            digits: 8,
+            period: 30,
+            issuer: None,
+            account_name: None,
+        }
+    }
+
+    // Appendix B of RFC 6238, whose seeds are the ASCII digits repeated to
+    // the hash's length
+    #[test]
+    fn rfc_6238_vectors() {
+        let sha1 = config(b"12345678901234567890", TotpAlgorithm::Sha1);
+        let sha256 = config(b"12345678901234567890123456789012", TotpAlgorithm::Sha256);
+        let sha512 = config(
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+            TotpAlgorithm::Sha512,
+        );
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, expected_sha1, expected_sha256, expected_sha512) in vectors {
+            assert_eq!(sha1.code_at(time).code, expected_sha1, "SHA1 at {}", time);
+            assert_eq!(sha256.code_at(time).code, expected_sha256, "SHA256 at {}", time);
+            assert_eq!(sha512.code_at(time).code, expected_sha512, "SHA512 at {}", time);
+        }
+    }
+
+    #[test]
+    fn six_digit_codes_and_time_left() {
+        let code = TotpConfig { digits: 6, ..config(b"12345678901234567890", TotpAlgorithm::Sha1) }.code_at(59);
+        assert_eq!(code.code, "287082");
+        assert_eq!(code.seconds_remaining, 1);
+    }
+
+    #[test]
+    fn parses_and_round_trips_uris() {
+        let parsed = TotpConfig::parse(
+            "otpauth://totp/ACME%20Co:jane@example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&algorithm=SHA256&digits=8&period=60&issuer=ACME%20Co",
+        )
+        .unwrap();
+        assert_eq!(parsed.secret, b"12345678901234567890");
+        assert_eq!(parsed.algorithm, TotpAlgorithm::Sha256);
+        assert_eq!((parsed.digits, parsed.period), (8, 60));
+        assert_eq!(parsed.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!(parsed.account_name.as_deref(), Some("jane@example.com"));
+        assert_eq!(TotpConfig::parse(&parsed.to_uri()).unwrap(), parsed);
+
+        let bare = TotpConfig::parse("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap();
+        assert_eq!(bare.secret, b"12345678901234567890");
+        assert_eq!((bare.algorithm, bare.digits, bare.period), (TotpAlgorithm::Sha1, 6, 30));
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(TotpConfig::parse("GEZDGNBV").is_err());
+        assert!(TotpConfig::parse("not base32!").is_err());
+        assert!(TotpConfig::parse("otpauth://hotp/x?secret=GEZDGNBVGY3TQOJQ").is_err());
+        assert!(TotpConfig::parse("otpauth://totp/x?secret=GEZDGNBVGY3TQOJQ&digits=7").is_err());
+        assert!(TotpConfig::parse("otpauth://totp/x?secret=GEZDGNBVGY3TQOJQ&algorithm=MD5").is_err());
+        assert!(TotpConfig::parse("otpauth://totp/x?issuer=ACME").is_err());
+    }
+}
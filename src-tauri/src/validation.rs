@@ -1,14 +1,40 @@
 use crate::errors::AppError;
+use crate::models::*;
+use crate::passwords;
+use crate::scheduler::{MAX_RETENTION, MIN_RETENTION};
+use crate::totp::TotpConfig;
+
+// Most custom fields one vault entry can have
+pub const MAX_CUSTOM_FIELDS: usize = 50;
+
+// Longest time a flashcard answer can be logged as taking
+pub const MAX_REVIEW_DURATION_MS: i64 = 24 * 60 * 60 * 1000;
+
+// Allowed values for the enumerated string columns
+pub const PROJECT_STATUSES: &[&str] = &["active", "on-hold", "completed", "cancelled"];
+pub const TASK_STATUSES: &[&str] = &["todo", "in-progress", "completed", "blocked"];
+pub const EVENT_TYPES: &[&str] = &["meeting", "task", "reminder", "appointment", "event"];
 
 // Trait for validation
 pub trait Validator {
     fn validate(&self) -> Result<(), AppError>;
 }
 
+// Tags a failed check with the request field it applies to
+pub trait FieldResult {
+    fn field(self, name: &str) -> Self;
+}
+
+impl<T> FieldResult for Result<T, AppError> {
+    fn field(self, name: &str) -> Self {
+        self.map_err(|e| e.with_field(name))
+    }
+}
+
 // Validation functions
 pub fn validate_not_empty(value: &str, field_name: &str) -> Result<(), AppError> {
     if value.trim().is_empty() {
-        return Err(AppError::Validation(format!("{} cannot be empty", field_name)));
+        return Err(AppError::validation(format!("{} cannot be empty", field_name)));
     }
     Ok(())
 }
@@ -16,36 +42,44 @@ pub fn validate_not_empty(value: &str, field_name: &str) -> Result<(), AppError>
 pub fn validate_string_length(value: &str, field_name: &str, min: usize, max: usize) -> Result<(), AppError> {
     let len = value.len();
     if len < min || len > max {
-        return Err(AppError::Validation(format!("{} must be between {} and {} characters", field_name, min, max)));
+        return Err(AppError::validation(format!("{} must be between {} and {} characters", field_name, min, max)));
     }
     Ok(())
 }
 
 pub fn validate_difficulty_level(level: i64) -> Result<(), AppError> {
-    if level < 1 || level > 5 {
-        return Err(AppError::Validation("Difficulty level must be between 1 and 5".to_string()));
+    if !(1..=5).contains(&level) {
+        return Err(AppError::validation("Difficulty level must be between 1 and 5".to_string()));
     }
     Ok(())
 }
 
 pub fn validate_quality_rating(rating: i64) -> Result<(), AppError> {
-    if rating < 1 || rating > 5 {
-        return Err(AppError::Validation("Quality rating must be between 1 and 5".to_string()));
+    if !(1..=5).contains(&rating) {
+        return Err(AppError::validation("Quality rating must be between 1 and 5".to_string()));
     }
     Ok(())
 }
 
 pub fn validate_date_format(date: &str) -> Result<(), AppError> {
-    // Simple date format validation - you can make this more sophisticated
-    if date.len() != 10 || !date.chars().all(|c| c.is_ascii_digit() || c == '-') {
-        return Err(AppError::Validation("Date must be in YYYY-MM-DD format".to_string()));
+    if date.len() != 10 || chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+        return Err(AppError::validation("Date must be in YYYY-MM-DD format".to_string()));
+    }
+    Ok(())
+}
+
+pub fn validate_time_format(time: &str) -> Result<(), AppError> {
+    let parsed = chrono::NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| chrono::NaiveTime::parse_from_str(time, "%H:%M:%S"));
+    if parsed.is_err() {
+        return Err(AppError::validation("Time must be in HH:MM format".to_string()));
     }
     Ok(())
 }
 
 pub fn validate_status(value: &str, allowed_values: &[&str]) -> Result<(), AppError> {
     if !allowed_values.contains(&value) {
-        return Err(AppError::Validation(format!("Status must be one of: {}", allowed_values.join(", "))));
+        return Err(AppError::validation(format!("Status must be one of: {}", allowed_values.join(", "))));
     }
     Ok(())
 }
@@ -56,29 +90,55 @@ pub fn sanitize_string(value: String) -> String {
 }
 
 pub fn sanitize_optional_string(value: Option<String>) -> Option<String> {
-    value.map(|v| v.trim().to_string())
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+// Optional form fields arrive as empty strings when left blank
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
 }
 
 pub fn validate_language_code(code: &str) -> Result<(), AppError> {
     if code.len() != 2 {
-        return Err(AppError::Validation("Language code must be exactly 2 characters".to_string()));
+        return Err(AppError::validation("Language code must be exactly 2 characters".to_string()));
     }
     if !code.chars().all(|c| c.is_ascii_lowercase()) {
-        return Err(AppError::Validation("Language code must be lowercase letters".to_string()));
+        return Err(AppError::validation("Language code must be lowercase letters".to_string()));
     }
     Ok(())
 }
 
 pub fn validate_email(email: &str) -> Result<(), AppError> {
     if !email.contains('@') || !email.contains('.') {
-        return Err(AppError::Validation("Invalid email format".to_string()));
+        return Err(AppError::validation("Invalid email format".to_string()));
+    }
+    Ok(())
+}
+
+pub fn validate_password_length(password: &str) -> Result<(), AppError> {
+    if password.chars().count() > passwords::MAX_PASSWORD_LENGTH {
+        return Err(AppError::validation(format!(
+            "Password must be at most {} characters long",
+            passwords::MAX_PASSWORD_LENGTH
+        )));
     }
     Ok(())
 }
 
 pub fn validate_password_strength(password: &str) -> Result<(), AppError> {
     if password.len() < 8 {
-        return Err(AppError::Validation("Password must be at least 8 characters long".to_string()));
+        return Err(AppError::validation("Password must be at least 8 characters long".to_string()));
+    }
+    validate_password_length(password)?;
+    let strength = passwords::estimate_strength(password);
+    if strength.score < 2 {
+        let mut message = format!("Password is too weak ({})", strength.label.to_lowercase());
+        if !strength.feedback.is_empty() {
+            message = format!("{}: {}", message, strength.feedback.join("; "));
+        }
+        return Err(AppError::validation(message));
     }
     Ok(())
 }
@@ -86,6 +146,361 @@ pub fn validate_password_strength(password: &str) -> Result<(), AppError> {
 pub fn validate_priority(priority: &str) -> Result<(), AppError> {
     match priority {
         "low" | "medium" | "high" => Ok(()),
-        _ => Err(AppError::Validation("Priority must be 'low', 'medium', or 'high'".to_string())),
+        _ => Err(AppError::validation("Priority must be 'low', 'medium', or 'high'".to_string())),
+    }
+}
+
+pub fn validate_event_type(event_type: &str) -> Result<(), AppError> {
+    if !EVENT_TYPES.contains(&event_type) {
+        return Err(AppError::validation(format!("Event type must be one of: {}", EVENT_TYPES.join(", "))));
+    }
+    Ok(())
+}
+
+// Request validation
+impl Validator for CreateLanguageRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.name, "Language name").field("name")?;
+        validate_string_length(self.name.trim(), "Language name", 1, 50).field("name")?;
+        validate_language_code(self.code.trim()).field("code")?;
+        validate_not_empty(&self.flag_emoji, "Flag emoji").field("flag_emoji")?;
+        Ok(())
+    }
+}
+
+impl Validator for CreateVocabularyRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.word, "Word").field("word")?;
+        validate_string_length(self.word.trim(), "Word", 1, 200).field("word")?;
+        validate_not_empty(&self.translation, "Translation").field("translation")?;
+        validate_string_length(self.translation.trim(), "Translation", 1, 500).field("translation")?;
+        validate_difficulty_level(self.difficulty_level).field("difficulty_level")?;
+        Ok(())
+    }
+}
+
+impl Validator for FlashcardReviewRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_quality_rating(self.quality).field("quality")?;
+        if let Some(duration) = self.duration_ms {
+            if !(0..=MAX_REVIEW_DURATION_MS).contains(&duration) {
+                return Err(AppError::invalid_field("duration_ms", "Time taken must be between 0 and 24 hours"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validator for UpdateSchedulerSettingsRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        if !self.desired_retention.is_finite() || !(MIN_RETENTION..=MAX_RETENTION).contains(&self.desired_retention) {
+            return Err(AppError::invalid_field(
+                "desired_retention",
+                format!("Desired retention must be between {} and {}", MIN_RETENTION, MAX_RETENTION),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Validator for CreateTechSpaceRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.name, "Tech space name").field("name")?;
+        validate_string_length(self.name.trim(), "Tech space name", 1, 100).field("name")?;
+        validate_not_empty(&self.icon, "Icon").field("icon")?;
+        Ok(())
+    }
+}
+
+impl Validator for CreateCodeSnippetRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.title, "Title").field("title")?;
+        validate_string_length(self.title.trim(), "Title", 1, 200).field("title")?;
+        validate_not_empty(&self.code, "Code").field("code")?;
+        validate_not_empty(&self.language, "Language").field("language")?;
+        Ok(())
+    }
+}
+
+impl Validator for CreateProjectRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.name, "Project name").field("name")?;
+        validate_string_length(self.name.trim(), "Project name", 1, 200).field("name")?;
+        validate_status(&self.status, PROJECT_STATUSES).field("status")?;
+        validate_priority(&self.priority).field("priority")?;
+        if let Some(start_date) = non_empty(&self.start_date) {
+            validate_date_format(start_date).field("start_date")?;
+        }
+        if let Some(end_date) = non_empty(&self.end_date) {
+            validate_date_format(end_date).field("end_date")?;
+        }
+        if let (Some(start), Some(end)) = (non_empty(&self.start_date), non_empty(&self.end_date)) {
+            if end < start {
+                return Err(AppError::invalid_field("end_date", "End date cannot be before start date"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validator for CreateTaskRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.title, "Task title").field("title")?;
+        validate_string_length(self.title.trim(), "Task title", 1, 200).field("title")?;
+        validate_status(&self.status, TASK_STATUSES).field("status")?;
+        validate_priority(&self.priority).field("priority")?;
+        if let Some(due_date) = non_empty(&self.due_date) {
+            validate_date_format(due_date).field("due_date")?;
+        }
+        Ok(())
+    }
+}
+
+impl Validator for CreateEventRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.title, "Event title").field("title")?;
+        validate_string_length(self.title.trim(), "Event title", 1, 200).field("title")?;
+        validate_date_format(&self.event_date).field("event_date")?;
+        if let Some(start_time) = non_empty(&self.start_time) {
+            validate_time_format(start_time).field("start_time")?;
+        }
+        if let Some(end_time) = non_empty(&self.end_time) {
+            validate_time_format(end_time).field("end_time")?;
+        }
+        validate_event_type(&self.event_type).field("event_type")?;
+        validate_priority(&self.priority).field("priority")?;
+        Ok(())
     }
 }
+
+impl Validator for CreateNoteRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.title, "Note title").field("title")?;
+        validate_string_length(self.title.trim(), "Note title", 1, 200).field("title")?;
+        validate_not_empty(&self.content, "Note content").field("content")?;
+        validate_date_format(&self.note_date).field("note_date")?;
+        Ok(())
+    }
+}
+
+impl Validator for CreatePersonalAccountRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_account(
+            &self.title,
+            &self.email,
+            Some(&self.password),
+            &self.category,
+            self.totp_secret.as_deref(),
+            &self.custom_fields,
+        )
+    }
+}
+
+impl Validator for UpdatePersonalAccountRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_account(
+            &self.title,
+            &self.email,
+            self.password.as_deref(),
+            &self.category,
+            self.totp_secret.as_deref(),
+            &self.custom_fields,
+        )
+    }
+}
+
+fn validate_account(
+    title: &str,
+    email: &str,
+    password: Option<&str>,
+    category: &str,
+    totp_secret: Option<&str>,
+    custom_fields: &[CustomFieldInput],
+) -> Result<(), AppError> {
+    validate_not_empty(title, "Account title").field("title")?;
+    validate_string_length(title.trim(), "Account title", 1, 200).field("title")?;
+    // Holds a username for sites that don't log in by email
+    validate_not_empty(email, "Email or username").field("email")?;
+    if email.contains('@') {
+        validate_email(email.trim()).field("email")?;
+    }
+    if let Some(password) = password {
+        validate_not_empty(password, "Password").field("password")?;
+        validate_password_length(password).field("password")?;
+    }
+    validate_not_empty(category, "Category").field("category")?;
+    if let Some(secret) = totp_secret.filter(|s| !s.trim().is_empty()) {
+        TotpConfig::parse(secret).field("totp_secret")?;
+    }
+    validate_custom_fields(custom_fields).field("custom_fields")?;
+    Ok(())
+}
+
+impl Validator for CreatePersonalInfoRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.title, "Info title").field("title")?;
+        validate_string_length(self.title.trim(), "Info title", 1, 200).field("title")?;
+        validate_not_empty(&self.content, "Content").field("content")?;
+        validate_not_empty(&self.category, "Category").field("category")?;
+        validate_custom_fields(&self.custom_fields).field("custom_fields")?;
+        Ok(())
+    }
+}
+
+impl Validator for CreateWorkspaceRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        validate_not_empty(&self.name, "Workspace name").field("name")?;
+        validate_string_length(self.name.trim(), "Workspace name", 1, 100).field("name")?;
+        validate_not_empty(&self.path, "Workspace path").field("path")?;
+        if !std::path::Path::new(self.path.trim()).is_absolute() {
+            return Err(AppError::invalid_field("path", "Workspace path must be absolute"));
+        }
+        Ok(())
+    }
+}
+
+impl Validator for BackupSettings {
+    fn validate(&self) -> Result<(), AppError> {
+        if let Some(folder) = non_empty(&self.folder) {
+            if !std::path::Path::new(folder).is_absolute() {
+                return Err(AppError::invalid_field("folder", "Backup folder must be an absolute path"));
+            }
+        }
+        if !(0..=24 * 30).contains(&self.interval_hours) {
+            return Err(AppError::invalid_field("interval_hours", "Backup interval must be between 0 and 720 hours"));
+        }
+        if !(0..=365).contains(&self.keep_daily) {
+            return Err(AppError::invalid_field("keep_daily", "Daily snapshots to keep must be between 0 and 365"));
+        }
+        if !(0..=520).contains(&self.keep_weekly) {
+            return Err(AppError::invalid_field("keep_weekly", "Weekly snapshots to keep must be between 0 and 520"));
+        }
+        Ok(())
+    }
+}
+
+impl Validator for TrashSettings {
+    fn validate(&self) -> Result<(), AppError> {
+        if !(0..=3650).contains(&self.retention_days) {
+            return Err(AppError::invalid_field("retention_days", "Trash retention must be between 0 and 3650 days"));
+        }
+        Ok(())
+    }
+}
+
+impl Validator for PasswordPolicy {
+    fn validate(&self) -> Result<(), AppError> {
+        match self {
+            PasswordPolicy::Random { length, lowercase, uppercase, digits, symbols, .. } => {
+                if !(8..=128).contains(length) {
+                    return Err(AppError::invalid_field("length", "Length must be between 8 and 128 characters"));
+                }
+                if !(*lowercase || *uppercase || *digits || *symbols) {
+                    return Err(AppError::validation("Select at least one character set"));
+                }
+            }
+            PasswordPolicy::Passphrase { words, separator, .. } => {
+                if !(3..=20).contains(words) {
+                    return Err(AppError::invalid_field("words", "A passphrase must have between 3 and 20 words"));
+                }
+                if separator.chars().count() > 3 {
+                    return Err(AppError::invalid_field("separator", "Separator must be at most 3 characters"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Validator for VaultAuditRequest {
+    fn validate(&self) -> Result<(), AppError> {
+        if !(1..=240).contains(&self.stale_after_months) {
+            return Err(AppError::invalid_field("stale_after_months", "Must be between 1 and 240 months"));
+        }
+        Ok(())
+    }
+}
+
+impl Validator for VaultSettings {
+    fn validate(&self) -> Result<(), AppError> {
+        if !(0..=24 * 60).contains(&self.auto_lock_minutes) {
+            return Err(AppError::invalid_field("auto_lock_minutes", "Auto-lock must be between 0 and 1440 minutes"));
+        }
+        Ok(())
+    }
+}
+
+// Each field is checked by its type, with errors naming the field's label
+pub fn validate_custom_fields(fields: &[CustomFieldInput]) -> Result<(), AppError> {
+    if fields.len() > MAX_CUSTOM_FIELDS {
+        return Err(AppError::validation(format!("An entry can have at most {} custom fields", MAX_CUSTOM_FIELDS)));
+    }
+    for field in fields {
+        validate_not_empty(&field.label, "Field label")?;
+        validate_string_length(field.label.trim(), "Field label", 1, 100)?;
+        let label = field.label.trim();
+        let value = normalize_custom_value(field.field_type, &field.value);
+        validate_not_empty(&value, label)?;
+        validate_string_length(&value, label, 1, 10_000)?;
+
+        let valid = match field.field_type {
+            CustomFieldType::Text | CustomFieldType::Hidden => true,
+            CustomFieldType::Url => is_url(&value),
+            CustomFieldType::Date => validate_date_format(&value).is_ok(),
+            CustomFieldType::Number => value.parse::<f64>().is_ok_and(f64::is_finite),
+            CustomFieldType::CardNumber => {
+                (12..=19).contains(&value.len()) && value.bytes().all(|b| b.is_ascii_digit()) && luhn_valid(&value)
+            }
+        };
+        if !valid {
+            let expected = match field.field_type {
+                CustomFieldType::Url => "must be a URL such as https://example.com",
+                CustomFieldType::Date => "must be a date in YYYY-MM-DD format",
+                CustomFieldType::Number => "must be a number",
+                _ => "is not a valid card number",
+            };
+            return Err(AppError::validation(format!("{} {}", label, expected)));
+        }
+    }
+    Ok(())
+}
+
+// Card numbers are kept as bare digits; every other value is trimmed
+pub fn normalize_custom_value(field_type: CustomFieldType, value: &str) -> String {
+    match field_type {
+        CustomFieldType::CardNumber => value.chars().filter(|c| !c.is_whitespace() && *c != '-').collect(),
+        _ => value.trim().to_string(),
+    }
+}
+
+// A scheme, "://" and something after it, e.g. https://example.com or ftp://host
+fn is_url(value: &str) -> bool {
+    match value.split_once("://") {
+        Some((scheme, rest)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+                && !rest.is_empty()
+                && !value.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+// The Luhn checksum every payment card number carries in its last digit
+fn luhn_valid(digits: &str) -> bool {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(i, b)| {
+            let digit = u32::from(b - b'0');
+            if i % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
@@ -1,5 +1,29 @@
 import { invoke } from '@tauri-apps/api/core';
 
+// Error interfaces
+// Every command rejects with this shape
+export type AppErrorKind =
+  | 'database'
+  | 'validation'
+  | 'not_found'
+  | 'encryption'
+  | 'io'
+  | 'migration'
+  | 'vault'
+  | 'constraint';
+
+export interface AppError {
+  kind: AppErrorKind;
+  code: string;
+  field: string | null;
+  message: string;
+  details: Record<string, unknown> | null;
+}
+
+export const isAppError = (error: unknown): error is AppError => {
+  return typeof error === 'object' && error !== null && 'kind' in error && 'code' in error;
+};
+
 // Types
 export interface Language {
   id: number;
@@ -28,9 +52,45 @@ export interface Flashcard {
   repetitions: number;
   next_review: string;
   last_reviewed?: string;
+  // FSRS memory state, absent until reviewed under FSRS
+  stability?: number;
+  difficulty?: number;
   created_at: string;
 }
 
+// One logged review; `prev_` values are absent for reviews logged before they
+// were recorded
+export interface ReviewLogEntry {
+  id: number;
+  flashcard_id: number;
+  reviewed_at: string;
+  quality: number;
+  duration_ms?: number;
+  elapsed_days?: number;
+  prev_ease_factor?: number;
+  new_ease_factor?: number;
+  prev_interval_days?: number;
+  new_interval_days?: number;
+}
+
+export type SchedulerKind = 'sm2' | 'fsrs';
+
+export interface SchedulerSettings {
+  language_id: number;
+  scheduler: SchedulerKind;
+  desired_retention: number;
+  fsrs_weights?: number[];
+  fitted_at?: string;
+}
+
+export interface FsrsFitReport {
+  settings: SchedulerSettings;
+  cards: number;
+  reviews: number;
+  log_loss_before: number;
+  log_loss_after: number;
+}
+
 export interface TechSpace {
   id: number;
   name: string;
@@ -80,6 +140,9 @@ export const vocabularyApi = {
   
   search: (query: string, limit?: number): Promise<Vocabulary[]> =>
     invoke('search_vocabulary', { query, limit }),
+  
+  delete: (id: number): Promise<void> =>
+    invoke('delete_vocabulary', { id }),
 };
 
 // Flashcard API
@@ -87,8 +150,30 @@ export const flashcardApi = {
   getDue: (limit?: number): Promise<[Flashcard, Vocabulary][]> =>
     invoke('get_due_flashcards', { limit }),
   
-  review: (req: { flashcard_id: number; quality: number }): Promise<void> =>
+  review: (req: { flashcard_id: number; quality: number; duration_ms?: number }): Promise<void> =>
     invoke('review_flashcard', { req }),
+
+  history: (flashcardId: number): Promise<ReviewLogEntry[]> =>
+    invoke('get_flashcard_history', { flashcardId }),
+
+  // Without a card, undoes the most recent review of any card
+  undoLastReview: (flashcardId?: number): Promise<Flashcard> =>
+    invoke('undo_last_review', { flashcardId }),
+};
+
+// Scheduler API
+export const schedulerApi = {
+  get: (languageId: number): Promise<SchedulerSettings> =>
+    invoke('get_scheduler_settings', { languageId }),
+
+  update: (
+    languageId: number,
+    req: { scheduler: SchedulerKind; desired_retention: number; reset_weights?: boolean }
+  ): Promise<SchedulerSettings> =>
+    invoke('update_scheduler_settings', { languageId, req }),
+
+  fitFsrs: (languageId: number): Promise<FsrsFitReport> =>
+    invoke('fit_fsrs_parameters', { languageId }),
 };
 
 // Tech Space API
@@ -104,6 +189,10 @@ export const getTechSpaces = async (): Promise<TechSpace[]> => {
   return await invoke('get_tech_spaces');
 };
 
+export const deleteTechSpace = async (id: number): Promise<void> => {
+  return await invoke('delete_tech_space', { id });
+};
+
 // Code Snippet API
 export const createCodeSnippet = async (data: {
   tech_space_id: number;
@@ -116,6 +205,17 @@ export const createCodeSnippet = async (data: {
   return await invoke('create_code_snippet', { req: data });
 };
 
+export const updateCodeSnippet = async (id: number, data: {
+  tech_space_id: number;
+  title: string;
+  description?: string;
+  code: string;
+  language: string;
+  tags?: string;
+}): Promise<CodeSnippet> => {
+  return await invoke('update_code_snippet', { id, req: data });
+};
+
 export const getCodeSnippetsByTechSpace = async (techSpaceId: number): Promise<CodeSnippet[]> => {
   return await invoke('get_code_snippets_by_tech_space', { techSpaceId });
 };
@@ -124,6 +224,10 @@ export const searchCodeSnippets = async (query: string, limit?: number): Promise
   return await invoke('search_code_snippets', { query, limit });
 };
 
+export const deleteCodeSnippet = async (id: number): Promise<void> => {
+  return await invoke('delete_code_snippet', { id });
+};
+
 // Project interfaces
 export interface Project {
   id: number;
@@ -162,10 +266,25 @@ export const createProject = async (data: {
   return await invoke('create_project', { req: data });
 };
 
+export const updateProject = async (id: number, data: {
+  name: string;
+  description?: string;
+  status: string;
+  priority: string;
+  start_date?: string;
+  end_date?: string;
+}): Promise<Project> => {
+  return await invoke('update_project', { id, req: data });
+};
+
 export const getProjects = async (): Promise<Project[]> => {
   return await invoke('get_projects');
 };
 
+export const deleteProject = async (id: number): Promise<void> => {
+  return await invoke('delete_project', { id });
+};
+
 // Task API
 export const createTask = async (data: {
   project_id: number;
@@ -178,6 +297,17 @@ export const createTask = async (data: {
   return await invoke('create_task', { req: data });
 };
 
+export const updateTask = async (id: number, data: {
+  project_id: number;
+  title: string;
+  description?: string;
+  status: string;
+  priority: string;
+  due_date?: string;
+}): Promise<Task> => {
+  return await invoke('update_task', { id, req: data });
+};
+
 export const getTasksByProject = async (projectId: number): Promise<Task[]> => {
   return await invoke('get_tasks_by_project', { projectId });
 };
@@ -186,6 +316,10 @@ export const updateTaskStatus = async (taskId: number, status: string): Promise<
   return await invoke('update_task_status', { taskId, status });
 };
 
+export const deleteTask = async (id: number): Promise<void> => {
+  return await invoke('delete_task', { id });
+};
+
 // Planner interfaces
 export interface Event {
   id: number;
@@ -227,6 +361,10 @@ export const getEventsByDate = async (date: string): Promise<Event[]> => {
   return await invoke('get_events_by_date', { date });
 };
 
+export const deleteEvent = async (id: number): Promise<void> => {
+  return await invoke('delete_event', { id });
+};
+
 // Note API
 export const createNote = async (data: {
   title: string;
@@ -237,10 +375,23 @@ export const createNote = async (data: {
   return await invoke('create_note', { req: data });
 };
 
+export const updateNote = async (id: number, data: {
+  title: string;
+  content: string;
+  note_date: string;
+  tags?: string;
+}): Promise<Note> => {
+  return await invoke('update_note', { id, req: data });
+};
+
 export const getNotesByDate = async (date: string): Promise<Note[]> => {
   return await invoke('get_notes_by_date', { date });
 };
 
+export const deleteNote = async (id: number): Promise<void> => {
+  return await invoke('delete_note', { id });
+};
+
 // Personal Vault interfaces
 export interface PersonalAccount {
   id: number;
@@ -250,20 +401,97 @@ export interface PersonalAccount {
   website?: string;
   notes?: string;
   category: string;
+  password_strength?: number;
+  totp_secret?: string;
+  custom_fields: CustomField[];
   created_at: string;
   updated_at: string;
 }
 
+// Lists leave out passwords, 2FA secrets and sensitive content; fetch them
+// one at a time with revealSecret
+export interface PersonalAccountSummary {
+  id: number;
+  title: string;
+  email: string;
+  website?: string;
+  notes?: string;
+  category: string;
+  password_strength?: number;
+  has_totp: boolean;
+  custom_fields: CustomField[];
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PasswordHistoryEntry {
+  id: number;
+  account_id: number;
+  replaced_at: string;
+}
+
 export interface PersonalInfo {
   id: number;
   title: string;
   content: string;
   category: string;
   is_sensitive: boolean;
+  custom_fields: CustomField[];
   created_at: string;
   updated_at: string;
 }
 
+export interface PersonalInfoSummary {
+  id: number;
+  title: string;
+  content?: string;
+  category: string;
+  is_sensitive: boolean;
+  attachment_count: number;
+  custom_fields: CustomField[];
+  created_at: string;
+  updated_at: string;
+}
+
+export type CustomFieldType = 'text' | 'hidden' | 'url' | 'date' | 'number' | 'card_number';
+
+// Hidden and card number values are left out of lists; card numbers come
+// with their last four digits as `hint`
+export interface CustomField {
+  id: number;
+  label: string;
+  field_type: CustomFieldType;
+  value?: string;
+  hint?: string;
+}
+
+export interface CustomFieldInput {
+  id?: number;
+  label: string;
+  field_type: CustomFieldType;
+  value: string;
+}
+
+export interface InfoAttachment {
+  id: number;
+  info_id: number;
+  file_name: string;
+  size_bytes: number;
+  created_at: string;
+}
+
+// The id is an account's for password and totp_secret, a password history
+// entry's for previous_password and a personal info entry's for content
+export type SecretField = 'password' | 'totp_secret' | 'previous_password' | 'content' | 'attachment' | 'custom_field' | 'bundled_account' | 'bundled_info';
+
+export interface SecretAccess {
+  id: number;
+  field: SecretField;
+  item_id: number;
+  title: string;
+  accessed_at: string;
+}
+
 // Personal Account API
 export const createPersonalAccount = async (data: {
   title: string;
@@ -272,32 +500,528 @@ export const createPersonalAccount = async (data: {
   website?: string;
   notes?: string;
   category: string;
+  totp_secret?: string;
+  custom_fields: CustomFieldInput[];
 }): Promise<PersonalAccount> => {
   return await invoke('create_personal_account', { req: data });
 };
 
-export const getPersonalAccounts = async (): Promise<PersonalAccount[]> => {
+export const getPersonalAccounts = async (): Promise<PersonalAccountSummary[]> => {
   return await invoke('get_personal_accounts');
 };
 
-export const getPersonalAccountsByCategory = async (category: string): Promise<PersonalAccount[]> => {
+export const getPersonalAccountsByCategory = async (category: string): Promise<PersonalAccountSummary[]> => {
   return await invoke('get_personal_accounts_by_category', { category });
 };
 
+// Without a password the account keeps its current one
+export const updatePersonalAccount = async (id: number, data: {
+  title: string;
+  email: string;
+  password?: string;
+  website?: string;
+  notes?: string;
+  category: string;
+  totp_secret?: string;
+  custom_fields: CustomFieldInput[];
+}): Promise<PersonalAccount> => {
+  return await invoke('update_personal_account', { id, req: data });
+};
+
+export const getPasswordHistory = async (accountId: number): Promise<PasswordHistoryEntry[]> => {
+  return await invoke('get_password_history', { accountId });
+};
+
+export const restorePassword = async (accountId: number, historyId: number): Promise<PersonalAccountSummary> => {
+  return await invoke('restore_password', { accountId, historyId });
+};
+
+// Decrypts one value; every call is recorded in the access log
+export const revealSecret = async (id: number, field: SecretField): Promise<string> => {
+  return await invoke('reveal_secret', { id, field });
+};
+
+export const listSecretAccess = async (limit?: number): Promise<SecretAccess[]> => {
+  return await invoke('list_secret_access', { limit });
+};
+
+export const addInfoAttachment = async (infoId: number, path: string): Promise<InfoAttachment> => {
+  return await invoke('add_info_attachment', { infoId, path });
+};
+
+export const listInfoAttachments = async (infoId: number): Promise<InfoAttachment[]> => {
+  return await invoke('list_info_attachments', { infoId });
+};
+
+// Returns the path of the file written
+export const exportInfoAttachment = async (id: number, path: string): Promise<string> => {
+  return await invoke('export_info_attachment', { id, path });
+};
+
+export const deleteInfoAttachment = async (id: number): Promise<void> => {
+  return await invoke('delete_info_attachment', { id });
+};
+
+export const deletePersonalAccount = async (id: number): Promise<void> => {
+  return await invoke('delete_personal_account', { id });
+};
+
+// Vault audit interfaces
+export type AuditIssue = 'reused' | 'weak' | 'stale' | 'breached';
+
+export interface AccountAudit {
+  id: number;
+  title: string;
+  website?: string;
+  category: string;
+  issues: AuditIssue[];
+  strength: PasswordStrength;
+  reused_with: number[];
+  months_since_change: number;
+  breach_count?: number;
+}
+
+export interface VaultAuditReport {
+  total_accounts: number;
+  reused: number;
+  weak: number;
+  stale: number;
+  breached: number;
+  breach_list_checked: boolean;
+  accounts: AccountAudit[];
+}
+
+// Vault audit API
+export const auditVault = async (options: {
+  stale_after_months?: number;
+  breach_list_path?: string;
+} = {}): Promise<VaultAuditReport> => {
+  return await invoke('audit_vault', { req: options });
+};
+
+// Account import interfaces
+export type AccountImportFormat =
+  | 'bitwarden_json'
+  | 'bitwarden_csv'
+  | 'keepass_xml'
+  | 'keepass_csv'
+  | 'one_password_csv'
+  | 'chromium_csv'
+  | 'firefox_csv';
+
+export interface ImportedAccount {
+  row: number;
+  title: string;
+  username: string;
+  website?: string;
+  has_totp: boolean;
+}
+
+export interface SkippedImportRow {
+  row: number;
+  title?: string;
+  reason: string;
+  duplicate_of?: number;
+}
+
+export interface AccountImportReport {
+  dry_run: boolean;
+  total_rows: number;
+  imported: ImportedAccount[];
+  skipped: SkippedImportRow[];
+}
+
+// Account import API
+export const importAccounts = async (data: {
+  path: string;
+  format: AccountImportFormat;
+  category: string;
+  dry_run?: boolean;
+}): Promise<AccountImportReport> => {
+  return await invoke('import_accounts', { req: data });
+};
+
+// Vault bundle interfaces
+// Public keys are X25519 keys in age's encoding (`age1…`)
+export type BundleRecipients =
+  | { kind: 'passphrase'; passphrase: string }
+  | { kind: 'public_keys'; public_keys: string[] };
+
+export type BundleUnlock =
+  | { kind: 'passphrase'; passphrase: string }
+  | { kind: 'secret_key'; secret_key: string };
+
+export type BundleConflict = 'skip' | 'replace' | 'keep_both';
+
+export interface BundleKeyPair {
+  public_key: string;
+  secret_key: string;
+}
+
+export interface VaultBundleExport {
+  path: string;
+  personal_accounts: number;
+  personal_info: number;
+}
+
+export interface BundleImportEntry {
+  kind: 'personal_account' | 'personal_info';
+  title: string;
+  outcome: 'added' | 'replaced' | 'skipped';
+  existing_id?: number;
+  reason?: string;
+}
+
+export interface VaultBundleImportReport {
+  dry_run: boolean;
+  entries: BundleImportEntry[];
+}
+
+// Vault bundle API
+export const exportVaultBundle = async (data: {
+  path: string;
+  account_ids: number[];
+  info_ids: number[];
+  recipients: BundleRecipients;
+}): Promise<VaultBundleExport> => {
+  return await invoke('export_vault_bundle', { req: data });
+};
+
+export const importVaultBundle = async (data: {
+  path: string;
+  unlock: BundleUnlock;
+  conflict: BundleConflict;
+  dry_run?: boolean;
+}): Promise<VaultBundleImportReport> => {
+  return await invoke('import_vault_bundle', { req: data });
+};
+
+export const generateBundleKeyPair = async (): Promise<BundleKeyPair> => {
+  return await invoke('generate_bundle_key_pair');
+};
+
+// TOTP interfaces
+export interface TotpCode {
+  code: string;
+  seconds_remaining: number;
+  period: number;
+  digits: number;
+  algorithm: 'SHA1' | 'SHA256' | 'SHA512';
+}
+
+// TOTP API
+export const getTotpCode = async (id: number): Promise<TotpCode> => {
+  return await invoke('get_totp_code', { id });
+};
+
+// Password generator interfaces
+export type PasswordPolicy =
+  | {
+      kind: 'random';
+      length: number;
+      lowercase: boolean;
+      uppercase: boolean;
+      digits: boolean;
+      symbols: boolean;
+      exclude_ambiguous: boolean;
+    }
+  | {
+      kind: 'passphrase';
+      words: number;
+      separator: string;
+      capitalize: boolean;
+      include_number: boolean;
+    };
+
+export interface PasswordStrength {
+  score: number;
+  label: string;
+  entropy_bits: number;
+  feedback: string[];
+}
+
+export interface GeneratedPassword {
+  password: string;
+  strength: PasswordStrength;
+}
+
+// Password generator API
+export const generatePassword = async (policy: PasswordPolicy): Promise<GeneratedPassword> => {
+  return await invoke('generate_password', { policy });
+};
+
+export const estimatePasswordStrength = async (password: string): Promise<PasswordStrength> => {
+  return await invoke('estimate_password_strength', { password });
+};
+
 // Personal Info API
 export const createPersonalInfo = async (data: {
   title: string;
   content: string;
   category: string;
   is_sensitive: boolean;
+  custom_fields: CustomFieldInput[];
 }): Promise<PersonalInfo> => {
   return await invoke('create_personal_info', { req: data });
 };
 
-export const getPersonalInfo = async (): Promise<PersonalInfo[]> => {
+export const getPersonalInfo = async (): Promise<PersonalInfoSummary[]> => {
   return await invoke('get_personal_info');
 };
 
-export const getPersonalInfoByCategory = async (category: string): Promise<PersonalInfo[]> => {
+export const getPersonalInfoByCategory = async (category: string): Promise<PersonalInfoSummary[]> => {
   return await invoke('get_personal_info_by_category', { category });
 };
+
+export const updatePersonalInfo = async (id: number, data: {
+  title: string;
+  content: string;
+  category: string;
+  is_sensitive: boolean;
+  custom_fields: CustomFieldInput[];
+}): Promise<PersonalInfo> => {
+  return await invoke('update_personal_info', { id, req: data });
+};
+
+export const deletePersonalInfo = async (id: number): Promise<void> => {
+  return await invoke('delete_personal_info', { id });
+};
+
+// Vault interfaces
+export interface VaultStatus {
+  initialized: boolean;
+  unlocked: boolean;
+  database_encrypted: boolean;
+  database_encryption_available: boolean;
+}
+
+export interface VaultSettings {
+  auto_lock_minutes: number;
+}
+
+// Vault API
+export const getVaultStatus = async (): Promise<VaultStatus> => {
+  return await invoke('get_vault_status');
+};
+
+export const setupVault = async (masterPassword: string): Promise<void> => {
+  return await invoke('setup_vault', { masterPassword });
+};
+
+export const unlockVault = async (masterPassword: string): Promise<void> => {
+  return await invoke('unlock_vault', { masterPassword });
+};
+
+export const lockVault = async (): Promise<void> => {
+  return await invoke('lock_vault');
+};
+
+export const changeMasterPassword = async (currentPassword: string, newPassword: string): Promise<void> => {
+  return await invoke('change_master_password', { currentPassword, newPassword });
+};
+
+export const rotateVaultKey = async (masterPassword: string): Promise<void> => {
+  return await invoke('rotate_vault_key', { masterPassword });
+};
+
+export const encryptDatabase = async (masterPassword: string): Promise<void> => {
+  return await invoke('encrypt_database', { masterPassword });
+};
+
+export const decryptDatabase = async (masterPassword: string): Promise<void> => {
+  return await invoke('decrypt_database', { masterPassword });
+};
+
+export const getVaultSettings = async (): Promise<VaultSettings> => {
+  return await invoke('get_vault_settings');
+};
+
+export const updateVaultSettings = async (settings: VaultSettings): Promise<VaultSettings> => {
+  return await invoke('update_vault_settings', { settings });
+};
+
+// Workspace interfaces
+export interface Workspace {
+  id: number;
+  name: string;
+  path: string;
+  created_at: string;
+  last_opened_at?: string;
+  backup: BackupSettings;
+  trash: TrashSettings;
+  vault: VaultSettings;
+}
+
+// Workspace API
+export const createWorkspace = async (data: {
+  name: string;
+  path: string;
+}): Promise<Workspace> => {
+  return await invoke('create_workspace', { req: data });
+};
+
+export const listWorkspaces = async (): Promise<Workspace[]> => {
+  return await invoke('list_workspaces');
+};
+
+export const getCurrentWorkspace = async (): Promise<Workspace | null> => {
+  return await invoke('get_current_workspace');
+};
+
+export const getStartupError = async (): Promise<string | null> => {
+  return await invoke('get_startup_error');
+};
+
+export const openWorkspace = async (id: number): Promise<Workspace> => {
+  return await invoke('open_workspace', { id });
+};
+
+export const closeWorkspace = async (): Promise<void> => {
+  return await invoke('close_workspace');
+};
+
+export const renameWorkspace = async (id: number, name: string): Promise<Workspace> => {
+  return await invoke('rename_workspace', { id, name });
+};
+
+// Backup interfaces
+export interface BackupSettings {
+  folder?: string;
+  interval_hours: number;
+  keep_daily: number;
+  keep_weekly: number;
+}
+
+export interface BackupInfo {
+  file_name: string;
+  path: string;
+  created_at: string;
+  size_bytes: number;
+}
+
+// Backup API
+export const getBackupSettings = async (): Promise<BackupSettings> => {
+  return await invoke('get_backup_settings');
+};
+
+export const updateBackupSettings = async (settings: BackupSettings): Promise<BackupSettings> => {
+  return await invoke('update_backup_settings', { settings });
+};
+
+export const createBackup = async (): Promise<BackupInfo> => {
+  return await invoke('create_backup');
+};
+
+export const listBackups = async (): Promise<BackupInfo[]> => {
+  return await invoke('list_backups');
+};
+
+export const restoreBackup = async (fileName: string): Promise<void> => {
+  return await invoke('restore_backup', { fileName });
+};
+
+// Workspace archive interfaces
+export type ImportMode = 'merge' | 'replace';
+
+export interface ImportSummary {
+  languages: number;
+  vocabulary: number;
+  flashcards: number;
+  tech_spaces: number;
+  code_snippets: number;
+  projects: number;
+  tasks: number;
+  events: number;
+  notes: number;
+  personal_accounts: number;
+  personal_info: number;
+  merged_languages: number;
+  merged_tech_spaces: number;
+}
+
+// Workspace archive API
+export const exportWorkspace = async (path: string): Promise<void> => {
+  return await invoke('export_workspace', { path });
+};
+
+export const importWorkspace = async (path: string, mode: ImportMode): Promise<ImportSummary> => {
+  return await invoke('import_workspace', { path, mode });
+};
+
+// Trash interfaces
+export type TrashKind =
+  | 'language'
+  | 'vocabulary'
+  | 'tech_space'
+  | 'code_snippet'
+  | 'project'
+  | 'task'
+  | 'event'
+  | 'note'
+  | 'personal_account'
+  | 'personal_info';
+
+export interface TrashItem {
+  kind: TrashKind;
+  id: number;
+  title: string;
+  deleted_at: string;
+}
+
+export interface TrashSettings {
+  retention_days: number;
+}
+
+// Trash API
+export const listTrash = async (): Promise<TrashItem[]> => {
+  return await invoke('list_trash');
+};
+
+export const restoreTrashItem = async (kind: TrashKind, id: number): Promise<void> => {
+  return await invoke('restore_trash_item', { kind, id });
+};
+
+export const purgeTrashItem = async (kind: TrashKind, id: number): Promise<void> => {
+  return await invoke('purge_trash_item', { kind, id });
+};
+
+export const emptyTrash = async (): Promise<number> => {
+  return await invoke('empty_trash');
+};
+
+export const getTrashSettings = async (): Promise<TrashSettings> => {
+  return await invoke('get_trash_settings');
+};
+
+export const updateTrashSettings = async (settings: TrashSettings): Promise<TrashSettings> => {
+  return await invoke('update_trash_settings', { settings });
+};
+
+// Revision interfaces
+export type RevisionKind = 'code_snippet' | 'note' | 'task' | 'project';
+
+export interface Revision {
+  id: number;
+  kind: RevisionKind;
+  entity_id: number;
+  snapshot: CodeSnippet | Note | Task | Project;
+  created_at: string;
+}
+
+export interface DiffLine {
+  op: 'equal' | 'insert' | 'delete';
+  text: string;
+}
+
+// Revision API
+export const listRevisions = async (kind: RevisionKind, entityId: number): Promise<Revision[]> => {
+  return await invoke('list_revisions', { kind, entityId });
+};
+
+export const diffRevisions = async (fromId: number, toId: number): Promise<DiffLine[]> => {
+  return await invoke('diff_revisions', { fromId, toId });
+};
+
+export const restoreRevision = async (revisionId: number): Promise<void> => {
+  return await invoke('restore_revision', { revisionId });
+};
@@ -1,5 +1,8 @@
 import React, { useState, useEffect } from 'react';
-import { languageApi, vocabularyApi, flashcardApi, Language, Vocabulary, Flashcard } from '../api/tauri';
+import {
+  languageApi, vocabularyApi, flashcardApi, schedulerApi, isAppError,
+  Language, Vocabulary, Flashcard, SchedulerSettings, SchedulerKind,
+} from '../api/tauri';
 
 interface FlashcardWithVocab {
   flashcard: Flashcard;
@@ -13,6 +16,11 @@ export default function LanguagePage() {
   const [dueFlashcards, setDueFlashcards] = useState<FlashcardWithVocab[]>([]);
   const [currentFlashcard, setCurrentFlashcard] = useState<FlashcardWithVocab | null>(null);
   const [showAnswer, setShowAnswer] = useState(false);
+  const [shownAt, setShownAt] = useState<number>(Date.now());
+  const [lastReviewed, setLastReviewed] = useState<FlashcardWithVocab | null>(null);
+  const [schedulerSettings, setSchedulerSettings] = useState<SchedulerSettings | null>(null);
+  const [schedulerMessage, setSchedulerMessage] = useState<string | null>(null);
+  const [fitting, setFitting] = useState(false);
   const [activeTab, setActiveTab] = useState<'languages' | 'vocabulary' | 'flashcards'>('languages');
   
   // Form states
@@ -31,6 +39,7 @@ export default function LanguagePage() {
   useEffect(() => {
     if (selectedLanguage) {
       loadVocabulary(selectedLanguage.id);
+      loadSchedulerSettings(selectedLanguage.id);
     }
   }, [selectedLanguage]);
 
@@ -55,6 +64,43 @@ export default function LanguagePage() {
     }
   };
 
+  const loadSchedulerSettings = async (languageId: number) => {
+    try {
+      setSchedulerSettings(await schedulerApi.get(languageId));
+      setSchedulerMessage(null);
+    } catch (error) {
+      console.error('Failed to load scheduler settings:', error);
+    }
+  };
+
+  const handleUpdateScheduler = async (scheduler: SchedulerKind, desiredRetention: number) => {
+    if (!selectedLanguage) return;
+    try {
+      setSchedulerSettings(await schedulerApi.update(selectedLanguage.id, { scheduler, desired_retention: desiredRetention }));
+      setSchedulerMessage(null);
+    } catch (error) {
+      setSchedulerMessage(isAppError(error) ? error.message : 'Failed to update scheduler');
+    }
+  };
+
+  const handleFitFsrs = async () => {
+    if (!selectedLanguage) return;
+    setFitting(true);
+    try {
+      const report = await schedulerApi.fitFsrs(selectedLanguage.id);
+      setSchedulerSettings(report.settings);
+      setSchedulerMessage(
+        report.log_loss_after < report.log_loss_before
+          ? `Fitted to ${report.reviews} reviews of ${report.cards} cards (log loss ${report.log_loss_before.toFixed(3)} → ${report.log_loss_after.toFixed(3)})`
+          : `The current parameters already fit ${report.reviews} reviews best`
+      );
+    } catch (error) {
+      setSchedulerMessage(isAppError(error) ? error.message : 'Failed to fit parameters');
+    } finally {
+      setFitting(false);
+    }
+  };
+
   const loadDueFlashcards = async () => {
     try {
       const flashcards = await flashcardApi.getDue(20);
@@ -98,22 +144,46 @@ export default function LanguagePage() {
     }
   };
 
+  useEffect(() => {
+    setShownAt(Date.now());
+  }, [currentFlashcard]);
+
   const handleFlashcardReview = async (quality: number) => {
     if (!currentFlashcard) return;
     
     try {
-      await flashcardApi.review({ flashcard_id: currentFlashcard.flashcard.id, quality });
+      await flashcardApi.review({
+        flashcard_id: currentFlashcard.flashcard.id,
+        quality,
+        duration_ms: Date.now() - shownAt,
+      });
       
       // Remove current flashcard and move to next
       const remaining = dueFlashcards.filter(fc => fc.flashcard.id !== currentFlashcard.flashcard.id);
       setDueFlashcards(remaining);
       setCurrentFlashcard(remaining.length > 0 ? remaining[0] : null);
+      setLastReviewed(currentFlashcard);
       setShowAnswer(false);
     } catch (error) {
       console.error('Failed to review flashcard:', error);
     }
   };
 
+  const handleUndoReview = async () => {
+    if (!lastReviewed) return;
+
+    try {
+      const flashcard = await flashcardApi.undoLastReview(lastReviewed.flashcard.id);
+      const restored = { flashcard, vocabulary: lastReviewed.vocabulary };
+      setDueFlashcards([restored, ...dueFlashcards]);
+      setCurrentFlashcard(restored);
+      setLastReviewed(null);
+      setShowAnswer(false);
+    } catch (error) {
+      console.error('Failed to undo review:', error);
+    }
+  };
+
   const renderLanguagesTab = () => (
     <div className="space-y-6">
       <div className="flex justify-between items-center">
@@ -194,6 +264,54 @@ export default function LanguagePage() {
           </div>
         ))}
       </div>
+
+      {selectedLanguage && schedulerSettings && (
+        <div className="card space-y-4">
+          <h4 className="font-medium">Review Scheduling - {selectedLanguage.name}</h4>
+          <div className="grid grid-cols-1 md:grid-cols-2 gap-4">
+            <div>
+              <label className="block text-sm font-medium mb-1">Scheduler</label>
+              <select
+                value={schedulerSettings.scheduler}
+                onChange={(e) => handleUpdateScheduler(e.target.value as SchedulerKind, schedulerSettings.desired_retention)}
+                className="input-field"
+              >
+                <option value="sm2">SM-2</option>
+                <option value="fsrs">FSRS</option>
+              </select>
+            </div>
+            {schedulerSettings.scheduler === 'fsrs' && (
+              <div>
+                <label className="block text-sm font-medium mb-1">
+                  Desired Retention ({Math.round(schedulerSettings.desired_retention * 100)}%)
+                </label>
+                <input
+                  type="range"
+                  min={0.7}
+                  max={0.97}
+                  step={0.01}
+                  value={schedulerSettings.desired_retention}
+                  onChange={(e) => handleUpdateScheduler('fsrs', parseFloat(e.target.value))}
+                  className="w-full"
+                />
+              </div>
+            )}
+          </div>
+          {schedulerSettings.scheduler === 'fsrs' && (
+            <div className="flex items-center justify-between">
+              <p className="text-sm text-notion-muted">
+                {schedulerSettings.fitted_at
+                  ? `Parameters fitted ${new Date(schedulerSettings.fitted_at).toLocaleDateString()}`
+                  : 'Using default parameters'}
+              </p>
+              <button onClick={handleFitFsrs} className="btn-secondary" disabled={fitting}>
+                {fitting ? 'Fitting…' : 'Fit from History'}
+              </button>
+            </div>
+          )}
+          {schedulerMessage && <p className="text-sm text-notion-muted">{schedulerMessage}</p>}
+        </div>
+      )}
     </div>
   );
 
@@ -311,9 +429,16 @@ export default function LanguagePage() {
     <div className="space-y-6">
       <div className="flex justify-between items-center">
         <h3 className="text-xl font-semibold">Flashcards Review</h3>
-        <span className="text-sm text-notion-muted">
-          {dueFlashcards.length} cards due
-        </span>
+        <div className="flex items-center gap-3">
+          {lastReviewed && (
+            <button onClick={handleUndoReview} className="btn-secondary text-sm">
+              Undo "{lastReviewed.vocabulary.word}"
+            </button>
+          )}
+          <span className="text-sm text-notion-muted">
+            {dueFlashcards.length} cards due
+          </span>
+        </div>
       </div>
 
       {currentFlashcard ? (
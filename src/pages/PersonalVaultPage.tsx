@@ -1,38 +1,136 @@
 import React, { useState, useEffect } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import {
-  PersonalAccount,
-  PersonalInfo,
+  PersonalAccountSummary,
+  PersonalInfoSummary,
+  SecretField,
+  SecretAccess,
+  VaultStatus,
+  PasswordStrength,
+  TotpCode,
+  VaultAuditReport,
+  AuditIssue,
+  PasswordHistoryEntry,
+  InfoAttachment,
+  CustomField,
+  CustomFieldInput,
+  CustomFieldType,
   createPersonalAccount,
+  updatePersonalAccount,
+  deletePersonalAccount,
   getPersonalAccounts,
   getPersonalAccountsByCategory,
+  getPasswordHistory,
+  restorePassword,
+  revealSecret,
+  listSecretAccess,
+  addInfoAttachment,
+  listInfoAttachments,
+  exportInfoAttachment,
+  deleteInfoAttachment,
   createPersonalInfo,
-  getPersonalInfo
+  updatePersonalInfo,
+  deletePersonalInfo,
+  getPersonalInfo,
+  getVaultStatus,
+  setupVault,
+  unlockVault,
+  lockVault,
+  encryptDatabase,
+  decryptDatabase,
+  generatePassword,
+  estimatePasswordStrength,
+  getTotpCode,
+  auditVault,
+  isAppError
 } from '../api/tauri';
 
+const AUDIT_ISSUE_LABELS: Record<AuditIssue, string> = {
+  reused: 'Reused',
+  weak: 'Weak',
+  stale: 'Old',
+  breached: 'Breached'
+};
+
+const EMPTY_ACCOUNT_FORM = {
+  title: '',
+  email: '',
+  password: '',
+  website: '',
+  notes: '',
+  category: 'email',
+  totp_secret: '',
+  custom_fields: [] as CustomFieldInput[]
+};
+
+const EMPTY_INFO_FORM = {
+  title: '',
+  content: '',
+  category: 'identity',
+  is_sensitive: false,
+  custom_fields: [] as CustomFieldInput[]
+};
+
+const SECRET_FIELD_LABELS: Record<SecretField, string> = {
+  password: 'Password',
+  totp_secret: '2FA secret',
+  previous_password: 'Previous password',
+  content: 'Content',
+  attachment: 'Attachment',
+  custom_field: 'Custom field',
+  bundled_account: 'Exported account',
+  bundled_info: 'Exported info'
+};
+
+const CUSTOM_FIELD_TYPES: { value: CustomFieldType; label: string }[] = [
+  { value: 'text', label: 'Text' },
+  { value: 'hidden', label: 'Hidden' },
+  { value: 'url', label: 'URL' },
+  { value: 'date', label: 'Date' },
+  { value: 'number', label: 'Number' },
+  { value: 'card_number', label: 'Card number' }
+];
+
+const isSecretField = (field: CustomField) => field.field_type === 'hidden' || field.field_type === 'card_number';
+
+const formatSize = (bytes: number) => {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+};
+
+const STRENGTH_COLORS = ['text-red-600', 'text-orange-600', 'text-yellow-600', 'text-green-600', 'text-green-700'];
+
 const PersonalVaultPage: React.FC = () => {
   const [activeTab, setActiveTab] = useState<'accounts' | 'info'>('accounts');
-  const [accounts, setAccounts] = useState<PersonalAccount[]>([]);
-  const [personalInfo, setPersonalInfo] = useState<PersonalInfo[]>([]);
+  const [accounts, setAccounts] = useState<PersonalAccountSummary[]>([]);
+  const [personalInfo, setPersonalInfo] = useState<PersonalInfoSummary[]>([]);
   const [selectedCategory, setSelectedCategory] = useState<string>('all');
-  const [showPassword, setShowPassword] = useState<{ [key: number]: boolean }>({});
+  // Secrets fetched with revealSecret, by secretKey(field, id)
+  const [revealed, setRevealed] = useState<{ [key: string]: string }>({});
+  const [accessLog, setAccessLog] = useState<SecretAccess[] | null>(null);
+  const [showDatabaseForm, setShowDatabaseForm] = useState(false);
+  const [databasePassword, setDatabasePassword] = useState('');
+  const [databaseError, setDatabaseError] = useState('');
+  const [vaultStatus, setVaultStatus] = useState<VaultStatus | null>(null);
+  const [masterPassword, setMasterPassword] = useState('');
+  const [vaultError, setVaultError] = useState('');
+  const [passwordStrength, setPasswordStrength] = useState<PasswordStrength | null>(null);
+  const [totpCodes, setTotpCodes] = useState<{ [key: number]: TotpCode }>({});
+  const [auditReport, setAuditReport] = useState<VaultAuditReport | null>(null);
+  const [passwordHistory, setPasswordHistory] = useState<{ [key: number]: PasswordHistoryEntry[] }>({});
+  const [attachments, setAttachments] = useState<{ [key: number]: InfoAttachment[] }>({});
+  const [attachPath, setAttachPath] = useState('');
+  const [exportFolder, setExportFolder] = useState('');
+  const [attachmentError, setAttachmentError] = useState('');
   
   // Form states
   const [showAccountForm, setShowAccountForm] = useState(false);
   const [showInfoForm, setShowInfoForm] = useState(false);
-  const [accountForm, setAccountForm] = useState({
-    title: '',
-    email: '',
-    password: '',
-    website: '',
-    notes: '',
-    category: 'email'
-  });
-  const [infoForm, setInfoForm] = useState({
-    title: '',
-    content: '',
-    category: 'identity',
-    is_sensitive: false
-  });
+  const [editingAccountId, setEditingAccountId] = useState<number | null>(null);
+  const [editingInfoId, setEditingInfoId] = useState<number | null>(null);
+  const [accountForm, setAccountForm] = useState(EMPTY_ACCOUNT_FORM);
+  const [infoForm, setInfoForm] = useState(EMPTY_INFO_FORM);
 
   const accountCategories = [
     { value: 'email', label: 'Email', icon: '📧' },
@@ -54,23 +152,101 @@ const PersonalVaultPage: React.FC = () => {
   ];
 
   useEffect(() => {
-    loadAccounts();
-    loadPersonalInfo();
+    loadVaultStatus();
+    // Sent by the backend when the vault auto-locks after being idle
+    const unlisten = listen('vault-locked', () => handleLocked());
+    return () => {
+      unlisten.then((stop) => stop());
+    };
   }, []);
 
   useEffect(() => {
+    if (!vaultStatus?.unlocked) {
+      return;
+    }
+    loadPersonalInfo();
     if (selectedCategory === 'all') {
       loadAccounts();
     } else {
       loadAccountsByCategory(selectedCategory);
     }
-  }, [selectedCategory]);
+  }, [vaultStatus, selectedCategory]);
+
+  // Count shown 2FA codes down and fetch the next one when the period ends
+  useEffect(() => {
+    const ids = Object.keys(totpCodes).map(Number);
+    if (ids.length === 0) {
+      return;
+    }
+    const timer = setTimeout(() => {
+      ids.forEach((id) => {
+        if (totpCodes[id].seconds_remaining <= 1) {
+          loadTotpCode(id);
+        } else {
+          setTotpCodes(prev => prev[id] ? { ...prev, [id]: { ...prev[id], seconds_remaining: prev[id].seconds_remaining - 1 } } : prev);
+        }
+      });
+    }, 1000);
+    return () => clearTimeout(timer);
+  }, [totpCodes]);
+
+  const loadVaultStatus = async () => {
+    try {
+      setVaultStatus(await getVaultStatus());
+    } catch (error) {
+      console.error('Failed to load vault status:', error);
+    }
+  };
+
+  // Drop every decrypted value as soon as the vault locks
+  const handleLocked = () => {
+    setAccounts([]);
+    setPersonalInfo([]);
+    setRevealed({});
+    setAccessLog(null);
+    setTotpCodes({});
+    setPasswordHistory({});
+    setAttachments({});
+    setAuditReport(null);
+    setVaultStatus((status) => status && { ...status, unlocked: false });
+  };
+
+  const isVaultLocked = (error: unknown) => isAppError(error) && error.code === 'vault_locked';
+
+  const handleLock = async () => {
+    try {
+      await lockVault();
+      handleLocked();
+    } catch (error) {
+      console.error('Failed to lock vault:', error);
+    }
+  };
+
+  const handleUnlock = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      if (vaultStatus?.initialized) {
+        await unlockVault(masterPassword);
+      } else {
+        await setupVault(masterPassword);
+      }
+      setMasterPassword('');
+      setVaultError('');
+      loadVaultStatus();
+    } catch (error) {
+      setVaultError(isAppError(error) ? error.message : String(error));
+    }
+  };
 
   const loadAccounts = async () => {
     try {
       const accountList = await getPersonalAccounts();
       setAccounts(accountList);
     } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
       console.error('Failed to load accounts:', error);
     }
   };
@@ -80,6 +256,10 @@ const PersonalVaultPage: React.FC = () => {
       const accountList = await getPersonalAccountsByCategory(category);
       setAccounts(accountList);
     } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
       console.error('Failed to load accounts by category:', error);
     }
   };
@@ -89,51 +269,478 @@ const PersonalVaultPage: React.FC = () => {
       const infoList = await getPersonalInfo();
       setPersonalInfo(infoList);
     } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
       console.error('Failed to load personal info:', error);
     }
   };
 
-  const handleCreateAccount = async (e: React.FormEvent) => {
+  const handleAudit = async () => {
+    try {
+      setAuditReport(await auditVault());
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to check password health:', error);
+    }
+  };
+
+  const loadTotpCode = async (accountId: number) => {
+    try {
+      const code = await getTotpCode(accountId);
+      setTotpCodes(prev => ({ ...prev, [accountId]: code }));
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to get 2FA code:', error);
+    }
+  };
+
+  const hideTotpCode = (accountId: number) => {
+    setTotpCodes(prev => {
+      const { [accountId]: _, ...rest } = prev;
+      return rest;
+    });
+  };
+
+  const handlePasswordChange = async (password: string) => {
+    setAccountForm(prev => ({ ...prev, password }));
+    setPasswordStrength(password ? await estimatePasswordStrength(password) : null);
+  };
+
+  const handleGeneratePassword = async () => {
+    try {
+      const generated = await generatePassword({
+        kind: 'random',
+        length: 20,
+        lowercase: true,
+        uppercase: true,
+        digits: true,
+        symbols: true,
+        exclude_ambiguous: true
+      });
+      setAccountForm(prev => ({ ...prev, password: generated.password }));
+      setPasswordStrength(generated.strength);
+    } catch (error) {
+      console.error('Failed to generate password:', error);
+    }
+  };
+
+  const handleSaveAccount = async (e: React.FormEvent) => {
     e.preventDefault();
     try {
-      await createPersonalAccount(accountForm);
-      setAccountForm({
-        title: '',
-        email: '',
-        password: '',
-        website: '',
-        notes: '',
-        category: 'email'
+      if (editingAccountId !== null) {
+        await updatePersonalAccount(editingAccountId, {
+          ...accountForm,
+          password: accountForm.password || undefined
+        });
+        hidePasswordHistory(editingAccountId);
+        setRevealed({});
+      } else {
+        await createPersonalAccount(accountForm);
+      }
+      closeAccountForm();
+      loadAccounts();
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to save account:', error);
+    }
+  };
+
+  // The password is left blank, and so unchanged, unless a new one is typed
+  const handleEditAccount = async (account: PersonalAccountSummary) => {
+    const totpSecret = account.has_totp ? await fetchSecret(account.id, 'totp_secret') : '';
+    const customFields = await editableCustomFields(account.custom_fields);
+    if (totpSecret === null || customFields === null) {
+      return;
+    }
+    setAccountForm({
+      title: account.title,
+      email: account.email,
+      password: '',
+      website: account.website || '',
+      notes: account.notes || '',
+      category: account.category,
+      totp_secret: totpSecret,
+      custom_fields: customFields
+    });
+    setPasswordStrength(null);
+    setEditingAccountId(account.id);
+    setShowAccountForm(true);
+  };
+
+  const closeAccountForm = () => {
+    setAccountForm(EMPTY_ACCOUNT_FORM);
+    setPasswordStrength(null);
+    setEditingAccountId(null);
+    setShowAccountForm(false);
+  };
+
+  const handleDeleteAccount = async (account: PersonalAccountSummary) => {
+    if (!confirm(`Move "${account.title}" to the trash?`)) {
+      return;
+    }
+    try {
+      await deletePersonalAccount(account.id);
+      loadAccounts();
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to delete account:', error);
+    }
+  };
+
+  const togglePasswordHistory = async (accountId: number) => {
+    if (passwordHistory[accountId]) {
+      hidePasswordHistory(accountId);
+      return;
+    }
+    try {
+      const history = await getPasswordHistory(accountId);
+      setPasswordHistory(prev => ({ ...prev, [accountId]: history }));
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to load password history:', error);
+    }
+  };
+
+  const toggleAttachments = async (infoId: number) => {
+    if (attachments[infoId]) {
+      setAttachments(prev => {
+        const { [infoId]: _, ...rest } = prev;
+        return rest;
       });
-      setShowAccountForm(false);
+      return;
+    }
+    await loadAttachments(infoId);
+  };
+
+  const loadAttachments = async (infoId: number) => {
+    try {
+      const files = await listInfoAttachments(infoId);
+      setAttachments(prev => ({ ...prev, [infoId]: files }));
+      setAttachmentError('');
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to load attachments:', error);
+    }
+  };
+
+  // Attachment changes also change the counts shown in the list
+  const runAttachmentAction = async (infoId: number, action: () => Promise<unknown>) => {
+    try {
+      await action();
+      setAttachmentError('');
+      await loadAttachments(infoId);
+      loadPersonalInfo();
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      setAttachmentError(isAppError(error) ? error.message : String(error));
+    }
+  };
+
+  const handleAddAttachment = (infoId: number) =>
+    runAttachmentAction(infoId, async () => {
+      await addInfoAttachment(infoId, attachPath);
+      setAttachPath('');
+    });
+
+  const handleExportAttachment = (attachment: InfoAttachment) =>
+    runAttachmentAction(attachment.info_id, () => exportInfoAttachment(attachment.id, exportFolder));
+
+  const handleDeleteAttachment = (attachment: InfoAttachment) => {
+    if (!confirm(`Delete "${attachment.file_name}"? It cannot be recovered.`)) {
+      return;
+    }
+    runAttachmentAction(attachment.info_id, () => deleteInfoAttachment(attachment.id));
+  };
+
+  const hidePasswordHistory = (accountId: number) => {
+    setPasswordHistory(prev => {
+      const { [accountId]: _, ...rest } = prev;
+      return rest;
+    });
+  };
+
+  const handleRestorePassword = async (accountId: number, historyId: number) => {
+    if (!confirm('Make this password current again? The current one will be kept in the history.')) {
+      return;
+    }
+    try {
+      await restorePassword(accountId, historyId);
+      setRevealed({});
+      const history = await getPasswordHistory(accountId);
+      setPasswordHistory(prev => ({ ...prev, [accountId]: history }));
       loadAccounts();
     } catch (error) {
-      console.error('Failed to create account:', error);
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to restore password:', error);
     }
   };
 
-  const handleCreateInfo = async (e: React.FormEvent) => {
+  const handleSaveInfo = async (e: React.FormEvent) => {
     e.preventDefault();
     try {
-      await createPersonalInfo(infoForm);
-      setInfoForm({
-        title: '',
-        content: '',
-        category: 'identity',
-        is_sensitive: false
-      });
-      setShowInfoForm(false);
+      if (editingInfoId !== null) {
+        await updatePersonalInfo(editingInfoId, infoForm);
+        setRevealed({});
+      } else {
+        await createPersonalInfo(infoForm);
+      }
+      closeInfoForm();
       loadPersonalInfo();
     } catch (error) {
-      console.error('Failed to create personal info:', error);
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to save personal info:', error);
+    }
+  };
+
+  const handleEditInfo = async (info: PersonalInfoSummary) => {
+    const content = info.content ?? await fetchSecret(info.id, 'content');
+    const customFields = await editableCustomFields(info.custom_fields);
+    if (content === null || customFields === null) {
+      return;
     }
+    setInfoForm({
+      title: info.title,
+      content,
+      category: info.category,
+      is_sensitive: info.is_sensitive,
+      custom_fields: customFields
+    });
+    setEditingInfoId(info.id);
+    setShowInfoForm(true);
   };
 
-  const togglePasswordVisibility = (accountId: number) => {
-    setShowPassword(prev => ({
-      ...prev,
-      [accountId]: !prev[accountId]
-    }));
+  const closeInfoForm = () => {
+    setInfoForm(EMPTY_INFO_FORM);
+    setEditingInfoId(null);
+    setShowInfoForm(false);
+  };
+
+  const handleDeleteInfo = async (info: PersonalInfoSummary) => {
+    if (!confirm(`Move "${info.title}" to the trash?`)) {
+      return;
+    }
+    try {
+      await deletePersonalInfo(info.id);
+      loadPersonalInfo();
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to delete personal info:', error);
+    }
+  };
+
+  const secretKey = (field: SecretField, id: number) => `${field}:${id}`;
+
+  // Every reveal is written to the vault's access log
+  const fetchSecret = async (id: number, field: SecretField): Promise<string | null> => {
+    try {
+      return await revealSecret(id, field);
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return null;
+      }
+      console.error('Failed to reveal secret:', error);
+      return null;
+    }
+  };
+
+  const toggleSecret = async (id: number, field: SecretField) => {
+    const key = secretKey(field, id);
+    if (key in revealed) {
+      setRevealed(prev => {
+        const { [key]: _, ...rest } = prev;
+        return rest;
+      });
+      return;
+    }
+    const value = await fetchSecret(id, field);
+    if (value !== null) {
+      setRevealed(prev => ({ ...prev, [key]: value }));
+    }
+  };
+
+  const copySecret = async (id: number, field: SecretField) => {
+    const value = revealed[secretKey(field, id)] ?? await fetchSecret(id, field);
+    if (value !== null) {
+      copyToClipboard(value);
+    }
+  };
+
+  // Hidden values are left out of lists, so they are revealed (and logged)
+  // before an entry can be edited
+  const editableCustomFields = async (fields: CustomField[]): Promise<CustomFieldInput[] | null> => {
+    const inputs: CustomFieldInput[] = [];
+    for (const field of fields) {
+      const value = field.value ?? await fetchSecret(field.id, 'custom_field');
+      if (value === null) {
+        return null;
+      }
+      inputs.push({ id: field.id, label: field.label, field_type: field.field_type, value });
+    }
+    return inputs;
+  };
+
+  const renderCustomFields = (fields: CustomField[]) => fields.map((field) => (
+    <div key={field.id}>
+      <label className="text-xs text-gray-500">{field.label}</label>
+      {isSecretField(field) ? (
+        <div className="flex items-center space-x-2">
+          <p className="text-sm font-mono bg-gray-50 px-2 py-1 rounded flex-1">
+            {revealed[secretKey('custom_field', field.id)]
+              ?? (field.field_type === 'card_number' ? `•••• ${field.hint ?? ''}` : '••••••••')}
+          </p>
+          <button
+            onClick={() => toggleSecret(field.id, 'custom_field')}
+            className="text-xs text-blue-600 hover:text-blue-800"
+          >
+            {secretKey('custom_field', field.id) in revealed ? 'Hide' : 'Show'}
+          </button>
+          <button
+            onClick={() => copySecret(field.id, 'custom_field')}
+            className="text-xs text-blue-600 hover:text-blue-800"
+          >
+            Copy
+          </button>
+        </div>
+      ) : (
+        <div className="flex items-center space-x-2">
+          <p className={`text-sm flex-1 ${field.field_type === 'url' ? 'text-blue-600' : 'text-gray-700'}`}>
+            {field.value}
+          </p>
+          <button
+            onClick={() => copyToClipboard(field.value ?? '')}
+            className="text-xs text-blue-600 hover:text-blue-800"
+          >
+            Copy
+          </button>
+        </div>
+      )}
+    </div>
+  ));
+
+  const renderCustomFieldsEditor = (fields: CustomFieldInput[], onChange: (fields: CustomFieldInput[]) => void) => {
+    const update = (index: number, change: Partial<CustomFieldInput>) =>
+      onChange(fields.map((field, i) => i === index ? { ...field, ...change } : field));
+    return (
+      <div>
+        <label className="block text-sm font-medium text-gray-700 mb-1">Custom Fields</label>
+        <div className="space-y-2">
+          {fields.map((field, index) => (
+            <div key={index} className="flex items-center space-x-2">
+              <input
+                type="text"
+                value={field.label}
+                onChange={(e) => update(index, { label: e.target.value })}
+                className="w-1/4 px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                placeholder="Label"
+                required
+              />
+              <select
+                value={field.field_type}
+                onChange={(e) => update(index, { field_type: e.target.value as CustomFieldType })}
+                className="px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+              >
+                {CUSTOM_FIELD_TYPES.map((type) => (
+                  <option key={type.value} value={type.value}>{type.label}</option>
+                ))}
+              </select>
+              <input
+                type={field.field_type === 'hidden' ? 'password' : field.field_type === 'date' ? 'date' : 'text'}
+                value={field.value}
+                onChange={(e) => update(index, { value: e.target.value })}
+                className="flex-1 px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                placeholder={field.field_type === 'url' ? 'https://' : field.field_type === 'card_number' ? '4111 1111 1111 1111' : ''}
+                required
+              />
+              <button
+                type="button"
+                onClick={() => onChange(fields.filter((_, i) => i !== index))}
+                className="text-xs text-red-600 hover:text-red-800"
+              >
+                Remove
+              </button>
+            </div>
+          ))}
+        </div>
+        <button
+          type="button"
+          onClick={() => onChange([...fields, { label: '', field_type: 'text', value: '' }])}
+          className="mt-2 text-xs text-blue-600 hover:text-blue-800"
+        >
+          + Add Field
+        </button>
+      </div>
+    );
+  };
+
+  const toggleAccessLog = async () => {
+    if (accessLog) {
+      setAccessLog(null);
+      return;
+    }
+    try {
+      setAccessLog(await listSecretAccess(50));
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      console.error('Failed to load access log:', error);
+    }
+  };
+
+  // Converts the whole database file; the master password confirms it
+  const handleDatabaseEncryption = async (e: React.FormEvent) => {
+    e.preventDefault();
+    try {
+      if (vaultStatus?.database_encrypted) {
+        await decryptDatabase(databasePassword);
+      } else {
+        await encryptDatabase(databasePassword);
+      }
+      setDatabasePassword('');
+      setDatabaseError('');
+      setShowDatabaseForm(false);
+      loadVaultStatus();
+    } catch (error) {
+      if (isVaultLocked(error)) {
+        handleLocked();
+        return;
+      }
+      setDatabaseError(isAppError(error) ? error.message : String(error));
+    }
   };
 
   const copyToClipboard = (text: string) => {
@@ -152,6 +759,41 @@ const PersonalVaultPage: React.FC = () => {
     return found ? found.label : category;
   };
 
+  if (vaultStatus && !vaultStatus.unlocked) {
+    return (
+      <div className="h-full flex items-center justify-center">
+        <form onSubmit={handleUnlock} className="bg-white rounded-lg border border-gray-200 p-6 w-full max-w-sm space-y-4">
+          <div>
+            <h1 className="text-xl font-semibold text-gray-900">🔐 Personal Vault</h1>
+            <p className="text-sm text-gray-600 mt-1">
+              {vaultStatus.database_encrypted
+                ? 'This workspace is encrypted. Enter your master password to open it.'
+                : vaultStatus.initialized
+                ? 'Enter your master password to unlock the vault'
+                : 'Choose a master password. It cannot be recovered if you forget it.'}
+            </p>
+          </div>
+          <input
+            type="password"
+            value={masterPassword}
+            onChange={(e) => setMasterPassword(e.target.value)}
+            className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+            placeholder="Master password"
+            autoFocus
+            required
+          />
+          {vaultError && <p className="text-sm text-red-600">{vaultError}</p>}
+          <button
+            type="submit"
+            className="w-full px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors"
+          >
+            {vaultStatus.initialized ? 'Unlock' : 'Create Vault'}
+          </button>
+        </form>
+      </div>
+    );
+  }
+
   return (
     <div className="h-full flex flex-col">
       {/* Header */}
@@ -161,6 +803,32 @@ const PersonalVaultPage: React.FC = () => {
           <p className="text-sm text-gray-600 mt-1">Secure storage for your personal information</p>
         </div>
         <div className="flex space-x-2">
+          <button
+            onClick={handleLock}
+            className="px-4 py-2 bg-gray-200 text-gray-700 rounded-lg hover:bg-gray-300 transition-colors"
+          >
+            🔒 Lock
+          </button>
+          <button
+            onClick={handleAudit}
+            className="px-4 py-2 bg-gray-200 text-gray-700 rounded-lg hover:bg-gray-300 transition-colors"
+          >
+            🩺 Password Health
+          </button>
+          <button
+            onClick={toggleAccessLog}
+            className="px-4 py-2 bg-gray-200 text-gray-700 rounded-lg hover:bg-gray-300 transition-colors"
+          >
+            🕵️ Access Log
+          </button>
+          {vaultStatus?.database_encryption_available && (
+            <button
+              onClick={() => setShowDatabaseForm(!showDatabaseForm)}
+              className="px-4 py-2 bg-gray-200 text-gray-700 rounded-lg hover:bg-gray-300 transition-colors"
+            >
+              🗄️ {vaultStatus.database_encrypted ? 'Decrypt Database' : 'Encrypt Database'}
+            </button>
+          )}
           <button
             onClick={() => setShowAccountForm(true)}
             className="px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors"
@@ -202,6 +870,75 @@ const PersonalVaultPage: React.FC = () => {
 
       {/* Content */}
       <div className="flex-1 p-6 overflow-auto">
+        {/* Database Encryption */}
+        {showDatabaseForm && (
+          <form
+            onSubmit={handleDatabaseEncryption}
+            className="bg-gray-50 border border-gray-200 rounded-lg px-4 py-3 mb-6 text-sm space-y-2"
+          >
+            <p className="text-gray-700">
+              {vaultStatus?.database_encrypted
+                ? 'Store this workspace\'s database as plain SQLite again. Vault entries stay encrypted.'
+                : 'Encrypt the whole workspace database, including notes, projects and the planner. It will only open with your master password.'}
+            </p>
+            <div className="flex space-x-2">
+              <input
+                type="password"
+                value={databasePassword}
+                onChange={(e) => setDatabasePassword(e.target.value)}
+                className="flex-1 px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                placeholder="Master password"
+                required
+              />
+              <button
+                type="submit"
+                className="px-4 py-2 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors"
+              >
+                {vaultStatus?.database_encrypted ? 'Decrypt' : 'Encrypt'}
+              </button>
+              <button
+                type="button"
+                onClick={() => {
+                  setShowDatabaseForm(false);
+                  setDatabasePassword('');
+                  setDatabaseError('');
+                }}
+                className="px-4 py-2 bg-gray-200 text-gray-700 rounded-lg hover:bg-gray-300 transition-colors"
+              >
+                Cancel
+              </button>
+            </div>
+            {databaseError && <p className="text-red-600">{databaseError}</p>}
+          </form>
+        )}
+
+        {/* Access Log */}
+        {accessLog && (
+          <div className="bg-gray-50 border border-gray-200 rounded-lg px-4 py-3 mb-6 text-sm">
+            <div className="flex items-center justify-between mb-2">
+              <span className="font-medium text-gray-700">Recently revealed secrets</span>
+              <button
+                onClick={() => setAccessLog(null)}
+                className="text-xs text-blue-600 hover:text-blue-800"
+              >
+                Dismiss
+              </button>
+            </div>
+            {accessLog.length === 0 ? (
+              <p className="text-gray-500">No secrets have been revealed yet</p>
+            ) : (
+              <ul className="space-y-1">
+                {accessLog.map((entry) => (
+                  <li key={entry.id} className="flex justify-between text-gray-700">
+                    <span>{entry.title} · {SECRET_FIELD_LABELS[entry.field]}</span>
+                    <span className="text-xs text-gray-500">{new Date(entry.accessed_at).toLocaleString()}</span>
+                  </li>
+                ))}
+              </ul>
+            )}
+          </div>
+        )}
+
         {activeTab === 'accounts' && (
           <div className="space-y-6">
             {/* Category Filter */}
@@ -231,6 +968,22 @@ const PersonalVaultPage: React.FC = () => {
               ))}
             </div>
 
+            {/* Password Health */}
+            {auditReport && (
+              <div className="flex items-center justify-between bg-gray-50 border border-gray-200 rounded-lg px-4 py-3 text-sm">
+                <span className="text-gray-700">
+                  {auditReport.total_accounts} accounts · {auditReport.weak} weak · {auditReport.reused} reused · {auditReport.stale} not changed in a year
+                  {auditReport.breach_list_checked && ` · ${auditReport.breached} breached`}
+                </span>
+                <button
+                  onClick={() => setAuditReport(null)}
+                  className="text-xs text-blue-600 hover:text-blue-800"
+                >
+                  Dismiss
+                </button>
+              </div>
+            )}
+
             {/* Accounts Grid */}
             <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-4">
               {accounts.map((account) => (
@@ -243,11 +996,18 @@ const PersonalVaultPage: React.FC = () => {
                         <p className="text-xs text-gray-500">{getCategoryLabel(account.category, 'account')}</p>
                       </div>
                     </div>
+                    <div className="flex flex-wrap gap-1 justify-end">
+                      {auditReport?.accounts.find(a => a.id === account.id)?.issues.map((issue) => (
+                        <span key={issue} className="px-2 py-0.5 rounded-full text-xs bg-red-100 text-red-700">
+                          {AUDIT_ISSUE_LABELS[issue]}
+                        </span>
+                      ))}
+                    </div>
                   </div>
                   
                   <div className="space-y-2">
                     <div>
-                      <label className="text-xs text-gray-500">Email</label>
+                      <label className="text-xs text-gray-500">Email / Username</label>
                       <div className="flex items-center space-x-2">
                         <p className="text-sm font-mono bg-gray-50 px-2 py-1 rounded flex-1">
                           {account.email}
@@ -262,25 +1022,57 @@ const PersonalVaultPage: React.FC = () => {
                     </div>
                     
                     <div>
-                      <label className="text-xs text-gray-500">Password</label>
+                      <label className="text-xs text-gray-500">
+                        Password
+                        {account.password_strength !== undefined && account.password_strength !== null && account.password_strength < 2 && (
+                          <span className="ml-2 text-red-600">Weak</span>
+                        )}
+                      </label>
                       <div className="flex items-center space-x-2">
                         <p className="text-sm font-mono bg-gray-50 px-2 py-1 rounded flex-1">
-                          {showPassword[account.id] ? account.password : '••••••••'}
+                          {revealed[secretKey('password', account.id)] ?? '••••••••'}
                         </p>
                         <button
-                          onClick={() => togglePasswordVisibility(account.id)}
+                          onClick={() => toggleSecret(account.id, 'password')}
                           className="text-xs text-blue-600 hover:text-blue-800"
                         >
-                          {showPassword[account.id] ? 'Hide' : 'Show'}
+                          {secretKey('password', account.id) in revealed ? 'Hide' : 'Show'}
                         </button>
                         <button
-                          onClick={() => copyToClipboard(account.password)}
+                          onClick={() => copySecret(account.id, 'password')}
                           className="text-xs text-blue-600 hover:text-blue-800"
                         >
                           Copy
                         </button>
                       </div>
                     </div>
+
+                    {account.has_totp && (
+                      <div>
+                        <label className="text-xs text-gray-500">2FA Code</label>
+                        <div className="flex items-center space-x-2">
+                          <p className="text-sm font-mono bg-gray-50 px-2 py-1 rounded flex-1">
+                            {totpCodes[account.id]
+                              ? `${totpCodes[account.id].code} (${totpCodes[account.id].seconds_remaining}s)`
+                              : '••••••'}
+                          </p>
+                          <button
+                            onClick={() => totpCodes[account.id] ? hideTotpCode(account.id) : loadTotpCode(account.id)}
+                            className="text-xs text-blue-600 hover:text-blue-800"
+                          >
+                            {totpCodes[account.id] ? 'Hide' : 'Show'}
+                          </button>
+                          {totpCodes[account.id] && (
+                            <button
+                              onClick={() => copyToClipboard(totpCodes[account.id].code)}
+                              className="text-xs text-blue-600 hover:text-blue-800"
+                            >
+                              Copy
+                            </button>
+                          )}
+                        </div>
+                      </div>
+                    )}
                     
                     {account.website && (
                       <div>
@@ -297,6 +1089,63 @@ const PersonalVaultPage: React.FC = () => {
                         <p className="text-sm text-gray-700">{account.notes}</p>
                       </div>
                     )}
+
+                    {renderCustomFields(account.custom_fields)}
+
+                    {passwordHistory[account.id] && (
+                      <div>
+                        <label className="text-xs text-gray-500">Previous Passwords</label>
+                        {passwordHistory[account.id].length === 0 ? (
+                          <p className="text-sm text-gray-500">The password has never been changed</p>
+                        ) : (
+                          <ul className="space-y-1">
+                            {passwordHistory[account.id].map((entry) => (
+                              <li key={entry.id} className="flex items-center space-x-2">
+                                <p className="text-sm font-mono bg-gray-50 px-2 py-1 rounded flex-1">
+                                  {revealed[secretKey('previous_password', entry.id)] ?? '••••••••'}
+                                </p>
+                                <span className="text-xs text-gray-500">
+                                  {new Date(entry.replaced_at).toLocaleDateString()}
+                                </span>
+                                <button
+                                  onClick={() => toggleSecret(entry.id, 'previous_password')}
+                                  className="text-xs text-blue-600 hover:text-blue-800"
+                                >
+                                  {secretKey('previous_password', entry.id) in revealed ? 'Hide' : 'Show'}
+                                </button>
+                                <button
+                                  onClick={() => handleRestorePassword(account.id, entry.id)}
+                                  className="text-xs text-blue-600 hover:text-blue-800"
+                                >
+                                  Restore
+                                </button>
+                              </li>
+                            ))}
+                          </ul>
+                        )}
+                      </div>
+                    )}
+                  </div>
+
+                  <div className="flex justify-end space-x-3 mt-3">
+                    <button
+                      onClick={() => togglePasswordHistory(account.id)}
+                      className="text-xs text-blue-600 hover:text-blue-800"
+                    >
+                      {passwordHistory[account.id] ? 'Hide History' : 'History'}
+                    </button>
+                    <button
+                      onClick={() => handleEditAccount(account)}
+                      className="text-xs text-blue-600 hover:text-blue-800"
+                    >
+                      Edit
+                    </button>
+                    <button
+                      onClick={() => handleDeleteAccount(account)}
+                      className="text-xs text-red-600 hover:text-red-800"
+                    >
+                      Delete
+                    </button>
                   </div>
                 </div>
               ))}
@@ -323,19 +1172,107 @@ const PersonalVaultPage: React.FC = () => {
                       </div>
                     </div>
                   </div>
-                  <button
-                    onClick={() => copyToClipboard(info.content)}
-                    className="text-xs text-blue-600 hover:text-blue-800"
-                  >
-                    Copy
-                  </button>
+                  <div className="flex space-x-3">
+                    {info.is_sensitive && (
+                      <button
+                        onClick={() => toggleSecret(info.id, 'content')}
+                        className="text-xs text-blue-600 hover:text-blue-800"
+                      >
+                        {secretKey('content', info.id) in revealed ? 'Hide' : 'Show'}
+                      </button>
+                    )}
+                    <button
+                      onClick={() => info.content !== undefined ? copyToClipboard(info.content) : copySecret(info.id, 'content')}
+                      className="text-xs text-blue-600 hover:text-blue-800"
+                    >
+                      Copy
+                    </button>
+                    <button
+                      onClick={() => toggleAttachments(info.id)}
+                      className="text-xs text-blue-600 hover:text-blue-800"
+                    >
+                      {attachments[info.id] ? 'Hide Files' : `Files (${info.attachment_count})`}
+                    </button>
+                    <button
+                      onClick={() => handleEditInfo(info)}
+                      className="text-xs text-blue-600 hover:text-blue-800"
+                    >
+                      Edit
+                    </button>
+                    <button
+                      onClick={() => handleDeleteInfo(info)}
+                      className="text-xs text-red-600 hover:text-red-800"
+                    >
+                      Delete
+                    </button>
+                  </div>
                 </div>
                 
                 <div className="bg-gray-50 p-3 rounded">
                   <pre className="text-sm text-gray-700 whitespace-pre-wrap font-sans">
-                    {info.content}
+                    {info.content ?? revealed[secretKey('content', info.id)] ?? '••••••••'}
                   </pre>
                 </div>
+
+                {info.custom_fields.length > 0 && (
+                  <div className="space-y-2 mt-3">
+                    {renderCustomFields(info.custom_fields)}
+                  </div>
+                )}
+
+                {attachments[info.id] && (
+                  <div className="mt-3 border-t border-gray-100 pt-3 space-y-2 text-sm">
+                    {attachments[info.id].length === 0 ? (
+                      <p className="text-gray-500">No files attached</p>
+                    ) : (
+                      <ul className="space-y-1">
+                        {attachments[info.id].map((attachment) => (
+                          <li key={attachment.id} className="flex items-center justify-between text-gray-700">
+                            <span>📎 {attachment.file_name} <span className="text-xs text-gray-500">({formatSize(attachment.size_bytes)})</span></span>
+                            <span className="space-x-3">
+                              <button
+                                onClick={() => handleExportAttachment(attachment)}
+                                className="text-xs text-blue-600 hover:text-blue-800"
+                              >
+                                Export
+                              </button>
+                              <button
+                                onClick={() => handleDeleteAttachment(attachment)}
+                                className="text-xs text-red-600 hover:text-red-800"
+                              >
+                                Delete
+                              </button>
+                            </span>
+                          </li>
+                        ))}
+                      </ul>
+                    )}
+                    <div className="flex space-x-2">
+                      <input
+                        type="text"
+                        value={attachPath}
+                        onChange={(e) => setAttachPath(e.target.value)}
+                        className="flex-1 px-3 py-1 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                        placeholder="Path of a file to attach (max 25 MB)"
+                      />
+                      <button
+                        onClick={() => handleAddAttachment(info.id)}
+                        disabled={!attachPath.trim()}
+                        className="px-3 py-1 bg-blue-600 text-white rounded-lg hover:bg-blue-700 transition-colors disabled:opacity-50"
+                      >
+                        Attach
+                      </button>
+                    </div>
+                    <input
+                      type="text"
+                      value={exportFolder}
+                      onChange={(e) => setExportFolder(e.target.value)}
+                      className="w-full px-3 py-1 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                      placeholder="Folder to export files to"
+                    />
+                    {attachmentError && <p className="text-red-600">{attachmentError}</p>}
+                  </div>
+                )}
                 
                 <div className="text-xs text-gray-500 mt-2">
                   Created: {new Date(info.created_at).toLocaleDateString()}
@@ -346,12 +1283,12 @@ const PersonalVaultPage: React.FC = () => {
         )}
       </div>
 
-      {/* Create Account Modal */}
+      {/* Account Modal */}
       {showAccountForm && (
         <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
           <div className="bg-white rounded-lg p-6 w-full max-w-md">
-            <h2 className="text-xl font-semibold mb-4">Add New Account</h2>
-            <form onSubmit={handleCreateAccount} className="space-y-4">
+            <h2 className="text-xl font-semibold mb-4">{editingAccountId !== null ? 'Edit Account' : 'Add New Account'}</h2>
+            <form onSubmit={handleSaveAccount} className="space-y-4">
               <div>
                 <label className="block text-sm font-medium text-gray-700 mb-1">Title</label>
                 <input
@@ -364,9 +1301,9 @@ const PersonalVaultPage: React.FC = () => {
                 />
               </div>
               <div>
-                <label className="block text-sm font-medium text-gray-700 mb-1">Email</label>
+                <label className="block text-sm font-medium text-gray-700 mb-1">Email / Username</label>
                 <input
-                  type="email"
+                  type="text"
                   value={accountForm.email}
                   onChange={(e) => setAccountForm({ ...accountForm, email: e.target.value })}
                   className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
@@ -375,13 +1312,31 @@ const PersonalVaultPage: React.FC = () => {
               </div>
               <div>
                 <label className="block text-sm font-medium text-gray-700 mb-1">Password</label>
-                <input
-                  type="password"
-                  value={accountForm.password}
-                  onChange={(e) => setAccountForm({ ...accountForm, password: e.target.value })}
-                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
-                  required
-                />
+                <div className="flex space-x-2">
+                  <input
+                    type="password"
+                    value={accountForm.password}
+                    onChange={(e) => handlePasswordChange(e.target.value)}
+                    className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                    placeholder={editingAccountId !== null ? 'Leave blank to keep the current password' : undefined}
+                    required={editingAccountId === null}
+                  />
+                  <button
+                    type="button"
+                    onClick={handleGeneratePassword}
+                    className="px-3 py-2 text-sm text-blue-600 border border-blue-600 rounded-lg hover:bg-blue-50"
+                  >
+                    Generate
+                  </button>
+                </div>
+                {passwordStrength && (
+                  <div className="mt-1 text-xs">
+                    <span className={STRENGTH_COLORS[passwordStrength.score]}>{passwordStrength.label}</span>
+                    {passwordStrength.feedback.length > 0 && (
+                      <span className="text-gray-500"> · {passwordStrength.feedback.join('. ')}</span>
+                    )}
+                  </div>
+                )}
               </div>
               <div>
                 <label className="block text-sm font-medium text-gray-700 mb-1">Website (Optional)</label>
@@ -393,6 +1348,16 @@ const PersonalVaultPage: React.FC = () => {
                   placeholder="https://gmail.com"
                 />
               </div>
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">2FA Secret (Optional)</label>
+                <input
+                  type="password"
+                  value={accountForm.totp_secret}
+                  onChange={(e) => setAccountForm({ ...accountForm, totp_secret: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:ring-2 focus:ring-blue-500 focus:border-transparent"
+                  placeholder="otpauth://totp/... or base32 secret"
+                />
+              </div>
               <div>
                 <label className="block text-sm font-medium text-gray-700 mb-1">Category</label>
                 <select
@@ -416,6 +1381,7 @@ const PersonalVaultPage: React.FC = () => {
                   rows={3}
                 />
               </div>
+              {renderCustomFieldsEditor(accountForm.custom_fields, (custom_fields) => setAccountForm({ ...accountForm, custom_fields }))}
               <div className="flex space-x-3">
                 <button
                   type="submit"
@@ -425,7 +1391,7 @@ const PersonalVaultPage: React.FC = () => {
                 </button>
                 <button
                   type="button"
-                  onClick={() => setShowAccountForm(false)}
+                  onClick={closeAccountForm}
                   className="flex-1 px-4 py-2 bg-gray-300 text-gray-700 rounded-lg hover:bg-gray-400 transition-colors"
                 >
                   Cancel
@@ -436,12 +1402,12 @@ const PersonalVaultPage: React.FC = () => {
         </div>
       )}
 
-      {/* Create Info Modal */}
+      {/* Info Modal */}
       {showInfoForm && (
         <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center z-50">
           <div className="bg-white rounded-lg p-6 w-full max-w-2xl">
-            <h2 className="text-xl font-semibold mb-4">Add Personal Information</h2>
-            <form onSubmit={handleCreateInfo} className="space-y-4">
+            <h2 className="text-xl font-semibold mb-4">{editingInfoId !== null ? 'Edit Personal Information' : 'Add Personal Information'}</h2>
+            <form onSubmit={handleSaveInfo} className="space-y-4">
               <div>
                 <label className="block text-sm font-medium text-gray-700 mb-1">Title</label>
                 <input
@@ -490,6 +1456,7 @@ const PersonalVaultPage: React.FC = () => {
                   Mark as sensitive information
                 </label>
               </div>
+              {renderCustomFieldsEditor(infoForm.custom_fields, (custom_fields) => setInfoForm({ ...infoForm, custom_fields }))}
               <div className="flex space-x-3">
                 <button
                   type="submit"
@@ -499,7 +1466,7 @@ const PersonalVaultPage: React.FC = () => {
                 </button>
                 <button
                   type="button"
-                  onClick={() => setShowInfoForm(false)}
+                  onClick={closeInfoForm}
                   className="flex-1 px-4 py-2 bg-gray-300 text-gray-700 rounded-lg hover:bg-gray-400 transition-colors"
                 >
                   Cancel